@@ -0,0 +1,117 @@
+// Records a frame of mixed ui panels (a rect and rows of labels from a
+// monospace and a proportional bitmap atlas, one layer per panel) headlessly
+// and prints the render pass state changes drawing it layer by layer takes,
+// quads and text alternating like `Renderer::render` draws them. Once with
+// each renderer on a pipeline of its own, once with the text on the quads'
+// `Pipeline2d`, where the whole frame binds one pipeline.
+use wrs::camera::Camera;
+use wrs::font::{self, FontRenderer};
+use wrs::quad::QuadRenderer;
+use wrs::sort::{BoundPipeline, DrawStats, SortKey};
+
+const PANELS: u16 = 16;
+const ROWS: u32 = 8;
+
+fn main() {
+    env_logger::init();
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let Ok(adapter) =
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+    else {
+        println!("no graphics adapter available");
+        return;
+    };
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).unwrap();
+
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let size = winit::dpi::PhysicalSize::new(1280, 720);
+    let cam = Camera::new_from_size(&device, size);
+    let font_data = wrs::state::FontSource::Embedded.load().unwrap();
+    let mono = wrs::create_monospace_atlas(&device, &queue, &font_data, 32.0);
+    let proportional = font::create_glyph_atlas(&device, &queue, &font_data, 32.0);
+
+    let mut quads = QuadRenderer::new(&device, &cam, format);
+    let mut text = FontRenderer::new(&device, &cam, &mono, format);
+    for panel in 0..PANELS {
+        let (x, y) = ((panel % 4) as f32 * 320.0, (panel / 4) as f32 * 180.0);
+        let key = SortKey::new(panel, 0);
+        quads.push_with_key(key, x, y, 310.0, 170.0, [0.05, 0.05, 0.05]);
+        text.push_str_with_key(key, x + 8.0, y + 4.0, 20.0, [1.0, 1.0, 1.0], "Panel title", &proportional)
+            .unwrap();
+        for row in 0..ROWS {
+            let row_y = y + 30.0 + row as f32 * 17.0;
+            quads.push_with_key(key, x + 8.0, row_y, 12.0, 12.0, [0.2, 0.6, 0.2]);
+            text.push_str_with_key(key, x + 24.0, row_y, 14.0, [0.9, 0.9, 0.9], "label", &proportional)
+                .unwrap();
+            text.push_str_with_key(key, x + 200.0, row_y, 14.0, [0.6, 0.6, 0.6], "0.125", &mono)
+                .unwrap();
+        }
+    }
+    quads.prepare(&device, &queue);
+    text.prepare(&device, &queue);
+
+    let separate = draw_frame(&device, &queue, &cam, &quads, &text, format, size);
+    text.set_pipeline_2d(&device, quads.pipeline_2d().clone());
+    let shared = draw_frame(&device, &queue, &cam, &quads, &text, format, size);
+
+    println!("own pipelines:    {separate:?}");
+    println!("shared pipeline:  {shared:?}");
+    println!(
+        "pipeline binds: {} shared, {} with a pipeline each",
+        shared.pipeline_binds, separate.pipeline_binds
+    );
+}
+
+// the panels layer by layer into a fresh target, the rects of a layer then its text
+fn draw_frame(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    cam: &Camera,
+    quads: &QuadRenderer,
+    text: &FontRenderer,
+    format: wgpu::TextureFormat,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> DrawStats {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let mut encoder = device.create_command_encoder(&Default::default());
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            depth_slice: None,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    let mut stats = DrawStats::default();
+    let mut bound = BoundPipeline::default();
+    for panel in 0..PANELS {
+        let layer = |layer| layer == panel;
+        stats += quads.render_with(&mut render_pass, cam, layer, &mut bound);
+        stats += text.render_with(&mut render_pass, cam, layer, &mut bound);
+    }
+    drop(render_pass);
+    queue.submit([encoder.finish()]);
+    stats
+}
@@ -0,0 +1,66 @@
+// Renders the color reference scene headlessly into linear and srgb targets and
+// checks the readback against the expected values. The software renderer is
+// always checked, the gpu formats only when an adapter is available. Pass a
+// directory to also write the rendered images there, `tests/golden` updates
+// the image the software renderer is tested against. Needs `--features color-check`.
+use wrs::color_check;
+
+fn main() {
+    env_logger::init();
+    let out_dir = std::env::args().nth(1);
+
+    let image = color_check::render_software();
+    if let Some(dir) = &out_dir {
+        image
+            .save(std::path::Path::new(dir).join("color_check_software.png"))
+            .unwrap();
+    }
+    let mut failed = report(
+        "software",
+        &color_check::verify(&image, wgpu::TextureFormat::Rgba8UnormSrgb, 1),
+    );
+
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let Ok(adapter) =
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+    else {
+        println!("no graphics adapter available, skipping the gpu formats");
+        std::process::exit(failed as i32);
+    };
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).unwrap();
+
+    for format in [
+        wgpu::TextureFormat::Rgba8Unorm,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+        wgpu::TextureFormat::Bgra8Unorm,
+        wgpu::TextureFormat::Bgra8UnormSrgb,
+    ] {
+        let image = color_check::render_offscreen(&device, &queue, format);
+        if let Some(dir) = &out_dir {
+            image
+                .save(std::path::Path::new(dir).join(format!("color_check_{format:?}.png")))
+                .unwrap();
+        }
+
+        failed |= report(
+            &format!("{format:?}"),
+            &color_check::verify(&image, format, 1),
+        );
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn report(name: &str, mismatches: &[color_check::Mismatch]) -> bool {
+    println!("{name}: {} mismatches", mismatches.len());
+    for m in mismatches {
+        println!(
+            "  {}: expected {:?}, got {:?}",
+            m.probe, m.expected, m.actual
+        );
+    }
+    !mismatches.is_empty()
+}
@@ -0,0 +1,33 @@
+// A quad moved around from an egui window drawn over the scene.
+// Run with: cargo run --example egui_panel --features egui
+use wrs::{App, Frame};
+
+fn main() {
+    env_logger::init();
+    wrs::run(Panel {
+        position: [100.0, 100.0],
+        size: 80.0,
+        color: [0.9, 0.3, 0.2],
+    })
+    .unwrap();
+}
+
+struct Panel {
+    position: [f32; 2],
+    size: f32,
+    color: [f32; 3],
+}
+
+impl App for Panel {
+    fn draw(&mut self, frame: &mut Frame) {
+        frame.quad(self.position[0], self.position[1], self.size, self.size, self.color);
+        frame.egui(|ctx| {
+            egui::Window::new("quad").show(ctx, |ui| {
+                ui.add(egui::Slider::new(&mut self.position[0], 0.0..=800.0).text("x"));
+                ui.add(egui::Slider::new(&mut self.position[1], 0.0..=600.0).text("y"));
+                ui.add(egui::Slider::new(&mut self.size, 1.0..=300.0).text("size"));
+                ui.color_edit_button_rgb(&mut self.color);
+            });
+        });
+    }
+}
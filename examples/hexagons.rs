@@ -0,0 +1,201 @@
+// A custom primitive renderer built outside the crate on `wrs::batch::Batch`:
+// hexagons, each pushed as the quad around it with the shader discarding the
+// corners. They're drawn headlessly into the same pass as a `QuadRenderer`
+// background and checked: a hexagon's center has its color, the corners of
+// its quad keep the background's. The shader gets the camera binding and the
+// hexagon's distance from the crate's shader includes.
+use wrs::batch::Batch;
+use wrs::camera::Camera;
+use wrs::quad::QuadRenderer;
+use wrs::shader::ShaderPreprocessor;
+use wrs::sort::SortKey;
+
+const SIZE: winit::dpi::PhysicalSize<u32> = winit::dpi::PhysicalSize::new(128, 64);
+
+const SHADER: &str = "
+#include \"wrs/globals.wgsl\"
+#include \"wrs/sdf.wgsl\"
+
+struct VertexOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    @location(1) local: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) pos: vec2<f32>, @location(1) color: vec3<f32>, @location(2) local: vec2<f32>) -> VertexOut {
+    var out: VertexOut;
+    out.position = camera.view_proj * vec4<f32>(pos, 0.0, 1.0);
+    out.color = color;
+    out.local = local;
+    return out;
+}
+
+// pointy top hexagon inscribed in the -1..1 square
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    if sd_hexagon(in.local, 1.0) > 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.color, 1.0);
+}
+";
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct HexVertex {
+    pos: [f32; 2],
+    color: [f32; 3],
+    // -1..1 across the hexagon's quad
+    local: [f32; 2],
+}
+
+struct HexRenderer {
+    pipeline: wgpu::RenderPipeline,
+    batch: Batch<HexVertex>,
+}
+
+impl HexRenderer {
+    fn new(device: &wgpu::Device, cam: &Camera, format: wgpu::TextureFormat) -> Self {
+        let shader = ShaderPreprocessor::new()
+            .create_module(device, "Hexagon Shader", SHADER)
+            .unwrap();
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[cam.get_bind_group_layout()],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Hexagon Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<HexVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x3, 2 => Float32x2],
+                }],
+                compilation_options: Default::default(),
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(format.into())],
+                compilation_options: Default::default(),
+            }),
+            multiview: None,
+            cache: None,
+        });
+        Self {
+            pipeline,
+            batch: Batch::new(device, "Hexagon Batch", 64),
+        }
+    }
+
+    fn push(&mut self, key: SortKey, center: [f32; 2], radius: f32, color: [f32; 3]) {
+        let corner = |dx: f32, dy: f32| HexVertex {
+            pos: [center[0] + dx * radius, center[1] + dy * radius],
+            color,
+            local: [dx, dy],
+        };
+        self.batch.push(
+            key,
+            [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)],
+        );
+    }
+
+    fn render(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera) {
+        if !self.batch.is_drawable() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, cam.get_bind_group(), &[]);
+        self.batch.set_buffers(render_pass);
+        for (_, range) in self.batch.runs(|_| true) {
+            render_pass.draw_indexed(range, 0, 0..1);
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let Ok(adapter) =
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+    else {
+        println!("no graphics adapter available");
+        return;
+    };
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).unwrap();
+
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let cam = Camera::new_from_size(&device, SIZE);
+    let mut quads = QuadRenderer::new(&device, &cam, format);
+    let mut hexagons = HexRenderer::new(&device, &cam, format);
+
+    quads.push(0.0, 0.0, SIZE.width as f32, SIZE.height as f32, [0.0, 0.0, 1.0]);
+    // pushed out of order, the batch sorts them by key
+    hexagons.push(SortKey::new(1, 0), [32.0, 32.0], 24.0, [1.0, 0.0, 0.0]);
+    hexagons.push(SortKey::new(0, 0), [96.0, 32.0], 24.0, [0.0, 1.0, 0.0]);
+    quads.prepare(&device, &queue);
+    hexagons.batch.prepare(&device, &queue);
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Hexagon Target"),
+        size: wgpu::Extent3d {
+            width: SIZE.width,
+            height: SIZE.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let mut encoder = device.create_command_encoder(&Default::default());
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            depth_slice: None,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    quads.render(&mut render_pass, &cam, |_| true);
+    hexagons.render(&mut render_pass, &cam);
+    drop(render_pass);
+    queue.submit([encoder.finish()]);
+
+    let image = wrs::export::read_texture(&device, &queue, &texture);
+    let mut failed = false;
+    for (name, (x, y), expected) in [
+        ("red hexagon center", (32, 32), [255, 0, 0]),
+        ("green hexagon center", (96, 32), [0, 255, 0]),
+        ("red hexagon quad corner", (10, 10), [0, 0, 255]),
+        ("green hexagon quad corner", (117, 53), [0, 0, 255]),
+    ] {
+        let pixel = image.get_pixel(x, y).0;
+        let ok = pixel[..3] == expected;
+        println!("{name}: {:?}{}", &pixel[..3], if ok { "" } else { " (wrong)" });
+        failed |= !ok;
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
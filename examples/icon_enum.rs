@@ -0,0 +1,15 @@
+// Prints a typed enum for the icons of a codepoints listing, see
+// `icons::generate_enum`.
+// Usage: icon_enum <codepoints file> [enum name] > icons.rs
+use wrs::font::icons;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("usage: icon_enum <codepoints file> [enum name]");
+        std::process::exit(1);
+    };
+    let enum_name = args.next().unwrap_or_else(|| "Icon".to_string());
+    let codepoints = std::fs::read_to_string(&path).expect("couldn't read the codepoints file");
+    print!("{}", icons::generate_enum(&codepoints, &enum_name));
+}
@@ -0,0 +1,15 @@
+// A window with a few shapes and the mouse drawn into it, in the fewest lines
+// wrs takes, see `wrs::Canvas`.
+fn main() -> Result<(), winit::error::EventLoopError> {
+    let mut t = 0.0;
+    wrs::Canvas::run("quickstart", 800, 600, |canvas, input| {
+        t += canvas.dt();
+        canvas.clear([0.05, 0.05, 0.08]);
+        canvas.rect(40.0, 40.0, 200.0, 120.0, [0.2, 0.4, 0.8]);
+        canvas.circle(400.0 + 100.0 * t.cos(), 300.0, 40.0, [0.9, 0.6, 0.1]);
+        if let Some((x, y)) = input.mouse_position() {
+            canvas.line((400.0, 300.0), (x, y), 3.0, [1.0, 1.0, 1.0]);
+        }
+        canvas.text(40.0, 200.0, 32.0, [1.0, 1.0, 1.0], "hello from wrs");
+    })
+}
@@ -0,0 +1,77 @@
+// Shows the draw lists an app streams through `remote::DebugServer`.
+// Usage: remote_viewer [host:port], defaults to 127.0.0.1:7878
+use std::sync::Arc;
+use wrs::Renderer;
+use wrs::draw_list::DrawList;
+use wrs::remote::DebugClient;
+
+fn main() {
+    env_logger::init();
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7878".to_string());
+    let client = DebugClient::connect(&addr).expect("couldn't connect to the debug server");
+
+    let event_loop = winit::event_loop::EventLoop::new().unwrap();
+    event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
+
+    let mut viewer = Viewer {
+        client,
+        frame: DrawList::new(),
+        renderer: None,
+    };
+    event_loop.run_app(&mut viewer).unwrap();
+}
+
+struct Viewer {
+    client: DebugClient,
+    frame: DrawList,
+    renderer: Option<Renderer>,
+}
+
+impl winit::application::ApplicationHandler for Viewer {
+    fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        let window = Arc::new(
+            event_loop
+                .create_window(winit::window::Window::default_attributes().with_title("wrs remote"))
+                .unwrap(),
+        );
+        self.renderer = Some(pollster::block_on(Renderer::new(window.clone())));
+        window.request_redraw();
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        _window_id: winit::window::WindowId,
+        event: winit::event::WindowEvent,
+    ) {
+        let renderer = self.renderer.as_mut().unwrap();
+        match event {
+            winit::event::WindowEvent::CloseRequested => {
+                event_loop.exit();
+            }
+            winit::event::WindowEvent::RedrawRequested => {
+                match self.client.latest() {
+                    Ok(Some(frame)) => self.frame = frame,
+                    Ok(None) => {}
+                    Err(err) => {
+                        eprintln!("connection lost: {err}");
+                        event_loop.exit();
+                        return;
+                    }
+                }
+                let mut frame = renderer.begin_frame();
+                if let Err(err) = self.frame.submit(&mut frame) {
+                    eprintln!("{err}");
+                }
+                frame.submit();
+                renderer.get_window().request_redraw();
+            }
+            winit::event::WindowEvent::Resized(size) => {
+                renderer.resize(size);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Steps through a recorded session, see `replay::Replay` for the controls.
+// Usage: replay [recording file], without one a generated demo session is shown.
+use std::sync::Arc;
+use wrs::Renderer;
+use wrs::draw_list::{DrawCommand, DrawList};
+use wrs::rect::Rect;
+use wrs::replay::{Recording, Replay};
+
+fn main() {
+    env_logger::init();
+    let recording = match std::env::args().nth(1) {
+        Some(path) => Recording::load(&path).expect("couldn't load the recording"),
+        None => demo_recording(),
+    };
+
+    let event_loop = winit::event_loop::EventLoop::new().unwrap();
+    event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
+
+    let mut app = App {
+        replay: Replay::new(recording),
+        renderer: None,
+    };
+    event_loop.run_app(&mut app).unwrap();
+}
+
+// a quad sliding across the screen, with a one-frame glitch to find
+fn demo_recording() -> Recording {
+    let mut recording = Recording::new();
+    for i in 0..240 {
+        let mut frame = DrawList::new();
+        let color = if i == 137 { [1.0, 0.0, 1.0] } else { [0.0, 1.0, 0.0] };
+        frame.push(DrawCommand::Quad {
+            rect: Rect::new(i as f32 * 3.0, 200.0, 100.0, 100.0),
+            color,
+            key: Default::default(),
+        });
+        recording.capture(&frame);
+    }
+    recording
+}
+
+struct App {
+    replay: Replay,
+    renderer: Option<Renderer>,
+}
+
+impl winit::application::ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        let window = Arc::new(
+            event_loop
+                .create_window(winit::window::Window::default_attributes().with_title("wrs replay"))
+                .unwrap(),
+        );
+        self.renderer = Some(pollster::block_on(Renderer::new(window.clone())));
+        window.request_redraw();
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        _window_id: winit::window::WindowId,
+        event: winit::event::WindowEvent,
+    ) {
+        let renderer = self.renderer.as_mut().unwrap();
+        let size = renderer.get_window().inner_size();
+        if self
+            .replay
+            .handle_event(&event, (size.width as f32, size.height as f32))
+        {
+            return;
+        }
+
+        match event {
+            winit::event::WindowEvent::CloseRequested => {
+                event_loop.exit();
+            }
+            winit::event::WindowEvent::RedrawRequested => {
+                self.replay.advance();
+                let mut frame = renderer.begin_frame();
+                if let Err(err) = self.replay.draw(&mut frame) {
+                    eprintln!("{err}");
+                }
+                frame.submit();
+                renderer.get_window().request_redraw();
+            }
+            winit::event::WindowEvent::Resized(size) => {
+                renderer.resize(size);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// Shows a scene file and reloads it whenever it's saved.
+// Usage: scene_viewer [path], defaults to examples/scene.json
+use wrs::scene::LiveScene;
+use wrs::{App, Frame};
+
+fn main() {
+    env_logger::init();
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "examples/scene.json".to_string());
+    let scene = LiveScene::load(path).unwrap_or_else(|err| panic!("couldn't load the scene: {err}"));
+    wrs::run(Viewer { scene }).unwrap();
+}
+
+struct Viewer {
+    scene: LiveScene,
+}
+
+impl App for Viewer {
+    fn draw(&mut self, frame: &mut Frame) {
+        match self.scene.reload_if_changed() {
+            Some(Ok(())) => frame.toast("scene reloaded", std::time::Duration::from_secs(1)),
+            Some(Err(err)) => frame.toast(format!("{err}"), std::time::Duration::from_secs(3)),
+            None => {}
+        }
+        if let Err(err) = self.scene.draw(frame) {
+            eprintln!("{err}");
+        }
+    }
+}
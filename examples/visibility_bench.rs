@@ -0,0 +1,67 @@
+// Times `VisibilityPolygon::new` and `line_of_sight` over scattered boxes, from
+// a hundred occluding segments up to over a thousand. Checks on the way that
+// a point behind a box isn't visible and one next to the origin is.
+use std::time::Instant;
+
+use wrs::rect::Rect;
+use wrs::visibility::{Segment, VisibilityPolygon, line_of_sight};
+
+const WORLD: Rect = Rect::new(0.0, 0.0, 2000.0, 2000.0);
+const ORIGIN: [f32; 2] = [1000.0, 1000.0];
+const RUNS: u32 = 20;
+const QUERIES: u32 = 10_000;
+
+fn main() {
+    for boxes in [25, 50, 100, 200, 300] {
+        let occluders = scatter(boxes);
+
+        let start = Instant::now();
+        let mut polygon = VisibilityPolygon::new(ORIGIN, &occluders, WORLD);
+        for _ in 1..RUNS {
+            polygon = VisibilityPolygon::new(ORIGIN, &occluders, WORLD);
+        }
+        let per_polygon = start.elapsed() / RUNS;
+
+        let mut rng = 7;
+        let start = Instant::now();
+        let visible = (0..QUERIES)
+            .filter(|_| line_of_sight(ORIGIN, [random(&mut rng) * 2000.0, random(&mut rng) * 2000.0], &occluders))
+            .count();
+        let per_query = start.elapsed() / QUERIES;
+
+        assert!(polygon.contains([ORIGIN[0] + 1.0, ORIGIN[1]]), "next to the origin is visible");
+        let hidden = Segment::rect(Rect::new(ORIGIN[0] + 10.0, ORIGIN[1] - 10.0, 5.0, 20.0));
+        let behind = VisibilityPolygon::new(ORIGIN, &hidden, WORLD);
+        assert!(!behind.contains([ORIGIN[0] + 50.0, ORIGIN[1]]), "behind a box is hidden");
+        assert!(!line_of_sight(ORIGIN, [ORIGIN[0] + 50.0, ORIGIN[1]], &hidden));
+
+        println!(
+            "{:5} segments: polygon of {:5} points in {per_polygon:?}, line of sight in {per_query:?} ({visible} of {QUERIES} visible)",
+            occluders.len(),
+            polygon.points.len(),
+        );
+    }
+}
+
+// `count` small boxes at random positions, keeping clear of the origin
+fn scatter(count: usize) -> Vec<Segment> {
+    let mut rng = 1;
+    let mut segments = vec![];
+    while segments.len() < count * 4 {
+        let (x, y) = (random(&mut rng) * 1960.0, random(&mut rng) * 1960.0);
+        let rect = Rect::new(x, y, 10.0 + random(&mut rng) * 30.0, 10.0 + random(&mut rng) * 30.0);
+        if rect.intersects(&Rect::new(ORIGIN[0] - 50.0, ORIGIN[1] - 50.0, 100.0, 100.0)) {
+            continue;
+        }
+        segments.extend(Segment::rect(rect));
+    }
+    segments
+}
+
+// xorshift, 0 to 1
+fn random(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    (*state >> 8) as f32 / (1 << 24) as f32
+}
@@ -0,0 +1,30 @@
+// The immediate mode widgets of `wrs::ui` driving a square, see `wrs::ui::Ui`.
+use wrs::rect::Rect;
+use wrs::ui::{Ui, UiState};
+
+fn main() -> Result<(), winit::error::EventLoopError> {
+    let mut state = UiState::new();
+    let (mut size, mut filled, mut name) = (80.0, true, String::from("square"));
+    wrs::Canvas::run("widgets", 800, 600, move |canvas, input| {
+        canvas.clear([0.08, 0.08, 0.1]);
+        let mut ui = Ui::new(canvas.frame(), input, &mut state);
+        ui.slider("Size", Rect::new(20.0, 20.0, 240.0, 28.0), &mut size, 10.0..=200.0);
+        ui.checkbox("Filled", Rect::new(20.0, 60.0, 240.0, 28.0), &mut filled);
+        ui.text_input("Name", Rect::new(20.0, 100.0, 240.0, 28.0), &mut name);
+        if ui.button("Reset", Rect::new(20.0, 140.0, 100.0, 28.0)) {
+            (size, filled) = (80.0, true);
+        }
+        drop(ui);
+
+        let (x, y) = (500.0 - size / 2.0, 300.0 - size / 2.0);
+        if filled {
+            canvas.rect(x, y, size, size, [0.9, 0.6, 0.1]);
+        } else {
+            let corners = [(x, y), (x + size, y), (x + size, y + size), (x, y + size)];
+            for i in 0..4 {
+                canvas.line(corners[i], corners[(i + 1) % 4], 2.0, [0.9, 0.6, 0.1]);
+            }
+        }
+        canvas.text(x, y + size + 10.0, 20.0, [1.0, 1.0, 1.0], &name);
+    })
+}
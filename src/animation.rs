@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+// Whether anything on screen is still moving, so `RedrawMode::Reactive`
+// keeps drawing frames while it is and idles once it stops. `Frame::tween`,
+// `Frame::particles`, toasts and `CameraControls` mark it on their own, other
+// animations call `request_frame` every frame they change something.
+#[derive(Debug, Clone, Default)]
+pub struct Animations {
+    next_frame: bool,
+    until: Option<Instant>,
+}
+
+impl Animations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // draws one more frame after this one
+    pub fn request_frame(&mut self) {
+        self.next_frame = true;
+    }
+
+    // keeps drawing frames for `duration`, for things that run on their own
+    // without being touched every frame, like a blinking caret
+    pub fn animate_for(&mut self, duration: Duration) {
+        let until = Instant::now() + duration;
+        self.until = Some(self.until.map_or(until, |at| at.max(until)));
+    }
+
+    pub fn is_active(&self, now: Instant) -> bool {
+        self.next_frame || self.until.is_some_and(|until| until > now)
+    }
+
+    // whether another frame is wanted, forgetting the requests of this one
+    pub(crate) fn take_active(&mut self, now: Instant) -> bool {
+        let active = self.is_active(now);
+        self.next_frame = false;
+        if self.until.is_some_and(|until| until <= now) {
+            self.until = None;
+        }
+        active
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    // cubic, slow at the start
+    In,
+    // cubic, slow at the end
+    Out,
+    InOut,
+}
+
+impl Easing {
+    // `t` from 0 to 1 eased, clamped to that range
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::In => t * t * t,
+            Easing::Out => 1.0 - (1.0 - t).powi(3),
+            Easing::InOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::InOut => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+        }
+    }
+}
+
+// A value moving from one number to another over `duration` seconds. Advance
+// it with `Frame::tween`, which keeps frames coming until it's done, or with
+// `update` and the app's own bookkeeping.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tween {
+    pub from: f32,
+    pub to: f32,
+    // seconds
+    pub duration: f32,
+    pub easing: Easing,
+    elapsed: f32,
+}
+
+impl Tween {
+    pub fn new(from: f32, to: f32, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing: Easing::default(),
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    // moves `dt` seconds further, returns the value there
+    pub fn update(&mut self, dt: f32) -> f32 {
+        self.elapsed = (self.elapsed + dt).min(self.duration.max(0.0));
+        self.value()
+    }
+
+    pub fn value(&self) -> f32 {
+        let t = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+        self.from + (self.to - self.from) * self.easing.apply(t)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    // heads towards `to` from wherever it is now, taking the whole duration
+    // again, so it doesn't jump when the target changes mid animation
+    pub fn retarget(&mut self, to: f32) {
+        self.from = self.value();
+        self.to = to;
+        self.elapsed = 0.0;
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+
+use crate::profiler::FrameStats;
+use crate::state::RendererState;
+
+// anomalies kept, the oldest are dropped
+const KEPT: usize = 16;
+
+// What a frame may cost before it's reported, see
+// `Renderer::set_anomaly_budget`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnomalyBudget {
+    // seconds from the start of the frame to the start of the next one
+    pub frame_time: f32,
+    // bytes the batches may upload in a frame
+    pub upload_bytes: usize,
+    // keeps the batches of a frame over budget, see `Anomaly::capture`
+    pub capture: bool,
+}
+
+impl Default for AnomalyBudget {
+    // two frames at 60 fps and 4 MiB
+    fn default() -> Self {
+        Self {
+            frame_time: 2.0 / 60.0,
+            upload_bytes: 4 << 20,
+            capture: false,
+        }
+    }
+}
+
+// A frame over budget.
+#[derive(Debug, Clone)]
+pub struct Anomaly {
+    pub frame: u64,
+    // what the frame cost, `stats.dt` its time and `stats.batches.uploaded`
+    // its uploads
+    pub stats: FrameStats,
+    // seconds its uploads took on the cpu
+    pub upload_time: f32,
+    pub over_time: bool,
+    pub over_upload: bool,
+    // With `AnomalyBudget::capture`, the renderer's state with the quads and
+    // text of the frame, which `Renderer::from_state` draws again to look at
+    // after the fact. The frame's sprites and particles aren't part of it.
+    pub capture: Option<RendererState>,
+}
+
+// Checks every finished frame against a budget and keeps the last few that
+// weren't within it, logged as warnings and noted for the crash report.
+pub(crate) struct AnomalyLog {
+    pub budget: AnomalyBudget,
+    pub recent: VecDeque<Anomaly>,
+}
+
+impl AnomalyLog {
+    pub fn new(budget: AnomalyBudget) -> Self {
+        Self {
+            budget,
+            recent: VecDeque::new(),
+        }
+    }
+
+    // Whether a frame with `stats` is over budget, keeping it when it is.
+    // `capture` is only called for frames that are.
+    pub fn check(&mut self, frame: u64, stats: FrameStats, upload_time: f32, capture: impl FnOnce() -> RendererState) {
+        let over_time = stats.dt > self.budget.frame_time;
+        let over_upload = stats.batches.uploaded > self.budget.upload_bytes;
+        if !over_time && !over_upload {
+            return;
+        }
+        let message = format!(
+            "frame {frame} over budget: {:.2} ms (budget {:.2}), uploaded {} KiB (budget {}) in {:.2} ms",
+            stats.dt * 1000.0,
+            self.budget.frame_time * 1000.0,
+            stats.batches.uploaded >> 10,
+            self.budget.upload_bytes >> 10,
+            upload_time * 1000.0,
+        );
+        log::warn!("{message}");
+        crate::crash::note(&message);
+        if self.recent.len() == KEPT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Anomaly {
+            frame,
+            stats,
+            upload_time,
+            over_time,
+            over_upload,
+            capture: self.budget.capture.then(capture),
+        });
+    }
+}
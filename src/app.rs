@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::keyboard::KeyCode;
+use winit::window::{Window, WindowAttributes, WindowId};
+
+use crate::Renderer;
+use crate::camera_controls::CameraControls;
+use crate::context::GraphicsContext;
+use crate::frame::Frame;
+use crate::input::{self, InputState};
+
+// The callbacks of a program run by `run`. `setup` is called once the window
+// and renderer exist, the other two once per redraw, `update` first, with the
+// seconds since the last frame and the input that arrived in between.
+//
+// Tools that need more windows, like an editor with palettes, return them from
+// `windows`. Each gets a renderer of its own on the same gpu, `setup` and
+// `draw` are called for all of them, `frame.window_id()` tells which one it
+// is, and `update_window` takes the place of `update` for all but the main one.
+pub trait App {
+    // opened after the main window
+    fn windows(&mut self) -> Vec<WindowAttributes> {
+        vec![]
+    }
+
+    // whether windows redraw every frame or only when something happens
+    fn redraw_mode(&self) -> RedrawMode {
+        RedrawMode::Continuous
+    }
+
+    // how often windows redraw while they're unfocused or hidden
+    fn background(&self) -> Background {
+        Background::MaxFps(10.0)
+    }
+
+    // built in camera navigation, applied between `update` and `draw`, kept
+    // by the app since it animates zooming
+    fn camera_controls(&mut self) -> Option<&mut CameraControls> {
+        None
+    }
+
+    // toggles the window it's pressed in between windowed and fullscreen, see
+    // `Renderer::toggle_fullscreen`, `None` leaves the key to the app
+    fn fullscreen_key(&self) -> Option<KeyCode> {
+        Some(input::FULLSCREEN_KEY)
+    }
+
+    fn setup(&mut self, _renderer: &mut Renderer) {}
+
+    // The app went into the background on mobile, or lost its surfaces on
+    // wayland. The windows stop redrawing until `resumed`, their renderers
+    // keep everything but their surfaces.
+    fn suspended(&mut self) {}
+
+    fn resumed(&mut self) {}
+
+    fn update(&mut self, _dt: f32, _input: &InputState) {}
+
+    fn update_window(&mut self, _window: WindowId, _input: &InputState) {}
+
+    fn draw(&mut self, frame: &mut Frame);
+}
+
+// When windows redraw, see `App::redraw_mode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedrawMode {
+    // every frame while in the foreground, for games
+    #[default]
+    Continuous,
+    // Only on input, resizes and other window events, `Frame::request_redraw`,
+    // and while `Renderer::animations` are running, so editors and tools idle
+    // without using any cpu or gpu. `Background` doesn't apply.
+    Reactive,
+}
+
+// What a window does while it's unfocused or occluded (minimized, covered,
+// on another virtual desktop), so tools left open don't keep the gpu busy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Background {
+    // redraws as often as in the foreground
+    Continue,
+    // redraws at most this many times per second
+    MaxFps(f32),
+    // doesn't redraw, so `update` isn't called either, until it's back
+    Pause,
+}
+
+// Opens a window and drives `app` until it's closed, redrawing continuously
+// while it's in the foreground, see `App::redraw_mode` and `App::background`
+// for the rest.
+pub fn run(app: impl App) -> Result<(), winit::error::EventLoopError> {
+    run_with(Window::default_attributes(), app)
+}
+
+pub fn run_with(
+    attributes: WindowAttributes,
+    app: impl App,
+) -> Result<(), winit::error::EventLoopError> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Poll);
+    event_loop.run_app(&mut Runner {
+        app,
+        attributes,
+        context: None,
+        main: None,
+        windows: HashMap::new(),
+    })
+}
+
+struct Runner<A> {
+    app: A,
+    attributes: WindowAttributes,
+    context: Option<GraphicsContext>,
+    main: Option<WindowId>,
+    windows: HashMap<WindowId, WindowState>,
+}
+
+struct WindowState {
+    renderer: Renderer,
+    input: InputState,
+    focused: bool,
+    occluded: bool,
+    // when a throttled window redraws next
+    next_redraw: Option<Instant>,
+}
+
+impl WindowState {
+    fn in_background(&self) -> bool {
+        !self.focused || self.occluded
+    }
+}
+
+impl<A: App> Runner<A> {
+    fn open(&mut self, event_loop: &ActiveEventLoop, context: &GraphicsContext, attributes: WindowAttributes) -> WindowId {
+        let window = Arc::new(event_loop.create_window(attributes).unwrap());
+        let mut renderer = Renderer::with_context(context, window.clone());
+        self.app.setup(&mut renderer);
+        self.windows.insert(
+            window.id(),
+            WindowState {
+                renderer,
+                input: InputState::new(),
+                focused: true,
+                occluded: false,
+                next_redraw: None,
+            },
+        );
+        window.request_redraw();
+        window.id()
+    }
+
+    fn redraw(&mut self, id: WindowId) {
+        let Some(window) = self.windows.get_mut(&id) else {
+            return;
+        };
+        if window.renderer.is_suspended() {
+            return;
+        }
+        if self.app.fullscreen_key().is_some_and(|key| window.input.was_key_pressed(key)) {
+            window.renderer.toggle_fullscreen();
+        }
+        let mut frame = window.renderer.begin_frame();
+        if self.main == Some(id) {
+            self.app.update(frame.clock().dt(), &window.input);
+        } else {
+            self.app.update_window(id, &window.input);
+        }
+        if let Some(controls) = self.app.camera_controls() {
+            controls.apply(&window.input, &mut frame);
+        }
+        self.app.draw(&mut frame);
+        frame.submit();
+        window.input.end_frame();
+        window.next_redraw = None;
+        let animating = window.renderer.animations.take_active(Instant::now());
+        if self.app.redraw_mode() == RedrawMode::Reactive {
+            if animating {
+                window.renderer.get_window().request_redraw();
+            }
+            return;
+        }
+        match self.app.background() {
+            _ if !window.in_background() => window.renderer.get_window().request_redraw(),
+            Background::Continue => window.renderer.get_window().request_redraw(),
+            Background::MaxFps(fps) => {
+                window.next_redraw = Some(Instant::now() + Duration::from_secs_f32(1.0 / fps.max(0.01)));
+            }
+            Background::Pause => {}
+        }
+    }
+}
+
+impl<A: App> ApplicationHandler for Runner<A> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // back from `suspended`, only the surfaces are gone
+        if self.context.is_some() {
+            for window in self.windows.values_mut() {
+                window.renderer.resume();
+                window.renderer.get_window().request_redraw();
+            }
+            self.app.resumed();
+            return;
+        }
+        let context = pollster::block_on(GraphicsContext::new());
+        self.main = Some(self.open(event_loop, &context, self.attributes.clone()));
+        for attributes in self.app.windows() {
+            self.open(event_loop, &context, attributes);
+        }
+        self.context = Some(context);
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        for window in self.windows.values_mut() {
+            window.renderer.suspend();
+        }
+        self.app.suspended();
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
+        let Some(window) = self.windows.get_mut(&window_id) else {
+            return;
+        };
+        // what egui consumed isn't input to the app, but a button or key
+        // let go of over it still is, so none stays held down
+        #[cfg(feature = "egui")]
+        let consumed = window.renderer.egui_event(&event) && !is_release(&event);
+        #[cfg(not(feature = "egui"))]
+        let consumed = false;
+        if !consumed {
+            window.input.handle_event(&event);
+        }
+        let wakes = self.app.redraw_mode() == RedrawMode::Reactive
+            && !matches!(event, WindowEvent::RedrawRequested | WindowEvent::CloseRequested | WindowEvent::Destroyed);
+        if wakes {
+            window.renderer.get_window().request_redraw();
+        }
+        match event {
+            // closing the main window ends the program, any other only closes
+            WindowEvent::CloseRequested if self.main == Some(window_id) => event_loop.exit(),
+            WindowEvent::CloseRequested => {
+                self.windows.remove(&window_id);
+            }
+            WindowEvent::Resized(size) => window.renderer.resize(size),
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                window.renderer.set_scale_factor(scale_factor)
+            }
+            WindowEvent::Focused(focused) => {
+                window.focused = focused;
+                window.renderer.get_window().request_redraw();
+            }
+            WindowEvent::Occluded(occluded) => {
+                window.occluded = occluded;
+                window.renderer.get_window().request_redraw();
+            }
+            WindowEvent::RedrawRequested => self.redraw(window_id),
+            _ => {}
+        }
+    }
+
+    // Redraws the throttled windows that are due and sleeps until the next
+    // one is while no window redraws continuously.
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let now = Instant::now();
+        let mut wake = None;
+        for window in self.windows.values_mut() {
+            match window.next_redraw {
+                Some(at) if at <= now => {
+                    window.next_redraw = None;
+                    window.renderer.get_window().request_redraw();
+                }
+                Some(at) => wake = Some(wake.map_or(at, |wake: Instant| wake.min(at))),
+                None => {}
+            }
+        }
+        let continuous = self.app.redraw_mode() == RedrawMode::Continuous
+            && self
+                .windows
+                .values()
+                .any(|w| !w.renderer.is_suspended() && (!w.in_background() || self.app.background() == Background::Continue));
+        event_loop.set_control_flow(match wake {
+            _ if continuous => ControlFlow::Poll,
+            Some(at) => ControlFlow::WaitUntil(at),
+            None => ControlFlow::Wait,
+        });
+    }
+}
+
+#[cfg(feature = "egui")]
+fn is_release(event: &WindowEvent) -> bool {
+    use winit::event::ElementState;
+    match event {
+        WindowEvent::KeyboardInput { event, .. } => event.state == ElementState::Released,
+        WindowEvent::MouseInput { state, .. } => *state == ElementState::Released,
+        _ => false,
+    }
+}
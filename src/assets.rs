@@ -0,0 +1,191 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::font::{AtlasKind, BakedGlyphs, GlyphAtlas, GlyphInfo, bake_glyphs};
+use crate::sprite::FlipBook;
+
+const MANIFEST: &str = "manifest.json";
+
+// What an `AssetBuilder` wrote, paths relative to the manifest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetManifest {
+    // the json `FlipBook::save` wrote for each
+    #[serde(default)]
+    pub flipbooks: BTreeMap<String, PathBuf>,
+    #[serde(default)]
+    pub glyph_sets: BTreeMap<String, GlyphSet>,
+}
+
+// A `GlyphAtlas` baked by `AssetBuilder::glyph_set`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlyphSet {
+    // a copy of the font, still needed for kerning and glyphs added at runtime
+    pub font: PathBuf,
+    pub scale: f32,
+    pub kind: AtlasKind,
+    // one png per layer of the atlas
+    pub pages: Vec<PathBuf>,
+    pub used_heights: Vec<u32>,
+    pub glyphs: BTreeMap<char, GlyphInfo>,
+}
+
+// Bakes sprite sheets and glyph atlases ahead of time into `out_dir` with a
+// manifest `Assets::load` reads back without rasterizing or packing anything,
+// e.g. from a build script:
+//
+//   let mut assets = AssetBuilder::new("assets/baked");
+//   assets.flipbook("walk", "art/walk", 12.0)?;
+//   assets.glyph_set("ui", "fonts/inter.ttf", 32.0, AtlasKind::Sdf, "äöü")?;
+//   assets.rerun_if_changed();
+//   assets.write()?;
+pub struct AssetBuilder {
+    out_dir: PathBuf,
+    manifest: AssetManifest,
+    inputs: Vec<PathBuf>,
+}
+
+impl AssetBuilder {
+    pub fn new(out_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            out_dir: out_dir.into(),
+            manifest: AssetManifest::default(),
+            inputs: vec![],
+        }
+    }
+
+    // the numbered pngs in `frames_dir` as a sheet, see `FlipBook::bake_dir`
+    pub fn flipbook(&mut self, name: &str, frames_dir: impl AsRef<Path>, fps: f32) -> image::ImageResult<&mut Self> {
+        let frames_dir = frames_dir.as_ref();
+        let mut book = FlipBook::bake_dir(frames_dir, fps)?;
+        std::fs::create_dir_all(&self.out_dir)?;
+        let png = PathBuf::from(format!("{name}.png"));
+        book.save(self.out_dir.join(&png))?;
+        self.manifest.flipbooks.insert(name.to_string(), png.with_extension("json"));
+        self.inputs.push(frames_dir.to_owned());
+        Ok(self)
+    }
+
+    // The glyphs of `create_glyph_atlas` or `create_sdf_atlas` at `scale`
+    // from the font at `font`, and the chars of `extra` with them.
+    pub fn glyph_set(
+        &mut self,
+        name: &str,
+        font: impl AsRef<Path>,
+        scale: f32,
+        kind: AtlasKind,
+        extra: &str,
+    ) -> image::ImageResult<&mut Self> {
+        let font = font.as_ref();
+        let data = std::fs::read(font)?;
+        let baked = bake_glyphs(&data, scale, kind, extra).map_err(|err| invalid_data(format!("{}: {err}", font.display())))?;
+        std::fs::create_dir_all(&self.out_dir)?;
+        let font_copy = PathBuf::from(name).with_extension(font.extension().unwrap_or("ttf".as_ref()));
+        std::fs::write(self.out_dir.join(&font_copy), &data)?;
+        let mut pages = vec![];
+        for (i, layer) in baked.layers.iter().enumerate() {
+            let page = PathBuf::from(format!("{name}-{i}.png"));
+            layer.save(self.out_dir.join(&page))?;
+            pages.push(page);
+        }
+        self.manifest.glyph_sets.insert(
+            name.to_string(),
+            GlyphSet {
+                font: font_copy,
+                scale,
+                kind,
+                pages,
+                used_heights: baked.used_heights,
+                glyphs: baked.glyphs.into_iter().collect(),
+            },
+        );
+        self.inputs.push(font.to_owned());
+        Ok(self)
+    }
+
+    // the files and directories read so far
+    pub fn inputs(&self) -> &[PathBuf] {
+        &self.inputs
+    }
+
+    // tells cargo to run the build script again when an input changes
+    pub fn rerun_if_changed(&self) {
+        for input in &self.inputs {
+            println!("cargo:rerun-if-changed={}", input.display());
+        }
+    }
+
+    // writes the manifest, returns its path for `Assets::load`
+    pub fn write(&self) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.out_dir)?;
+        let path = self.out_dir.join(MANIFEST);
+        let json = serde_json::to_string_pretty(&self.manifest).map_err(std::io::Error::other)?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+// The assets of a manifest an `AssetBuilder` wrote, by name.
+pub struct Assets {
+    flipbooks: HashMap<String, FlipBook>,
+    glyph_sets: HashMap<String, GlyphAtlas>,
+}
+
+impl Assets {
+    // Loads everything `manifest` lists, the glyph atlases uploaded as they
+    // were baked.
+    pub fn load(device: &wgpu::Device, queue: &wgpu::Queue, manifest: impl AsRef<Path>) -> image::ImageResult<Self> {
+        let manifest = manifest.as_ref();
+        let dir = manifest.parent().unwrap_or(Path::new(""));
+        let parsed: AssetManifest =
+            serde_json::from_str(&std::fs::read_to_string(manifest)?).map_err(|err| invalid_data(err.to_string()))?;
+
+        let mut flipbooks = HashMap::new();
+        for (name, json) in parsed.flipbooks {
+            flipbooks.insert(name, FlipBook::load(dir.join(json))?);
+        }
+        let mut glyph_sets = HashMap::new();
+        for (name, set) in parsed.glyph_sets {
+            let layers = set
+                .pages
+                .iter()
+                .map(|page| Ok(image::open(dir.join(page))?.to_rgba8()))
+                .collect::<image::ImageResult<Vec<_>>>()?;
+            let same_size = layers.windows(2).all(|pair| pair[0].dimensions() == pair[1].dimensions());
+            if layers.is_empty() || !same_size || layers.len() != set.used_heights.len() {
+                return Err(invalid_data(format!("glyph set {name} has broken pages")));
+            }
+            let baked = BakedGlyphs {
+                layers,
+                glyphs: set.glyphs.into_iter().collect(),
+                used_heights: set.used_heights,
+                kind: set.kind,
+                scale: set.scale,
+            };
+            let data = std::fs::read(dir.join(&set.font))?;
+            let atlas = GlyphAtlas::from_baked(device, queue, &data, &baked)
+                .map_err(|err| invalid_data(format!("glyph set {name}: {err}")))?;
+            glyph_sets.insert(name, atlas);
+        }
+        Ok(Self { flipbooks, glyph_sets })
+    }
+
+    pub fn flipbook(&self, name: &str) -> Option<&FlipBook> {
+        self.flipbooks.get(name)
+    }
+
+    pub fn glyph_set(&self, name: &str) -> Option<&GlyphAtlas> {
+        self.glyph_sets.get(name)
+    }
+
+    // for handing the atlas to a `FontRenderer` or `warm_cache`, which want it
+    // owned or mutable
+    pub fn take_glyph_set(&mut self, name: &str) -> Option<GlyphAtlas> {
+        self.glyph_sets.remove(name)
+    }
+}
+
+fn invalid_data(message: String) -> image::ImageError {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message).into()
+}
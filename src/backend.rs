@@ -0,0 +1,79 @@
+use crate::color::Color;
+use crate::font::{GlyphSource, MissingGlyphs, layout};
+use crate::frame::Frame;
+use crate::rect::{ClipShape, Rect};
+use crate::sort::SortKey;
+
+// The drawing operations everything above the renderer (draw lists, replays,
+// ...) is written against, so it runs on any backend. A wgpu `Frame` is the
+// gpu implementation, starting and presenting frames is up to each backend.
+pub trait RenderBackend {
+    fn size(&self) -> winit::dpi::PhysicalSize<u32>;
+
+    // applies to everything pushed afterwards, `None` turns clipping off
+    fn set_clip(&mut self, clip: Option<Rect>);
+
+    // the clip set last, for putting it back with `set_clip_shape`
+    fn clip(&self) -> Option<ClipShape>;
+
+    // like `set_clip`, backends that only clip to rects clip to the shape's bounds
+    fn set_clip_shape(&mut self, clip: Option<ClipShape>);
+
+    fn push_quad(&mut self, key: SortKey, rect: Rect, color: impl Into<Color>);
+
+    // a single line of text at `size` pixels, or the backend font's own size
+    fn push_str(
+        &mut self,
+        key: SortKey,
+        x: f32,
+        y: f32,
+        size: Option<f32>,
+        color: impl Into<Color>,
+        text: &str,
+    ) -> Result<(), MissingGlyphs>;
+
+    // width and line height of `push_str` with the same `size` and `text`
+    fn measure_str(&self, size: Option<f32>, text: &str) -> (f32, f32);
+}
+
+impl RenderBackend for Frame<'_> {
+    fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        Frame::size(self)
+    }
+
+    fn set_clip(&mut self, clip: Option<Rect>) {
+        Frame::set_clip(self, clip);
+    }
+
+    fn clip(&self) -> Option<ClipShape> {
+        Frame::clip_shape(self)
+    }
+
+    fn set_clip_shape(&mut self, clip: Option<ClipShape>) {
+        Frame::set_clip_shape(self, clip);
+    }
+
+    fn push_quad(&mut self, key: SortKey, rect: Rect, color: impl Into<Color>) {
+        self.quad_with_key(key, rect.x, rect.y, rect.w, rect.h, color);
+    }
+
+    fn push_str(
+        &mut self,
+        key: SortKey,
+        x: f32,
+        y: f32,
+        size: Option<f32>,
+        color: impl Into<Color>,
+        text: &str,
+    ) -> Result<(), MissingGlyphs> {
+        let size = size.unwrap_or(self.font_atlas().font_size());
+        self.text_with_key(key, x, y, size, color, text)
+    }
+
+    fn measure_str(&self, size: Option<f32>, text: &str) -> (f32, f32) {
+        let renderer = &self.renderer;
+        let scale = size.map_or(1.0, |size| size / renderer.font_atlas.font_size());
+        let width = layout::line_width(text, renderer.font_renderer.tab_stops(), &renderer.font_atlas);
+        (width * scale, renderer.font_atlas.line_height() * scale)
+    }
+}
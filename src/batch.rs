@@ -0,0 +1,413 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
+
+use crate::buffer::GrowableBuffer;
+use crate::sort::{self, SortKey};
+use crate::state::BatchSnapshot;
+
+// What the batches hold this frame and how often their gpu buffers had to
+// grow so far, for profiling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchStats {
+    pub vertices: usize,
+    pub indices: usize,
+    pub reallocations: u32,
+    // bytes the last `prepare` wrote to the gpu
+    pub uploaded: usize,
+}
+
+impl std::ops::AddAssign for BatchStats {
+    fn add_assign(&mut self, other: Self) {
+        self.vertices += other.vertices;
+        self.indices += other.indices;
+        self.reallocations += other.reallocations;
+        self.uploaded += other.uploaded;
+    }
+}
+
+// The quads one renderer draws in a frame, the unit `QuadRenderer` and
+// `FontRenderer` are built on and the one to build a custom primitive renderer
+// on, see `examples/hexagons.rs`. Every primitive is a quad of 4 vertices `V`
+// with a `SortKey`; its shape is up to the renderer's shader, which can e.g.
+// discard whatever lies outside of a hexagon.
+//
+// Per frame: `clear`, `push` the quads, `prepare` to sort and upload them,
+// then in the render pass `set_buffers` and draw the index ranges of `runs`,
+// binding whatever the texture id of each run stands for.
+//
+// Layers made static with `set_static_layer` keep their quads and gpu buffers
+// of their own across frames, for backgrounds and HUDs that rarely change;
+// draw them with `static_runs` and `set_run_buffers`.
+pub struct Batch<V> {
+    vertices: Vec<V>,
+    // u32, a frame's quads easily pass the 16384 u16 indices can address
+    indices: Vec<u32>,
+    keys: Vec<SortKey>,
+    vbo: GrowableBuffer,
+    ibo: GrowableBuffer,
+    label: &'static str,
+    statics: BTreeMap<u16, StaticLayer<V>>,
+    // what's in the buffers, a frame pushing the same quads as the one before
+    // doesn't upload them again
+    uploaded_vertices: Vec<V>,
+    uploaded_indices: Vec<u32>,
+    // bytes the last `prepare` wrote
+    uploaded: usize,
+}
+
+// The quads of a static layer, replaced by the first push to it after a
+// `prepare`.
+struct StaticLayer<V> {
+    vertices: Vec<V>,
+    indices: Vec<u32>,
+    keys: Vec<SortKey>,
+    vbo: GrowableBuffer,
+    ibo: GrowableBuffer,
+    // of what was uploaded last, the same quads aren't uploaded again
+    hash: Option<u64>,
+    // pushed to since the last `prepare`
+    rebuilding: bool,
+}
+
+impl<V> StaticLayer<V> {
+    fn rebuild(&mut self) {
+        if !self.rebuilding {
+            self.rebuilding = true;
+            self.keys.clear();
+            self.indices.clear();
+            self.vertices.clear();
+        }
+    }
+
+    fn uploaded(&self) -> u32 {
+        (self.ibo.len() / std::mem::size_of::<u32>() as wgpu::BufferAddress) as u32
+    }
+}
+
+fn push_quad<V: Copy>(keys: &mut Vec<SortKey>, vertices: &mut Vec<V>, indices: &mut Vec<u32>, key: SortKey, quad: [V; 4]) {
+    keys.push(key);
+    let start = vertices.len() as u32;
+    vertices.extend_from_slice(&quad);
+    indices.extend_from_slice(&[start, start + 1, start + 2, start, start + 2, start + 3]);
+}
+
+impl<V: bytemuck::Pod> Batch<V> {
+    // `initial_quads` sizes the gpu buffers, they grow as needed
+    pub fn new(device: &wgpu::Device, label: &'static str, initial_quads: wgpu::BufferAddress) -> Self {
+        Self {
+            vertices: vec![],
+            indices: vec![],
+            keys: vec![],
+            vbo: GrowableBuffer::new(
+                device,
+                label,
+                wgpu::BufferUsages::VERTEX,
+                initial_quads * 4 * std::mem::size_of::<V>() as wgpu::BufferAddress,
+            ),
+            ibo: GrowableBuffer::new(
+                device,
+                label,
+                wgpu::BufferUsages::INDEX,
+                initial_quads * 6 * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            ),
+            label,
+            statics: BTreeMap::new(),
+            uploaded_vertices: vec![],
+            uploaded_indices: vec![],
+            uploaded: 0,
+        }
+    }
+
+    // `quad` goes clockwise on screen from the top left corner, drawn as the
+    // triangles 0 1 2 and 0 2 3
+    pub fn push(&mut self, key: SortKey, quad: [V; 4]) {
+        match self.statics.get_mut(&key.layer()) {
+            Some(layer) => {
+                layer.rebuild();
+                push_quad(&mut layer.keys, &mut layer.vertices, &mut layer.indices, key, quad);
+            }
+            None => push_quad(&mut self.keys, &mut self.vertices, &mut self.indices, key, quad),
+        }
+    }
+
+    // Sorts the quads by key and uploads them. Call once per frame, after the
+    // last push and before the render pass starts. Quads that came out the
+    // same as the last frame's aren't uploaded again, static layers only when
+    // they were pushed to and came out different.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        sort::sort_quads(&mut self.keys, &mut self.vertices, &mut self.indices);
+        self.uploaded = 0;
+        let unchanged = self.indices == self.uploaded_indices
+            && bytemuck::cast_slice::<V, u8>(&self.vertices) == bytemuck::cast_slice::<V, u8>(&self.uploaded_vertices);
+        if !unchanged {
+            self.vbo.write(device, queue, bytemuck::cast_slice(&self.vertices));
+            self.ibo.write(device, queue, bytemuck::cast_slice(&self.indices));
+            self.uploaded = std::mem::size_of_val(self.vertices.as_slice()) + std::mem::size_of_val(self.indices.as_slice());
+            self.uploaded_vertices.clone_from(&self.vertices);
+            self.uploaded_indices.clone_from(&self.indices);
+        }
+        // restored layers haven't been uploaded yet either
+        for layer in self.statics.values_mut().filter(|layer| layer.rebuilding || layer.hash.is_none()) {
+            layer.rebuilding = false;
+            sort::sort_quads(&mut layer.keys, &mut layer.vertices, &mut layer.indices);
+            let mut hasher = DefaultHasher::new();
+            bytemuck::cast_slice::<V, u8>(&layer.vertices).hash(&mut hasher);
+            layer.keys.hash(&mut hasher);
+            let hash = hasher.finish();
+            if layer.hash != Some(hash) {
+                layer.hash = Some(hash);
+                layer.vbo.write(device, queue, bytemuck::cast_slice(&layer.vertices));
+                layer.ibo.write(device, queue, bytemuck::cast_slice(&layer.indices));
+                self.uploaded +=
+                    std::mem::size_of_val(layer.vertices.as_slice()) + std::mem::size_of_val(layer.indices.as_slice());
+            }
+        }
+    }
+
+    // Keeps the quads pushed to `layer` across frames instead of dropping
+    // them in `clear`, see `rebuild_static_layer`. Quads pushed to it before
+    // this frame's `prepare` stay with the others for this frame, turning it
+    // off drops what it kept.
+    pub fn set_static_layer(&mut self, device: &wgpu::Device, layer: u16, enabled: bool) {
+        if !enabled {
+            self.statics.remove(&layer);
+            return;
+        }
+        let label = self.label;
+        self.statics.entry(layer).or_insert_with(|| StaticLayer {
+            vertices: vec![],
+            indices: vec![],
+            keys: vec![],
+            vbo: GrowableBuffer::new(device, label, wgpu::BufferUsages::VERTEX, 4 * std::mem::size_of::<V>() as wgpu::BufferAddress),
+            ibo: GrowableBuffer::new(device, label, wgpu::BufferUsages::INDEX, 6 * std::mem::size_of::<u32>() as wgpu::BufferAddress),
+            hash: None,
+            rebuilding: false,
+        });
+    }
+
+    pub fn is_static_layer(&self, layer: u16) -> bool {
+        self.statics.contains_key(&layer)
+    }
+
+    // Drops the quads a static layer kept, as its first push would: whatever
+    // is pushed to it until the next `prepare` replaces them.
+    pub fn rebuild_static_layer(&mut self, layer: u16) {
+        if let Some(layer) = self.statics.get_mut(&layer) {
+            layer.rebuild();
+        }
+    }
+
+    // the static layers being replaced at the next `prepare`
+    pub fn rebuilt_static_layers(&self) -> Vec<u16> {
+        self.statics
+            .iter()
+            .filter(|(_, layer)| layer.rebuilding)
+            .map(|(layer, _)| *layer)
+            .collect()
+    }
+
+    // the texture ids the static layers draw with, which have to stand for
+    // the same texture as long as they're kept
+    pub fn static_textures(&self) -> HashSet<u16> {
+        self.statics
+            .values()
+            .flat_map(|layer| layer.keys.iter().map(|key| key.texture()))
+            .collect()
+    }
+
+    // whether there's anything to draw since the last `prepare`
+    pub fn is_drawable(&self) -> bool {
+        (!self.vertices.is_empty() && !self.ibo.is_empty()) || self.statics.values().any(|layer| !layer.ibo.is_empty())
+    }
+
+    // binds the vertex buffer to slot 0 and the index buffer, only valid after
+    // a `prepare` that uploaded something
+    pub fn set_buffers(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_vertex_buffer(0, self.vbo.slice());
+        render_pass.set_index_buffer(self.ibo.slice(), wgpu::IndexFormat::Uint32);
+    }
+
+    // The texture id and index range of every run of consecutive quads with
+    // the same texture whose layer passes `draw_layer`, out of what the last
+    // `prepare` uploaded.
+    pub fn runs(&self, draw_layer: impl Fn(u16) -> bool) -> Vec<(u16, Range<u32>)> {
+        let uploaded = (self.ibo.len() / std::mem::size_of::<u32>() as wgpu::BufferAddress) as u32;
+        sort::layer_runs(&self.keys, uploaded, draw_layer)
+    }
+
+    // `runs` also split where the camera view of their layer changes, the
+    // view first, see `Camera::layer_view`
+    pub fn view_runs(
+        &self,
+        draw_layer: impl Fn(u16) -> bool,
+        view: impl Fn(u16) -> Option<u16>,
+    ) -> Vec<(Option<u16>, u16, Range<u32>)> {
+        let uploaded = (self.ibo.len() / std::mem::size_of::<u32>() as wgpu::BufferAddress) as u32;
+        sort::view_runs(&self.keys, uploaded, draw_layer, view)
+    }
+
+    // `view_runs` with the runs of the static layers merged in by layer, each
+    // with the static layer whose buffers it indexes into first, None for the
+    // frame's quads, see `set_run_buffers`
+    #[allow(clippy::type_complexity)]
+    pub fn static_runs(
+        &self,
+        draw_layer: impl Fn(u16) -> bool,
+        view: impl Fn(u16) -> Option<u16>,
+    ) -> Vec<(Option<u16>, Option<u16>, u16, Range<u32>)> {
+        let uploaded = (self.ibo.len() / std::mem::size_of::<u32>() as wgpu::BufferAddress) as u32;
+        let mut runs = vec![];
+        // the frame's quads from `below` up to the next static layer
+        let mut below = 0;
+        for (&layer, kept) in &self.statics {
+            let between = |l: u16| (below..layer as u32).contains(&(l as u32)) && draw_layer(l);
+            let frame_runs = sort::view_runs(&self.keys, uploaded, between, &view);
+            runs.extend(frame_runs.into_iter().map(|(v, texture, range)| (None, v, texture, range)));
+            let kept_runs = sort::view_runs(&kept.keys, kept.uploaded(), &draw_layer, &view);
+            runs.extend(kept_runs.into_iter().map(|(v, texture, range)| (Some(layer), v, texture, range)));
+            below = layer as u32 + 1;
+        }
+        let frame_runs = sort::view_runs(&self.keys, uploaded, |l| l as u32 >= below && draw_layer(l), &view);
+        runs.extend(frame_runs.into_iter().map(|(v, texture, range)| (None, v, texture, range)));
+        runs
+    }
+
+    // `set_buffers` for the runs of `static_runs` out of `static_layer`
+    pub fn set_run_buffers(&self, render_pass: &mut wgpu::RenderPass, static_layer: Option<u16>) {
+        match static_layer.and_then(|layer| self.statics.get(&layer)) {
+            Some(layer) => {
+                render_pass.set_vertex_buffer(0, layer.vbo.slice());
+                render_pass.set_index_buffer(layer.ibo.slice(), wgpu::IndexFormat::Uint32);
+            }
+            None => self.set_buffers(render_pass),
+        }
+    }
+
+    pub fn has_layer(&self, pred: impl Fn(u16) -> bool) -> bool {
+        self.keys.iter().any(|key| pred(key.layer()))
+            || self.statics.values().flat_map(|layer| &layer.keys).any(|key| pred(key.layer()))
+    }
+
+    // the layers passing `pred` with something on them, static ones included
+    pub fn layers(&self, pred: impl Fn(u16) -> bool) -> BTreeSet<u16> {
+        let kept = self.statics.values().flat_map(|layer| &layer.keys);
+        self.keys.iter().chain(kept).map(|key| key.layer()).filter(|&layer| pred(layer)).collect()
+    }
+
+    pub fn keys(&self) -> &[SortKey] {
+        &self.keys
+    }
+
+    pub fn vertices(&self) -> &[V] {
+        &self.vertices
+    }
+
+    pub(crate) fn vertices_mut(&mut self) -> &mut [V] {
+        &mut self.vertices
+    }
+
+    pub(crate) fn keys_mut(&mut self) -> &mut [SortKey] {
+        &mut self.keys
+    }
+
+    // number of quads
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn stats(&self) -> BatchStats {
+        let mut stats = BatchStats {
+            vertices: self.vertices.len(),
+            indices: self.indices.len(),
+            reallocations: self.vbo.reallocations() + self.ibo.reallocations(),
+            uploaded: self.uploaded,
+        };
+        for layer in self.statics.values() {
+            stats += BatchStats {
+                vertices: layer.vertices.len(),
+                indices: layer.indices.len(),
+                reallocations: layer.vbo.reallocations() + layer.ibo.reallocations(),
+                uploaded: 0,
+            };
+        }
+        stats
+    }
+
+    // turns the quads from `from` on by `angle` radians around `origin`,
+    // clockwise on screen like the camera's rotation
+    pub(crate) fn turn_since(&mut self, from: usize, origin: [f32; 2], angle: f32, pos: impl Fn(&mut V) -> &mut [f32; 3]) {
+        let (sin, cos) = angle.sin_cos();
+        for vertex in &mut self.vertices[from * 4..] {
+            let pos = pos(vertex);
+            let (dx, dy) = (pos[0] - origin[0], pos[1] - origin[1]);
+            pos[0] = origin[0] + dx * cos - dy * sin;
+            pos[1] = origin[1] + dx * sin + dy * cos;
+        }
+    }
+
+    // drops this frame's quads, the static layers keep theirs
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.indices.clear();
+        self.vertices.clear();
+    }
+
+    pub fn snapshot(&self) -> BatchSnapshot<V> {
+        BatchSnapshot {
+            vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+            keys: self.keys.clone(),
+        }
+    }
+
+    // Snapshots from before keys were saved get default ones, every key is
+    // passed through `key` so the renderer can fill in its texture ids.
+    pub fn restore(&mut self, snapshot: BatchSnapshot<V>, key: impl Fn(SortKey) -> SortKey) {
+        (self.vertices, self.indices, self.keys) = restored(snapshot, key);
+    }
+
+    // what each static layer kept, by layer
+    pub fn static_snapshots(&self) -> BTreeMap<u16, BatchSnapshot<V>> {
+        self.statics
+            .iter()
+            .map(|(&layer, kept)| {
+                let snapshot = BatchSnapshot {
+                    vertices: kept.vertices.clone(),
+                    indices: kept.indices.clone(),
+                    keys: kept.keys.clone(),
+                };
+                (layer, snapshot)
+            })
+            .collect()
+    }
+
+    // Makes `layer` static with the quads of `snapshot`, uploaded by the next
+    // `prepare` and replaced by the first push to it like any other. `key` is
+    // the one of `restore`.
+    pub fn restore_static_layer(
+        &mut self,
+        device: &wgpu::Device,
+        layer: u16,
+        snapshot: BatchSnapshot<V>,
+        key: impl Fn(SortKey) -> SortKey,
+    ) {
+        self.set_static_layer(device, layer, true);
+        let kept = self.statics.get_mut(&layer).unwrap();
+        (kept.vertices, kept.indices, kept.keys) = restored(snapshot, key);
+        kept.hash = None;
+    }
+}
+
+fn restored<V>(snapshot: BatchSnapshot<V>, key: impl Fn(SortKey) -> SortKey) -> (Vec<V>, Vec<u32>, Vec<SortKey>) {
+    let mut keys = snapshot.keys;
+    keys.resize(snapshot.vertices.len() / 4, SortKey::default());
+    for k in &mut keys {
+        *k = key(*k);
+    }
+    (snapshot.vertices, snapshot.indices, keys)
+}
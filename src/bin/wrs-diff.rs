@@ -0,0 +1,153 @@
+// Compares two captures, or a capture and its golden, for triaging visual
+// regressions. Opens a window showing them with a slider between the two,
+// Tab switches to flipping between them with Space and to the heatmap of
+// `ImageDiff`. With --heatmap it writes the heatmap instead and exits with 1
+// when the captures differ, for scripts.
+// Usage: wrs-diff <a.png> <b.png> [--threshold 0.1] [--heatmap out.png]
+use std::time::Duration;
+
+use winit::keyboard::KeyCode;
+use wrs::image_diff::ImageDiff;
+use wrs::rect::Rect;
+use wrs::sprite::SpriteTexture;
+use wrs::{App, Frame, InputState, Renderer};
+
+const USAGE: &str = "usage: wrs-diff <a.png> <b.png> [--threshold 0.1] [--heatmap out.png]";
+
+fn main() {
+    env_logger::init();
+    let mut paths = vec![];
+    let mut threshold = 0.1;
+    let mut heatmap = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threshold" => match args.next().and_then(|t| t.parse::<f32>().ok()) {
+                Some(t) if (0.0..=1.0).contains(&t) => threshold = t,
+                _ => exit(2, "the threshold has to be between 0 and 1"),
+            },
+            "--heatmap" => heatmap = Some(args.next().unwrap_or_else(|| exit(2, USAGE))),
+            _ => paths.push(arg),
+        }
+    }
+    let [a, b] = paths.as_slice() else {
+        exit(2, USAGE);
+    };
+    let open = |path: &String| match image::open(path) {
+        Ok(image) => image.to_rgba8(),
+        Err(err) => exit(1, &format!("{path}: {err}")),
+    };
+    let (a, b) = (open(a), open(b));
+    let diff = ImageDiff::new(&a, &b, threshold);
+    let summary = format!(
+        "{} pixels differ, max {:.3}, mean {:.4}",
+        diff.differing, diff.max, diff.mean
+    );
+    println!("{summary}");
+
+    if let Some(out) = heatmap {
+        if let Err(err) = diff.heatmap.save(&out) {
+            exit(1, &format!("{out}: {err}"));
+        }
+        std::process::exit(diff.differs() as i32);
+    }
+    let images = [a, b, diff.heatmap];
+    wrs::run(Viewer {
+        images,
+        textures: vec![],
+        summary,
+        mode: Mode::Slider,
+        showing_b: false,
+        slider: None,
+    })
+    .unwrap();
+}
+
+fn exit(code: i32, message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(code);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    // a left of the cursor, b right of it
+    Slider,
+    // a or b, Space swaps them
+    Flip,
+    Heatmap,
+}
+
+struct Viewer {
+    // a, b and the heatmap
+    images: [image::RgbaImage; 3],
+    textures: Vec<SpriteTexture>,
+    summary: String,
+    mode: Mode,
+    showing_b: bool,
+    // window x of the slider, the middle until the cursor moves
+    slider: Option<f32>,
+}
+
+impl App for Viewer {
+    fn setup(&mut self, renderer: &mut Renderer) {
+        self.textures = self
+            .images
+            .iter()
+            .map(|image| renderer.create_sprite_texture(image, wgpu::FilterMode::Nearest))
+            .collect();
+    }
+
+    fn update(&mut self, _dt: f32, input: &InputState) {
+        if input.was_key_pressed(KeyCode::Tab) {
+            self.mode = match self.mode {
+                Mode::Slider => Mode::Flip,
+                Mode::Flip => Mode::Heatmap,
+                Mode::Heatmap => Mode::Slider,
+            };
+        }
+        if input.was_key_pressed(KeyCode::Space) {
+            self.showing_b = !self.showing_b;
+        }
+        if let Some((x, _)) = input.mouse_position() {
+            self.slider = Some(x);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let size = frame.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+        // the larger capture fitted into the window under a line of text
+        let (iw, ih) = (self.textures[2].width as f32, self.textures[2].height as f32);
+        let scale = (width / iw).min((height - 24.0) / ih);
+        let fitted = |texture: &SpriteTexture| {
+            Rect::new(0.0, 24.0, texture.width as f32 * scale, texture.height as f32 * scale)
+        };
+
+        let label = match self.mode {
+            Mode::Slider => {
+                let slider = self.slider.unwrap_or(width / 2.0);
+                // the gap between the two shows the quad under them as the line
+                frame.quad(slider - 1.0, 24.0, 2.0, height, [1.0, 0.0, 1.0]);
+                frame.set_clip(Some(Rect::new(0.0, 0.0, slider - 1.0, height)));
+                frame.sprite(fitted(&self.textures[0]), &self.textures[0]);
+                frame.set_clip(Some(Rect::new(slider + 1.0, 0.0, width, height)));
+                frame.sprite(fitted(&self.textures[1]), &self.textures[1]);
+                frame.set_clip(None);
+                "a | b"
+            }
+            Mode::Flip => {
+                let texture = &self.textures[self.showing_b as usize];
+                frame.sprite(fitted(texture), texture);
+                if self.showing_b { "b" } else { "a" }
+            }
+            Mode::Heatmap => {
+                frame.sprite(fitted(&self.textures[2]), &self.textures[2]);
+                "heatmap"
+            }
+        };
+        let text = format!("{label}  {}  (tab: mode, space: flip)", self.summary);
+        if let Err(err) = frame.text(4.0, 4.0, 16.0, [1.0; 3], &text) {
+            frame.toast(format!("{err}"), Duration::from_secs(1));
+        }
+    }
+}
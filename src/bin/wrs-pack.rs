@@ -0,0 +1,42 @@
+// Bakes a directory of numbered pngs into a sprite sheet and its animation
+// metadata, see `FlipBook`.
+// Usage: wrs-pack <frames dir> <sheet.png> [fps], fps defaults to 12
+use wrs::sprite::FlipBook;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let (Some(dir), Some(out)) = (args.next(), args.next()) else {
+        eprintln!("usage: wrs-pack <frames dir> <sheet.png> [fps]");
+        std::process::exit(2);
+    };
+    let fps = match args.next().map(|fps| fps.parse::<f32>()) {
+        None => 12.0,
+        Some(Ok(fps)) if fps > 0.0 => fps,
+        Some(_) => {
+            eprintln!("fps has to be a positive number");
+            std::process::exit(2);
+        }
+    };
+
+    let mut book = match FlipBook::bake_dir(&dir, fps) {
+        Ok(book) if !book.is_empty() => book,
+        Ok(_) => {
+            eprintln!("no pngs in {dir}");
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("{dir}: {err}");
+            std::process::exit(1);
+        }
+    };
+    if let Err(err) = book.save(&out) {
+        eprintln!("{out}: {err}");
+        std::process::exit(1);
+    }
+    println!(
+        "{} frames into a {}x{} sheet",
+        book.len(),
+        book.sheet.width(),
+        book.sheet.height()
+    );
+}
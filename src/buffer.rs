@@ -0,0 +1,99 @@
+// A COPY_DST gpu buffer that grows by doubling, so a batch that keeps its size
+// (or shrinks) reuses the same allocation frame after frame. The logical length
+// of the last write is tracked separately from the capacity, and only that
+// range is ever bound.
+pub struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    len: wgpu::BufferAddress,
+    usage: wgpu::BufferUsages,
+    label: &'static str,
+    reallocations: u32,
+}
+
+impl GrowableBuffer {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        capacity: wgpu::BufferAddress,
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        Self {
+            buffer: Self::allocate(device, label, usage, capacity),
+            len: 0,
+            usage,
+            label,
+            reallocations: 0,
+        }
+    }
+
+    // returns whether the buffer had to be reallocated to fit `data`
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) -> bool {
+        let len = data.len() as wgpu::BufferAddress;
+        self.len = len;
+        if len == 0 {
+            return false;
+        }
+        let aligned = len.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+
+        let grown = aligned > self.capacity();
+        if grown {
+            let mut capacity = self.capacity().max(wgpu::COPY_BUFFER_ALIGNMENT);
+            while capacity < aligned {
+                capacity *= 2;
+            }
+            self.buffer.destroy();
+            self.buffer = Self::allocate(device, self.label, self.usage, capacity);
+            self.reallocations += 1;
+        }
+
+        if aligned == len {
+            queue.write_buffer(&self.buffer, 0, data);
+        } else {
+            let mut padded = data.to_vec();
+            padded.resize(aligned as usize, 0);
+            queue.write_buffer(&self.buffer, 0, &padded);
+        }
+        grown
+    }
+
+    pub fn len(&self) -> wgpu::BufferAddress {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // the bytes written by the last `write`, must not be empty
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..self.len)
+    }
+
+    pub fn capacity(&self) -> wgpu::BufferAddress {
+        self.buffer.size()
+    }
+
+    // how often `write` had to grow the buffer so far
+    pub fn reallocations(&self) -> u32 {
+        self.reallocations
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    fn allocate(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        capacity: wgpu::BufferAddress,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: capacity.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+}
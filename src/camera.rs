@@ -1,17 +1,112 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
 use wgpu::util::DeviceExt;
 
+use crate::rect::Rect;
+use crate::viewport::Viewport;
+
+// How far the view reaches into the screen. Geometry pushed with a `z` (see
+// `QuadRenderer::push_with_z`) sits between 0, nearest and where everything
+// else is, and this.
+pub const DEPTH: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CameraState {
+    pub size: (u32, u32),
+    #[serde(default)]
+    pub position: [f32; 2],
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+    #[serde(default)]
+    pub mode: CameraMode,
+    #[serde(default)]
+    pub viewport: Option<Viewport>,
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+fn default_zoom() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CameraMode {
+    // world units are view pixels (physical ones, or logical ones with a
+    // `Viewport`) times the zoom, any zoom and position go
+    #[default]
+    Free,
+    // For pixel art: the zoom counts logical pixels per world unit and is
+    // rounded so every world unit covers the same whole number of physical
+    // pixels (of viewport pixels with a `Viewport`, which wants
+    // `integer_scale` then), and the position is snapped to that grid, so
+    // sprites at integer coordinates never shimmer while the camera moves.
+    // Pair it with `Nearest` filtering on the atlases.
+    PixelPerfect,
+}
+
+// How much of the camera's movement and zoom a layer follows, set with
+// `Renderer::set_layer_parallax`. 1 follows fully, 0 not at all: a background
+// with a `translate` of 0.5 scrolls at half the speed of the world, a UI layer
+// with `Parallax::SCREEN` stays put in view pixels. The zoom factor is an
+// exponent, a layer with 0.5 zooms by 2 when the camera zooms by 4. Layers
+// turn with the camera's rotation unless their zoom factor is 0, which pins
+// them to the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Parallax {
+    pub translate: [f32; 2],
+    pub zoom: f32,
+}
+
+impl Parallax {
+    pub const WORLD: Parallax = Parallax::new([1.0, 1.0], 1.0);
+    pub const SCREEN: Parallax = Parallax::new([0.0, 0.0], 0.0);
+
+    pub const fn new(translate: [f32; 2], zoom: f32) -> Self {
+        Self { translate, zoom }
+    }
+
+    // scrolls at `factor` of the camera's speed on both axes and zooms with it
+    pub const fn scroll(factor: f32) -> Self {
+        Self::new([factor, factor], 1.0)
+    }
+}
+
+impl Default for Parallax {
+    fn default() -> Self {
+        Self::WORLD
+    }
+}
+
+// the uniform a layer with a `Parallax` is drawn with instead of the camera's
+#[derive(Debug)]
+struct LayerView {
+    parallax: Parallax,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
 #[derive(Debug)]
 pub struct Camera {
     size: winit::dpi::PhysicalSize<u32>,
+    // world point at the top left corner of the window
+    position: [f32; 2],
+    zoom: f32,
+    scale_factor: f32,
+    mode: CameraMode,
+    viewport: Option<Viewport>,
+    // radians the world is turned clockwise on screen around the view's center
+    rotation: f32,
     uniform_buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
     bind_group_layout: wgpu::BindGroupLayout,
     view_proj: [[f32; 4]; 4],
+    layer_views: BTreeMap<u16, LayerView>,
 }
 
 impl Camera {
     pub fn new_from_size(device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) -> Self {
-        let proj = Self::build_proj(&size);
+        let proj = Self::build_proj(&size, [0.0, 0.0], 1.0, 0.0);
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             contents: bytemuck::cast_slice(&[proj]),
@@ -43,20 +138,281 @@ impl Camera {
         });
         Self {
             size,
+            position: [0.0, 0.0],
+            zoom: 1.0,
+            scale_factor: 1.0,
+            mode: CameraMode::Free,
+            viewport: None,
+            rotation: 0.0,
             uniform_buffer: camera_buffer,
             bind_group: camera_bind_group,
             bind_group_layout: camera_bind_group_layout,
             view_proj: proj,
+            layer_views: BTreeMap::new(),
         }
     }
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>, queue: &wgpu::Queue) {
         self.size = new_size;
-        self.view_proj = Self::build_proj(&new_size);
+        self.update(queue);
+    }
+
+    pub fn set_position(&mut self, position: [f32; 2], queue: &wgpu::Queue) {
+        self.position = position;
+        self.update(queue);
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32, queue: &wgpu::Queue) {
+        self.zoom = zoom;
+        self.update(queue);
+    }
+
+    // Turns the view around its center, `position` stays the top left corner of
+    // the unturned view. `CameraMode::PixelPerfect` only lines up with pixels at
+    // multiples of a quarter turn.
+    pub fn set_rotation(&mut self, rotation: f32, queue: &wgpu::Queue) {
+        self.rotation = rotation;
+        self.update(queue);
+    }
+
+    // the window's, only `CameraMode::PixelPerfect` uses it
+    pub fn set_scale_factor(&mut self, scale_factor: f64, queue: &wgpu::Queue) {
+        self.scale_factor = scale_factor as f32;
+        self.update(queue);
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode, queue: &wgpu::Queue) {
+        self.mode = mode;
+        self.update(queue);
+    }
+
+    // `None` draws at the window's resolution
+    pub fn set_viewport(&mut self, viewport: Option<Viewport>, queue: &wgpu::Queue) {
+        self.viewport = viewport;
+        self.update(queue);
+    }
+
+    pub fn viewport(&self) -> Option<Viewport> {
+        self.viewport
+    }
+
+    // physical pixels, what mouse positions are in
+    pub fn window_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    // the size the projection spans, the viewport's logical size when one is set
+    pub fn view_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.viewport.map_or(self.size, |viewport| viewport.size())
+    }
+
+    pub fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    // view pixels per world unit, after the mode's rounding
+    pub fn effective_zoom(&self) -> f32 {
+        // a viewport's pixels are logical already
+        let scale_factor = if self.viewport.is_some() { 1.0 } else { self.scale_factor };
+        match self.mode {
+            CameraMode::Free => self.zoom,
+            CameraMode::PixelPerfect => (self.zoom * scale_factor).round().max(1.0),
+        }
+    }
+
+    // the position the view is actually built from, after the mode's snapping
+    pub fn effective_position(&self) -> [f32; 2] {
+        match self.mode {
+            CameraMode::Free => self.position,
+            CameraMode::PixelPerfect => {
+                let zoom = self.effective_zoom();
+                self.position.map(|p| (p * zoom).round() / zoom)
+            }
+        }
+    }
+
+    // a point in physical window pixels, e.g. the mouse, in view pixels
+    pub fn screen_to_logical(&self, x: f32, y: f32) -> (f32, f32) {
+        match self.viewport {
+            Some(viewport) => viewport.to_logical(self.size, x, y),
+            None => (x, y),
+        }
+    }
+
+    // a point in physical window pixels, e.g. the mouse, in world units
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        let (x, y) = self.screen_to_logical(x, y);
+        let (x, y) = self.turn(x, y, -self.rotation);
+        let zoom = self.effective_zoom();
+        let [px, py] = self.effective_position();
+        (x / zoom + px, y / zoom + py)
+    }
+
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        let zoom = self.effective_zoom();
+        let [px, py] = self.effective_position();
+        let (x, y) = self.turn((x - px) * zoom, (y - py) * zoom, self.rotation);
+        match self.viewport {
+            Some(viewport) => viewport.to_physical(self.size, x, y),
+            None => (x, y),
+        }
+    }
+
+    // the world area the view spans, all of it when it's turned
+    pub fn visible_rect(&self) -> Rect {
+        let zoom = self.effective_zoom();
+        let [x, y] = self.effective_position();
+        let size = self.view_size();
+        let (w, h) = (size.width as f32, size.height as f32);
+        if self.rotation == 0.0 {
+            return Rect::new(x, y, w / zoom, h / zoom);
+        }
+        let corners = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)].map(|(cx, cy)| self.turn(cx, cy, -self.rotation));
+        let (min_x, max_x) = corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), c| (lo.min(c.0), hi.max(c.0)));
+        let (min_y, max_y) = corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), c| (lo.min(c.1), hi.max(c.1)));
+        Rect::new(x + min_x / zoom, y + min_y / zoom, (max_x - min_x) / zoom, (max_y - min_y) / zoom)
+    }
+
+    // a point in view pixels turned by `angle` around the view's center
+    fn turn(&self, x: f32, y: f32, angle: f32) -> (f32, f32) {
+        if angle == 0.0 {
+            return (x, y);
+        }
+        let size = self.view_size();
+        let (cx, cy) = (size.width as f32 / 2.0, size.height as f32 / 2.0);
+        let (sin, cos) = angle.sin_cos();
+        let (dx, dy) = (x - cx, y - cy);
+        (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+    }
+
+    pub fn state(&self) -> CameraState {
+        CameraState {
+            size: (self.size.width, self.size.height),
+            position: self.position,
+            zoom: self.zoom,
+            mode: self.mode,
+            viewport: self.viewport,
+            rotation: self.rotation,
+        }
+    }
+
+    pub fn restore(&mut self, state: CameraState, queue: &wgpu::Queue) {
+        let (width, height) = state.size;
+        self.position = state.position;
+        self.zoom = state.zoom;
+        self.mode = state.mode;
+        self.viewport = state.viewport;
+        self.rotation = state.rotation;
+        self.resize(winit::dpi::PhysicalSize::new(width, height), queue);
+    }
+
+    fn update(&mut self, queue: &wgpu::Queue) {
+        self.view_proj = Self::build_proj(
+            &self.view_size(),
+            self.effective_position(),
+            self.effective_zoom(),
+            self.rotation,
+        );
         queue.write_buffer(
             &self.uniform_buffer,
             0,
             bytemuck::cast_slice(&[self.view_proj]),
         );
+        for view in self.layer_views.values() {
+            let (position, zoom, rotation) = self.layer_transform(view.parallax);
+            let view_proj = Self::build_proj(&self.view_size(), position, zoom, rotation);
+            queue.write_buffer(&view.uniform_buffer, 0, bytemuck::cast_slice(&[view_proj]));
+        }
+    }
+
+    // `None` draws `layer` with the camera's own view again
+    pub fn set_layer_parallax(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layer: u16,
+        parallax: Option<Parallax>,
+    ) {
+        let Some(parallax) = parallax.filter(|p| *p != Parallax::WORLD) else {
+            self.layer_views.remove(&layer);
+            return;
+        };
+        match self.layer_views.get_mut(&layer) {
+            Some(view) => view.parallax = parallax,
+            None => {
+                let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Layer View"),
+                    size: std::mem::size_of::<[[f32; 4]; 4]>() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &self.bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    }],
+                    label: Some("Layer View"),
+                });
+                self.layer_views.insert(layer, LayerView {
+                    parallax,
+                    uniform_buffer,
+                    bind_group,
+                });
+            }
+        }
+        self.update(queue);
+    }
+
+    pub fn layer_parallax(&self, layer: u16) -> Parallax {
+        self.layer_views
+            .get(&layer)
+            .map_or(Parallax::WORLD, |view| view.parallax)
+    }
+
+    // The view `layer` is drawn with: None for the camera's own, the layer
+    // itself when it has a `Parallax`. Renderers bind `view_bind_group` of it
+    // and split their draws where it changes.
+    pub fn layer_view(&self, layer: u16) -> Option<u16> {
+        self.layer_views.contains_key(&layer).then_some(layer)
+    }
+
+    // the layers with a `Parallax`
+    pub(crate) fn parallax_layers(&self) -> Vec<u16> {
+        self.layer_views.keys().copied().collect()
+    }
+
+    pub fn view_bind_group(&self, view: Option<u16>) -> &wgpu::BindGroup {
+        view.and_then(|layer| self.layer_views.get(&layer))
+            .map_or(&self.bind_group, |view| &view.bind_group)
+    }
+
+    // a point in physical window pixels in the world units of `layer`, see
+    // `screen_to_world`
+    pub fn screen_to_layer(&self, layer: u16, x: f32, y: f32) -> (f32, f32) {
+        let (x, y) = self.screen_to_logical(x, y);
+        let ([px, py], zoom, rotation) = self.layer_transform(self.layer_parallax(layer));
+        let (x, y) = self.turn(x, y, -rotation);
+        (x / zoom + px, y / zoom + py)
+    }
+
+    // position, zoom and rotation of a layer's view
+    fn layer_transform(&self, parallax: Parallax) -> ([f32; 2], f32, f32) {
+        let [px, py] = self.effective_position();
+        let [tx, ty] = parallax.translate;
+        let rotation = if parallax.zoom == 0.0 { 0.0 } else { self.rotation };
+        ([px * tx, py * ty], self.effective_zoom().powf(parallax.zoom), rotation)
     }
 
     pub fn get_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
@@ -67,9 +423,19 @@ impl Camera {
         &self.bind_group
     }
 
-    fn build_proj(size: &winit::dpi::PhysicalSize<u32>) -> [[f32; 4]; 4] {
-        let m = OPENGL_TO_WGPU_MATRIX
-            * cgmath::ortho(0.0, size.width as f32, size.height as f32, 0.0, 0.0, 2.0);
+    fn build_proj(size: &winit::dpi::PhysicalSize<u32>, [x, y]: [f32; 2], zoom: f32, rotation: f32) -> [[f32; 4]; 4] {
+        let mut m = OPENGL_TO_WGPU_MATRIX
+            * cgmath::ortho(0.0, size.width as f32, size.height as f32, 0.0, 0.0, DEPTH);
+        if rotation != 0.0 {
+            let center = cgmath::Vector3::new(size.width as f32 / 2.0, size.height as f32 / 2.0, 0.0);
+            m = m
+                * cgmath::Matrix4::from_translation(center)
+                * cgmath::Matrix4::from_angle_z(cgmath::Rad(rotation))
+                * cgmath::Matrix4::from_translation(-center);
+        }
+        let m = m
+            * cgmath::Matrix4::from_nonuniform_scale(zoom, zoom, 1.0)
+            * cgmath::Matrix4::from_translation(cgmath::Vector3::new(-x, -y, 0.0));
         m.into()
     }
 }
@@ -0,0 +1,150 @@
+use winit::event::MouseButton;
+
+use crate::camera::Camera;
+use crate::frame::Frame;
+use crate::input::InputState;
+
+// Map style navigation of the camera: dragging and scrolling pan, pinching
+// and ctrl scrolling zoom into the cursor, rotating with two fingers turns
+// the view around it. Return it from `App::camera_controls` to have it applied
+// before every `draw`, or call `apply` where the app wants it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraControls {
+    // dragging with it held pans, `None` leaves the buttons to the app
+    pub pan_button: Option<MouseButton>,
+    // scrolling without ctrl pans instead of doing nothing
+    pub scroll_pans: bool,
+    pub rotate: bool,
+    // its limits and smoothing apply to pinches
+    pub zoom: SmoothZoom,
+}
+
+impl Default for CameraControls {
+    fn default() -> Self {
+        Self {
+            pan_button: Some(MouseButton::Middle),
+            scroll_pans: true,
+            rotate: true,
+            zoom: SmoothZoom::new(0.05, 1.0 / 16.0, 16.0),
+        }
+    }
+}
+
+impl CameraControls {
+    // moves the camera by the input since the last frame
+    pub fn apply(&mut self, input: &InputState, frame: &mut Frame) {
+        // zooming and rotating keep the world under the cursor in place, or
+        // the middle of the window while it's outside
+        let size = frame.renderer.size;
+        let cursor = input
+            .mouse_position()
+            .unwrap_or((size.width as f32 / 2.0, size.height as f32 / 2.0));
+
+        // the world under where the cursor was moves to where it is
+        let (mut dx, mut dy) = (0.0, 0.0);
+        if self.pan_button.is_some_and(|button| input.is_mouse_down(button)) {
+            (dx, dy) = input.mouse_moved();
+        }
+        if self.scroll_pans {
+            let (sx, sy) = input.scroll();
+            (dx, dy) = (dx + sx, dy + sy);
+        }
+        if (dx, dy) != (0.0, 0.0) {
+            let camera = frame.camera();
+            let from = camera.screen_to_world(cursor.0 - dx, cursor.1 - dy);
+            let to = camera.screen_to_world(cursor.0, cursor.1);
+            let [x, y] = camera.position();
+            frame.set_camera_position([x + from.0 - to.0, y + from.1 - to.1]);
+        }
+
+        if self.rotate && input.rotation() != 0.0 {
+            // the camera's rotation turns the world clockwise
+            let rotation = frame.camera().rotation() - input.rotation();
+            around(frame, cursor, |frame| frame.set_camera_rotation(rotation));
+        }
+        if input.pinch() != 1.0 {
+            self.zoom.zoom_by(frame.camera(), input.pinch(), cursor);
+        }
+        let dt = frame.clock().dt();
+        self.zoom.update(frame, dt);
+    }
+}
+
+// Zooms the camera towards a target zoom around an anchor, the cursor
+// usually, getting halfway there every `half_life` seconds. The steps are
+// even in log space, so zooming in and out by the same factor takes as long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothZoom {
+    // 0 zooms at once
+    pub half_life: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    target: Option<f32>,
+    // physical window pixels
+    anchor: (f32, f32),
+}
+
+impl SmoothZoom {
+    pub fn new(half_life: f32, min_zoom: f32, max_zoom: f32) -> Self {
+        Self {
+            half_life,
+            min_zoom,
+            max_zoom,
+            target: None,
+            anchor: (0.0, 0.0),
+        }
+    }
+
+    // the zoom being approached, `None` once it's reached
+    pub fn target(&self) -> Option<f32> {
+        self.target
+    }
+
+    // Zooms by `factor` from where the last zoom was headed, so a fast series
+    // of wheel notches adds up instead of getting lost mid animation.
+    // `anchor` is in physical window pixels, like the mouse position.
+    pub fn zoom_by(&mut self, camera: &Camera, factor: f32, anchor: (f32, f32)) {
+        let from = self.target.unwrap_or(camera.zoom());
+        self.zoom_to(from * factor, anchor);
+    }
+
+    pub fn zoom_to(&mut self, zoom: f32, anchor: (f32, f32)) {
+        self.target = Some(zoom.clamp(self.min_zoom, self.max_zoom));
+        self.anchor = anchor;
+    }
+
+    // moves the camera `dt` seconds further towards the target
+    pub fn update(&mut self, frame: &mut Frame, dt: f32) {
+        let Some(target) = self.target else {
+            return;
+        };
+        let zoom = frame.camera().zoom();
+        let amount = if self.half_life > 0.0 { 1.0 - 0.5f32.powf(dt / self.half_life) } else { 1.0 };
+        let mut next = zoom * (target / zoom).powf(amount);
+        // close enough to not be seen any more
+        if (next / target).ln().abs() < 1e-3 {
+            next = target;
+            self.target = None;
+        }
+        zoom_at(frame, next, self.anchor);
+        if self.target.is_some() {
+            frame.animations().request_frame();
+        }
+    }
+}
+
+// sets the camera's zoom keeping the world under `anchor`, in physical window
+// pixels, in place
+pub fn zoom_at(frame: &mut Frame, zoom: f32, anchor: (f32, f32)) {
+    around(frame, anchor, |frame| frame.set_camera_zoom(zoom));
+}
+
+// changes the camera with `change` and moves it back so the world under
+// `anchor` stays there
+fn around(frame: &mut Frame, anchor: (f32, f32), change: impl FnOnce(&mut Frame)) {
+    let before = frame.camera().screen_to_world(anchor.0, anchor.1);
+    change(frame);
+    let after = frame.camera().screen_to_world(anchor.0, anchor.1);
+    let [x, y] = frame.camera().position();
+    frame.set_camera_position([x + before.0 - after.0, y + before.1 - after.1]);
+}
@@ -0,0 +1,83 @@
+// The target frames are drawn into with `ClearMode::Preserve`. Surface
+// textures rotate through the swapchain and don't keep what was drawn into
+// them, so the frame goes into this texture, which lives across frames, and
+// is copied to the surface texture afterwards.
+pub(crate) struct Canvas {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    view_fmt: wgpu::TextureFormat,
+}
+
+impl Canvas {
+    pub fn new(
+        device: &wgpu::Device,
+        size: winit::dpi::PhysicalSize<u32>,
+        surface_fmt: wgpu::TextureFormat,
+        // the surface's view format, see `SurfaceEncoding`
+        view_fmt: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Canvas"),
+            size: wgpu::Extent3d {
+                width: size.width.max(1),
+                height: size.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_fmt,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[view_fmt],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(view_fmt),
+            ..Default::default()
+        });
+        Self { texture, view, view_fmt }
+    }
+
+    // a canvas of `size` that keeps the part of this one's content that fits
+    pub fn resized(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Self {
+        let canvas = Self::new(device, size, self.texture.format(), self.view_fmt);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            canvas.texture.as_image_copy(),
+            wgpu::Extent3d {
+                width: self.texture.width().min(canvas.texture.width()),
+                height: self.texture.height().min(canvas.texture.height()),
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit([encoder.finish()]);
+        canvas
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn copy_to(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::Texture) {
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            target.as_image_copy(),
+            wgpu::Extent3d {
+                width: self.texture.width().min(target.width()),
+                height: self.texture.height().min(target.height()),
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+// weight of the newest frame in the smoothed fps
+const FPS_SMOOTHING: f32 = 0.1;
+// fixed steps run per frame at most, time beyond that is dropped so a long
+// stall (breakpoint, window drag) doesn't make the next frames catch up forever
+const MAX_FIXED_STEPS: u32 = 8;
+
+// The time source for animations: ticked once per frame, it knows how long the
+// last frame took, the smoothed frame rate and the time since it was created.
+#[derive(Debug, Clone)]
+pub struct FrameClock {
+    start: Instant,
+    last: Instant,
+    delta: Duration,
+    fps: f32,
+    frame: u64,
+    accumulator: Duration,
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    pub fn starting_at(now: Instant) -> Self {
+        Self {
+            start: now,
+            last: now,
+            delta: Duration::ZERO,
+            fps: 0.0,
+            frame: 0,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    // starts a new frame, returns `dt`
+    pub fn tick(&mut self) -> f32 {
+        self.tick_at(Instant::now())
+    }
+
+    pub fn tick_at(&mut self, now: Instant) -> f32 {
+        self.delta = now.saturating_duration_since(self.last);
+        self.last = now;
+        self.frame += 1;
+        self.accumulator += self.delta;
+
+        let dt = self.dt();
+        if dt > 0.0 {
+            let fps = 1.0 / dt;
+            self.fps = match self.fps {
+                0.0 => fps,
+                smoothed => smoothed + (fps - smoothed) * FPS_SMOOTHING,
+            };
+        }
+        dt
+    }
+
+    // seconds between the last two ticks
+    pub fn dt(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    // time from the clock's start to the last tick
+    pub fn elapsed(&self) -> Duration {
+        self.last - self.start
+    }
+
+    // ticks so far
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    // Calls `update` once for every whole `step` ticked since the last call, for
+    // simulations that have to advance in equal steps regardless of the frame
+    // rate. Returns how far into the next step the clock is, 0..1, to
+    // interpolate the drawn state between the last two updates.
+    pub fn fixed_update(&mut self, step: Duration, mut update: impl FnMut(Duration)) -> f32 {
+        if step.is_zero() {
+            return 0.0;
+        }
+        let mut steps = 0;
+        while self.accumulator >= step {
+            if steps == MAX_FIXED_STEPS {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            update(step);
+            self.accumulator -= step;
+            steps += 1;
+        }
+        self.accumulator.as_secs_f32() / step.as_secs_f32()
+    }
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+// A linear rgba color with straight alpha, in the `ColorSpace` the renderer
+// it's pushed to is set to. Everything that takes a color takes
+// `impl Into<Color>`, so `[r, g, b]` and `[r, g, b, a]` arrays still work.
+//
+// Hex strings and hsv are srgb encoded like in image editors and css, and
+// converted to linear on the way in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const RED: Color = Color::rgb(1.0, 0.0, 0.0);
+    pub const GREEN: Color = Color::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color::rgb(0.0, 0.0, 1.0);
+    pub const YELLOW: Color = Color::rgb(1.0, 1.0, 0.0);
+    pub const CYAN: Color = Color::rgb(0.0, 1.0, 1.0);
+    pub const MAGENTA: Color = Color::rgb(1.0, 0.0, 1.0);
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    // `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`, the `#` is optional
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.is_ascii() {
+            return None;
+        }
+        let digits: Vec<u8> = match hex.len() {
+            3 | 4 => hex
+                .chars()
+                .map(|c| u8::from_str_radix(&c.to_string(), 16).map(|d| d * 17))
+                .collect::<Result<_, _>>()
+                .ok()?,
+            6 | 8 => (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<Result<_, _>>()
+                .ok()?,
+            _ => return None,
+        };
+        let channel = |i: usize| srgb_to_linear(digits[i] as f32 / 255.0);
+        let alpha = digits.get(3).map_or(1.0, |a| *a as f32 / 255.0);
+        Some(Self::rgba(channel(0), channel(1), channel(2), alpha))
+    }
+
+    // `hue` in degrees, `saturation` and `value` from 0 to 1
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let c = value * saturation;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let m = value - c;
+        Self::rgb(srgb_to_linear(r + m), srgb_to_linear(g + m), srgb_to_linear(b + m))
+    }
+
+    // hue in degrees, saturation and value, the inverse of `from_hsv`
+    pub fn to_hsv(self) -> [f32; 3] {
+        let [r, g, b] = self.to_rgb().map(|c| linear_to_srgb(c.clamp(0.0, 1.0)));
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        [hue, saturation, max]
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    // the color times its alpha, for blending with `BlendState::PREMULTIPLIED_ALPHA_BLENDING`
+    pub fn premultiplied(self) -> Self {
+        Self::rgba(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    pub fn to_rgb(self) -> [f32; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl From<[f32; 3]> for Color {
+    fn from([r, g, b]: [f32; 3]) -> Self {
+        Self::rgb(r, g, b)
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::rgba(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
+
+// the srgb transfer functions, for one channel from 0 to 1
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_functions_hit_known_values() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-6);
+        assert!((linear_to_srgb(0.5) - 0.735_357).abs() < 1e-5);
+        assert!((srgb_to_linear(0.5) - 0.214_041).abs() < 1e-5);
+        // the linear segment near black
+        assert!((linear_to_srgb(0.001) - 0.012_92).abs() < 1e-6);
+        assert!((srgb_to_linear(0.04) - 0.04 / 12.92).abs() < 1e-6);
+    }
+
+    #[test]
+    fn transfer_functions_round_trip_every_8_bit_value() {
+        for i in 0..=255 {
+            let c = i as f32 / 255.0;
+            assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-5, "{i}");
+            assert_eq!((linear_to_srgb(srgb_to_linear(c)) * 255.0).round() as u8, i);
+        }
+    }
+}
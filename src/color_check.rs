@@ -0,0 +1,258 @@
+use crate::backend::RenderBackend;
+use crate::camera::Camera;
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::font::create_cpu_glyph_atlas;
+use crate::quad::QuadRenderer;
+use crate::rect::Rect;
+use crate::software::SoftwareRenderer;
+use crate::sort::SortKey;
+use crate::state::FontSource;
+
+// Reference scene for the color pipeline: a gray ramp, a 1px gamma checker next
+// to a solid 50% patch, and a row of known swatches. Rendered into a target of
+// a given format, every probe must read back the value that format implies
+// (srgb encoded for *Srgb formats, linear otherwise).
+
+pub const WIDTH: u32 = 256;
+pub const HEIGHT: u32 = 96;
+
+const RAMP_STEPS: u32 = 16;
+const CHECKER: (u32, u32, u32, u32) = (0, 32, 64, 32);
+const HALF_PATCH: (u32, u32, u32, u32) = (64, 32, 64, 32);
+const SWATCHES: [(&str, [f32; 3]); 8] = [
+    ("red", [1.0, 0.0, 0.0]),
+    ("green", [0.0, 1.0, 0.0]),
+    ("blue", [0.0, 0.0, 1.0]),
+    ("cyan", [0.0, 1.0, 1.0]),
+    ("magenta", [1.0, 0.0, 1.0]),
+    ("yellow", [1.0, 1.0, 0.0]),
+    ("white", [1.0, 1.0, 1.0]),
+    ("orange", [1.0, 0.5, 0.0]),
+];
+
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub probe: String,
+    pub expected: [u8; 3],
+    pub actual: [u8; 3],
+}
+
+// `push` gets x, y, w, h and the color of every quad, so the scene can go into
+// any renderer
+pub fn push_scene(mut push: impl FnMut(f32, f32, f32, f32, [f32; 3])) {
+    let step_w = (WIDTH / RAMP_STEPS) as f32;
+    for i in 0..RAMP_STEPS {
+        let v = i as f32 / (RAMP_STEPS - 1) as f32;
+        push(i as f32 * step_w, 0.0, step_w, 32.0, [v, v, v]);
+    }
+
+    let (cx, cy, cw, ch) = CHECKER;
+    push(cx as f32, cy as f32, cw as f32, ch as f32, [0.0, 0.0, 0.0]);
+    for y in cy..cy + ch {
+        for x in (cx + (y % 2)..cx + cw).step_by(2) {
+            push(x as f32, y as f32, 1.0, 1.0, [1.0, 1.0, 1.0]);
+        }
+    }
+    let (px, py, pw, ph) = HALF_PATCH;
+    push(px as f32, py as f32, pw as f32, ph as f32, [0.5, 0.5, 0.5]);
+
+    let swatch_w = (WIDTH / SWATCHES.len() as u32) as f32;
+    for (i, (_, color)) in SWATCHES.iter().enumerate() {
+        push(i as f32 * swatch_w, 64.0, swatch_w, 32.0, *color);
+    }
+}
+
+// compares a readback of `push_scene` against the values implied by `format`,
+// `tolerance` is in 8 bit steps
+pub fn verify(
+    image: &image::RgbaImage,
+    format: wgpu::TextureFormat,
+    tolerance: u8,
+) -> Vec<Mismatch> {
+    let srgb = format.is_srgb();
+    let encode = |c: f32| -> u8 {
+        let c = if srgb { linear_to_srgb(c) } else { c };
+        (c * 255.0).round() as u8
+    };
+    let decode = |c: u8| -> f32 {
+        let c = c as f32 / 255.0;
+        if srgb { srgb_to_linear(c) } else { c }
+    };
+
+    let mut probes: Vec<(String, (u32, u32), [f32; 3])> = vec![];
+    let step_w = WIDTH / RAMP_STEPS;
+    for i in 0..RAMP_STEPS {
+        let v = i as f32 / (RAMP_STEPS - 1) as f32;
+        probes.push((
+            format!("ramp {i}"),
+            (i * step_w + step_w / 2, 16),
+            [v, v, v],
+        ));
+    }
+    let swatch_w = WIDTH / SWATCHES.len() as u32;
+    for (i, (name, color)) in SWATCHES.iter().enumerate() {
+        probes.push((
+            name.to_string(),
+            (i as u32 * swatch_w + swatch_w / 2, 80),
+            *color,
+        ));
+    }
+    let (px, py, pw, ph) = HALF_PATCH;
+    probes.push((
+        "half patch".to_string(),
+        (px + pw / 2, py + ph / 2),
+        [0.5; 3],
+    ));
+
+    let mut mismatches = vec![];
+    for (name, (x, y), color) in probes {
+        let expected = color.map(encode);
+        let pixel = image.get_pixel(x, y).0;
+        let actual = [pixel[0], pixel[1], pixel[2]];
+        if expected
+            .iter()
+            .zip(actual)
+            .any(|(e, a)| e.abs_diff(a) > tolerance)
+        {
+            mismatches.push(Mismatch {
+                probe: name,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    // the checker has to emit the same amount of light as the 50% patch
+    let (cx, cy, cw, ch) = CHECKER;
+    let mut sum = 0.0;
+    for y in cy..cy + ch {
+        for x in cx..cx + cw {
+            sum += decode(image.get_pixel(x, y).0[0]);
+        }
+    }
+    let average = sum / (cw * ch) as f32;
+    let patch = image.get_pixel(px + pw / 2, py + ph / 2).0[0];
+    if encode(average).abs_diff(patch) > tolerance {
+        mismatches.push(Mismatch {
+            probe: "gamma checker".to_string(),
+            expected: [patch; 3],
+            actual: [encode(average); 3],
+        });
+    }
+
+    mismatches
+}
+
+// renders `push_scene` with `SoftwareRenderer`, the result is srgb encoded like
+// an Rgba8UnormSrgb target
+pub fn render_software() -> image::RgbaImage {
+    let font = FontSource::Embedded.load().unwrap();
+    let mut renderer = SoftwareRenderer::new(
+        winit::dpi::PhysicalSize::new(WIDTH, HEIGHT),
+        create_cpu_glyph_atlas(&font, 32.0),
+    );
+    renderer.begin_frame();
+    push_scene(|x, y, w, h, color| {
+        renderer.push_quad(SortKey::default(), Rect::new(x, y, w, h), color)
+    });
+    renderer.render();
+    renderer.image().clone()
+}
+
+// renders `push_scene` into an offscreen target, `format` must be an 8 bit rgba or bgra format
+pub fn render_offscreen(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: wgpu::TextureFormat,
+) -> image::RgbaImage {
+    let size = winit::dpi::PhysicalSize::new(WIDTH, HEIGHT);
+    let cam = Camera::new_from_size(device, size);
+    let mut quads = QuadRenderer::new(device, &cam, format);
+    push_scene(|x, y, w, h, color| quads.push(x, y, w, h, color));
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Color Check Target"),
+        size: wgpu::Extent3d {
+            width: WIDTH,
+            height: HEIGHT,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    quads.prepare(device, queue);
+    let mut encoder = device.create_command_encoder(&Default::default());
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            depth_slice: None,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    quads.render(&mut render_pass, &cam, |_| true);
+    drop(render_pass);
+
+    queue.submit([encoder.finish()]);
+
+    crate::export::read_texture(device, queue, &texture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_diff::ImageDiff;
+
+    // `render_software` as of the last intended change, rewritten by
+    // `cargo run --example color_check --features color-check tests/golden`
+    const GOLDEN: &[u8] = include_bytes!("../tests/golden/color_check_software.png");
+
+    #[test]
+    fn swatches_read_back_their_reference_values() {
+        let image = render_software();
+        // srgb encoded like the hex codes of the colors
+        let expected = [
+            [255, 0, 0],
+            [0, 255, 0],
+            [0, 0, 255],
+            [0, 255, 255],
+            [255, 0, 255],
+            [255, 255, 0],
+            [255, 255, 255],
+            [255, 188, 0],
+        ];
+        let swatch_w = WIDTH / SWATCHES.len() as u32;
+        for (i, ((name, _), expected)) in SWATCHES.iter().zip(expected).enumerate() {
+            let pixel = image.get_pixel(i as u32 * swatch_w + swatch_w / 2, 80).0;
+            assert_eq!([pixel[0], pixel[1], pixel[2]], expected, "{name}");
+        }
+        let (px, py, pw, ph) = HALF_PATCH;
+        assert_eq!(image.get_pixel(px + pw / 2, py + ph / 2).0[..3], [188, 188, 188]);
+    }
+
+    #[test]
+    fn software_render_passes_verify() {
+        let mismatches = verify(&render_software(), wgpu::TextureFormat::Rgba8UnormSrgb, 1);
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+    }
+
+    #[test]
+    fn software_render_matches_golden_image() {
+        let golden = image::load_from_memory(GOLDEN).unwrap().to_rgba8();
+        let diff = ImageDiff::new(&render_software(), &golden, 0.01);
+        assert!(!diff.differs(), "{} pixels differ, up to {}", diff.differing, diff.max);
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+// The gamut the linear colors (and color glyph texels) handed to the renderers
+// are in. The shaders convert everything to linear srgb, values outside of
+// 0..1 are kept on wide gamut surfaces (see `RendererSettings::wide_gamut`)
+// and clipped on the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    DisplayP3,
+}
+
+// How the surface stores the linear colors the shaders write, see
+// `RendererSettings::surface_encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SurfaceEncoding {
+    // through an srgb format or view, the gpu applies the srgb curve on
+    // write, which is what monitors expect
+    #[default]
+    Srgb,
+    // written as is, for apps that encode in their own shaders or post
+    // process the frame, and for Rgba16Float surfaces, which are linear
+    Linear,
+}
+
+impl SurfaceEncoding {
+    // the format frames are drawn through on a surface of `format`
+    pub fn view_format(self, format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        match self {
+            Self::Srgb => format.add_srgb_suffix(),
+            Self::Linear => format.remove_srgb_suffix(),
+        }
+    }
+
+    // what drawing through a view of `format` does
+    pub fn of(format: wgpu::TextureFormat) -> Self {
+        if format.is_srgb() { Self::Srgb } else { Self::Linear }
+    }
+}
+
+// linear display p3 to linear srgb, both with a d65 white point
+const P3_TO_SRGB: [[f32; 3]; 3] = [
+    [1.2249401, -0.2249404, 0.0],
+    [-0.0420569, 1.0420571, 0.0],
+    [-0.0196376, -0.0786361, 1.0982735],
+];
+
+impl ColorSpace {
+    // as stored in the vertices and checked by the shaders
+    pub(crate) fn id(self) -> u32 {
+        match self {
+            Self::Srgb => 0,
+            Self::DisplayP3 => 1,
+        }
+    }
+
+    // the conversion the shaders do, for drawing on the cpu
+    pub fn to_srgb(self, color: [f32; 3]) -> [f32; 3] {
+        match self {
+            Self::Srgb => color,
+            Self::DisplayP3 => P3_TO_SRGB.map(|row| {
+                row[0] * color[0] + row[1] * color[1] + row[2] * color[2]
+            }),
+        }
+    }
+}
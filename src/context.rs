@@ -0,0 +1,37 @@
+// The gpu every window renders with. Create it once and hand it to
+// `Renderer::with_context` for each window, so their atlases and buffers live
+// on the same device. The handles are reference counted, cloning is cheap.
+#[derive(Debug, Clone)]
+pub struct GraphicsContext {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+}
+
+impl GraphicsContext {
+    pub async fn new() -> Self {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .unwrap();
+
+        // for `GpuProfiler`, wherever the adapter has them
+        let timestamps = wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                required_features: adapter.features() & timestamps,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        Self {
+            instance,
+            adapter,
+            device,
+            queue,
+        }
+    }
+}
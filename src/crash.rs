@@ -0,0 +1,204 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, TryLockError, mpsc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use winit::window::WindowId;
+
+use crate::profiler::FrameStats;
+use crate::sanitize::GeometryIssues;
+
+// the newest diagnostics kept for the report
+const DIAGNOSTICS: usize = 64;
+// how long the hook waits for the screenshots, a lost device never delivers them
+const SCREENSHOT_TIMEOUT: Duration = Duration::from_secs(2);
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static STATE: Mutex<Option<CrashState>> = Mutex::new(None);
+
+struct CrashState {
+    dir: PathBuf,
+    started: Instant,
+    diagnostics: VecDeque<String>,
+    windows: HashMap<WindowId, WindowReport>,
+}
+
+impl CrashState {
+    fn note(&mut self, message: impl std::fmt::Display) {
+        if self.diagnostics.len() == DIAGNOSTICS {
+            self.diagnostics.pop_front();
+        }
+        let line = format!("[{:.3}s] {message}", self.started.elapsed().as_secs_f32());
+        self.diagnostics.push_back(line);
+    }
+}
+
+// What a renderer recorded for the report at the end of its last frame.
+pub(crate) struct WindowReport {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub adapter: wgpu::AdapterInfo,
+    pub surface: wgpu::SurfaceConfiguration,
+    pub frame: u64,
+    pub stats: FrameStats,
+    pub issues: GeometryIssues,
+    // the canvas of `ClearMode::Preserve` and `Trail`, the only target that
+    // still holds the last frame once it's presented
+    pub canvas: Option<wgpu::Texture>,
+}
+
+// Installs a panic hook that writes a crash report into `dir` before the
+// hook installed so far runs: the panic with a backtrace, and for every
+// window its adapter, surface configuration, last frame's stats and geometry
+// issues, a screenshot of that frame where it was drawn into a canvas, and the
+// recent diagnostics: wgpu errors, surface changes, toasts and `note`s.
+// Returns false when it's installed already.
+pub fn install(dir: impl Into<PathBuf>) -> bool {
+    if INSTALLED.swap(true, Ordering::AcqRel) {
+        return false;
+    }
+    *STATE.lock().unwrap() = Some(CrashState {
+        dir: dir.into(),
+        started: Instant::now(),
+        diagnostics: VecDeque::new(),
+        windows: HashMap::new(),
+    });
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        match write_report(info) {
+            Some(Ok(path)) => eprintln!("crash report written to {}", path.display()),
+            Some(Err(err)) => eprintln!("couldn't write the crash report: {err}"),
+            None => {}
+        }
+    }));
+    true
+}
+
+pub fn is_installed() -> bool {
+    INSTALLED.load(Ordering::Acquire)
+}
+
+// Adds a line to the diagnostics of the report, a breadcrumb of what the app
+// was doing. Dropped while no hook is installed.
+pub fn note(message: impl std::fmt::Display) {
+    if !is_installed() {
+        return;
+    }
+    // a panic while the state is locked can't reach it anymore
+    let Ok(mut state) = STATE.lock() else {
+        return;
+    };
+    if let Some(state) = state.as_mut() {
+        state.note(message);
+    }
+}
+
+// Replaces what the report says about `window`, noting geometry issues that
+// differ from its last frame's. Its device's wgpu errors are noted from its
+// first report on, they still panic like they do by default.
+pub(crate) fn record(window: WindowId, report: WindowReport) {
+    let Ok(mut state) = STATE.lock() else {
+        return;
+    };
+    let Some(state) = state.as_mut() else {
+        return;
+    };
+    match state.windows.get(&window) {
+        Some(last) if last.issues == report.issues => {}
+        Some(_) => state.note(format!("frame {}: {:?}", report.frame, report.issues)),
+        None => {
+            report.device.on_uncaptured_error(Arc::new(|err| {
+                note(format!("wgpu error: {err}"));
+                panic!("wgpu error: {err}\n");
+            }));
+        }
+    }
+    state.windows.insert(window, report);
+}
+
+// for a window that's gone
+pub(crate) fn forget(window: WindowId) {
+    if let Ok(mut state) = STATE.lock()
+        && let Some(state) = state.as_mut()
+    {
+        state.windows.remove(&window);
+    }
+}
+
+fn write_report(info: &std::panic::PanicHookInfo) -> Option<std::io::Result<PathBuf>> {
+    // another thread only holds it for a moment, this one for good when it
+    // panicked while holding it
+    let state = (0..100).find_map(|_| match STATE.try_lock() {
+        Ok(state) => Some(state),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => {
+            std::thread::sleep(Duration::from_millis(1));
+            None
+        }
+    })?;
+    let state = state.as_ref()?;
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    Some(write_files(state, info, stamp))
+}
+
+fn write_files(state: &CrashState, info: &std::panic::PanicHookInfo, stamp: u64) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(&state.dir)?;
+    let mut text = String::new();
+    let thread = std::thread::current();
+    let _ = writeln!(text, "wrs crash report");
+    let _ = writeln!(text, "after {:.3}s on thread {}", state.started.elapsed().as_secs_f32(), thread.name().unwrap_or("unnamed"));
+    let _ = writeln!(text, "{info}");
+    let _ = writeln!(text, "\nbacktrace:\n{}", std::backtrace::Backtrace::force_capture());
+
+    for (i, window) in state.windows.values().enumerate() {
+        let adapter = &window.adapter;
+        let _ = writeln!(text, "window {i}:");
+        let _ = writeln!(
+            text,
+            "  adapter: {} ({:?}, {:?}), driver {} {}",
+            adapter.name, adapter.device_type, adapter.backend, adapter.driver, adapter.driver_info
+        );
+        let _ = writeln!(text, "  surface: {:?}", window.surface);
+        let _ = writeln!(text, "  frame {}: {:?}", window.frame, window.stats);
+        let _ = writeln!(text, "  geometry issues: {:?}", window.issues);
+        let screenshot = match &window.canvas {
+            Some(canvas) => {
+                let path = state.dir.join(format!("crash-{stamp}-window{i}.png"));
+                match screenshot(window, canvas, &path) {
+                    Ok(()) => path.display().to_string(),
+                    Err(err) => format!("failed, {err}"),
+                }
+            }
+            None => "none, frames aren't drawn into a canvas".to_string(),
+        };
+        let _ = writeln!(text, "  screenshot: {screenshot}");
+    }
+
+    let _ = writeln!(text, "\ndiagnostics:");
+    for line in &state.diagnostics {
+        let _ = writeln!(text, "  {line}");
+    }
+    let path = state.dir.join(format!("crash-{stamp}.txt"));
+    std::fs::write(&path, text)?;
+    Ok(path)
+}
+
+// Reads `canvas` back on a thread of its own, a device that panicked in the
+// middle of a call can stay locked.
+fn screenshot(window: &WindowReport, canvas: &wgpu::Texture, path: &Path) -> Result<(), String> {
+    let (device, queue, canvas, path) = (window.device.clone(), window.queue.clone(), canvas.clone(), path.to_owned());
+    let (done, result) = mpsc::channel();
+    std::thread::spawn(move || {
+        let saved = match crate::export::try_read_texture(&device, &queue, &canvas, SCREENSHOT_TIMEOUT) {
+            Some(image) => image.save(&path).map_err(|err| err.to_string()),
+            None => Err("the canvas couldn't be read back".to_string()),
+        };
+        let _ = done.send(saved);
+    });
+    result
+        .recv_timeout(SCREENSHOT_TIMEOUT * 2)
+        .unwrap_or_else(|_| Err("timed out".to_string()))
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rect::Rect;
+
+// Which triangles the quad and text pipelines rasterize. Every pushed quad is
+// wound clockwise on screen, so with the default `FrontFace::Cw` culling
+// `Face::Back` keeps all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Culling {
+    pub front_face: wgpu::FrontFace,
+    pub cull_mode: Option<wgpu::Face>,
+}
+
+impl Culling {
+    pub(crate) fn primitive_state(self) -> wgpu::PrimitiveState {
+        wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: self.front_face,
+            cull_mode: self.cull_mode,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        }
+    }
+}
+
+impl Default for Culling {
+    fn default() -> Self {
+        Self {
+            front_face: wgpu::FrontFace::Cw,
+            cull_mode: None,
+        }
+    }
+}
+
+// What pushes drop with `Renderer::set_cpu_culling`, so a world far larger
+// than the screen doesn't build and upload the quads off it every frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CullRect {
+    // world units, covering what every view of the world shows
+    pub rect: Rect,
+    // drawn with another view than the camera's, like layers with a
+    // `Parallax`, which are never culled
+    pub skip_layers: Vec<u16>,
+}
+
+impl CullRect {
+    // whether a quad within `bounds` on `layer` is entirely outside
+    pub fn culls(&self, layer: u16, bounds: &Rect) -> bool {
+        !self.rect.intersects(&bounds.normalized()) && !self.skip_layers.contains(&layer)
+    }
+}
+
+// Whether the quad starting at `quad[0]` winds the other way around than a
+// rect with a positive size, as a rect with one negative side does.
+fn mis_wound(quad: [[f32; 3]; 3]) -> bool {
+    let [a, b, c] = quad;
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]) < 0.0
+}
+
+// Swaps the second and fourth vertex of every mis-wound quad of a batch, which
+// keeps the quad and its texture coordinates but reverses its winding.
+pub(crate) fn rewind_quads<V>(vertices: &mut [V], pos: impl Fn(&V) -> [f32; 3]) {
+    for quad in vertices.chunks_exact_mut(4) {
+        if mis_wound([pos(&quad[0]), pos(&quad[1]), pos(&quad[2])]) {
+            quad.swap(1, 3);
+        }
+    }
+}
+
+// number of mis-wound quads of a batch, which pushing never produces
+pub(crate) fn count_mis_wound<V>(vertices: &[V], pos: impl Fn(&V) -> [f32; 3]) -> usize {
+    vertices
+        .chunks_exact(4)
+        .filter(|quad| mis_wound([pos(&quad[0]), pos(&quad[1]), pos(&quad[2])]))
+        .count()
+}
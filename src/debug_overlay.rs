@@ -0,0 +1,72 @@
+use crate::backend::RenderBackend;
+use crate::font::MissingGlyphs;
+use crate::frame::Frame;
+use crate::rect::Rect;
+use crate::sort::SortKey;
+
+const TEXT_SIZE: f32 = 14.0;
+const PADDING: f32 = 6.0;
+// from the window edges
+const MARGIN: f32 = 8.0;
+const BACKGROUND: [f32; 3] = [0.02, 0.02, 0.02];
+const TEXT_COLOR: [f32; 3] = [0.4, 1.0, 0.4];
+// pinned to the screen while the overlay is shown, below the toasts
+pub(crate) const DEBUG_LAYER: u16 = u16::MAX - 2;
+
+// Pushes the overlay of `Renderer::debug_overlay` into the top left corner,
+// see `Renderer::frame_stats` for what the numbers are of. The gpu times are
+// there with `Renderer::set_gpu_profiling`.
+pub(crate) fn draw(frame: &mut Frame) -> Result<(), MissingGlyphs> {
+    let stats = frame.renderer.frame_stats();
+    let mut lines = vec![
+        format!("{:.0} fps  {:.2} ms", stats.fps, stats.dt * 1000.0),
+        format!("vertices {}  indices {}", stats.batches.vertices, stats.batches.indices),
+        format!(
+            "batches {}  draw calls {}  pipelines {}  bind groups {}  quads {}",
+            stats.draws.batches,
+            stats.draws.draw_calls,
+            stats.draws.pipeline_binds,
+            stats.draws.bind_group_binds,
+            stats.draws.quads
+        ),
+        format!(
+            "uploaded {:.1} KiB  buffer reallocations {}",
+            stats.batches.uploaded as f32 / 1024.0,
+            stats.batches.reallocations
+        ),
+    ];
+    if let Some(gpu) = stats.gpu {
+        lines.push(format!("gpu {:.2} ms  blended {:.2} ms", gpu.total, gpu.blended));
+        let part = |name: &str, ms: Option<f32>| ms.map(|ms| format!("{name} {ms:.2}"));
+        let parts: Vec<_> = [
+            part("tiles", gpu.tilemaps),
+            part("quads", gpu.quads),
+            part("sprites", gpu.sprites),
+            part("particles", gpu.particles),
+            part("text", gpu.text),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !parts.is_empty() {
+            lines.push(parts.join("  "));
+        }
+    }
+
+    let key = SortKey::new(DEBUG_LAYER, 0);
+    let measured: Vec<_> = lines.iter().map(|line| frame.measure_str(Some(TEXT_SIZE), line)).collect();
+    let width = measured.iter().map(|(w, _)| *w).fold(0.0, f32::max);
+    let height: f32 = measured.iter().map(|(_, h)| h).sum();
+    frame.set_clip(None);
+    frame.push_quad(key, Rect::new(MARGIN, MARGIN, width + 2.0 * PADDING, height + 2.0 * PADDING), BACKGROUND);
+
+    let mut missing = MissingGlyphs(vec![]);
+    let mut y = MARGIN + PADDING;
+    for (line, (_, h)) in lines.iter().zip(measured) {
+        if let Err(err) = frame.push_str(key, MARGIN + PADDING, y, Some(TEXT_SIZE), TEXT_COLOR, line) {
+            missing.merge(err);
+        }
+        y += h;
+    }
+    missing.into_result()
+}
@@ -0,0 +1,440 @@
+use serde::{Deserialize, Serialize};
+
+use crate::backend::RenderBackend;
+use crate::font::MissingGlyphs;
+use crate::rect::Rect;
+use crate::sort::SortKey;
+
+// Draw commands in a form external tools (editors, remote viewers, ...) can
+// produce without linking against wrs. A list is encoded either as JSON or in
+// the binary format below, both carry the same commands.
+//
+// JSON:
+//   {"version": 1, "commands": [{"type": "quad", "rect": {...}, "color": [r, g, b], "key": 0}, ...]}
+//   command types: "quad" {rect, color, key?}, "text" {x, y, color, text, key?, size?},
+//   "clip" {rect?}, where `?` fields may be left out.
+//
+// Binary, all numbers little endian:
+//   b"WRSD", u16 version, then records until the end of the data:
+//   u8 tag, u32 payload length, payload
+//     tag 1 quad: f32 x, y, w, h, f32 r, g, b, [u64 key]
+//     tag 2 text: f32 x, y, f32 r, g, b, u32 byte length, utf-8 bytes, [u64 key],
+//                 [f32 size]
+//     tag 3 clip: u8 has_rect, [f32 x, y, w, h]
+//
+// Streams of lists (recordings, the debug server) frame every list as a u32
+// little endian byte length followed by its binary encoding.
+//
+// Compatibility rules:
+// - `version` only changes when an existing command or field changes meaning,
+//   is renamed or removed. Readers reject lists with a newer version.
+// - Within a version, commands may gain new optional fields at the end.
+//   Readers ignore fields (and trailing payload bytes) they don't know, and
+//   fields missing from older writers take their defaults.
+// - Within a version, new command types may be added. Readers skip commands
+//   they don't know, binary records by their payload length.
+pub const FORMAT_VERSION: u16 = 1;
+
+const MAGIC: &[u8; 4] = b"WRSD";
+
+const TAG_QUAD: u8 = 1;
+const TAG_TEXT: u8 = 2;
+const TAG_CLIP: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawList {
+    pub version: u16,
+    pub commands: Vec<DrawCommand>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DrawCommand {
+    Quad {
+        rect: Rect,
+        color: [f32; 3],
+        #[serde(default)]
+        key: SortKey,
+    },
+    // `text` is drawn as a single line, like `FontRenderer::push_str`, at
+    // `size` pixels or the atlas' own size without one
+    Text {
+        x: f32,
+        y: f32,
+        color: [f32; 3],
+        text: String,
+        #[serde(default)]
+        key: SortKey,
+        #[serde(default)]
+        size: Option<f32>,
+    },
+    Clip {
+        #[serde(default)]
+        rect: Option<Rect>,
+    },
+    // a command type from a newer writer, ignored when drawing
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    InvalidUtf8,
+    Json(serde_json::Error),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a wrs draw list"),
+            Self::UnsupportedVersion(v) => write!(
+                f,
+                "draw list version {v} is newer than the supported version {FORMAT_VERSION}"
+            ),
+            Self::Truncated => write!(f, "draw list ends in the middle of a command"),
+            Self::InvalidUtf8 => write!(f, "text command isn't valid utf-8"),
+            Self::Json(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Default for DrawList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawList {
+    pub fn new() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            commands: vec![],
+        }
+    }
+
+    pub fn push(&mut self, command: DrawCommand) {
+        self.commands.push(command);
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, DecodeError> {
+        let list: Self = serde_json::from_str(s).map_err(DecodeError::Json)?;
+        if list.version > FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(list.version));
+        }
+        Ok(list)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.extend_from_slice(&self.version.to_le_bytes());
+        for command in &self.commands {
+            let mut payload = vec![];
+            let tag = match command {
+                DrawCommand::Quad { rect, color, key } => {
+                    put_f32s(&mut payload, &[rect.x, rect.y, rect.w, rect.h]);
+                    put_f32s(&mut payload, color);
+                    payload.extend_from_slice(&key.0.to_le_bytes());
+                    TAG_QUAD
+                }
+                DrawCommand::Text {
+                    x,
+                    y,
+                    color,
+                    text,
+                    key,
+                    size,
+                } => {
+                    put_f32s(&mut payload, &[*x, *y]);
+                    put_f32s(&mut payload, color);
+                    payload.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                    payload.extend_from_slice(text.as_bytes());
+                    payload.extend_from_slice(&key.0.to_le_bytes());
+                    if let Some(size) = size {
+                        put_f32s(&mut payload, &[*size]);
+                    }
+                    TAG_TEXT
+                }
+                DrawCommand::Clip { rect } => {
+                    payload.push(rect.is_some() as u8);
+                    if let Some(rect) = rect {
+                        put_f32s(&mut payload, &[rect.x, rect.y, rect.w, rect.h]);
+                    }
+                    TAG_CLIP
+                }
+                DrawCommand::Unknown => continue,
+            };
+            out.push(tag);
+            out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            out.extend_from_slice(&payload);
+        }
+        out
+    }
+
+    pub fn write_framed(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        let bytes = self.to_bytes();
+        out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        out.write_all(&bytes)
+    }
+
+    // Splits the complete frames off the front of `bytes`, returns them and the
+    // number of bytes they took up. A trailing partial frame is left alone.
+    pub fn read_framed(bytes: &[u8]) -> (Vec<&[u8]>, usize) {
+        let mut frames = vec![];
+        let mut start = 0;
+        while bytes.len() - start >= 4 {
+            let len = u32::from_le_bytes(bytes[start..start + 4].try_into().unwrap()) as usize;
+            let end = start + 4 + len;
+            if bytes.len() < end {
+                break;
+            }
+            frames.push(&bytes[start + 4..end]);
+            start = end;
+        }
+        (frames, start)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader { bytes };
+        if reader.take(4).ok() != Some(MAGIC.as_slice()) {
+            return Err(DecodeError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version > FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        let mut commands = vec![];
+        while !reader.bytes.is_empty() {
+            let tag = reader.u8()?;
+            let len = reader.u32()? as usize;
+            let mut payload = Reader {
+                bytes: reader.take(len)?,
+            };
+            let command = match tag {
+                TAG_QUAD => DrawCommand::Quad {
+                    rect: payload.rect()?,
+                    color: payload.color()?,
+                    key: payload.key(),
+                },
+                TAG_TEXT => DrawCommand::Text {
+                    x: payload.f32()?,
+                    y: payload.f32()?,
+                    color: payload.color()?,
+                    text: {
+                        let len = payload.u32()? as usize;
+                        let text = payload.take(len)?;
+                        String::from_utf8(text.to_vec()).map_err(|_| DecodeError::InvalidUtf8)?
+                    },
+                    key: payload.key(),
+                    size: payload.f32().ok(),
+                },
+                TAG_CLIP => DrawCommand::Clip {
+                    rect: match payload.u8()? {
+                        0 => None,
+                        _ => Some(payload.rect()?),
+                    },
+                },
+                _ => continue,
+            };
+            commands.push(command);
+        }
+        Ok(Self { version, commands })
+    }
+
+    // Pushes every command into the backend's batches. Clip rects set here stay
+    // set after the list.
+    pub fn submit(&self, backend: &mut impl RenderBackend) -> Result<(), MissingGlyphs> {
+        let mut missing = MissingGlyphs(vec![]);
+        for command in &self.commands {
+            match command {
+                DrawCommand::Quad { rect, color, key } => {
+                    backend.push_quad(*key, *rect, *color);
+                }
+                DrawCommand::Text {
+                    x,
+                    y,
+                    color,
+                    text,
+                    key,
+                    size,
+                } => {
+                    if let Err(err) = backend.push_str(*key, *x, *y, *size, *color, text) {
+                        missing.merge(err);
+                    }
+                }
+                DrawCommand::Clip { rect } => backend.set_clip(*rect),
+                DrawCommand::Unknown => {}
+            }
+        }
+        missing.into_result()
+    }
+}
+
+fn put_f32s(out: &mut Vec<u8>, values: &[f32]) {
+    for v in values {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < n {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, DecodeError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn rect(&mut self) -> Result<Rect, DecodeError> {
+        Ok(Rect::new(self.f32()?, self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    fn color(&mut self) -> Result<[f32; 3], DecodeError> {
+        Ok([self.f32()?, self.f32()?, self.f32()?])
+    }
+
+    // optional trailing field, older writers may leave it out
+    fn key(&mut self) -> SortKey {
+        match self.take(8) {
+            Ok(bytes) => SortKey(u64::from_le_bytes(bytes.try_into().unwrap())),
+            Err(_) => SortKey::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list() -> DrawList {
+        let mut list = DrawList::new();
+        list.push(DrawCommand::Quad {
+            rect: Rect::new(1.0, 2.0, 30.0, 40.0),
+            color: [0.25, 0.5, 1.0],
+            key: SortKey::new(3, 7),
+        });
+        list.push(DrawCommand::Clip {
+            rect: Some(Rect::new(0.0, 0.0, 100.0, 50.0)),
+        });
+        list.push(DrawCommand::Text {
+            x: 5.0,
+            y: 6.0,
+            color: [1.0, 1.0, 1.0],
+            text: "grüße ✓".to_string(),
+            key: SortKey::new(4, 0),
+            size: Some(18.0),
+        });
+        list.push(DrawCommand::Text {
+            x: 0.0,
+            y: 0.0,
+            color: [0.0, 0.0, 0.0],
+            text: String::new(),
+            key: SortKey::default(),
+            size: None,
+        });
+        list.push(DrawCommand::Clip { rect: None });
+        list
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let list = list();
+        assert_eq!(DrawList::from_json(&list.to_json().unwrap()).unwrap(), list);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let list = list();
+        assert_eq!(DrawList::from_bytes(&list.to_bytes()).unwrap(), list);
+    }
+
+    #[test]
+    fn framed_round_trip() {
+        let mut stream = vec![];
+        list().write_framed(&mut stream).unwrap();
+        DrawList::new().write_framed(&mut stream).unwrap();
+        // the start of a third frame
+        stream.extend_from_slice(&[9, 0]);
+
+        let (frames, used) = DrawList::read_framed(&stream);
+        assert_eq!(used, stream.len() - 2);
+        let lists: Vec<_> = frames.iter().map(|frame| DrawList::from_bytes(frame).unwrap()).collect();
+        assert_eq!(lists, [list(), DrawList::new()]);
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let json = format!(r#"{{"version": {}, "commands": []}}"#, FORMAT_VERSION + 1);
+        assert!(matches!(DrawList::from_json(&json), Err(DecodeError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1));
+
+        let mut bytes = DrawList::new().to_bytes();
+        bytes[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(DrawList::from_bytes(&bytes), Err(DecodeError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1));
+
+        assert!(matches!(DrawList::from_bytes(b"WRSX\x01\x00"), Err(DecodeError::BadMagic)));
+    }
+
+    #[test]
+    fn unknown_commands_are_skipped() {
+        let json = r#"{"version": 1, "commands": [{"type": "circle", "r": 4}, {"type": "clip"}]}"#;
+        let list = DrawList::from_json(json).unwrap();
+        assert_eq!(list.commands, [DrawCommand::Unknown, DrawCommand::Clip { rect: None }]);
+
+        // tag 200 with a 3 byte payload, then a clip
+        let mut bytes = DrawList::new().to_bytes();
+        bytes.extend_from_slice(&[200, 3, 0, 0, 0, 1, 2, 3]);
+        bytes.extend_from_slice(&[TAG_CLIP, 1, 0, 0, 0, 0]);
+        assert_eq!(DrawList::from_bytes(&bytes).unwrap().commands, [DrawCommand::Clip { rect: None }]);
+    }
+
+    #[test]
+    fn older_records_take_the_defaults() {
+        // a quad without its key, text without key and size
+        let mut bytes = DrawList::new().to_bytes();
+        let mut quad = vec![];
+        put_f32s(&mut quad, &[1.0, 2.0, 3.0, 4.0, 0.5, 0.5, 0.5]);
+        bytes.push(TAG_QUAD);
+        bytes.extend_from_slice(&(quad.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&quad);
+        let list = DrawList::from_bytes(&bytes).unwrap();
+        assert_eq!(list.commands, [DrawCommand::Quad {
+            rect: Rect::new(1.0, 2.0, 3.0, 4.0),
+            color: [0.5; 3],
+            key: SortKey::default(),
+        }]);
+
+        // a record shorter than its payload length
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(matches!(DrawList::from_bytes(truncated), Err(DecodeError::Truncated)));
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+// What a frame's ui came out as, drawn by the next `Renderer::render`.
+struct Output {
+    primitives: Vec<egui::ClippedPrimitive>,
+    textures: egui::TexturesDelta,
+    pixels_per_point: f32,
+}
+
+// An egui ui drawn over a wrs scene, for tooling like inspectors and editor
+// panels, with the `egui` feature. `Renderer::egui` adds it on first use,
+// `Frame::egui` runs the ui for a frame and the renderer draws it after the
+// scene's passes in the same encoder, straight onto the window, so the canvas
+// of `ClearMode::Preserve` never keeps it. `run` passes window events to it
+// first and leaves the ones it consumed out of the `InputState`.
+pub struct EguiLayer {
+    context: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    output: Option<Output>,
+    // every delta of each texture since its last whole upload, replayed into
+    // the renderer built for a new target format
+    uploads: HashMap<egui::TextureId, Vec<egui::epaint::ImageDelta>>,
+}
+
+impl EguiLayer {
+    pub fn new(device: &wgpu::Device, window: &Window, target: wgpu::TextureFormat) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            window.theme(),
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        Self {
+            context,
+            state,
+            renderer: egui_wgpu::Renderer::new(device, target, egui_wgpu::RendererOptions::default()),
+            output: None,
+            uploads: HashMap::new(),
+        }
+    }
+
+    // for styles, fonts and textures outside of a frame's ui
+    pub fn context(&self) -> &egui::Context {
+        &self.context
+    }
+
+    // Whether egui consumed `event`, a click on one of its windows or a key
+    // typed into one of its text fields.
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.state.on_window_event(window, event).consumed
+    }
+
+    // whether the pointer is over or dragging an egui window
+    pub fn wants_pointer(&self) -> bool {
+        self.context.wants_pointer_input()
+    }
+
+    // whether an egui text field has the keyboard focus
+    pub fn wants_keyboard(&self) -> bool {
+        self.context.wants_keyboard_input()
+    }
+
+    // Runs `ui` with the input since the last run. Running it more than once
+    // a frame draws only the last one.
+    pub fn run(&mut self, window: &Window, ui: impl FnMut(&egui::Context)) {
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, ui);
+        self.state.handle_platform_output(window, output.platform_output);
+        let primitives = self.context.tessellate(output.shapes, output.pixels_per_point);
+        // the textures of a run that wasn't drawn still have to be uploaded
+        let textures = match self.output.take() {
+            Some(mut undrawn) => {
+                undrawn.textures.append(output.textures_delta);
+                undrawn.textures
+            }
+            None => output.textures_delta,
+        };
+        self.output = Some(Output {
+            primitives,
+            textures,
+            pixels_per_point: output.pixels_per_point,
+        });
+    }
+
+    pub(crate) fn set_target_format(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, target: wgpu::TextureFormat) {
+        self.renderer = egui_wgpu::Renderer::new(device, target, egui_wgpu::RendererOptions::default());
+        for (id, deltas) in &self.uploads {
+            for delta in deltas {
+                self.renderer.update_texture(device, queue, *id, delta);
+            }
+        }
+    }
+
+    // Draws the last run's ui over `view`, `size` in physical pixels. Returns
+    // the command buffers of its paint callbacks, which are submitted before
+    // `encoder`.
+    pub(crate) fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: [u32; 2],
+    ) -> Vec<wgpu::CommandBuffer> {
+        let Some(output) = self.output.take() else {
+            return vec![];
+        };
+        for (id, delta) in output.textures.set {
+            self.renderer.update_texture(device, queue, id, &delta);
+            let deltas = self.uploads.entry(id).or_default();
+            if delta.is_whole() {
+                deltas.clear();
+            }
+            deltas.push(delta);
+        }
+        let screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: size,
+            pixels_per_point: output.pixels_per_point,
+        };
+        let commands = self
+            .renderer
+            .update_buffers(device, queue, encoder, &output.primitives, &screen);
+        let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("egui"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.renderer
+            .render(&mut render_pass.forget_lifetime(), &output.primitives, &screen);
+        for id in &output.textures.free {
+            self.renderer.free_texture(id);
+            self.uploads.remove(id);
+        }
+        commands
+    }
+}
@@ -0,0 +1,325 @@
+use std::path::Path;
+
+use crate::Renderer;
+use crate::camera::Camera;
+use crate::font::FontRenderer;
+use crate::oit::{Compositing, OitCompositor};
+use crate::depth::DepthBuffer;
+use crate::quad::QuadRenderer;
+use crate::sort::{self, DrawBatch, Part};
+use crate::state::{RenderTarget, RendererSettings};
+use crate::viewport;
+
+// every device supports 4 samples for the export format
+pub const EXPORT_SAMPLES: u32 = 4;
+const EXPORT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+#[derive(Debug)]
+pub enum ExportError {
+    // the scaled size is past the device's texture size limit
+    TooLarge {
+        size: winit::dpi::PhysicalSize<u32>,
+        max: u32,
+    },
+    Image(image::ImageError),
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge { size, max } => write!(
+                f,
+                "export size {}x{} is larger than the device limit of {max}",
+                size.width, size.height
+            ),
+            Self::Image(err) => write!(f, "failed to save the export: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<image::ImageError> for ExportError {
+    fn from(err: image::ImageError) -> Self {
+        Self::Image(err)
+    }
+}
+
+impl Renderer {
+    // Renders the last finished frame at `scale` times the window size with
+    // `EXPORT_SAMPLES`x msaa into an offscreen target, whatever the window's
+    // present mode or surface format are.
+    pub fn export(&mut self, scale: f32) -> Result<image::RgbaImage, ExportError> {
+        let size = winit::dpi::PhysicalSize::new(
+            ((self.size.width as f32 * scale).round() as u32).max(1),
+            ((self.size.height as f32 * scale).round() as u32).max(1),
+        );
+        let max = self.context.device.limits().max_texture_dimension_2d;
+        if size.width > max || size.height > max {
+            return Err(ExportError::TooLarge { size, max });
+        }
+        Ok(render_scene(
+            &self.context.device,
+            &self.context.queue,
+            &self.camera,
+            &mut self.quad_renderer,
+            &mut self.font_renderer,
+            &self.settings,
+            size,
+        ))
+    }
+
+    pub fn export_png(&mut self, path: impl AsRef<Path>, scale: f32) -> Result<(), ExportError> {
+        self.export(scale)?.save(path)?;
+        Ok(())
+    }
+}
+
+// Draws what's batched in `quads` and `text` into a `size` target. The
+// projection of `cam` stays the same, so a target larger than the camera's
+// size renders the same scene at a higher resolution. A `Viewport` of the
+// camera is fitted into the target like into the window, and with
+// `depth_buffer` in the settings nearer geometry covers further one like there.
+pub fn render_scene(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    cam: &Camera,
+    quads: &mut QuadRenderer,
+    text: &mut FontRenderer,
+    settings: &RendererSettings,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> image::RgbaImage {
+    let create_target = |label, samples, usage| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: samples,
+            dimension: wgpu::TextureDimension::D2,
+            format: EXPORT_FORMAT,
+            usage,
+            view_formats: &[],
+        })
+    };
+    let msaa = create_target(
+        "Export MSAA Target",
+        EXPORT_SAMPLES,
+        wgpu::TextureUsages::RENDER_ATTACHMENT,
+    );
+    let target = create_target(
+        "Export Target",
+        1,
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    );
+    let msaa_view = msaa.create_view(&wgpu::TextureViewDescriptor::default());
+    let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+    let depth_buffer = settings
+        .depth_buffer
+        .then(|| DepthBuffer::new(device, size, EXPORT_SAMPLES));
+    let depth = depth_buffer.as_ref().map(|_| DepthBuffer::FORMAT);
+
+    quads.prepare(device, queue);
+    text.prepare(device, queue);
+    quads.prepare_multisampled(device, EXPORT_FORMAT, EXPORT_SAMPLES, depth);
+    text.prepare_multisampled(device, EXPORT_FORMAT, EXPORT_SAMPLES, depth);
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &msaa_view,
+            depth_slice: None,
+            resolve_target: Some(&view),
+            ops: settings.target_ops(RenderTarget::ExportMsaa).operations(),
+        })],
+        depth_stencil_attachment: depth_buffer.as_ref().map(DepthBuffer::attachment),
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+
+    viewport::apply(&mut render_pass, cam, size);
+
+    let sorted = |layer| settings.compositing(layer) == Compositing::Sorted;
+    let blended = |layer| settings.compositing(layer) == Compositing::WeightedBlended;
+    // layer by layer like `Renderer::render`, text over the quads of its layer,
+    // the layers above the highest blended one after the composite
+    let top_blended = quads.layers(blended).last().max(text.layers(blended).last()).copied();
+    let layers = quads.layers(sorted).into_iter().map(|layer| DrawBatch { layer, part: Part::Quads });
+    let text_layers = text.layers(sorted).into_iter().map(|layer| DrawBatch { layer, part: Part::Text });
+    let (below, above): (Vec<_>, Vec<_>) = sort::merge_batches(layers.chain(text_layers))
+        .into_iter()
+        .partition(|batch| top_blended.is_none_or(|top| batch.layer <= top));
+    let draw = |pass: &mut wgpu::RenderPass, batch: DrawBatch, quads: &QuadRenderer, text: &FontRenderer| {
+        let layer = |layer| layer == batch.layer;
+        match batch.part {
+            Part::Quads => quads.render_multisampled(pass, cam, layer),
+            _ => text.render_multisampled(pass, cam, layer),
+        };
+    };
+    for &batch in &below {
+        draw(&mut render_pass, batch, quads, text);
+    }
+    drop(render_pass);
+
+    // transparent layers are composited over the resolved image without msaa
+    if top_blended.is_some() {
+        let oit = OitCompositor::new(device, size, EXPORT_FORMAT);
+        let mut oit_pass = oit.begin_pass_with(
+            &mut encoder,
+            settings.target_ops(RenderTarget::OitAccum),
+            settings.target_ops(RenderTarget::OitReveal),
+        );
+        viewport::apply(&mut oit_pass, cam, size);
+        quads.render_oit(&mut oit_pass, cam, blended);
+        text.render_oit(&mut oit_pass, cam, blended);
+        drop(oit_pass);
+
+        let mut composite_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        oit.composite(&mut composite_pass);
+    }
+    // and so is what's above them
+    if !above.is_empty() {
+        quads.prepare_multisampled(device, EXPORT_FORMAT, 1, None);
+        text.prepare_multisampled(device, EXPORT_FORMAT, 1, None);
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        viewport::apply(&mut pass, cam, size);
+        for &batch in &above {
+            draw(&mut pass, batch, quads, text);
+        }
+    }
+    queue.submit([encoder.finish()]);
+
+    let mut image = read_texture(device, queue, &target);
+    // the window shows the frame opaque, whatever blending left in alpha
+    for pixel in image.pixels_mut() {
+        pixel.0[3] = 255;
+    }
+    image
+}
+
+// copies an 8 bit rgba or bgra texture back to the cpu, its first layer for
+// texture arrays
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> image::RgbaImage {
+    read_back(device, queue, texture, wgpu::PollType::wait_indefinitely()).expect("the texture can be read back")
+}
+
+// `read_texture` that gives up after `timeout` or when the device is lost,
+// None then and for other than 8 bit formats
+pub fn try_read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    timeout: std::time::Duration,
+) -> Option<image::RgbaImage> {
+    use wgpu::TextureFormat::*;
+    if !matches!(texture.format(), Rgba8Unorm | Rgba8UnormSrgb | Bgra8Unorm | Bgra8UnormSrgb) {
+        return None;
+    }
+    read_back(device, queue, texture, wgpu::PollType::Wait {
+        submission_index: None,
+        timeout: Some(timeout),
+    })
+}
+
+fn read_back(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    poll: wgpu::PollType,
+) -> Option<image::RgbaImage> {
+    let (width, height) = (texture.width(), texture.height());
+    let bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback"),
+        size: (bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &readback,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit([encoder.finish()]);
+
+    let slice = readback.slice(..);
+    let mapped = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let done = mapped.clone();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        done.store(result.is_ok(), std::sync::atomic::Ordering::Release);
+    });
+    device.poll(poll).ok()?;
+    if !mapped.load(std::sync::atomic::Ordering::Acquire) {
+        return None;
+    }
+
+    let bgra = matches!(
+        texture.format(),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    );
+    let data = slice.get_mapped_range();
+    let mut image = image::RgbaImage::new(width, height);
+    for (y, row) in data.chunks(bytes_per_row as usize).enumerate() {
+        for x in 0..width {
+            let i = x as usize * 4;
+            let mut pixel = [row[i], row[i + 1], row[i + 2], row[i + 3]];
+            if bgra {
+                pixel.swap(0, 2);
+            }
+            image.put_pixel(x, y as u32, image::Rgba(pixel));
+        }
+    }
+    drop(data);
+    readback.unmap();
+
+    Some(image)
+}
@@ -0,0 +1,646 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{Font, ScaleFont};
+use image::EncodableLayout;
+use serde::{Deserialize, Serialize};
+
+use super::layout::PositionedGlyph;
+
+// Placement of one rasterized glyph.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GlyphInfo {
+    pub uv: (f32, f32, f32, f32),
+    // top left corner of the quad relative to the pen, which sits at the top of the line
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+    // how far the pen moves after this glyph
+    pub advance: f32,
+    // atlas page the uv coordinates point into
+    pub page: u32,
+    // a color bitmap like an emoji, drawn as is instead of tinted with the text color
+    pub color: bool,
+}
+
+// rasterized into every atlas up front, and the default fallback glyph
+pub const REPLACEMENT_CHAR: char = '\u{FFFD}';
+
+// How the atlas texture stores glyphs, `FontRenderer` picks the matching shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum AtlasKind {
+    // plain coverage bitmaps, crisp only near the rasterization size
+    #[default]
+    Bitmap,
+    // signed distance fields, see `sdf::distance_field`
+    Sdf,
+}
+
+// Glyph placement and metrics, everything text layout needs to know.
+pub trait GlyphSource {
+    fn glyph(&self, c: char) -> Option<GlyphInfo>;
+
+    // the glyph drawn in place of characters missing from the atlas, if any
+    fn fallback(&self) -> Option<char>;
+
+    fn glyph_or_fallback(&self, c: char) -> Option<GlyphInfo> {
+        self.glyph(c).or_else(|| self.fallback().and_then(|f| self.glyph(f)))
+    }
+
+    // extra horizontal advance between two neighbouring glyphs
+    fn kern(&self, _left: char, _right: char) -> f32 {
+        0.0
+    }
+
+    fn line_height(&self) -> f32;
+
+    // from the top of a line to its baseline
+    fn ascent(&self) -> f32;
+
+    // pixel size the glyphs were rasterized at, metrics are in this size
+    fn font_size(&self) -> f32;
+
+    fn kind(&self) -> AtlasKind {
+        AtlasKind::Bitmap
+    }
+
+    // Shapes one line of text without tabs at the atlas' font size. Returns the
+    // positioned glyphs and the advance of the whole run, or `None` when the
+    // source can't shape and glyphs are placed char by char instead.
+    fn shape(&self, _text: &str) -> Option<(Vec<PositionedGlyph>, f32)> {
+        None
+    }
+
+    // a glyph by its id in the font, for glyphs coming out of `shape`
+    fn glyph_by_id(&self, _id: ab_glyph::GlyphId) -> Option<GlyphInfo> {
+        None
+    }
+}
+
+// Anything `FontRenderer` can draw glyphs from.
+pub trait Atlas: GlyphSource {
+    fn bind_group(&self, page: u32) -> wgpu::BindGroup;
+
+    // the layer of `page` in the texture array its bind group binds, for
+    // atlases keeping all their pages in one array
+    fn layer(&self, _page: u32) -> u32 {
+        0
+    }
+
+    fn bind_group_layout(&self) -> &wgpu::BindGroupLayout;
+}
+
+pub struct MonoGlyphAtlas {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub bind_group: wgpu::BindGroup,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub glyph_map: std::collections::HashMap<char, (f32, f32, f32, f32)>,
+    pub cell_size: (u32, u32),
+    pub h_adv: f32,
+    // vertical metrics of the font at the atlas scale, descent is negative
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+    pub fallback: Option<char>,
+
+    font: ab_glyph::FontArc,
+    scale: ab_glyph::PxScale,
+    // where the pen sits on the baseline in every cell, glyphs are rasterized
+    // around it at their own bearings
+    origin: (f32, f32),
+    cols: u32,
+    rows: u32,
+    next_cell: u32,
+    // chars drawn with their own colors, see `insert_image`
+    color_glyphs: std::collections::HashSet<char>,
+}
+
+// cells reserved in the atlas texture, the printable ascii range and the
+// replacement character take the first 96
+const ATLAS_CELLS: u32 = 256;
+
+// What `MonoGlyphAtlas::save` writes next to the atlas image, paths relative
+// to the json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MonoAtlasMeta {
+    image: PathBuf,
+    // a copy of the font, still needed for glyphs added at runtime
+    font: PathBuf,
+    scale: f32,
+    cell_size: (u32, u32),
+    origin: (f32, f32),
+    cols: u32,
+    rows: u32,
+    next_cell: u32,
+    h_adv: f32,
+    ascent: f32,
+    descent: f32,
+    line_gap: f32,
+    fallback: Option<char>,
+    glyph_map: BTreeMap<char, (f32, f32, f32, f32)>,
+    color_glyphs: BTreeSet<char>,
+}
+
+impl MonoGlyphAtlas {
+    // distance between two baselines as the font designer intended it
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+
+    // the font the atlas was rasterized from, e.g. for `outline_text`
+    pub fn font(&self) -> &ab_glyph::FontArc {
+        &self.font
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.glyph_map.contains_key(&c)
+    }
+
+    // Writes the atlas image to `png`, read back from the gpu with everything
+    // `warm_cache` and `insert_image` added, and its glyph metrics to a json of
+    // the same name with a copy of the font next to them. `load` takes the
+    // json and skips rasterizing.
+    pub fn save(&self, device: &wgpu::Device, queue: &wgpu::Queue, png: impl AsRef<Path>) -> image::ImageResult<()> {
+        let png = png.as_ref();
+        crate::export::read_texture(device, queue, &self.texture).save(png)?;
+        let font = png.with_extension("ttf");
+        std::fs::write(&font, self.font.font_data())?;
+        let meta = MonoAtlasMeta {
+            image: png.file_name().map(PathBuf::from).unwrap_or_default(),
+            font: font.file_name().map(PathBuf::from).unwrap_or_default(),
+            scale: self.scale.y,
+            cell_size: self.cell_size,
+            origin: self.origin,
+            cols: self.cols,
+            rows: self.rows,
+            next_cell: self.next_cell,
+            h_adv: self.h_adv,
+            ascent: self.ascent,
+            descent: self.descent,
+            line_gap: self.line_gap,
+            fallback: self.fallback,
+            glyph_map: self.glyph_map.iter().map(|(&c, &uv)| (c, uv)).collect(),
+            color_glyphs: self.color_glyphs.iter().copied().collect(),
+        };
+        let json = serde_json::to_string_pretty(&meta).map_err(std::io::Error::other)?;
+        std::fs::write(png.with_extension("json"), json)?;
+        Ok(())
+    }
+
+    // an atlas saved by `save`, from its json
+    pub fn load(device: &wgpu::Device, queue: &wgpu::Queue, json: impl AsRef<Path>) -> image::ImageResult<Self> {
+        let json = json.as_ref();
+        let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let meta: MonoAtlasMeta =
+            serde_json::from_str(&std::fs::read_to_string(json)?).map_err(|err| invalid(err.to_string()))?;
+        let dir = json.parent().unwrap_or(Path::new(""));
+        let image = image::open(dir.join(&meta.image))?.to_rgba8();
+        let (cell_w, cell_h) = meta.cell_size;
+        if image.dimensions() != (meta.cols * cell_w, meta.rows * cell_h) || meta.next_cell > meta.cols * meta.rows {
+            return Err(invalid(format!("{} doesn't match its atlas image", json.display())).into());
+        }
+        let font = ab_glyph::FontArc::try_from_vec(std::fs::read(dir.join(&meta.font))?)
+            .map_err(|err| invalid(format!("{}: {err}", meta.font.display())))?;
+
+        let (texture, view, sampler, bind_group_layout, bind_group) =
+            upload_atlas_texture(device, queue, std::slice::from_ref(&image));
+        Ok(MonoGlyphAtlas {
+            texture,
+            view,
+            sampler,
+            bind_group,
+            bind_group_layout,
+            glyph_map: meta.glyph_map.into_iter().collect(),
+            cell_size: meta.cell_size,
+            h_adv: meta.h_adv,
+            ascent: meta.ascent,
+            descent: meta.descent,
+            line_gap: meta.line_gap,
+            fallback: meta.fallback,
+            font,
+            scale: ab_glyph::PxScale::from(meta.scale),
+            origin: meta.origin,
+            cols: meta.cols,
+            rows: meta.rows,
+            next_cell: meta.next_cell,
+            color_glyphs: meta.color_glyphs.into_iter().collect(),
+        })
+    }
+
+    // `Nearest` keeps pixel fonts drawn at integer scales crisp, see
+    // `CameraMode::PixelPerfect`
+    pub fn set_filter(&mut self, device: &wgpu::Device, filter: wgpu::FilterMode) {
+        (self.sampler, self.bind_group) =
+            atlas_bind_group(device, &self.bind_group_layout, &self.view, filter);
+    }
+
+    // Rasterizes every glyph of `text` that isn't in the atlas yet into a free
+    // cell, so drawing it later doesn't hitch. Glyphs without an outline, like
+    // space, are drawn as nothing and take no cell. It takes no sizes, every
+    // size is scaled from the one raster, unlike in `FontManager::warm_cache`.
+    // Returns the characters that didn't fit because the atlas is full.
+    pub fn warm_cache(&mut self, queue: &wgpu::Queue, text: &str) -> Vec<char> {
+        let mut dropped = vec![];
+        for c in text.chars() {
+            if self.contains(c) || dropped.contains(&c) || c.is_control() {
+                continue;
+            }
+            let Some(img) = rasterize_cell(&self.font, self.scale, c, self.origin, self.cell_size) else {
+                self.glyph_map.insert(c, (0.0, 0.0, 0.0, 0.0));
+                continue;
+            };
+            if self.next_cell == self.cols * self.rows {
+                dropped.push(c);
+                continue;
+            }
+            self.write_cell(queue, c, &img);
+        }
+        dropped
+    }
+
+    // Draws `c` as `image` from now on, e.g. emoji from a png set. The image is
+    // scaled to fit a cell, keeping its aspect ratio, and drawn with its own
+    // colors. Returns false when the atlas is full.
+    pub fn insert_image(&mut self, queue: &wgpu::Queue, c: char, image: &image::RgbaImage) -> bool {
+        if self.next_cell == self.cols * self.rows && self.cell_of(c).is_none() {
+            return false;
+        }
+        let (cell_w, cell_h) = self.cell_size;
+        let fit = (cell_w as f32 / image.width().max(1) as f32)
+            .min(cell_h as f32 / image.height().max(1) as f32);
+        let (w, h) = (
+            ((image.width() as f32 * fit) as u32).clamp(1, cell_w),
+            ((image.height() as f32 * fit) as u32).clamp(1, cell_h),
+        );
+        let scaled = image::imageops::resize(image, w, h, image::imageops::FilterType::Triangle);
+        // centered and sitting on the bottom of the cell
+        let mut img = image::RgbaImage::new(cell_w, cell_h);
+        image::imageops::overlay(&mut img, &scaled, ((cell_w - w) / 2).into(), (cell_h - h).into());
+        self.write_cell(queue, c, &img);
+        self.color_glyphs.insert(c);
+        true
+    }
+
+    // the cell `c` was drawn into, if it has one
+    fn cell_of(&self, c: char) -> Option<u32> {
+        let &(u0, v0, u1, _) = self.glyph_map.get(&c)?;
+        (u1 > u0).then(|| {
+            (v0 * self.rows as f32).round() as u32 * self.cols + (u0 * self.cols as f32).round() as u32
+        })
+    }
+
+    // into the cell `c` already has, or the next free one
+    fn write_cell(&mut self, queue: &wgpu::Queue, c: char, img: &image::RgbaImage) {
+        let (cell_w, cell_h) = self.cell_size;
+        let (atlas_width, atlas_height) = (self.cols * cell_w, self.rows * cell_h);
+        let cell = self.cell_of(c).unwrap_or_else(|| {
+            self.next_cell += 1;
+            self.next_cell - 1
+        });
+
+        let x = (cell % self.cols) * cell_w;
+        let y = (cell / self.cols) * cell_h;
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            img.as_bytes(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * cell_w),
+                rows_per_image: Some(cell_h),
+            },
+            wgpu::Extent3d {
+                width: cell_w,
+                height: cell_h,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let u0 = x as f32 / atlas_width as f32;
+        let v0 = y as f32 / atlas_height as f32;
+        let u1 = (x + cell_w) as f32 / atlas_width as f32;
+        let v1 = (y + cell_h) as f32 / atlas_height as f32;
+        self.glyph_map.insert(c, (u0, v0, u1, v1));
+    }
+}
+
+impl GlyphSource for MonoGlyphAtlas {
+    fn glyph(&self, c: char) -> Option<GlyphInfo> {
+        let &uv = self.glyph_map.get(&c)?;
+        Some(GlyphInfo {
+            uv,
+            offset: (-self.origin.0, self.ascent - self.origin.1),
+            size: (self.cell_size.0 as f32, self.cell_size.1 as f32),
+            advance: self.h_adv,
+            page: 0,
+            color: self.color_glyphs.contains(&c),
+        })
+    }
+
+    fn fallback(&self) -> Option<char> {
+        self.fallback
+    }
+
+    fn line_height(&self) -> f32 {
+        MonoGlyphAtlas::line_height(self)
+    }
+
+    fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    fn font_size(&self) -> f32 {
+        self.scale.y
+    }
+}
+
+impl Atlas for MonoGlyphAtlas {
+    fn bind_group(&self, _page: u32) -> wgpu::BindGroup {
+        self.bind_group.clone()
+    }
+
+    fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+}
+
+// `ch` with the pen at `origin` on the baseline, so descenders hang below it
+// and punctuation sits where the font puts it
+fn rasterize_cell(
+    font: &ab_glyph::FontArc,
+    scale: ab_glyph::PxScale,
+    ch: char,
+    origin: (f32, f32),
+    (cell_w, cell_h): (u32, u32),
+) -> Option<image::RgbaImage> {
+    let glyph = font
+        .glyph_id(ch)
+        .with_scale_and_position(scale, ab_glyph::point(origin.0, origin.1));
+    let og = font.outline_glyph(glyph)?;
+    let mut img = image::RgbaImage::new(cell_w, cell_h);
+    let glyph_bb = og.px_bounds();
+
+    og.draw(|x, y, v| {
+        let px = (x as i32 + glyph_bb.min.x as i32).max(0) as u32;
+        let py = (y as i32 + glyph_bb.min.y as i32).max(0) as u32;
+        if px < cell_w && py < cell_h {
+            img.put_pixel(px, py, image::Rgba([255, 255, 255, (v * 255.0) as u8]));
+        }
+    });
+    Some(img)
+}
+
+// largest pixel size `atlas_scale` picks for a display, which keeps the
+// monospace atlas of 16x16 cells inside every device's texture size limit
+const MAX_DPI_SCALE: f32 = 256.0;
+
+// Pixel size to rasterize an atlas at whose text is drawn up to `scale` logical
+// pixels tall, on a display with `scale_factor` physical pixels per logical
+// one. Rasterizing at the physical size keeps the glyphs from being stretched
+// and blurred on high dpi displays.
+pub fn atlas_scale(scale: f32, scale_factor: f64) -> f32 {
+    (scale * scale_factor as f32).min(MAX_DPI_SCALE.max(scale)).round()
+}
+
+pub fn create_monospace_atlas(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    font_data: &[u8],
+    scale: f32,
+) -> MonoGlyphAtlas {
+    create_monospace_atlas_of_face(device, queue, font_data, 0, scale)
+}
+
+// of the font at `index` of a collection, see `FontSource::load_face`
+pub(crate) fn create_monospace_atlas_of_face(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    font_data: &[u8],
+    index: u32,
+    scale: f32,
+) -> MonoGlyphAtlas {
+    let font = ab_glyph::FontArc::new(ab_glyph::FontVec::try_from_vec_and_index(font_data.to_vec(), index).unwrap());
+    let scale = ab_glyph::PxScale::from(scale);
+
+    let mut chars: Vec<char> = (' '..='~').collect();
+
+
+    chars.push(REPLACEMENT_CHAR);
+    // big enough for the line and every outline around a pen at 0, 0 on the
+    // baseline
+    let union = |a: ab_glyph::Rect, b: ab_glyph::Rect| ab_glyph::Rect {
+        min: ab_glyph::point(a.min.x.min(b.min.x), a.min.y.min(b.min.y)),
+        max: ab_glyph::point(a.max.x.max(b.max.x), a.max.y.max(b.max.y)),
+    };
+    let scaled = font.as_scaled(scale);
+    let mut bb = ab_glyph::Rect {
+        min: ab_glyph::point(0.0, -scaled.ascent()),
+        max: ab_glyph::point(scaled.h_advance(font.glyph_id('M')), -scaled.descent()),
+    };
+    for &c in &chars {
+        if let Some(outline) = font.outline_glyph(font.glyph_id(c).with_scale(scale)) {
+            bb = union(bb, outline.px_bounds());
+        }
+    }
+    let origin = (-bb.min.x.floor(), -bb.min.y.floor());
+    let cell_w = (bb.max.x.ceil() - bb.min.x.floor()) as u32;
+    let cell_h = (bb.max.y.ceil() - bb.min.y.floor()) as u32;
+
+    let cols = 16;
+    let rows = ATLAS_CELLS.max(chars.len() as u32).div_ceil(cols);
+    let atlas_width = cols * cell_w;
+    let atlas_height = rows * cell_h;
+
+    let mut atlas = image::RgbaImage::new(atlas_width, atlas_height);
+    let mut glyph_map = std::collections::HashMap::new();
+
+    for (i, &ch) in chars.iter().enumerate() {
+        if let Some(img) = rasterize_cell(&font, scale, ch, origin, (cell_w, cell_h)) {
+            let x = (i as u32 % cols) * cell_w;
+            let y = (i as u32 / cols) * cell_h;
+
+            image::imageops::overlay(&mut atlas, &img, x.into(), y.into());
+
+            let u0 = x as f32 / atlas_width as f32;
+            let v0 = y as f32 / atlas_height as f32;
+            let u1 = (x + cell_w) as f32 / atlas_width as f32;
+            let v1 = (y + cell_h) as f32 / atlas_height as f32;
+            glyph_map.insert(ch, (u0, v0, u1, v1));
+        } else {
+            glyph_map.insert(ch, (0.0, 0.0, 0.0, 0.0));
+
+        }
+    }
+
+    let (texture, view, sampler, bind_group_layout, bind_group) =
+        upload_atlas_texture(device, queue, std::slice::from_ref(&atlas));
+
+    MonoGlyphAtlas {
+        texture,
+        view,
+        sampler,
+        glyph_map,
+        cell_size: (cell_w, cell_h),
+        bind_group,
+        bind_group_layout,
+        h_adv: scaled.h_advance(font.glyph_id('M')),
+        ascent: scaled.ascent(),
+        descent: scaled.descent(),
+        line_gap: scaled.line_gap(),
+        fallback: Some(REPLACEMENT_CHAR),
+        next_cell: chars.len() as u32,
+        color_glyphs: std::collections::HashSet::new(),
+        font,
+        scale,
+        origin,
+        cols,
+        rows,
+    }
+}
+
+// creates the texture and the bind group every atlas kind hands to `FontRenderer`
+// Every atlas texture is bound as a texture array, so atlases with several
+// pages of the same size (`GlyphAtlas`) keep them in one bind group. The
+// others have a single layer.
+pub(crate) fn create_atlas_texture(
+    device: &wgpu::Device,
+    (width, height): (u32, u32),
+    layers: u32,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    // GL guesses how a texture is viewed from its layer count, one layer
+    // reads as a plain 2d texture and multiples of 6 as cube maps, so those
+    // get a spare layer
+    let layers = match layers {
+        0 | 1 => 2,
+        n if n % 6 == 0 => n + 1,
+        n => n,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        // copied from when `FontManager::compact` repacks pages or a
+        // `GlyphAtlas` grows by a layer
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        ..Default::default()
+    });
+    (texture, view)
+}
+
+// `layers` all have the same size
+pub(crate) fn upload_atlas_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layers: &[image::RgbaImage],
+) -> (
+    wgpu::Texture,
+    wgpu::TextureView,
+    wgpu::Sampler,
+    wgpu::BindGroupLayout,
+    wgpu::BindGroup,
+) {
+    let (width, height) = layers[0].dimensions();
+    let (texture, view) = create_atlas_texture(device, (width, height), layers.len() as u32);
+
+    for (layer, image) in layers.iter().enumerate() {
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: 0, y: 0, z: layer as u32 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            image.as_bytes(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    let bind_group_layout = atlas_bind_group_layout(device);
+
+    let (sampler, bind_group) =
+        atlas_bind_group(device, &bind_group_layout, &view, wgpu::FilterMode::Linear);
+
+    (texture, view, sampler, bind_group_layout, bind_group)
+}
+
+// the layout of every atlas page's bind group, the texture array and its sampler
+pub(crate) fn atlas_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
+
+// the sampler and bind group of an atlas texture, recreated to change the filter
+pub(crate) fn atlas_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    filter: wgpu::FilterMode,
+) -> (wgpu::Sampler, wgpu::BindGroup) {
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Glyph Sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        mag_filter: filter,
+        min_filter: filter,
+        ..Default::default()
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+        label: None,
+    });
+    (sampler, bind_group)
+}
@@ -1,22 +1,34 @@
+#include "wrs/globals.wgsl"
+// color glyph texels are converted too, so to_srgb runs on the fragment color
+#include "wrs/color.wgsl"
+#include "wrs/clip.wgsl"
+#include "wrs/oit.wgsl"
+
 struct VertexOut {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
     @location(1) texture_coords: vec2<f32>,
+    @location(2) world_pos: vec2<f32>,
+    @location(3) clip: vec4<f32>,
+    @location(4) @interpolate(flat) color_space: u32,
+    @location(5) @interpolate(flat) layer: u32,
+    @location(6) clip_radius: f32,
+    @location(7) alpha: f32,
 };
 
 struct VertexIn {
     @location(0) pos: vec3<f32>,
     @location(1) color: vec3<f32>,
     @location(2) texture_coords: vec2<f32>,
+    @location(3) clip: vec4<f32>,
+    @location(4) color_space: u32,
+    // of the atlas texture array
+    @location(5) layer: u32,
+    @location(6) clip_radius: f32,
+    // straight
+    @location(7) alpha: f32,
 }
 
-struct CameraUniform {
-    view_proj: mat4x4<f32>,
-};
-
-@group(0) @binding(0)
-var<uniform> camera: CameraUniform;
-
 @vertex
 fn vs_main(
     model: VertexIn
@@ -25,19 +37,107 @@ fn vs_main(
     out.color = model.color;
     out.clip_position = camera.view_proj * vec4<f32>(model.pos, 1.0);
     out.texture_coords = model.texture_coords;
+    out.world_pos = model.pos.xy;
+    out.clip = model.clip;
+    out.color_space = model.color_space;
+    out.layer = model.layer;
+    out.clip_radius = model.clip_radius;
+    out.alpha = model.alpha;
     return out;
 }
 
 @group(1) @binding(0)
-var t_diffuse: texture_2d<f32>;
+var t_diffuse: texture_2d_array<f32>;
 @group(1) @binding(1)
 var s_diffuse: sampler;
 
+// `pipeline_2d::FLAT`, the layer of flat quads, which draw white texels
+const FLAT: u32 = 0xffffffffu;
+
+fn texel(in: VertexOut) -> vec4<f32> {
+    let layer = min(in.layer, textureNumLayers(t_diffuse) - 1u);
+    let tex = textureSample(t_diffuse, s_diffuse, in.texture_coords, layer);
+    return select(tex, vec4<f32>(1.0), in.layer == FLAT);
+}
+
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
-    let tex = textureSample(t_diffuse, s_diffuse, in.texture_coords);
+    let clip = clip_coverage(in.world_pos, in.clip, in.clip_radius);
+    if clip <= 0.0 {
+        discard;
+    }
+    let tex = texel(in);
+    if tex.a < 0.001 {
+        discard;
+    }
+    return vec4<f32>(to_srgb(in.color * tex.rgb, in.color_space), tex.a * in.alpha * clip);
+}
+
+// see `SdfStyle`, distances are in atlas pixels
+struct SdfStyle {
+    outline_color: vec3<f32>,
+    outline_width: f32,
+    shadow_color: vec3<f32>,
+    shadow_opacity: f32,
+    shadow_offset: vec2<f32>,
+    spread: f32,
+};
+
+@group(2) @binding(0)
+var<uniform> sdf_style: SdfStyle;
+
+// Distance field alpha is 0.5 on the glyph outline and changes by
+// 1 / (2 * spread) per atlas pixel. Returns premultiplied color.
+fn sdf_color(in: VertexOut) -> vec4<f32> {
+    let dist = textureSample(t_diffuse, s_diffuse, in.texture_coords, in.layer).a;
+    let shadow_uv = in.texture_coords - sdf_style.shadow_offset / vec2<f32>(textureDimensions(t_diffuse));
+    let shadow_dist = textureSample(t_diffuse, s_diffuse, shadow_uv, in.layer).a;
+    let aa = max(fwidth(dist), 0.0001);
+
+    let fill = smoothstep(0.5 - aa, 0.5 + aa, dist);
+    let edge = 0.5 - sdf_style.outline_width / (2.0 * sdf_style.spread);
+    let body = smoothstep(edge - aa, edge + aa, dist);
+    let shadow = smoothstep(edge - aa, edge + aa, shadow_dist) * sdf_style.shadow_opacity;
+
+    let text = mix(sdf_style.outline_color, in.color, fill) * body;
+    return vec4<f32>(text, body) + vec4<f32>(sdf_style.shadow_color, 1.0) * shadow * (1.0 - body);
+}
+
+@fragment
+fn fs_sdf(in: VertexOut) -> @location(0) vec4<f32> {
+    let clip = clip_coverage(in.world_pos, in.clip, in.clip_radius);
+    if clip <= 0.0 {
+        discard;
+    }
+    let color = sdf_color(in);
+    if color.a < 0.001 {
+        discard;
+    }
+    return vec4<f32>(to_srgb(color.rgb / color.a, in.color_space), color.a * in.alpha * clip);
+}
+
+@fragment
+fn fs_oit(in: VertexOut) -> OitOut {
+    let clip = clip_coverage(in.world_pos, in.clip, in.clip_radius);
+    if clip <= 0.0 {
+        discard;
+    }
+    let tex = texel(in);
     if tex.a < 0.001 {
         discard;
     }
-    return vec4<f32>(in.color * tex.rgb, tex.a);
+    return oit_out(vec4<f32>(to_srgb(in.color * tex.rgb, in.color_space), tex.a * in.alpha * clip), in.clip_position.z);
+}
+
+@fragment
+fn fs_sdf_oit(in: VertexOut) -> OitOut {
+    let clip = clip_coverage(in.world_pos, in.clip, in.clip_radius);
+    if clip <= 0.0 {
+        discard;
+    }
+    let color = sdf_color(in);
+    if color.a < 0.001 {
+        discard;
+    }
+    return oit_out(vec4<f32>(to_srgb(color.rgb / color.a, in.color_space), color.a * in.alpha * clip), in.clip_position.z);
 }
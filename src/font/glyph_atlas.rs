@@ -0,0 +1,631 @@
+use ab_glyph::{Font, ScaleFont};
+use image::EncodableLayout;
+use std::collections::HashMap;
+
+use super::atlas::{
+    Atlas, AtlasKind, GlyphInfo, GlyphSource, REPLACEMENT_CHAR, atlas_bind_group, create_atlas_texture,
+    upload_atlas_texture,
+};
+use super::packer::ShelfPacker;
+use super::sdf::{self, SDF_SPREAD};
+
+const ATLAS_WIDTH: u32 = 2048;
+const MAX_ATLAS_HEIGHT: u32 = 8192;
+// texture array layers, the lowest `max_texture_array_layers` wgpu allows
+const MAX_LAYERS: u32 = 256;
+
+// Atlas for proportional fonts: every glyph is packed at its own size and keeps
+// its own advance and bearing, and kerning pairs come straight from the font.
+// Its pages are the layers of one texture array, a layer is added whenever
+// the last one is full, so large character sets at large sizes still draw in
+// one call.
+pub struct GlyphAtlas {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub bind_group: wgpu::BindGroup,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub glyphs: HashMap<char, GlyphInfo>,
+    // vertical metrics of the font at the atlas scale, descent is negative
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+    pub fallback: Option<char>,
+
+    font: ab_glyph::FontArc,
+    scale: ab_glyph::PxScale,
+    kind: AtlasKind,
+    // one per layer
+    packers: Vec<ShelfPacker>,
+    device: wgpu::Device,
+    filter: wgpu::FilterMode,
+}
+
+pub(super) struct RasterizedGlyph {
+    pub(super) image: Option<image::RgbaImage>,
+    pub(super) offset: (f32, f32),
+    pub(super) advance: f32,
+    // the image has colors of its own, see `GlyphInfo::color`
+    pub(super) color: bool,
+}
+
+pub fn create_glyph_atlas(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    font_data: &[u8],
+    scale: f32,
+) -> GlyphAtlas {
+    build_glyph_atlas(device, queue, font_data, scale, AtlasKind::Bitmap)
+}
+
+// Like `create_glyph_atlas`, but stores distance fields, so one bake at a modest
+// `scale` (32-64px) stays sharp when drawn much larger or smaller.
+pub fn create_sdf_atlas(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    font_data: &[u8],
+    scale: f32,
+) -> GlyphAtlas {
+    build_glyph_atlas(device, queue, font_data, scale, AtlasKind::Sdf)
+}
+
+fn build_glyph_atlas(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    font_data: &[u8],
+    scale: f32,
+    kind: AtlasKind,
+) -> GlyphAtlas {
+    let font = ab_glyph::FontArc::try_from_vec(font_data.to_vec()).unwrap();
+    let scale = ab_glyph::PxScale::from(scale);
+    let (layers, glyphs, packers) = bake(&font, scale, kind, MAX_ATLAS_HEIGHT, "");
+
+    let (texture, view, sampler, bind_group_layout, bind_group) =
+        upload_atlas_texture(device, queue, &layers);
+
+    GlyphAtlas {
+        texture,
+        view,
+        sampler,
+        bind_group,
+        bind_group_layout,
+        glyphs,
+        ascent: font.as_scaled(scale).ascent(),
+        descent: font.as_scaled(scale).descent(),
+        line_gap: font.as_scaled(scale).line_gap(),
+        fallback: Some(REPLACEMENT_CHAR),
+        font,
+        scale,
+        kind,
+        packers,
+        device: device.clone(),
+        filter: wgpu::FilterMode::Linear,
+    }
+}
+
+// Rasterizes printable ASCII, the replacement character and `extra` into
+// packed layers of at most `max_height`, leaving about as much room again for
+// glyphs added later. Only fonts too large for one layer get more than one.
+fn bake(
+    font: &ab_glyph::FontArc,
+    scale: ab_glyph::PxScale,
+    kind: AtlasKind,
+    max_height: u32,
+    extra: &str,
+) -> (Vec<image::RgbaImage>, HashMap<char, GlyphInfo>, Vec<ShelfPacker>) {
+    let mut chars: Vec<char> = (' '..='~').chain([REPLACEMENT_CHAR]).collect();
+    for c in extra.chars() {
+        if !chars.contains(&c) && c != '\t' && c != '\n' {
+            chars.push(c);
+        }
+    }
+    let rasterized: Vec<RasterizedGlyph> =
+        chars.iter().map(|&c| rasterize_kind(font, scale, c, kind)).collect();
+
+    // pack once to find out how many layers there are and how tall they have to be
+    let mut packers = vec![ShelfPacker::new(ATLAS_WIDTH, max_height)];
+    let positions: Vec<Option<(u32, u32, u32)>> = rasterized
+        .iter()
+        .map(|g| {
+            let img = g.image.as_ref()?;
+            let (w, h) = img.dimensions();
+            if w > ATLAS_WIDTH || h > max_height {
+                return None;
+            }
+            let (x, y) = match packers.last_mut().unwrap().allocate(w, h) {
+                Some(pos) => pos,
+                None => {
+                    packers.push(ShelfPacker::new(ATLAS_WIDTH, max_height));
+                    packers.last_mut().unwrap().allocate(w, h)?
+                }
+            };
+            Some((packers.len() as u32 - 1, x, y))
+        })
+        .collect();
+    let height = match packers.len() {
+        1 => (packers[0].used_height() * 2).clamp(1, max_height),
+        _ => max_height,
+    };
+    for packer in &mut packers {
+        packer.height = height;
+    }
+
+    let mut layers = vec![image::RgbaImage::new(ATLAS_WIDTH, height); packers.len()];
+    let mut glyphs = HashMap::new();
+    for ((&c, glyph), pos) in chars.iter().zip(&rasterized).zip(positions) {
+        let info = match (&glyph.image, pos) {
+            (Some(img), Some((layer, x, y))) => {
+                image::imageops::overlay(&mut layers[layer as usize], img, x.into(), y.into());
+                GlyphInfo {
+                    page: layer,
+                    ..glyph_info(glyph, img, (x, y), (ATLAS_WIDTH, height))
+                }
+            }
+            _ => empty_glyph_info(glyph),
+        };
+        glyphs.insert(c, info);
+    }
+    (layers, glyphs, packers)
+}
+
+// The pages and placements of a `GlyphAtlas` rasterized ahead of time, e.g.
+// by `assets::AssetBuilder` in a build script, which `GlyphAtlas::from_baked`
+// uploads as they are.
+#[derive(Debug, Clone)]
+pub struct BakedGlyphs {
+    pub layers: Vec<image::RgbaImage>,
+    pub glyphs: HashMap<char, GlyphInfo>,
+    // rows of each layer taken by its glyphs, the rest is left for glyphs
+    // added at runtime
+    pub used_heights: Vec<u32>,
+    pub kind: AtlasKind,
+    pub scale: f32,
+}
+
+// The glyphs `create_glyph_atlas` or `create_sdf_atlas` rasterize, and the
+// chars of `extra` with them, without a gpu.
+pub fn bake_glyphs(font_data: &[u8], scale: f32, kind: AtlasKind, extra: &str) -> Result<BakedGlyphs, ab_glyph::InvalidFont> {
+    let font = ab_glyph::FontArc::try_from_vec(font_data.to_vec())?;
+    let (layers, glyphs, packers) = bake(&font, ab_glyph::PxScale::from(scale), kind, MAX_ATLAS_HEIGHT, extra);
+    Ok(BakedGlyphs {
+        layers,
+        glyphs,
+        used_heights: packers.iter().map(ShelfPacker::used_height).collect(),
+        kind,
+        scale,
+    })
+}
+
+// The same glyphs as `create_glyph_atlas`, kept in memory instead of uploaded,
+// for drawing without a gpu.
+pub struct CpuGlyphAtlas {
+    pub image: image::RgbaImage,
+    pub glyphs: HashMap<char, GlyphInfo>,
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+    pub fallback: Option<char>,
+
+    font: ab_glyph::FontArc,
+    scale: ab_glyph::PxScale,
+}
+
+pub fn create_cpu_glyph_atlas(font_data: &[u8], scale: f32) -> CpuGlyphAtlas {
+    let font = ab_glyph::FontArc::try_from_vec(font_data.to_vec()).unwrap();
+    let scale = ab_glyph::PxScale::from(scale);
+    // one image, glyphs that don't fit into it are left out
+    let (mut layers, mut glyphs, _) = bake(&font, scale, AtlasKind::Bitmap, MAX_ATLAS_HEIGHT, "");
+    glyphs.retain(|_, info| info.page == 0);
+    let image = layers.swap_remove(0);
+    CpuGlyphAtlas {
+        image,
+        glyphs,
+        ascent: font.as_scaled(scale).ascent(),
+        descent: font.as_scaled(scale).descent(),
+        line_gap: font.as_scaled(scale).line_gap(),
+        fallback: Some(REPLACEMENT_CHAR),
+        font,
+        scale,
+    }
+}
+
+impl GlyphSource for CpuGlyphAtlas {
+    fn glyph(&self, c: char) -> Option<GlyphInfo> {
+        self.glyphs.get(&c).copied()
+    }
+
+    fn fallback(&self) -> Option<char> {
+        self.fallback
+    }
+
+    fn kern(&self, left: char, right: char) -> f32 {
+        self.font
+            .as_scaled(self.scale)
+            .kern(self.font.glyph_id(left), self.font.glyph_id(right))
+    }
+
+    fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+
+    fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    fn font_size(&self) -> f32 {
+        self.scale.y
+    }
+}
+
+impl GlyphAtlas {
+    // An atlas of glyphs baked from `font_data` by `bake_glyphs`, uploaded
+    // without rasterizing any. Glyphs missing from it are still rasterized
+    // when `warm_cache` asks for them.
+    pub fn from_baked(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        font_data: &[u8],
+        baked: &BakedGlyphs,
+    ) -> Result<GlyphAtlas, ab_glyph::InvalidFont> {
+        let font = ab_glyph::FontArc::try_from_vec(font_data.to_vec())?;
+        let scale = ab_glyph::PxScale::from(baked.scale);
+        let (texture, view, sampler, bind_group_layout, bind_group) =
+            upload_atlas_texture(device, queue, &baked.layers);
+        let (width, height) = (texture.width(), texture.height());
+        let packers = baked
+            .used_heights
+            .iter()
+            .map(|used| ShelfPacker::resume(width, height, *used))
+            .collect();
+        Ok(GlyphAtlas {
+            texture,
+            view,
+            sampler,
+            bind_group,
+            bind_group_layout,
+            glyphs: baked.glyphs.clone(),
+            ascent: font.as_scaled(scale).ascent(),
+            descent: font.as_scaled(scale).descent(),
+            line_gap: font.as_scaled(scale).line_gap(),
+            fallback: Some(REPLACEMENT_CHAR),
+            font,
+            scale,
+            kind: baked.kind,
+            packers,
+            device: device.clone(),
+            filter: wgpu::FilterMode::Linear,
+        })
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+
+    // the font the atlas was rasterized from, e.g. for `outline_text`
+    pub fn font(&self) -> &ab_glyph::FontArc {
+        &self.font
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.glyphs.contains_key(&c)
+    }
+
+    // see `MonoGlyphAtlas::set_filter`
+    pub fn set_filter(&mut self, device: &wgpu::Device, filter: wgpu::FilterMode) {
+        self.filter = filter;
+        (self.sampler, self.bind_group) =
+            atlas_bind_group(device, &self.bind_group_layout, &self.view, filter);
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.packers.len()
+    }
+
+    // Rasterizes and packs every glyph of `text` that isn't in the atlas yet,
+    // glyphs without an outline take no room, every size is scaled from the
+    // one raster like in `MonoGlyphAtlas::warm_cache`. Returns the characters
+    // that didn't fit because the atlas is full, which takes `MAX_LAYERS`
+    // layers or a glyph larger than one.
+    pub fn warm_cache(&mut self, queue: &wgpu::Queue, text: &str) -> Vec<char> {
+        let mut dropped = vec![];
+        for c in text.chars() {
+            if self.contains(c) || dropped.contains(&c) || c.is_control() {
+                continue;
+            }
+            let glyph = rasterize_kind(&self.font, self.scale, c, self.kind);
+            if !self.upload(queue, c, &glyph) {
+                dropped.push(c);
+            }
+        }
+        dropped
+    }
+
+    // Draws `c` as `image` from now on, e.g. emoji from a png set for fonts that
+    // have none. The image is scaled to the height of the line without the gap
+    // and drawn with its own colors. Returns false when the atlas is full.
+    pub fn insert_image(&mut self, queue: &wgpu::Queue, c: char, image: &image::RgbaImage) -> bool {
+        let height = self.ascent - self.descent;
+        let width = image.width() as f32 * height / image.height().max(1) as f32;
+        let image = image::imageops::resize(
+            image,
+            (width.round() as u32).max(1),
+            (height.round() as u32).max(1),
+            image::imageops::FilterType::Triangle,
+        );
+        let glyph = RasterizedGlyph {
+            image: Some(image),
+            offset: (0.0, 0.0),
+            advance: width,
+            color: true,
+        };
+        self.upload(queue, c, &to_kind(glyph, self.kind))
+    }
+
+    fn upload(&mut self, queue: &wgpu::Queue, c: char, glyph: &RasterizedGlyph) -> bool {
+        let Some(img) = &glyph.image else {
+            self.glyphs.insert(c, empty_glyph_info(glyph));
+            return true;
+        };
+        let (w, h) = img.dimensions();
+        let pos = match self.packers.last_mut().unwrap().allocate(w, h) {
+            Some(pos) => Some(pos),
+            None if self.add_layer(queue) => self.packers.last_mut().unwrap().allocate(w, h),
+            None => None,
+        };
+        let Some((x, y)) = pos else {
+            return false;
+        };
+        let layer = self.packers.len() as u32 - 1;
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: layer },
+                aspect: wgpu::TextureAspect::All,
+            },
+            img.as_bytes(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * img.width()),
+                rows_per_image: Some(img.height()),
+            },
+            wgpu::Extent3d {
+                width: img.width(),
+                height: img.height(),
+                depth_or_array_layers: 1,
+            },
+        );
+        let packer = &self.packers[0];
+        let info = glyph_info(glyph, img, (x, y), (packer.width, packer.height));
+        self.glyphs.insert(c, GlyphInfo { page: layer, ..info });
+        true
+    }
+
+    // Moves the layers into a texture array with one more. Glyphs pushed
+    // before keep drawing from the old texture through the old bind group.
+    fn add_layer(&mut self, queue: &wgpu::Queue) -> bool {
+        let layers = self.packers.len() as u32;
+        if layers >= MAX_LAYERS.min(self.device.limits().max_texture_array_layers) {
+            return false;
+        }
+        let (width, height) = (self.texture.width(), self.texture.height());
+        // see `create_atlas_texture`
+        if self.texture.depth_or_array_layers() > layers {
+            self.packers.push(ShelfPacker::new(width, height));
+            return true;
+        }
+        let (texture, view) = create_atlas_texture(&self.device, (width, height), layers + 1);
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Glyph Atlas Growth"),
+        });
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            texture.as_image_copy(),
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: layers,
+            },
+        );
+        queue.submit([encoder.finish()]);
+
+        self.texture = texture;
+        self.view = view;
+        (self.sampler, self.bind_group) =
+            atlas_bind_group(&self.device, &self.bind_group_layout, &self.view, self.filter);
+        self.packers.push(ShelfPacker::new(width, height));
+        true
+    }
+}
+
+impl GlyphSource for GlyphAtlas {
+    fn glyph(&self, c: char) -> Option<GlyphInfo> {
+        self.glyphs.get(&c).copied()
+    }
+
+    fn fallback(&self) -> Option<char> {
+        self.fallback
+    }
+
+    fn kern(&self, left: char, right: char) -> f32 {
+        self.font
+            .as_scaled(self.scale)
+            .kern(self.font.glyph_id(left), self.font.glyph_id(right))
+    }
+
+    fn line_height(&self) -> f32 {
+        GlyphAtlas::line_height(self)
+    }
+
+    fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    fn font_size(&self) -> f32 {
+        self.scale.y
+    }
+
+    fn kind(&self) -> AtlasKind {
+        self.kind
+    }
+}
+
+impl Atlas for GlyphAtlas {
+    fn bind_group(&self, _page: u32) -> wgpu::BindGroup {
+        self.bind_group.clone()
+    }
+
+    fn layer(&self, page: u32) -> u32 {
+        page
+    }
+
+    fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+}
+
+pub(super) fn rasterize(font: &ab_glyph::FontArc, scale: ab_glyph::PxScale, c: char) -> RasterizedGlyph {
+    rasterize_id(font, scale, font.glyph_id(c))
+}
+
+pub(super) fn rasterize_id(
+    font: &ab_glyph::FontArc,
+    scale: ab_glyph::PxScale,
+    id: ab_glyph::GlyphId,
+) -> RasterizedGlyph {
+    let scaled = font.as_scaled(scale);
+    let advance = scaled.h_advance(id);
+    // with the glyph origin on the baseline one ascent below the pen, px_bounds
+    // is directly the quad offset from the top of the line
+    let glyph = id.with_scale_and_position(scale, ab_glyph::point(0.0, scaled.ascent()));
+    let Some(og) = font.outline_glyph(glyph) else {
+        return rasterize_color(font, scale, id).unwrap_or(RasterizedGlyph {
+            image: None,
+            offset: (0.0, 0.0),
+            advance,
+            color: false,
+        });
+    };
+
+    let bounds = og.px_bounds();
+    let (w, h) = (bounds.width() as u32, bounds.height() as u32);
+    let mut img = image::RgbaImage::new(w.max(1), h.max(1));
+    og.draw(|x, y, v| {
+        if x < w && y < h {
+            img.put_pixel(x, y, image::Rgba([255, 255, 255, (v * 255.0) as u8]));
+        }
+    });
+    RasterizedGlyph {
+        image: Some(img),
+        offset: (bounds.min.x, bounds.min.y),
+        advance,
+        color: false,
+    }
+}
+
+// Glyphs without an outline from fonts with color bitmaps (CBDT, sbix), like
+// most emoji fonts. The closest strike is scaled to the requested size.
+fn rasterize_color(
+    font: &ab_glyph::FontArc,
+    scale: ab_glyph::PxScale,
+    id: ab_glyph::GlyphId,
+) -> Option<RasterizedGlyph> {
+    let scaled = font.as_scaled(scale);
+    let em = scaled.h_scale_factor() * font.units_per_em()?;
+    let raster = font.glyph_raster_image2(id, em.round() as u16)?;
+    let img = match raster.format {
+        ab_glyph::GlyphImageFormat::Png => {
+            image::load_from_memory_with_format(raster.data, image::ImageFormat::Png)
+                .ok()?
+                .into_rgba8()
+        }
+        ab_glyph::GlyphImageFormat::BitmapPremulBgra32 => {
+            let mut img = image::RgbaImage::new(raster.width.into(), raster.height.into());
+            for (pixel, bgra) in img.pixels_mut().zip(raster.data.chunks_exact(4)) {
+                let unpremultiply = |c: u8| match bgra[3] {
+                    0 => 0,
+                    a => (c as u32 * 255 / a as u32).min(255) as u8,
+                };
+                *pixel = image::Rgba([unpremultiply(bgra[2]), unpremultiply(bgra[1]), unpremultiply(bgra[0]), bgra[3]]);
+            }
+            img
+        }
+        _ => return None,
+    };
+
+    let factor = em / raster.pixels_per_em as f32;
+    let (w, h) = (
+        (img.width() as f32 * factor).round().max(1.0) as u32,
+        (img.height() as f32 * factor).round().max(1.0) as u32,
+    );
+    let img = image::imageops::resize(&img, w, h, image::imageops::FilterType::Triangle);
+    // the raster origin is the bottom left corner relative to the baseline, y up
+    Some(RasterizedGlyph {
+        offset: (
+            raster.origin.x * factor,
+            scaled.ascent() - raster.origin.y * factor - h as f32,
+        ),
+        image: Some(img),
+        advance: scaled.h_advance(id),
+        color: true,
+    })
+}
+
+fn rasterize_kind(
+    font: &ab_glyph::FontArc,
+    scale: ab_glyph::PxScale,
+    c: char,
+    kind: AtlasKind,
+) -> RasterizedGlyph {
+    to_kind(rasterize(font, scale, c), kind)
+}
+
+// color glyphs only keep their silhouette in a distance field
+fn to_kind(glyph: RasterizedGlyph, kind: AtlasKind) -> RasterizedGlyph {
+    match (kind, glyph.image) {
+        (AtlasKind::Sdf, Some(img)) => RasterizedGlyph {
+            image: Some(sdf::distance_field(&img)),
+            offset: (
+                glyph.offset.0 - SDF_SPREAD as f32,
+                glyph.offset.1 - SDF_SPREAD as f32,
+            ),
+            advance: glyph.advance,
+            color: false,
+        },
+        (_, image) => RasterizedGlyph { image, ..glyph },
+    }
+}
+
+pub(super) fn glyph_info(
+    glyph: &RasterizedGlyph,
+    img: &image::RgbaImage,
+    (x, y): (u32, u32),
+    (atlas_w, atlas_h): (u32, u32),
+) -> GlyphInfo {
+    let (w, h) = img.dimensions();
+    GlyphInfo {
+        uv: (
+            x as f32 / atlas_w as f32,
+            y as f32 / atlas_h as f32,
+            (x + w) as f32 / atlas_w as f32,
+            (y + h) as f32 / atlas_h as f32,
+        ),
+        offset: glyph.offset,
+        size: (w as f32, h as f32),
+        advance: glyph.advance,
+        page: 0,
+        color: glyph.color,
+    }
+}
+
+pub(super) fn empty_glyph_info(glyph: &RasterizedGlyph) -> GlyphInfo {
+    GlyphInfo {
+        uv: (0.0, 0.0, 0.0, 0.0),
+        offset: (0.0, 0.0),
+        size: (0.0, 0.0),
+        advance: glyph.advance,
+        page: 0,
+        color: false,
+    }
+}
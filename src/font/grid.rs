@@ -0,0 +1,99 @@
+use super::layout::PositionedGlyph;
+use super::{GlyphSource, TabStops};
+use crate::rect::Rect;
+
+// A fixed grid of character cells for terminal style text, every char taking
+// one cell whatever its width, see `Renderer::push_text_grid`. Cells are as
+// wide as the font's 'M' and as tall as its line at `size` pixels.
+//
+//   let grid = TextGrid::new(10.0, 10.0, 16.0).with_tab_width(8);
+//   frame.text_grid(&grid, 0, row, "$ ls\tsrc", &TextStyle::new([1.0, 1.0, 1.0]))?;
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextGrid {
+    // top left corner of the cell at column 0, row 0
+    pub x: f32,
+    pub y: f32,
+    pub size: f32,
+    pub tab_stops: TabStops,
+}
+
+impl TextGrid {
+    pub fn new(x: f32, y: f32, size: f32) -> Self {
+        Self {
+            x,
+            y,
+            size,
+            tab_stops: TabStops::default(),
+        }
+    }
+
+    pub fn with_tab_stops(mut self, tab_stops: TabStops) -> Self {
+        self.tab_stops = tab_stops;
+        self
+    }
+
+    // a stop every `width` columns
+    pub fn with_tab_width(self, width: usize) -> Self {
+        self.with_tab_stops(TabStops::Every(width))
+    }
+
+    // width and height of one cell
+    pub fn cell_size(&self, atlas: &impl GlyphSource) -> (f32, f32) {
+        let scale = self.size / atlas.font_size();
+        let advance = atlas.glyph_or_fallback('M').map_or(0.0, |g| g.advance);
+        (advance * scale, atlas.line_height() * scale)
+    }
+
+    pub fn cell_rect(&self, col: usize, row: usize, atlas: &impl GlyphSource) -> Rect {
+        let (w, h) = self.cell_size(atlas);
+        Rect::new(self.x + col as f32 * w, self.y + row as f32 * h, w, h)
+    }
+
+    // the cell under a point, e.g. the mouse, None left of or above the grid
+    pub fn cell_at(&self, [x, y]: [f32; 2], atlas: &impl GlyphSource) -> Option<(usize, usize)> {
+        let (w, h) = self.cell_size(atlas);
+        let (col, row) = ((x - self.x) / w, (y - self.y) / h);
+        (col >= 0.0 && row >= 0.0).then_some((col as usize, row as usize))
+    }
+
+    // The column and row each char of `text` lands in when it starts at `col`,
+    // `row`. A '\t' moves on to the next tab stop and a '\n' to `col` of the
+    // next row, neither takes a cell.
+    pub fn cells(&self, col: usize, row: usize, text: &str) -> Vec<(usize, usize, char)> {
+        let (mut at, mut line) = (col, row);
+        let mut cells = vec![];
+        for c in text.chars() {
+            match c {
+                '\t' => at = self.tab_stops.next_stop(at),
+                '\n' => (at, line) = (col, line + 1),
+                c => {
+                    cells.push((at, line, c));
+                    at += 1;
+                }
+            }
+        }
+        cells
+    }
+
+    // glyph positions of `cells` relative to the grid's top left corner, glyphs
+    // narrower than a cell centered in theirs
+    pub fn layout(&self, col: usize, row: usize, text: &str, atlas: &impl GlyphSource) -> Vec<PositionedGlyph> {
+        let scale = self.size / atlas.font_size();
+        let (w, h) = self.cell_size(atlas);
+        let indices = text.char_indices().filter(|&(_, c)| c != '\t' && c != '\n');
+        self.cells(col, row, text)
+            .into_iter()
+            .zip(indices)
+            .map(|((col, row, c), (index, _))| {
+                let advance = atlas.glyph_or_fallback(c).map_or(w, |g| g.advance * scale);
+                PositionedGlyph {
+                    x: col as f32 * w + ((w - advance) / 2.0).max(0.0),
+                    y: row as f32 * h,
+                    c,
+                    id: None,
+                    index,
+                }
+            })
+            .collect()
+    }
+}
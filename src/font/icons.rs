@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::glyph_atlas::{GlyphAtlas, create_sdf_atlas};
+
+// Icon fonts like Material Symbols or Font Awesome, looked up by icon name and
+// stored as distance fields, so one bake draws crisp at any size. Names come
+// from a codepoints listing, the format Material Symbols ships next to its
+// fonts: one `name hex-codepoint` pair per line.
+pub struct IconFont {
+    // pass it wherever an `Atlas` is expected, e.g. `FontRenderer::push`
+    pub atlas: GlyphAtlas,
+    names: HashMap<String, char>,
+}
+
+impl IconFont {
+    // `scale` is the pixel size the icons are baked at, 48-64 covers the usual
+    // ui sizes and well beyond
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        font_data: &[u8],
+        codepoints: &str,
+        scale: f32,
+    ) -> Self {
+        Self {
+            atlas: create_sdf_atlas(device, queue, font_data, scale),
+            names: parse_codepoints(codepoints).into_iter().collect(),
+        }
+    }
+
+    // the char to draw for the icon called `name`
+    pub fn icon(&self, name: &str) -> Option<char> {
+        self.names.get(name).copied()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.keys().map(String::as_str)
+    }
+
+    // Bakes the named icons into the atlas. Only icons baked this way can be
+    // drawn, the rest show up as the atlas' fallback. Returns the names that
+    // are unknown or didn't fit because the atlas is full.
+    pub fn warm_cache(&mut self, queue: &wgpu::Queue, names: &[&str]) -> Vec<String> {
+        let mut failed = vec![];
+        for &name in names {
+            let Some(c) = self.icon(name) else {
+                failed.push(name.to_string());
+                continue;
+            };
+            if !self.atlas.warm_cache(queue, &c.to_string()).is_empty() {
+                failed.push(name.to_string());
+            }
+        }
+        failed
+    }
+}
+
+// Reads a codepoints listing. Codepoints may carry a `0x` or `U+` prefix, blank
+// lines, `#` comments and lines that don't parse are skipped.
+pub fn parse_codepoints(text: &str) -> Vec<(String, char)> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.starts_with('#') {
+                return None;
+            }
+            let mut parts = line.split_whitespace();
+            let (name, hex) = (parts.next()?, parts.next()?);
+            let hex = hex
+                .trim_start_matches("0x")
+                .trim_start_matches("U+")
+                .trim_start_matches("u+");
+            let c = char::from_u32(u32::from_str_radix(hex, 16).ok()?)?;
+            Some((name.to_string(), c))
+        })
+        .collect()
+}
+
+// Generates Rust source for an enum with one variant per icon of a codepoints
+// listing, so icons are checked at compile time instead of looked up by string.
+// Meant for build scripts, or `cargo run --example icon_enum`. Variants are the
+// names in CamelCase, prefixed with `I` when they'd start with a digit; later
+// names that collide with an earlier one are left out.
+pub fn generate_enum(codepoints: &str, enum_name: &str) -> String {
+    let mut variants: Vec<(String, String, char)> = vec![];
+    for (name, c) in parse_codepoints(codepoints) {
+        let ident = variant_name(&name);
+        if !variants.iter().any(|(existing, _, _)| *existing == ident) {
+            variants.push((ident, name, c));
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// generated by wrs::font::icons::generate_enum, don't edit").unwrap();
+    writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]").unwrap();
+    writeln!(out, "pub enum {enum_name} {{").unwrap();
+    for (ident, _, _) in &variants {
+        writeln!(out, "    {ident},").unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+    writeln!(out, "impl {enum_name} {{").unwrap();
+    writeln!(out, "    pub const ALL: &'static [{enum_name}] = &[").unwrap();
+    for (ident, _, _) in &variants {
+        writeln!(out, "        {enum_name}::{ident},").unwrap();
+    }
+    writeln!(out, "    ];\n").unwrap();
+    writeln!(out, "    pub fn char(self) -> char {{").unwrap();
+    writeln!(out, "        match self {{").unwrap();
+    for (ident, _, c) in &variants {
+        writeln!(out, "            {enum_name}::{ident} => '\\u{{{:x}}}',", *c as u32).unwrap();
+    }
+    writeln!(out, "        }}\n    }}\n").unwrap();
+    writeln!(out, "    pub fn name(self) -> &'static str {{").unwrap();
+    writeln!(out, "        match self {{").unwrap();
+    for (ident, name, _) in &variants {
+        writeln!(out, "            {enum_name}::{ident} => {name:?},").unwrap();
+    }
+    writeln!(out, "        }}\n    }}\n}}").unwrap();
+    out
+}
+
+// "arrow_back" -> "ArrowBack", "10k" -> "I10k"
+fn variant_name(name: &str) -> String {
+    let mut ident = String::new();
+    for word in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            ident.push(first.to_ascii_uppercase());
+            ident.extend(chars);
+        }
+    }
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) || ident == "Self" {
+        ident.insert(0, 'I');
+    }
+    ident
+}
@@ -0,0 +1,672 @@
+use std::ops::Range;
+
+use super::{GlyphSource, TabStops};
+use crate::rect::Rect;
+
+// Lays out multi-paragraph text. Every '\n' ends a paragraph; line heights are
+// taken from the font's ascent, descent and line gap, not the atlas cell.
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+    // multiplier of the font's natural line height
+    pub line_height: f32,
+    // extra pixels between the last line of a paragraph and the next one
+    pub paragraph_spacing: f32,
+    // pixels the first line of every paragraph is shifted right by
+    pub first_line_indent: f32,
+    pub tab_stops: TabStops,
+    // lines are wrapped at spaces to fit, words wider than this are split
+    pub max_width: Option<f32>,
+    pub align: Align,
+    // where `offset_in` puts the block within a rect
+    pub valign: VAlign,
+    // what the point text is drawn at is, the block's top left corner or the
+    // start of its first baseline
+    pub origin: TextOrigin,
+    // Pixels between the lines of a baseline grid counted from the top of the
+    // block: every line's baseline moves down onto the next one, so text of
+    // mixed sizes drawn at the same top shares its baselines where its lines
+    // fit into the same number of grid steps.
+    pub baseline_grid: Option<f32>,
+}
+
+// Horizontal alignment of every line within `max_width`, or within the widest
+// line without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+    // stretches the spaces of wrapped lines, the last line of a paragraph stays left aligned
+    Justify,
+}
+
+// Vertical alignment of the block within a rect, see `TextLayout::offset_in`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VAlign {
+    #[default]
+    Top,
+    Middle,
+    // the first line's baseline on the bottom edge, like text written on a
+    // ruled line, so rows of mixed sizes in the same rect line up; descenders
+    // and further lines reach below it
+    Baseline,
+    Bottom,
+}
+
+// The point glyph positions are relative to, see `TextLayout::origin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextOrigin {
+    #[default]
+    TopLeft,
+    // the left end of the first line's baseline, for lining text up with
+    // other text or a drawn line whatever its size
+    Baseline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub x: f32,
+    pub y: f32,
+    pub c: char,
+    // set for shaped glyphs, `c` is then the first char of the cluster the glyph
+    // was shaped from
+    pub id: Option<ab_glyph::GlyphId>,
+    // byte offset of `c` in the text laid out
+    pub index: usize,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self {
+            line_height: 1.0,
+            paragraph_spacing: 0.0,
+            first_line_indent: 0.0,
+            tab_stops: TabStops::default(),
+            max_width: None,
+            align: Align::Left,
+            valign: VAlign::Top,
+            origin: TextOrigin::TopLeft,
+            baseline_grid: None,
+        }
+    }
+}
+
+// one line after wrapping, glyph positions relative to its start
+struct Line {
+    glyphs: Vec<PositionedGlyph>,
+    indent: f32,
+    // up to the end of the last visible glyph, trailing spaces don't count
+    width: f32,
+    ends_paragraph: bool,
+    // bytes of the text on the line, up to the spaces or the '\n' it ends at
+    range: Range<usize>,
+}
+
+// a line as `layout` places it, relative to the top of the block
+struct PlacedLine {
+    // where the line starts after its indent and alignment
+    x: f32,
+    y: f32,
+    glyphs: Vec<PositionedGlyph>,
+    range: Range<usize>,
+}
+
+impl TextLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_line_height(mut self, line_height: f32) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn with_paragraph_spacing(mut self, paragraph_spacing: f32) -> Self {
+        self.paragraph_spacing = paragraph_spacing;
+        self
+    }
+
+    pub fn with_first_line_indent(mut self, first_line_indent: f32) -> Self {
+        self.first_line_indent = first_line_indent;
+        self
+    }
+
+    pub fn with_tab_stops(mut self, tab_stops: TabStops) -> Self {
+        self.tab_stops = tab_stops;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_valign(mut self, valign: VAlign) -> Self {
+        self.valign = valign;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: TextOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn with_baseline_grid(mut self, grid: f32) -> Self {
+        self.baseline_grid = Some(grid).filter(|grid| *grid > 0.0);
+        self
+    }
+
+    // glyph positions relative to the origin of the text drawn at `size`
+    // pixels
+    pub fn layout(&self, text: &str, size: f32, atlas: &impl GlyphSource) -> Vec<PositionedGlyph> {
+        let mut glyphs = self.layout_from_top(text, size, atlas);
+        let top = self.origin_offset(size, atlas);
+        if top != 0.0 {
+            for glyph in &mut glyphs {
+                glyph.y -= top;
+            }
+        }
+        glyphs
+    }
+
+    // How far below the top of the block its origin is at `size` pixels, the
+    // first baseline for `TextOrigin::Baseline`.
+    pub fn origin_offset(&self, size: f32, atlas: &impl GlyphSource) -> f32 {
+        match self.origin {
+            TextOrigin::TopLeft => 0.0,
+            TextOrigin::Baseline => self.snap_baseline(atlas.ascent() * size / atlas.font_size()),
+        }
+    }
+
+    // Where a caret in front of the char at byte `index` of `text` goes, a
+    // rect without width as tall as the line, relative to the origin like
+    // `layout`. An index past the end puts it behind the last char.
+    pub fn caret(&self, text: &str, size: f32, atlas: &impl GlyphSource, index: usize) -> Rect {
+        let scale = size / atlas.font_size();
+        let lines = self.place_lines(text, size, atlas);
+        let line = line_of(&lines, index);
+        let top = self.origin_offset(size, atlas);
+        Rect::new(caret_x(line, index, scale, atlas), line.y - top, 0.0, atlas.line_height() * scale)
+    }
+
+    // One rect per line the bytes of `range` cover, for drawing a selection
+    // under the text. A line whose break is selected gets the width of a space
+    // more, so selected empty lines show.
+    pub fn selection(&self, text: &str, size: f32, atlas: &impl GlyphSource, range: Range<usize>) -> Vec<Rect> {
+        if range.is_empty() {
+            return vec![];
+        }
+        let scale = size / atlas.font_size();
+        let top = self.origin_offset(size, atlas);
+        let height = atlas.line_height() * scale;
+        let space = atlas.glyph_or_fallback(' ').map_or(0.0, |g| g.advance) * scale;
+        self.place_lines(text, size, atlas)
+            .iter()
+            .filter(|line| range.start <= line.range.end && range.end > line.range.start)
+            .map(|line| {
+                let left = caret_x(line, range.start.max(line.range.start), scale, atlas);
+                let mut right = caret_x(line, range.end.min(line.range.end), scale, atlas);
+                if range.end > line.range.end {
+                    right += space;
+                }
+                Rect::new(left, line.y - top, right - left, height)
+            })
+            .collect()
+    }
+
+    // The byte index of the caret position nearest to `point`, relative to the
+    // origin like `layout`, for putting the caret where a click went.
+    pub fn index_at(&self, text: &str, size: f32, atlas: &impl GlyphSource, [x, y]: [f32; 2]) -> usize {
+        let scale = size / atlas.font_size();
+        let y = y + self.origin_offset(size, atlas);
+        let lines = self.place_lines(text, size, atlas);
+        let line = lines.iter().rev().find(|line| line.y <= y).unwrap_or(&lines[0]);
+        let end = (caret_x(line, line.range.end, scale, atlas), line.range.end);
+        line.glyphs
+            .iter()
+            .map(|g| (g.x, g.index))
+            .chain([end])
+            .min_by(|a, b| (a.0 - x).abs().total_cmp(&(b.0 - x).abs()))
+            .map_or(line.range.start, |(_, index)| index)
+    }
+
+    fn layout_from_top(&self, text: &str, size: f32, atlas: &impl GlyphSource) -> Vec<PositionedGlyph> {
+        self.place_lines(text, size, atlas)
+            .into_iter()
+            .flat_map(|line| line.glyphs)
+            .collect()
+    }
+
+    fn place_lines(&self, text: &str, size: f32, atlas: &impl GlyphSource) -> Vec<PlacedLine> {
+        let scale = size / atlas.font_size();
+        let line_advance = atlas.line_height() * scale * self.line_height;
+        let mut lines = vec![];
+        let mut offset = 0;
+        for paragraph in text.split('\n') {
+            self.wrap(paragraph, offset, scale, atlas, &mut lines);
+            offset += paragraph.len() + 1;
+        }
+        let block_width = self.max_width.unwrap_or_else(|| {
+            lines
+                .iter()
+                .map(|line| line.indent + line.width)
+                .fold(0.0, f32::max)
+        });
+
+        let ascent = atlas.ascent() * scale;
+        let mut placed = vec![];
+        let mut y = 0.0;
+        for line in lines {
+            y = self.snap_baseline(y + ascent) - ascent;
+            let free = (block_width - line.indent - line.width).max(0.0);
+            let spaces = line.glyphs.iter().filter(|g| g.c == ' ').count();
+            let (start, space_stretch) = match self.align {
+                Align::Left => (0.0, 0.0),
+                Align::Center => (free / 2.0, 0.0),
+                Align::Right => (free, 0.0),
+                Align::Justify if !line.ends_paragraph && spaces > 0 => {
+                    (0.0, free / spaces as f32)
+                }
+                Align::Justify => (0.0, 0.0),
+            };
+            let mut spaces_before = 0;
+            let mut glyphs = vec![];
+            for glyph in &line.glyphs {
+                if glyph.c == ' ' {
+                    spaces_before += 1;
+                }
+                glyphs.push(PositionedGlyph {
+                    x: line.indent + start + glyph.x + space_stretch * spaces_before as f32,
+                    y: y + glyph.y,
+                    ..*glyph
+                });
+            }
+            placed.push(PlacedLine {
+                x: line.indent + start,
+                y,
+                glyphs,
+                range: line.range,
+            });
+            y += line_advance;
+            if line.ends_paragraph {
+                y += self.paragraph_spacing;
+            }
+        }
+        placed
+    }
+
+    // Where to draw `text` at `size` pixels for `valign` to place it within
+    // `rect`. Only the top moves, horizontally the lines are aligned within
+    // `max_width` as always, which wants to be the rect's width for that.
+    // With a baseline grid it moves by whole grid steps, counted from the
+    // rect's top, so the baselines stay on the grid.
+    pub fn offset_in(&self, text: &str, size: f32, atlas: &impl GlyphSource, rect: Rect) -> (f32, f32) {
+        let origin = self.origin_offset(size, atlas);
+        let dy = match self.valign {
+            VAlign::Top => return (rect.x, rect.y + origin),
+            VAlign::Middle => (rect.h - self.measure(text, size, atlas).1) / 2.0,
+            VAlign::Bottom => rect.h - self.measure(text, size, atlas).1,
+            VAlign::Baseline => rect.h - self.snap_baseline(atlas.ascent() * size / atlas.font_size()),
+        };
+        let dy = match self.baseline_grid {
+            Some(grid) => (dy / grid).floor() * grid,
+            None => dy,
+        };
+        (rect.x, rect.y + dy + origin)
+    }
+
+    // a baseline `y` pixels below the top of the block moved onto the grid
+    fn snap_baseline(&self, y: f32) -> f32 {
+        match self.baseline_grid {
+            // a little slack so lines already on it don't skip a step
+            Some(grid) => (y / grid - 1e-3).ceil() * grid,
+            None => y,
+        }
+    }
+
+    // width and height of the block `layout` places `text` in, at `size` pixels
+    pub fn measure(&self, text: &str, size: f32, atlas: &impl GlyphSource) -> (f32, f32) {
+        let scale = size / atlas.font_size();
+        let glyphs = self.layout_from_top(text, size, atlas);
+        let width = glyphs
+            .iter()
+            .map(|g| g.x + advance(g, atlas) * scale)
+            .fold(0.0, f32::max);
+        let height = glyphs
+            .iter()
+            .map(|g| g.y + atlas.line_height() * scale)
+            .fold(0.0, f32::max);
+        (width, height)
+    }
+
+    // Splits one paragraph into lines no wider than `max_width`, breaking at the
+    // last space that fits. Spaces at a break are dropped. `offset` is the
+    // paragraph's byte offset in the whole text.
+    fn wrap(&self, paragraph: &str, offset: usize, scale: f32, atlas: &impl GlyphSource, lines: &mut Vec<Line>) {
+        let placed = layout_line(paragraph, &self.tab_stops, atlas);
+        let advance = |g: &PositionedGlyph| advance(g, atlas);
+        let mut start = 0;
+        loop {
+            let indent = if start == 0 { self.first_line_indent } else { 0.0 };
+            let origin = placed.get(start).map_or(0.0, |g| g.x);
+            let (mut end, mut next) = (placed.len(), placed.len());
+            if let Some(max_width) = self.max_width {
+                let mut last_space = None;
+                for (i, glyph) in placed.iter().enumerate().skip(start) {
+                    if glyph.c == ' ' {
+                        last_space = Some(i);
+                        continue;
+                    }
+                    let right = (glyph.x + advance(glyph) - origin) * scale;
+                    if right > max_width - indent && i > start {
+                        (end, next) = match last_space {
+                            Some(space) if space > start => (space, space + 1),
+                            _ => (i, i),
+                        };
+                        break;
+                    }
+                }
+            }
+
+            let line = &placed[start..end];
+            let width = line
+                .iter()
+                .filter(|g| g.c != ' ')
+                .map(|g| (g.x + advance(g) - origin) * scale)
+                .fold(0.0, f32::max);
+            while next < placed.len() && placed[next].c == ' ' {
+                next += 1;
+            }
+            let from = if start == 0 { 0 } else { placed[start].index };
+            let to = placed.get(end).filter(|_| next < placed.len()).map_or(paragraph.len(), |g| g.index);
+            lines.push(Line {
+                glyphs: line
+                    .iter()
+                    .map(|g| PositionedGlyph {
+                        x: (g.x - origin) * scale,
+                        y: g.y * scale,
+                        index: offset + g.index,
+                        ..*g
+                    })
+                    .collect(),
+                indent,
+                width,
+                ends_paragraph: next >= placed.len(),
+                range: offset + from..offset + to,
+            });
+            if next >= placed.len() {
+                break;
+            }
+            start = next;
+        }
+    }
+}
+
+// Places the glyphs of a single line using the atlas' advances and kerning, at
+// the atlas' font size. Tab stops are counted in widths of the space glyph.
+// Atlases that can shape get every run between tabs shaped as a whole.
+pub fn layout_line(text: &str, tab_stops: &TabStops, atlas: &impl GlyphSource) -> Vec<PositionedGlyph> {
+    let column_width = atlas.glyph(' ').map_or(0.0, |g| g.advance);
+    let mut glyphs = vec![];
+    let mut pen = 0.0;
+    let mut start = 0;
+    for (i, run) in text.split('\t').enumerate() {
+        if i > 0 && column_width > 0.0 {
+            let column = (pen / column_width + 0.001).floor() as usize;
+            pen = tab_stops.next_stop(column) as f32 * column_width;
+        }
+        match atlas.shape(run) {
+            Some((shaped, advance)) => {
+                glyphs.extend(shaped.iter().map(|g| PositionedGlyph {
+                    x: pen + g.x,
+                    index: start + g.index,
+                    ..*g
+                }));
+                pen += advance;
+            }
+            None => pen = place_chars(run, start, pen, atlas, &mut glyphs),
+        }
+        start += run.len() + 1;
+    }
+    glyphs
+}
+
+// How wide `layout_line` lays `text` out, up to the end of the last glyph, at
+// the atlas' font size.
+pub fn line_width(text: &str, tab_stops: &TabStops, atlas: &impl GlyphSource) -> f32 {
+    layout_line(text, tab_stops, atlas)
+        .iter()
+        .map(|g| g.x + advance(g, atlas))
+        .fold(0.0, f32::max)
+}
+
+fn advance(glyph: &PositionedGlyph, atlas: &impl GlyphSource) -> f32 {
+    glyph
+        .id
+        .and_then(|id| atlas.glyph_by_id(id))
+        .or_else(|| atlas.glyph_or_fallback(glyph.c))
+        .map_or(0.0, |g| g.advance)
+}
+
+// one glyph per char, returns the pen position after the last one, `start` is
+// the run's byte offset in the line
+fn place_chars(run: &str, start: usize, mut pen: f32, atlas: &impl GlyphSource, glyphs: &mut Vec<PositionedGlyph>) -> f32 {
+    let mut prev: Option<char> = None;
+    for (index, c) in run.char_indices() {
+        if let Some(prev) = prev {
+            pen += atlas.kern(prev, c);
+        }
+        glyphs.push(PositionedGlyph {
+            x: pen,
+            y: 0.0,
+            c,
+            id: None,
+            index: start + index,
+        });
+        pen += atlas.glyph_or_fallback(c).map_or(0.0, |g| g.advance);
+        prev = Some(c);
+    }
+    pen
+}
+
+// the line a caret at byte `index` is on, the last one starting at or before it
+fn line_of(lines: &[PlacedLine], index: usize) -> &PlacedLine {
+    lines.iter().rev().find(|line| line.range.start <= index).unwrap_or(&lines[0])
+}
+
+// a caret in front of the glyph at `index`, or behind the one before it
+fn caret_x(line: &PlacedLine, index: usize, scale: f32, atlas: &impl GlyphSource) -> f32 {
+    if let Some(glyph) = line.glyphs.iter().find(|g| g.index == index) {
+        return glyph.x;
+    }
+    line.glyphs
+        .iter()
+        .rfind(|g| g.index < index)
+        .map_or(line.x, |g| g.x + advance(g, atlas) * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::GlyphInfo;
+
+    // every char 10 pixels wide at a font size of 10, lines 20 tall
+    struct Mono;
+
+    impl GlyphSource for Mono {
+        fn glyph(&self, _c: char) -> Option<GlyphInfo> {
+            Some(GlyphInfo {
+                uv: (0.0, 0.0, 0.0, 0.0),
+                offset: (0.0, 0.0),
+                size: (10.0, 20.0),
+                advance: 10.0,
+                page: 0,
+                color: false,
+            })
+        }
+
+        fn fallback(&self) -> Option<char> {
+            None
+        }
+
+        fn line_height(&self) -> f32 {
+            20.0
+        }
+
+        fn ascent(&self) -> f32 {
+            15.0
+        }
+
+        fn font_size(&self) -> f32 {
+            10.0
+        }
+    }
+
+    fn caret_at(text: &str, index: usize) -> (f32, f32) {
+        let caret = TextLayout::new().caret(text, 10.0, &Mono, index);
+        (caret.x, caret.y)
+    }
+
+    #[test]
+    fn caret_at_start_and_end_of_lines() {
+        let text = "ab\ncd";
+        assert_eq!(caret_at(text, 0), (0.0, 0.0));
+        // in front of the '\n' is behind the first line's last char
+        assert_eq!(caret_at(text, 2), (20.0, 0.0));
+        assert_eq!(caret_at(text, 3), (0.0, 20.0));
+        assert_eq!(caret_at(text, 5), (20.0, 20.0));
+        let caret = TextLayout::new().caret(text, 20.0, &Mono, 2);
+        assert_eq!((caret.x, caret.h), (40.0, 40.0));
+    }
+
+    #[test]
+    fn caret_past_the_end_is_behind_the_last_char() {
+        assert_eq!(caret_at("ab\ncd", 100), (20.0, 20.0));
+        assert_eq!(caret_at("", 0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn caret_steps_over_multi_byte_chars() {
+        // 'é' is two bytes and '€' three, carets go on their char boundaries
+        let text = "aé€b";
+        assert_eq!(caret_at(text, 1), (10.0, 0.0));
+        assert_eq!(caret_at(text, 3), (20.0, 0.0));
+        assert_eq!(caret_at(text, 6), (30.0, 0.0));
+        assert_eq!(caret_at(text, 7), (40.0, 0.0));
+    }
+
+    #[test]
+    fn index_at_lands_on_char_boundaries() {
+        let layout = TextLayout::new();
+        let text = "aé€b";
+        assert_eq!(layout.index_at(text, 10.0, &Mono, [-5.0, 5.0]), 0);
+        assert_eq!(layout.index_at(text, 10.0, &Mono, [12.0, 5.0]), 1);
+        assert_eq!(layout.index_at(text, 10.0, &Mono, [18.0, 5.0]), 3);
+        assert_eq!(layout.index_at(text, 10.0, &Mono, [31.0, 5.0]), 6);
+        for x in 0..50 {
+            let index = layout.index_at(text, 10.0, &Mono, [x as f32, 5.0]);
+            assert!(text.is_char_boundary(index), "{index} at {x}");
+        }
+    }
+
+    #[test]
+    fn index_at_clicks_past_the_end() {
+        let layout = TextLayout::new();
+        let text = "ab\ncd";
+        // right of a line is its end, not the start of the next one
+        assert_eq!(layout.index_at(text, 10.0, &Mono, [100.0, 5.0]), 2);
+        assert_eq!(layout.index_at(text, 10.0, &Mono, [100.0, 25.0]), 5);
+        // below the last line and above the first snap to them
+        assert_eq!(layout.index_at(text, 10.0, &Mono, [12.0, 500.0]), 4);
+        assert_eq!(layout.index_at(text, 10.0, &Mono, [12.0, -50.0]), 1);
+        assert_eq!(layout.index_at("", 10.0, &Mono, [50.0, 50.0]), 0);
+    }
+
+    // the chars of each line and where it starts
+    fn lines(layout: &TextLayout, text: &str) -> Vec<(String, f32, f32)> {
+        let mut lines: Vec<(String, f32, f32)> = vec![];
+        for glyph in layout.layout(text, 10.0, &Mono) {
+            match lines.last_mut() {
+                Some((chars, _, y)) if *y == glyph.y => chars.push(glyph.c),
+                _ => lines.push((glyph.c.to_string(), glyph.x, glyph.y)),
+            }
+        }
+        lines
+    }
+
+    #[test]
+    fn words_wider_than_the_line_are_split() {
+        let layout = TextLayout::new().with_max_width(30.0);
+        assert_eq!(
+            lines(&layout, "abcdefgh"),
+            [("abc".into(), 0.0, 0.0), ("def".into(), 0.0, 20.0), ("gh".into(), 0.0, 40.0)]
+        );
+        // a long word after a short one goes onto a line of its own first
+        assert_eq!(
+            lines(&layout, "a bcdef"),
+            [("a".into(), 0.0, 0.0), ("bcd".into(), 0.0, 20.0), ("ef".into(), 0.0, 40.0)]
+        );
+        // narrower than a single char still places one char per line
+        let narrow = TextLayout::new().with_max_width(5.0);
+        assert_eq!(lines(&narrow, "ab").len(), 2);
+    }
+
+    #[test]
+    fn runs_of_spaces_break_as_one() {
+        let layout = TextLayout::new().with_max_width(40.0);
+        // the run doesn't start the next line
+        assert_eq!(lines(&layout, "ab   cd"), [("ab  ".into(), 0.0, 0.0), ("cd".into(), 0.0, 20.0)]);
+        // and trailing spaces don't count for alignment
+        let right = layout.clone().with_align(Align::Right);
+        let glyphs = right.layout("ab   cd", 10.0, &Mono);
+        assert_eq!((glyphs[1].c, glyphs[1].x), ('b', 30.0));
+        // spaces that fit stay within the line
+        assert_eq!(lines(&layout, "a  b"), [("a  b".into(), 0.0, 0.0)]);
+    }
+
+    #[test]
+    fn newlines_end_lines_and_reset_wrapping() {
+        let layout = TextLayout::new().with_max_width(30.0).with_first_line_indent(10.0);
+        assert_eq!(
+            lines(&layout, "ab cd
+
+ef"),
+            [("ab".into(), 10.0, 0.0), ("cd".into(), 0.0, 20.0), ("ef".into(), 10.0, 60.0)]
+        );
+        // the empty line still gets a caret of its own
+        assert_eq!(caret_at("ab
+
+cd", 3), (0.0, 20.0));
+        let spaced = TextLayout::new().with_paragraph_spacing(5.0);
+        assert_eq!(lines(&spaced, "a
+b")[1].2, 25.0);
+    }
+
+    #[test]
+    fn tabs_move_the_pen_to_stops() {
+        let x = |text: &str, stops: TabStops| layout_line(text, &stops, &Mono).last().unwrap().x;
+        assert_eq!(x("\tb", TabStops::Every(4)), 40.0);
+        assert_eq!(x("abcd\tb", TabStops::Every(4)), 80.0);
+        assert_eq!(x("a\tb", TabStops::Columns(vec![3, 6])), 30.0);
+        // past the last stop a tab is one column wide
+        assert_eq!(x("abcdefg\tb", TabStops::Columns(vec![3, 6])), 80.0);
+    }
+
+    #[test]
+    fn index_at_round_trips_carets() {
+        let layout = TextLayout::new().with_max_width(30.0);
+        let text = "one two\nthree";
+        for (index, _) in text.char_indices() {
+            let caret = layout.caret(text, 10.0, &Mono, index);
+            let hit = layout.index_at(text, 10.0, &Mono, [caret.x, caret.y + 1.0]);
+            let back = layout.caret(text, 10.0, &Mono, hit);
+            assert_eq!((back.x, back.y), (caret.x, caret.y), "{index}");
+        }
+    }
+}
@@ -0,0 +1,493 @@
+use ab_glyph::{Font, ScaleFont};
+use image::EncodableLayout;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::atlas::{
+    Atlas, GlyphInfo, GlyphSource, REPLACEMENT_CHAR, atlas_bind_group, upload_atlas_texture,
+};
+use super::glyph_atlas::{empty_glyph_info, glyph_info, rasterize_id};
+use super::packer::ShelfPacker;
+use super::system::find_system_font;
+#[cfg(feature = "shaping")]
+use super::layout::PositionedGlyph;
+
+const PAGE_SIZE: u32 = 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FontId(pub usize);
+
+#[derive(Debug)]
+pub enum FontLoadError {
+    Io(std::io::Error),
+    InvalidFont(ab_glyph::InvalidFont),
+    // no installed font has the family
+    NotFound(String),
+}
+
+impl std::fmt::Display for FontLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::InvalidFont(err) => write!(f, "{err}"),
+            Self::NotFound(family) => write!(f, "no installed font of the family {family}"),
+        }
+    }
+}
+
+impl std::error::Error for FontLoadError {}
+
+// Owns any number of fonts and rasterizes them on demand at whatever pixel size
+// is asked for. Glyphs of every font and size share the same atlas pages, a new
+// page is only added once the last one is full.
+pub struct FontManager {
+    pub fallback: Option<char>,
+
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    fonts: Vec<ab_glyph::FontArc>,
+    // rustybuzz parses the font itself from the raw data and face index
+    #[cfg(feature = "shaping")]
+    font_data: Vec<(Vec<u8>, u32)>,
+    bind_group_layout: wgpu::BindGroupLayout,
+    filter: wgpu::FilterMode,
+    cache: RefCell<GlyphCache>,
+}
+
+struct Page {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    packer: ShelfPacker,
+}
+
+// How much of the atlas pages is in use, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AtlasUsage {
+    pub pages: usize,
+    // handed out by the page packers, evicted glyphs included
+    pub used: u64,
+    // covered by glyphs that are still cached
+    pub live: u64,
+}
+
+impl AtlasUsage {
+    // the share of the used space evicted glyphs left behind
+    pub fn fragmentation(&self) -> f32 {
+        if self.used == 0 {
+            return 0.0;
+        }
+        1.0 - self.live as f32 / self.used as f32
+    }
+
+    pub fn texture_bytes(&self) -> u64 {
+        self.pages as u64 * PAGE_SIZE as u64 * PAGE_SIZE as u64 * 4
+    }
+}
+
+// What `FontManager::compact` did. Glyphs pushed before it point into pages
+// that are gone, pass it to `FontRenderer::apply_compaction` for batches that
+// are kept across it.
+pub struct Compaction {
+    pub before: AtlasUsage,
+    pub after: AtlasUsage,
+    // glyphs that ended up on another page index or position
+    pub moved: usize,
+    // bind groups of the pages before, by page index
+    pub(crate) old_pages: Vec<wgpu::BindGroup>,
+    pub(crate) moves: Vec<GlyphMove>,
+}
+
+impl Compaction {
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.before.texture_bytes().saturating_sub(self.after.texture_bytes())
+    }
+}
+
+// where one glyph was copied to, every cached glyph gets one
+pub(crate) struct GlyphMove {
+    pub(crate) from_page: u32,
+    pub(crate) from: (f32, f32, f32, f32),
+    pub(crate) to_page: u32,
+    pub(crate) to: (f32, f32, f32, f32),
+}
+
+#[derive(Default)]
+struct GlyphCache {
+    pages: Vec<Page>,
+    // sizes are keyed by their bits, they're only ever compared for equality
+    glyphs: HashMap<(FontId, u32, ab_glyph::GlyphId), Option<GlyphInfo>>,
+}
+
+impl FontManager {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let (page, bind_group_layout) = new_page(device, queue, wgpu::FilterMode::Linear);
+        Self {
+            fallback: Some(REPLACEMENT_CHAR),
+            device: device.clone(),
+            queue: queue.clone(),
+            fonts: vec![],
+            #[cfg(feature = "shaping")]
+            font_data: vec![],
+            bind_group_layout,
+            filter: wgpu::FilterMode::Linear,
+            cache: RefCell::new(GlyphCache {
+                pages: vec![page],
+                glyphs: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn load(&mut self, font_data: &[u8]) -> Result<FontId, ab_glyph::InvalidFont> {
+        self.load_face(font_data.to_vec(), 0)
+    }
+
+    // a ttf or otf file, or the first font of a collection
+    pub fn load_from_path(&mut self, path: impl AsRef<Path>) -> Result<FontId, FontLoadError> {
+        let data = std::fs::read(path).map_err(FontLoadError::Io)?;
+        self.load_face(data, 0).map_err(FontLoadError::InvalidFont)
+    }
+
+    // An installed font by its family name like "DejaVu Sans", its regular
+    // style when there is one. Looks through `system_font_dirs`, which reads
+    // font files until it finds the family, so load them once up front.
+    pub fn load_system_font(&mut self, family: &str) -> Result<FontId, FontLoadError> {
+        let (path, index) = find_system_font(family).ok_or_else(|| FontLoadError::NotFound(family.to_string()))?;
+        let data = std::fs::read(path).map_err(FontLoadError::Io)?;
+        self.load_face(data, index).map_err(FontLoadError::InvalidFont)
+    }
+
+    fn load_face(&mut self, font_data: Vec<u8>, index: u32) -> Result<FontId, ab_glyph::InvalidFont> {
+        #[cfg(feature = "shaping")]
+        let data = font_data.clone();
+        let font = ab_glyph::FontVec::try_from_vec_and_index(font_data, index)?;
+        self.fonts.push(ab_glyph::FontArc::new(font));
+        #[cfg(feature = "shaping")]
+        self.font_data.push((data, index));
+        Ok(FontId(self.fonts.len() - 1))
+    }
+
+    pub fn font(&self, id: FontId) -> &ab_glyph::FontArc {
+        &self.fonts[id.0]
+    }
+
+    // `font` at `size` pixels, pass it wherever an `Atlas` is expected
+    pub fn face(&self, font: FontId, size: f32) -> Face<'_> {
+        Face {
+            manager: self,
+            font,
+            scale: ab_glyph::PxScale::from(size),
+        }
+    }
+
+    // applies to every page, see `MonoGlyphAtlas::set_filter`
+    pub fn set_filter(&mut self, filter: wgpu::FilterMode) {
+        self.filter = filter;
+        for page in &mut self.cache.get_mut().pages {
+            (_, page.bind_group) =
+                atlas_bind_group(&self.device, &self.bind_group_layout, &page.view, filter);
+        }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.cache.borrow().pages.len()
+    }
+
+    pub(crate) fn page_bind_group(&self, page: u32) -> wgpu::BindGroup {
+        self.cache.borrow().pages[page as usize].bind_group.clone()
+    }
+
+    pub fn usage(&self) -> AtlasUsage {
+        let cache = self.cache.borrow();
+        AtlasUsage {
+            pages: cache.pages.len(),
+            used: cache.pages.iter().map(|page| page.packer.used_area()).sum(),
+            live: cache
+                .glyphs
+                .values()
+                .flatten()
+                .map(|info| info.size.0 as u64 * info.size.1 as u64)
+                .sum(),
+        }
+    }
+
+    // Drops every cached glyph of `font` at `size`, e.g. for a size that's no
+    // longer drawn. They're rasterized again when asked for, their space stays
+    // unused until `compact`. Returns the number of glyphs dropped.
+    pub fn evict(&mut self, font: FontId, size: f32) -> usize {
+        let bits = ab_glyph::PxScale::from(size).y.to_bits();
+        let glyphs = &mut self.cache.get_mut().glyphs;
+        let before = glyphs.len();
+        glyphs.retain(|&(f, s, _), _| (f, s) != (font, bits));
+        before - glyphs.len()
+    }
+
+    // Repacks the cached glyphs into as few new pages as they fit in, copying
+    // them on the gpu, and drops the old pages. Call it between frames, after
+    // the last render pass that used glyphs pushed before it.
+    pub fn compact(&mut self) -> Compaction {
+        let before = self.usage();
+        let cache = self.cache.get_mut();
+        // tallest first keeps the shelves full, the rest only makes the order
+        // independent of the hash map's
+        let mut live: Vec<_> = cache
+            .glyphs
+            .iter()
+            .filter_map(|(&key, info)| Some((key, (*info)?)))
+            .filter(|(_, info)| info.size.0 > 0.0 && info.size.1 > 0.0)
+            .collect();
+        live.sort_by(|(_, a), (_, b)| {
+            b.size.1
+                .total_cmp(&a.size.1)
+                .then(a.page.cmp(&b.page))
+                .then(a.uv.1.total_cmp(&b.uv.1))
+                .then(a.uv.0.total_cmp(&b.uv.0))
+        });
+
+        let mut pages = vec![new_page(&self.device, &self.queue, self.filter).0];
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Atlas Compaction"),
+        });
+        let mut moves = vec![];
+        let mut moved = 0;
+        for (key, info) in live {
+            let (w, h) = (info.size.0 as u32, info.size.1 as u32);
+            let (x, y) = match pages.last_mut().unwrap().packer.allocate(w, h) {
+                Some(pos) => pos,
+                None => {
+                    pages.push(new_page(&self.device, &self.queue, self.filter).0);
+                    pages.last_mut().unwrap().packer.allocate(w, h).unwrap()
+                }
+            };
+            let origin = (
+                (info.uv.0 * PAGE_SIZE as f32).round() as u32,
+                (info.uv.1 * PAGE_SIZE as f32).round() as u32,
+            );
+            let page = pages.len() as u32 - 1;
+            encoder.copy_texture_to_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &cache.pages[info.page as usize].texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: origin.0, y: origin.1, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::TexelCopyTextureInfo {
+                    texture: &pages[page as usize].texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                },
+            );
+            let size = PAGE_SIZE as f32;
+            let uv = (x as f32 / size, y as f32 / size, (x + w) as f32 / size, (y + h) as f32 / size);
+            moved += usize::from((page, uv) != (info.page, info.uv));
+            moves.push(GlyphMove {
+                from_page: info.page,
+                from: info.uv,
+                to_page: page,
+                to: uv,
+            });
+            cache.glyphs.insert(key, Some(GlyphInfo { uv, page, ..info }));
+        }
+        self.queue.submit([encoder.finish()]);
+
+        let old_pages = std::mem::replace(&mut cache.pages, pages)
+            .into_iter()
+            .map(|page| page.bind_group)
+            .collect();
+        Compaction {
+            before,
+            after: self.usage(),
+            moved,
+            old_pages,
+            moves,
+        }
+    }
+
+    // `compact` once evicted glyphs take up more than `max_fragmentation` of
+    // the used space and repacking would save a page, cheap enough to call
+    // every frame
+    pub fn compact_if_fragmented(&mut self, max_fragmentation: f32) -> Option<Compaction> {
+        let usage = self.usage();
+        let needed = usage.live.div_ceil(PAGE_SIZE as u64 * PAGE_SIZE as u64).max(1);
+        if usage.pages as u64 <= needed || usage.fragmentation() <= max_fragmentation {
+            return None;
+        }
+        Some(self.compact())
+    }
+
+    // Rasterizes every glyph of `text` at each of `sizes` up front. Returns the
+    // characters that couldn't be packed because they're larger than a page.
+    pub fn warm_cache(&self, font: FontId, text: &str, sizes: &[f32]) -> Vec<char> {
+        let mut dropped = vec![];
+        for &size in sizes {
+            let face = self.face(font, size);
+            for c in text.chars() {
+                if c == '\t' || c == '\n' || dropped.contains(&c) {
+                    continue;
+                }
+                if face.glyph(c).is_none() {
+                    dropped.push(c);
+                }
+            }
+        }
+        dropped
+    }
+
+    // glyphs are cached by id, so chars and shaped glyphs share the entries
+    fn glyph(&self, font: FontId, scale: ab_glyph::PxScale, id: ab_glyph::GlyphId) -> Option<GlyphInfo> {
+        let key = (font, scale.y.to_bits(), id);
+        if let Some(info) = self.cache.borrow().glyphs.get(&key) {
+            return *info;
+        }
+
+        let info = self.rasterize_glyph(font, scale, id);
+        self.cache.borrow_mut().glyphs.insert(key, info);
+        info
+    }
+
+    fn rasterize_glyph(&self, font: FontId, scale: ab_glyph::PxScale, id: ab_glyph::GlyphId) -> Option<GlyphInfo> {
+        let glyph = rasterize_id(&self.fonts[font.0], scale, id);
+        let Some(img) = &glyph.image else {
+            return Some(empty_glyph_info(&glyph));
+        };
+        if img.width() > PAGE_SIZE || img.height() > PAGE_SIZE {
+            return None;
+        }
+
+        let mut cache = self.cache.borrow_mut();
+        let pos = cache
+            .pages
+            .last_mut()
+            .and_then(|page| page.packer.allocate(img.width(), img.height()));
+        let (x, y) = match pos {
+            Some(pos) => pos,
+            None => {
+                let (page, _) = new_page(&self.device, &self.queue, self.filter);
+                cache.pages.push(page);
+                cache.pages.last_mut().unwrap().packer.allocate(img.width(), img.height())?
+            }
+        };
+        let page_index = cache.pages.len() - 1;
+        let page = &cache.pages[page_index];
+
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &page.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            img.as_bytes(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * img.width()),
+                rows_per_image: Some(img.height()),
+            },
+            wgpu::Extent3d {
+                width: img.width(),
+                height: img.height(),
+                depth_or_array_layers: 1,
+            },
+        );
+        Some(GlyphInfo {
+            page: page_index as u32,
+            ..glyph_info(&glyph, img, (x, y), (PAGE_SIZE, PAGE_SIZE))
+        })
+    }
+}
+
+fn new_page(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    filter: wgpu::FilterMode,
+) -> (Page, wgpu::BindGroupLayout) {
+    let blank = image::RgbaImage::new(PAGE_SIZE, PAGE_SIZE);
+    let (texture, view, _, bind_group_layout, bind_group) = upload_atlas_texture(device, queue, &[blank]);
+    let bind_group = match filter {
+        wgpu::FilterMode::Linear => bind_group,
+        filter => atlas_bind_group(device, &bind_group_layout, &view, filter).1,
+    };
+    let page = Page {
+        texture,
+        view,
+        bind_group,
+        packer: ShelfPacker::new(PAGE_SIZE, PAGE_SIZE),
+    };
+    (page, bind_group_layout)
+}
+
+// One font at one size out of a `FontManager`.
+#[derive(Copy, Clone)]
+pub struct Face<'a> {
+    manager: &'a FontManager,
+    font: FontId,
+    scale: ab_glyph::PxScale,
+}
+
+impl Face<'_> {
+    pub fn font_id(&self) -> FontId {
+        self.font
+    }
+
+    pub fn size(&self) -> f32 {
+        self.scale.y
+    }
+}
+
+impl GlyphSource for Face<'_> {
+    fn glyph(&self, c: char) -> Option<GlyphInfo> {
+        let id = self.manager.font(self.font).glyph_id(c);
+        self.manager.glyph(self.font, self.scale, id)
+    }
+
+    fn fallback(&self) -> Option<char> {
+        self.manager.fallback
+    }
+
+    fn kern(&self, left: char, right: char) -> f32 {
+        let font = self.manager.font(self.font);
+        font.as_scaled(self.scale)
+            .kern(font.glyph_id(left), font.glyph_id(right))
+    }
+
+    fn line_height(&self) -> f32 {
+        let font = self.manager.font(self.font).as_scaled(self.scale);
+        font.ascent() - font.descent() + font.line_gap()
+    }
+
+    fn ascent(&self) -> f32 {
+        self.manager.font(self.font).as_scaled(self.scale).ascent()
+    }
+
+    fn font_size(&self) -> f32 {
+        self.scale.y
+    }
+
+    #[cfg(feature = "shaping")]
+    fn shape(&self, text: &str) -> Option<(Vec<PositionedGlyph>, f32)> {
+        let manager = self.manager;
+        let (data, index) = &manager.font_data[self.font.0];
+        super::shaping::shape(data, *index, manager.font(self.font), self.scale, text)
+    }
+
+    fn glyph_by_id(&self, id: ab_glyph::GlyphId) -> Option<GlyphInfo> {
+        self.manager.glyph(self.font, self.scale, id)
+    }
+}
+
+impl Atlas for Face<'_> {
+    fn bind_group(&self, page: u32) -> wgpu::BindGroup {
+        self.manager.cache.borrow().pages[page as usize].bind_group.clone()
+    }
+
+    fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.manager.bind_group_layout
+    }
+}
@@ -1,2 +1,32 @@
+pub mod atlas;
+pub mod glyph_atlas;
+mod grid;
+pub mod icons;
+pub mod layout;
+pub mod manager;
+pub mod outline;
+pub(crate) mod packer;
 mod renderer;
-pub use renderer::FontRenderer;
+pub mod sdf;
+#[cfg(feature = "shaping")]
+mod shaping;
+mod style;
+mod system;
+mod tabs;
+mod text_buffer;
+pub use atlas::{Atlas, AtlasKind, GlyphInfo, GlyphSource, atlas_scale};
+pub use glyph_atlas::{
+    BakedGlyphs, CpuGlyphAtlas, GlyphAtlas, bake_glyphs, create_cpu_glyph_atlas, create_glyph_atlas,
+    create_sdf_atlas,
+};
+pub use grid::TextGrid;
+pub use icons::IconFont;
+pub use layout::{Align, TextLayout, TextOrigin, VAlign};
+pub use manager::{AtlasUsage, Compaction, Face, FontId, FontLoadError, FontManager};
+pub use outline::{PathCommand, TextPath, outline_text};
+pub use renderer::{FontRenderer, FontVertex, MissingGlyphs, SdfStyle};
+pub use style::TextStyle;
+pub use system::system_font_dirs;
+pub(crate) use system::find_system_font;
+pub use tabs::TabStops;
+pub use text_buffer::TextBuffer;
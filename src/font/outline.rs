@@ -0,0 +1,159 @@
+use ab_glyph::{Font, ScaleFont};
+
+// One drawing command of a glyph outline, in pixels with y pointing down and
+// the origin at the top left of the text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathCommand {
+    MoveTo([f32; 2]),
+    LineTo([f32; 2]),
+    QuadTo([f32; 2], [f32; 2]),
+    CurveTo([f32; 2], [f32; 2], [f32; 2]),
+    Close,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextPath {
+    pub commands: Vec<PathCommand>,
+}
+
+// Extracts the vector outlines of a single line of text at `scale` pixels,
+// advancing and kerning like `layout::layout_line`.
+pub fn outline_text(font: &ab_glyph::FontArc, scale: f32, text: &str) -> TextPath {
+    let scaled = font.as_scaled(ab_glyph::PxScale::from(scale));
+    let (sx, sy) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+    let ascent = scaled.ascent();
+
+    let mut path = TextPath::default();
+    let mut pen = 0.0;
+    let mut prev = None;
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(prev) = prev {
+            pen += scaled.kern(prev, id);
+        }
+        if let Some(outline) = font.outline(id) {
+            // font units are y up from the baseline
+            let map = |p: ab_glyph::Point| [pen + p.x * sx, ascent - p.y * sy];
+            path.push_outline(&outline, map);
+        }
+        pen += scaled.h_advance(id);
+        prev = Some(id);
+    }
+    path
+}
+
+impl TextPath {
+    fn push_outline(
+        &mut self,
+        outline: &ab_glyph::Outline,
+        map: impl Fn(ab_glyph::Point) -> [f32; 2],
+    ) {
+        let mut current: Option<[f32; 2]> = None;
+        for curve in &outline.curves {
+            let (start, command) = match *curve {
+                ab_glyph::OutlineCurve::Line(a, b) => (map(a), PathCommand::LineTo(map(b))),
+                ab_glyph::OutlineCurve::Quad(a, b, c) => {
+                    (map(a), PathCommand::QuadTo(map(b), map(c)))
+                }
+                ab_glyph::OutlineCurve::Cubic(a, b, c, d) => {
+                    (map(a), PathCommand::CurveTo(map(b), map(c), map(d)))
+                }
+            };
+            // a curve not continuing from the previous one starts a new contour
+            if current != Some(start) {
+                if current.is_some() {
+                    self.commands.push(PathCommand::Close);
+                }
+                self.commands.push(PathCommand::MoveTo(start));
+            }
+            current = Some(match command {
+                PathCommand::LineTo(p)
+                | PathCommand::QuadTo(_, p)
+                | PathCommand::CurveTo(_, _, p) => p,
+                _ => start,
+            });
+            self.commands.push(command);
+        }
+        if current.is_some() {
+            self.commands.push(PathCommand::Close);
+        }
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        let t = |p: &mut [f32; 2]| {
+            p[0] += dx;
+            p[1] += dy;
+        };
+        for command in &mut self.commands {
+            match command {
+                PathCommand::MoveTo(p) | PathCommand::LineTo(p) => t(p),
+                PathCommand::QuadTo(a, b) => {
+                    t(a);
+                    t(b);
+                }
+                PathCommand::CurveTo(a, b, c) => {
+                    t(a);
+                    t(b);
+                    t(c);
+                }
+                PathCommand::Close => {}
+            }
+        }
+    }
+
+    // Approximates the curves with line segments no longer than `tolerance`
+    // pixels (roughly), returning one closed polyline per contour.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Vec<[f32; 2]>> {
+        let mut contours = vec![];
+        let mut contour: Vec<[f32; 2]> = vec![];
+        let segments = |len: f32| ((len / tolerance.max(0.01)).ceil() as usize).clamp(1, 64);
+        let dist =
+            |a: [f32; 2], b: [f32; 2]| ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+
+        for command in &self.commands {
+            let last = contour.last().copied().unwrap_or([0.0, 0.0]);
+            match *command {
+                PathCommand::MoveTo(p) => {
+                    if !contour.is_empty() {
+                        contours.push(std::mem::take(&mut contour));
+                    }
+                    contour.push(p);
+                }
+                PathCommand::LineTo(p) => contour.push(p),
+                PathCommand::QuadTo(c, p) => {
+                    let n = segments(dist(last, c) + dist(c, p));
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let mt = 1.0 - t;
+                        contour.push([
+                            mt * mt * last[0] + 2.0 * mt * t * c[0] + t * t * p[0],
+                            mt * mt * last[1] + 2.0 * mt * t * c[1] + t * t * p[1],
+                        ]);
+                    }
+                }
+                PathCommand::CurveTo(c1, c2, p) => {
+                    let n = segments(dist(last, c1) + dist(c1, c2) + dist(c2, p));
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let mt = 1.0 - t;
+                        let (a, b, c, d) =
+                            (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                        contour.push([
+                            a * last[0] + b * c1[0] + c * c2[0] + d * p[0],
+                            a * last[1] + b * c1[1] + c * c2[1] + d * p[1],
+                        ]);
+                    }
+                }
+                PathCommand::Close => {
+                    if !contour.is_empty() {
+                        contours.push(std::mem::take(&mut contour));
+                    }
+                }
+            }
+        }
+        if !contour.is_empty() {
+            contours.push(contour);
+        }
+        contours
+    }
+}
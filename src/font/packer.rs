@@ -0,0 +1,57 @@
+const PADDING: u32 = 1;
+
+// Fills the atlas row by row, every row ("shelf") as tall as its tallest glyph.
+pub(crate) struct ShelfPacker {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    cursor: (u32, u32),
+    shelf_height: u32,
+}
+
+impl ShelfPacker {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            cursor: (0, 0),
+            shelf_height: 0,
+        }
+    }
+
+    // a packer for a page whose top `used` rows are taken, e.g. one baked
+    // ahead of time, continuing on a shelf below them
+    pub(crate) fn resume(width: u32, height: u32, used: u32) -> Self {
+        Self {
+            width,
+            height,
+            cursor: (0, (used + PADDING).min(height)),
+            shelf_height: 0,
+        }
+    }
+
+    pub(crate) fn allocate(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        if w > self.width {
+            return None;
+        }
+        if self.cursor.0 + w > self.width {
+            self.cursor = (0, self.cursor.1 + self.shelf_height + PADDING);
+            self.shelf_height = 0;
+        }
+        if self.cursor.1.saturating_add(h) > self.height {
+            return None;
+        }
+        let pos = self.cursor;
+        self.cursor.0 += w + PADDING;
+        self.shelf_height = self.shelf_height.max(h);
+        Some(pos)
+    }
+
+    pub(crate) fn used_height(&self) -> u32 {
+        self.cursor.1 + self.shelf_height
+    }
+
+    // pixels handed out so far, the unused rest of every full shelf included
+    pub(crate) fn used_area(&self) -> u64 {
+        self.cursor.1 as u64 * self.width as u64 + self.cursor.0 as u64 * self.shelf_height as u64
+    }
+}
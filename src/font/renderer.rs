@@ -1,24 +1,187 @@
-use wgpu::util::DeviceExt;
+use serde::{Deserialize, Serialize};
+use crate::batch::{Batch, BatchStats};
 use crate::camera::Camera;
-use crate::MonoGlyphAtlas;
+use crate::color::Color;
+use crate::color_space::ColorSpace;
+use crate::culling::{self, CullRect, Culling};
+use crate::oit;
+use crate::pipeline_2d::Pipeline2d;
+use crate::pipeline_cache::{PipelineCache, PipelineKey};
+use crate::rect::{ClipShape, Rect};
+use crate::sanitize::{GeometryIssues, sanitize};
+use crate::shader::ShaderPreprocessor;
+use crate::sort::{BoundPipeline, DrawStats, SortKey};
+use crate::state::BatchSnapshot;
+use std::collections::{BTreeMap, BTreeSet};
+use wgpu::util::DeviceExt;
+use super::sdf::SDF_SPREAD;
+use super::layout::PositionedGlyph;
+use super::{Atlas, AtlasKind, Compaction, FontId, FontManager, GlyphInfo, TabStops, TextBuffer, TextLayout, TextStyle, layout};
+
+// Texture ids of the sort keys are 1 + the index of the glyph's atlas in
+// `atlases`, id 0 stays reserved for untextured quads.
+
+const INITIAL_QUADS: wgpu::BufferAddress = 256;
+
+// glyphs are blended by their coverage times the alpha of their color
+const BLEND: wgpu::BlendState = wgpu::BlendState::ALPHA_BLENDING;
 
 pub struct FontRenderer {
-    render_pipeline: wgpu::RenderPipeline,
-    vertices: Vec<FontVertex>,
-    indices: Vec<u16>,
-    vbo: wgpu::Buffer,
-    ibo: wgpu::Buffer,
-    has_data: bool,
+    // bitmap glyphs are drawn with it, sdf ones with the pipelines below
+    pipeline: Pipeline2d,
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    culling: Culling,
+    sdf_pipelines: PipelineCache,
+    sdf_oit_pipeline: wgpu::RenderPipeline,
+    // what `render` draws into, and `render_multisampled` since the last
+    // `prepare_multisampled`
+    target: PipelineKey,
+    msaa_target: Option<PipelineKey>,
+    sdf_style: SdfStyle,
+    sdf_style_buffer: wgpu::Buffer,
+    sdf_style_bind_group: wgpu::BindGroup,
+    batch: Batch<FontVertex>,
+    clip: ClipShape,
+    color_space: ColorSpace,
+    world_bounds: Option<Rect>,
+    cull: Option<CullRect>,
+    issues: GeometryIssues,
+    tab_stops: TabStops,
+    // bind groups of the atlas pages glyphs were pushed from this frame or are
+    // kept in a static layer, the free ids are None
+    atlases: Vec<Option<(wgpu::BindGroup, AtlasKind)>>,
+    pixel_snap: bool,
+    // scale and offset from world units to physical pixels, see `set_pixel_grid`
+    pixel_grid: ([f32; 2], [f32; 2]),
+}
+
+// Effects applied to text drawn from `AtlasKind::Sdf` atlases, all distances are
+// in atlas pixels. The shadow can't reach further than `SDF_SPREAD`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct SdfStyle {
+    pub outline_width: f32,
+    pub outline_color: [f32; 3],
+    pub shadow_offset: [f32; 2],
+    pub shadow_color: [f32; 3],
+    pub shadow_opacity: f32,
+}
+
+// `SdfStyle` as laid out in font_shader.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SdfStyleUniform {
+    outline_color: [f32; 3],
+    outline_width: f32,
+    shadow_color: [f32; 3],
+    shadow_opacity: f32,
+    shadow_offset: [f32; 2],
+    spread: f32,
+    _padding: f32,
+}
+
+impl SdfStyle {
+    fn uniform(&self) -> SdfStyleUniform {
+        SdfStyleUniform {
+            outline_color: self.outline_color,
+            outline_width: self.outline_width,
+            shadow_color: self.shadow_color,
+            shadow_opacity: self.shadow_opacity,
+            shadow_offset: self.shadow_offset,
+            spread: SDF_SPREAD as f32,
+            _padding: 0.0,
+        }
+    }
+}
+
+// Characters that weren't in the atlas they were pushed with, each listed once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingGlyphs(pub Vec<char>);
+
+impl MissingGlyphs {
+    pub(crate) fn merge(&mut self, other: MissingGlyphs) {
+        for c in other.0 {
+            if !self.0.contains(&c) {
+                self.0.push(c);
+            }
+        }
+    }
+
+    pub(crate) fn into_result(self) -> Result<(), MissingGlyphs> {
+        if self.0.is_empty() { Ok(()) } else { Err(self) }
+    }
+}
+
+impl std::fmt::Display for MissingGlyphs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "glyphs missing from the atlas: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for MissingGlyphs {}
+
+#[allow(clippy::too_many_arguments)]
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+    targets: &[Option<wgpu::ColorTargetState>],
+    samples: u32,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    culling: Culling,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[FontVertex::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        primitive: culling.primitive_state(),
+        depth_stencil,
+        multisample: wgpu::MultisampleState {
+            count: samples,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(entry_point),
+            targets,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        multiview: None,
+        cache: None,
+    })
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
 pub struct FontVertex {
     pos: [f32; 3],
     color: [f32; 3],
     texture_coords: [f32; 2],
+    clip: [f32; 4],
+    // `ColorSpace::id`, recordings from before it existed are srgb
+    #[serde(default)]
+    color_space: u32,
+    // of the atlas texture array, see `Atlas::layer`
+    #[serde(default)]
+    layer: u32,
+    // see `QuadRenderer`'s vertices
+    #[serde(default)]
+    clip_radius: f32,
+    // straight, recordings from before it existed are opaque
+    #[serde(default = "opaque")]
+    alpha: f32,
 }
 
+fn opaque() -> f32 {
+    1.0
+}
 
 impl FontVertex {
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
@@ -41,6 +204,31 @@ impl FontVertex {
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x2,
                 },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 13]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 14]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 15]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32,
+                },
             ],
         }
     }
@@ -49,173 +237,684 @@ impl FontVertex {
 
 
 impl FontRenderer {
-    pub fn new(device: &wgpu::Device, cam: &Camera, atlas: &MonoGlyphAtlas, surface_fmt: wgpu::TextureFormat) -> Self {
-        let shader = device.create_shader_module(wgpu::include_wgsl!("font_shader.wgsl"));
+    pub fn new(device: &wgpu::Device, cam: &Camera, atlas: &impl Atlas, surface_fmt: wgpu::TextureFormat) -> Self {
+        Self::with_culling(device, cam, atlas, surface_fmt, Culling::default())
+    }
+
+    pub fn with_culling(device: &wgpu::Device, cam: &Camera, atlas: &impl Atlas, surface_fmt: wgpu::TextureFormat, culling: Culling) -> Self {
+        let shader = ShaderPreprocessor::new()
+            .create_module(device, "Font Shader", include_str!("font_shader.wgsl"))
+            .expect("bundled shader includes exist");
+
+        let sdf_style = SdfStyle::default();
+        let sdf_style_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SDF style"),
+            contents: bytemuck::cast_slice(&[sdf_style.uniform()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let sdf_style_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: None,
+        });
+        let sdf_style_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &sdf_style_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: sdf_style_buffer.as_entire_binding(),
+            }],
+            label: None,
+        });
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: None,
-                bind_group_layouts: &[cam.get_bind_group_layout(), &atlas.bind_group_layout],
+                bind_group_layouts: &[
+                    cam.get_bind_group_layout(),
+                    atlas.bind_group_layout(),
+                    &sdf_style_layout,
+                ],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[FontVertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_fmt,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            multiview: None,
-            cache: None,
-        });
-        Self {
-            render_pipeline,
-            vertices: vec![],
-            indices: vec![],
-            vbo: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: &[],
-                usage: wgpu::BufferUsages::VERTEX,
-            }),
-            ibo: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: &[],
-                usage: wgpu::BufferUsages::INDEX,
-            }),
-            has_data: false,
+        let sdf_oit_pipeline =
+            create_pipeline(device, &render_pipeline_layout, &shader, "fs_sdf_oit", &oit::targets(), 1, None, culling);
+        let mut renderer = Self {
+            pipeline: Pipeline2d::new(device, cam, culling),
+            shader,
+            pipeline_layout: render_pipeline_layout,
+            culling,
+            sdf_pipelines: PipelineCache::new(),
+            sdf_oit_pipeline,
+            target: PipelineKey::new(surface_fmt, BLEND),
+            msaa_target: None,
+            sdf_style,
+            sdf_style_buffer,
+            sdf_style_bind_group,
+            batch: Batch::new(device, "Font Batch", INITIAL_QUADS),
+            clip: ClipShape::EVERYTHING,
+            color_space: ColorSpace::Srgb,
+            world_bounds: None,
+            cull: None,
+            issues: GeometryIssues::default(),
+            tab_stops: TabStops::default(),
+            atlases: vec![],
+            pixel_snap: false,
+            pixel_grid: ([1.0; 2], [0.0; 2]),
+        };
+        renderer.build_pipelines(device, renderer.target);
+        renderer
+    }
+    pub fn push(&mut self, x: f32, y: f32, size: f32, color: impl Into<Color>, c: char, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        self.push_with_key(SortKey::default(), x, y, size, color, c, atlas)
+    }
+    // `x`, `y` is the pen position at the top of the line, `size` the pixel size
+    // to draw at, independent of the size the atlas was rasterized at.
+    // Characters missing from the atlas are drawn as the atlas' fallback glyph
+    // (or not at all without one) and reported in the error. Glyphs are blended
+    // by the alpha of `color`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_with_key(&mut self, key: SortKey, x: f32, y: f32, size: f32, color: impl Into<Color>, c: char, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        let result = match atlas.glyph(c) {
+            Some(_) => Ok(()),
+            None => Err(MissingGlyphs(vec![c])),
+        };
+        let Some(glyph) = atlas.glyph_or_fallback(c) else {
+            return result;
+        };
+        self.push_glyph(key, x, y, size, color.into(), glyph, atlas);
+        result
+    }
+
+    // a laid out glyph, shaped ones are looked up by their id
+    #[allow(clippy::too_many_arguments)]
+    fn push_positioned(&mut self, key: SortKey, x: f32, y: f32, size: f32, color: Color, placed: &PositionedGlyph, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        match placed.id.and_then(|id| atlas.glyph_by_id(id)) {
+            Some(glyph) => {
+                self.push_glyph(key, x, y, size, color, glyph, atlas);
+                Ok(())
+            }
+            None => self.push_with_key(key, x, y, size, color, placed.c, atlas),
         }
     }
-    pub fn push(&mut self, x: f32, y: f32, color: [f32; 3], c: char, atlas: &MonoGlyphAtlas) {
-        self.has_data = true;
-        let start = self.vertices.len() as u16;
 
-        let (u0, v0, u1, v1) = *atlas.glyph_map.get(&c).unwrap();
-        let (w, h) = (
-            atlas.cell_size.0 as f32,
-            atlas.cell_size.1 as f32,
+    #[allow(clippy::too_many_arguments)]
+    fn push_glyph(&mut self, key: SortKey, x: f32, y: f32, size: f32, color: Color, glyph: GlyphInfo, atlas: &impl Atlas) {
+        let scale = size / atlas.font_size();
+        let mut rect = Rect::new(
+            x + glyph.offset.0 * scale,
+            y + glyph.offset.1 * scale,
+            glyph.size.0 * scale,
+            glyph.size.1 * scale,
         );
+        if self.pixel_snap {
+            let ([sx, sy], [ox, oy]) = self.pixel_grid;
+            rect.x = ((rect.x * sx + ox).round() - ox) / sx;
+            rect.y = ((rect.y * sy + oy).round() - oy) / sy;
+        }
+        if self.culls(key, &rect) {
+            return;
+        }
+        let Some((Rect { x, y, w, h }, (u0, v0, u1, v1))) =
+            sanitize(rect, glyph.uv, self.world_bounds, &mut self.issues)
+        else {
+            return;
+        };
+
+        let texture = self.atlas_texture_id(atlas, glyph.page);
+        let layer = atlas.layer(glyph.page);
+        let (clip, clip_radius) = self.clip.to_bounds();
+        let color_space = self.color_space.id();
 
-        self.vertices.extend_from_slice(&[
+        // color glyphs keep their own colors, only the alpha applies
+        let (color, alpha) = (if glyph.color { [1.0; 3] } else { color.to_rgb() }, color.a);
+
+        self.batch.push(key.with_texture(texture), [
             FontVertex {
                 pos: [x, y, 0.0],
                 texture_coords: [u0, v0],
                 color,
+                clip,
+                color_space,
+                layer,
+                clip_radius,
+                alpha,
             },
             FontVertex {
                 pos: [x + w, y, 0.0],
                 texture_coords: [u1, v0],
                 color,
+                clip,
+                color_space,
+                layer,
+                clip_radius,
+                alpha,
             },
             FontVertex {
                 pos: [x + w, y + h, 0.0],
                 texture_coords: [u1, v1],
                 color,
+                clip,
+                color_space,
+                layer,
+                clip_radius,
+                alpha,
             },
             FontVertex {
                 pos: [x, y + h, 0.0],
                 texture_coords: [u0, v1],
                 color,
+                clip,
+                color_space,
+                layer,
+                clip_radius,
+                alpha,
             },
         ]);
+    }
+    pub fn push_str(&mut self, x: f32, y: f32, size: f32, color: impl Into<Color>, s: &str, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        self.push_str_with_key(SortKey::default(), x, y, size, color, s, atlas)
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_str_with_key(&mut self, key: SortKey, x: f32, y: f32, size: f32, color: impl Into<Color>, s: &str, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        let color = color.into();
+        let scale = size / atlas.font_size();
+        let mut missing = MissingGlyphs(vec![]);
+        for glyph in layout::layout_line(s, &self.tab_stops, atlas) {
+            if let Err(err) = self.push_positioned(key, x + glyph.x * scale, y + glyph.y * scale, size, color, &glyph, atlas) {
+                missing.merge(err);
+            }
+        }
+        missing.into_result()
+    }
 
-        self.indices.extend_from_slice(&[
-            start,
-            start + 1,
-            start + 2,
-            start,
-            start + 2,
-            start + 3,
-        ]);
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_text(&mut self, x: f32, y: f32, size: f32, color: impl Into<Color>, text: &str, layout: &TextLayout, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        self.push_text_with_key(SortKey::default(), x, y, size, color, text, layout, atlas)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_text_with_key(&mut self, key: SortKey, x: f32, y: f32, size: f32, color: impl Into<Color>, text: &str, layout: &TextLayout, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        self.push_glyphs(key, x, y, size, color.into(), &layout.layout(text, size, atlas), atlas)
+    }
+
+    // `push_text` of a `TextBuffer`, which is only laid out again when it
+    // changed
+    pub fn push_buffer(&mut self, x: f32, y: f32, color: impl Into<Color>, buffer: &mut TextBuffer, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        self.push_buffer_with_key(SortKey::default(), x, y, color, buffer, atlas)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_buffer_with_key(&mut self, key: SortKey, x: f32, y: f32, color: impl Into<Color>, buffer: &mut TextBuffer, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        let size = buffer.size();
+        self.push_glyphs(key, x, y, size, color.into(), buffer.glyphs(atlas), atlas)
     }
-    pub fn push_str(&mut self, x: f32, y: f32, color: [f32; 3], s: &str, atlas: &MonoGlyphAtlas) {
-        for (i, c) in s.chars().enumerate() {
-            self.push(x + (i as f32 * atlas.h_adv), y, color, c, atlas);
+
+    #[allow(clippy::too_many_arguments)]
+    fn push_glyphs(&mut self, key: SortKey, x: f32, y: f32, size: f32, color: Color, glyphs: &[PositionedGlyph], atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        let mut missing = MissingGlyphs(vec![]);
+        for glyph in glyphs {
+            if let Err(err) = self.push_positioned(key, x + glyph.x, y + glyph.y, size, color, glyph, atlas) {
+                missing.merge(err);
+            }
+        }
+        missing.into_result()
+    }
+
+    // `push_text` with the shadow and outline of `style` drawn underneath. The
+    // highlight is left out, it takes a quad, see `Renderer::push_text`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_text_styled(&mut self, x: f32, y: f32, size: f32, text: &str, layout: &TextLayout, style: &TextStyle, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        self.push_text_styled_with_key(SortKey::default(), x, y, size, text, layout, style, atlas)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_text_styled_with_key(&mut self, key: SortKey, x: f32, y: f32, size: f32, text: &str, layout: &TextLayout, style: &TextStyle, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        self.push_glyphs_styled(key, x, y, size, &layout.layout(text, size, atlas), style, atlas)
+    }
+
+    // `push_text_styled` of a `TextBuffer`
+    pub fn push_buffer_styled(&mut self, x: f32, y: f32, buffer: &mut TextBuffer, style: &TextStyle, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        self.push_buffer_styled_with_key(SortKey::default(), x, y, buffer, style, atlas)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_buffer_styled_with_key(&mut self, key: SortKey, x: f32, y: f32, buffer: &mut TextBuffer, style: &TextStyle, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        let size = buffer.size();
+        self.push_glyphs_styled(key, x, y, size, buffer.glyphs(atlas), style, atlas)
+    }
+
+    // the shadow and outline go under the text in the order they're pushed,
+    // all with `key`
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn push_glyphs_styled(&mut self, key: SortKey, x: f32, y: f32, size: f32, glyphs: &[PositionedGlyph], style: &TextStyle, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        let snap = self.pixel_snap;
+        self.pixel_snap |= style.pixel_snap;
+        let result = self.push_styled_glyphs(key, x, y, size, glyphs, style, atlas);
+        self.pixel_snap = snap;
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn push_styled_glyphs(&mut self, key: SortKey, x: f32, y: f32, size: f32, glyphs: &[PositionedGlyph], style: &TextStyle, atlas: &impl Atlas) -> Result<(), MissingGlyphs> {
+        let pass = |renderer: &mut Self, dx: f32, dy: f32, color: Color| {
+            for glyph in glyphs {
+                let _ = renderer.push_positioned(key, x + glyph.x + dx, y + glyph.y + dy, size, color, glyph, atlas);
+            }
+        };
+
+        if let Some(([dx, dy], color)) = style.shadow {
+            pass(self, dx, dy, color);
         }
+        // rings of copies in eight directions, one ring per pixel of thickness
+        if let Some((thickness, color)) = style.outline {
+            let rings = thickness.ceil().max(1.0) as usize;
+            for ring in 1..=rings {
+                let r = thickness * ring as f32 / rings as f32;
+                for i in 0..8 {
+                    let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+                    pass(self, r * angle.cos(), r * angle.sin(), color);
+                }
+            }
+        }
+
+        self.push_glyphs(key, x, y, size, style.color, glyphs, atlas)
+    }
+
+    // `push_text` with one of the fonts of `fonts`, rasterized at exactly `size` pixels
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_text_with_font(&mut self, x: f32, y: f32, color: impl Into<Color>, text: &str, layout: &TextLayout, fonts: &FontManager, font: FontId, size: f32) -> Result<(), MissingGlyphs> {
+        self.push_text(x, y, size, color, text, layout, &fonts.face(font, size))
+    }
+
+    fn atlas_texture_id(&mut self, atlas: &impl Atlas, page: u32) -> u16 {
+        self.texture_id(atlas.bind_group(page), atlas.kind())
+    }
+
+    fn texture_id(&mut self, bind_group: wgpu::BindGroup, kind: AtlasKind) -> u16 {
+        let slot = match self.atlases.iter().position(|atlas| atlas.as_ref().is_some_and(|(bg, _)| *bg == bind_group)) {
+            Some(slot) => slot,
+            None => match self.atlases.iter().position(Option::is_none) {
+                Some(free) => {
+                    self.atlases[free] = Some((bind_group, kind));
+                    free
+                }
+                None => {
+                    self.atlases.push(Some((bind_group, kind)));
+                    self.atlases.len() - 1
+                }
+            },
+        };
+        slot as u16 + 1
     }
-    pub fn flush(
+
+    // Snaps every glyph pushed afterwards to the pixel grid of the last
+    // `set_pixel_grid`, see `TextStyle::pixel_snap`.
+    pub fn set_pixel_snap(&mut self, snap: bool) {
+        self.pixel_snap = snap;
+    }
+
+    // The camera glyphs are snapped to physical pixels of. Snapping happens on
+    // push, so set it after moving the camera and before pushing the frame's
+    // text; `Renderer::push_text` does this itself.
+    pub fn set_pixel_grid(&mut self, cam: &Camera) {
+        let (ox, oy) = cam.world_to_screen(0.0, 0.0);
+        let (x, y) = cam.world_to_screen(1.0, 1.0);
+        self.pixel_grid = ([x - ox, y - oy], [ox, oy]);
+    }
+
+    pub fn set_tab_stops(&mut self, tab_stops: TabStops) {
+        self.tab_stops = tab_stops;
+    }
+
+    pub fn tab_stops(&self) -> &TabStops {
+        &self.tab_stops
+    }
+
+    // applies to all sdf text of the frame, it's uploaded by `prepare`
+    pub fn set_sdf_style(&mut self, style: SdfStyle) {
+        self.sdf_style = style;
+    }
+
+    pub fn sdf_style(&self) -> &SdfStyle {
+        &self.sdf_style
+    }
+    // Everything a frame needs before its render pass starts: sorts the batch
+    // and uploads it with the sdf style. Call once per frame, after the last
+    // push.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        debug_assert_eq!(culling::count_mis_wound(self.batch.vertices(), |v| v.pos), 0, "mis-wound glyphs in the batch");
+        self.batch.prepare(device, queue);
+        queue.write_buffer(
+            &self.sdf_style_buffer,
+            0,
+            bytemuck::cast_slice(&[self.sdf_style.uniform()]),
+        );
+    }
+
+    // see `QuadRenderer::set_target_format`
+    pub fn set_target_format(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.target.format = format;
+        self.build_pipelines(device, self.target);
+    }
+
+    // see `QuadRenderer::set_depth_format`
+    pub fn set_depth_format(&mut self, device: &wgpu::Device, depth: Option<wgpu::TextureFormat>) {
+        self.target.depth = depth;
+        self.build_pipelines(device, self.target);
+    }
+
+    // Builds the pipelines `render_multisampled` draws with into a `format`
+    // target with `samples` samples and a `depth` attachment, for offscreen
+    // exports. They're kept for later calls with the same target.
+    pub fn prepare_multisampled(
         &mut self,
-        render_pass: &mut wgpu::RenderPass,
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        cam: &Camera,
-        atlas: &MonoGlyphAtlas
+        format: wgpu::TextureFormat,
+        samples: u32,
+        depth: Option<wgpu::TextureFormat>,
     ) {
-        if self.has_data {
-            self.upload_data(device, queue);
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, cam.get_bind_group(), &[]);
-            render_pass.set_bind_group(1, &atlas.bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vbo.slice(..));
-            render_pass.set_index_buffer(self.ibo.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..self.indices.len() as u32, 0, 0..1);        
+        let target = PipelineKey::new(format, BLEND).with_samples(samples).with_depth(depth);
+        self.msaa_target = Some(target);
+        self.build_pipelines(device, target);
+    }
+
+    // Draws bitmap glyphs with `pipeline` from now on, the `QuadRenderer`'s
+    // so that text and flat quads drawn one after the other share their
+    // pipeline, see `QuadRenderer::pipeline_2d`.
+    pub fn set_pipeline_2d(&mut self, device: &wgpu::Device, pipeline: Pipeline2d) {
+        self.pipeline = pipeline;
+        self.build_pipelines(device, self.target);
+        if let Some(msaa_target) = self.msaa_target {
+            self.build_pipelines(device, msaa_target);
         }
     }
 
-    pub fn clear(&mut self) {
-        self.indices.clear();
-        self.vertices.clear();
-        self.has_data = false;
+    // the bitmap and sdf pipelines for `target`
+    fn build_pipelines(&mut self, device: &wgpu::Device, target: PipelineKey) {
+        let (layout, shader, culling) = (&self.pipeline_layout, &self.shader, self.culling);
+        self.pipeline.build(device, target);
+        self.sdf_pipelines.get_or_create(target, |key| {
+            create_pipeline(device, layout, shader, "fs_sdf", &[Some(key.color_target())], key.samples, key.depth_stencil(), culling)
+        });
     }
 
-    pub fn empty(&self) -> bool {
-        self.vertices.is_empty()
+    // the bitmap and sdf pipelines built for `target`
+    fn pipelines(&self, target: PipelineKey) -> [wgpu::RenderPipeline; 2] {
+        let sdf = self.sdf_pipelines.get(target).expect("pipelines are built for every target");
+        [self.pipeline.get(target), sdf.clone()]
     }
 
-    pub fn upload_data(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
-        if self.vertices.is_empty() {
-            return;
+    // draws the layers `draw_layer` accepts of what the last `prepare` uploaded
+    pub fn render(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, draw_layer: impl Fn(u16) -> bool) -> DrawStats {
+        self.render_with(render_pass, cam, draw_layer, &mut BoundPipeline::default())
+    }
+
+    // see `QuadRenderer::render_with`
+    pub fn render_with(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        cam: &Camera,
+        draw_layer: impl Fn(u16) -> bool,
+        bound: &mut BoundPipeline,
+    ) -> DrawStats {
+        if !self.batch.is_drawable() {
+            return DrawStats::default();
         }
-        if (self.vbo.size() as usize) < self.vertices.len() * std::mem::size_of::<FontVertex>() {
-            self.vbo.destroy();
-            let vbo = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(&self.vertices),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
-            self.vbo = vbo;
-        } else {
-            queue.write_buffer(&self.vbo, 0, bytemuck::cast_slice(&self.vertices));
+        self.draw_runs(render_pass, cam, self.pipelines(self.target), draw_layer, bound)
+    }
+
+    // `render` into the target set up by `prepare_multisampled`
+    pub fn render_multisampled(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, draw_layer: impl Fn(u16) -> bool) -> DrawStats {
+        if !self.batch.is_drawable() {
+            return DrawStats::default();
         }
+        let target = self
+            .msaa_target
+            .expect("render_multisampled without prepare_multisampled");
+        self.draw_runs(render_pass, cam, self.pipelines(target), draw_layer, &mut BoundPipeline::default())
+    }
 
-        if (self.ibo.size() as usize) < self.indices.len() * std::mem::size_of::<u16>() {
-            self.ibo.destroy();
-            let ibo = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(&self.indices),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+    // `render` into an `OitCompositor` pass
+    pub fn render_oit(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, draw_layer: impl Fn(u16) -> bool) -> DrawStats {
+        self.render_oit_with(render_pass, cam, draw_layer, &mut BoundPipeline::default())
+    }
+
+    // `render_oit` like `render_with`
+    pub fn render_oit_with(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        cam: &Camera,
+        draw_layer: impl Fn(u16) -> bool,
+        bound: &mut BoundPipeline,
+    ) -> DrawStats {
+        if !self.batch.is_drawable() {
+            return DrawStats::default();
+        }
+        let pipelines = [self.pipeline.oit().clone(), self.sdf_oit_pipeline.clone()];
+        self.draw_runs(render_pass, cam, pipelines, draw_layer, bound)
+    }
+
+    // `pipelines` are the bitmap and sdf variants, each is only bound where
+    // `bound` had another
+    fn draw_runs(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        cam: &Camera,
+        [bitmap, sdf]: [wgpu::RenderPipeline; 2],
+        draw_layer: impl Fn(u16) -> bool,
+        bound: &mut BoundPipeline,
+    ) -> DrawStats {
+        render_pass.set_bind_group(0, cam.get_bind_group(), &[]);
+        render_pass.set_bind_group(2, &self.sdf_style_bind_group, &[]);
+        let mut stats = DrawStats {
+            bind_group_binds: 2,
+            ..DrawStats::default()
+        };
+        let (mut bound_buffers, mut bound_view, mut bound_texture) = (None, None, None);
+        for (kept, view, texture, range) in self.batch.static_runs(draw_layer, |layer| cam.layer_view(layer)) {
+            if bound_buffers != Some(kept) {
+                self.batch.set_run_buffers(render_pass, kept);
+                bound_buffers = Some(kept);
+            }
+            if view != bound_view {
+                render_pass.set_bind_group(0, cam.view_bind_group(view), &[]);
+                bound_view = view;
+                stats.bind_group_binds += 1;
+            }
+            let Some((bind_group, kind)) = &self.atlases[texture as usize - 1] else {
+                continue;
+            };
+            let pipeline = match kind {
+                AtlasKind::Bitmap => &bitmap,
+                AtlasKind::Sdf => &sdf,
+            };
+            bound.set(render_pass, pipeline, &mut stats);
+            if bound_texture != Some(texture) {
+                render_pass.set_bind_group(1, bind_group, &[]);
+                bound_texture = Some(texture);
+                stats.bind_group_binds += 1;
+            }
+            stats.quads += range.len() as u32 / 6;
+            render_pass.draw_indexed(range, 0, 0..1);
+            stats.draw_calls += 1;
+        }
+        stats
+    }
+
+    pub fn has_layer(&self, pred: impl Fn(u16) -> bool) -> bool {
+        self.batch.has_layer(pred)
+    }
+
+    pub fn layers(&self, pred: impl Fn(u16) -> bool) -> BTreeSet<u16> {
+        self.batch.layers(pred)
+    }
+
+    // Clips everything pushed afterwards to `clip` in the fragment shader. The
+    // rect travels with the vertices, so changing it doesn't split the batch.
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = ClipShape::Rect(clip.unwrap_or(Rect::EVERYTHING));
+    }
+
+    // see `QuadRenderer::set_clip_shape`
+    pub fn set_clip_shape(&mut self, clip: Option<ClipShape>) {
+        self.clip = clip.unwrap_or(ClipShape::EVERYTHING);
+    }
+
+    // The space the colors of everything pushed afterwards are in, color
+    // glyphs included, so images in p3 are drawn with it set to `DisplayP3`.
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.color_space = color_space;
+    }
+
+    // `QuadRenderer::set_world_bounds` for glyphs, cut ones keep the part of
+    // the glyph that's inside
+    pub fn set_world_bounds(&mut self, bounds: Option<Rect>) {
+        self.world_bounds = bounds;
+    }
+
+    // see `QuadRenderer::set_cull_rect`
+    pub fn set_cull_rect(&mut self, cull: Option<CullRect>) {
+        self.cull = cull;
+    }
+
+    // what `set_cull_rect` was set to, for taking it off around text that's
+    // turned after it's pushed
+    pub(crate) fn cull_rect(&self) -> Option<&CullRect> {
+        self.cull.as_ref()
+    }
+
+    fn culls(&self, key: SortKey, bounds: &Rect) -> bool {
+        let culled = self.cull.as_ref().is_some_and(|cull| cull.culls(key.layer(), bounds));
+        culled && !self.batch.is_static_layer(key.layer())
+    }
+
+    // what pushing fixed or dropped since the last `clear`
+    pub fn issues(&self) -> GeometryIssues {
+        self.issues
+    }
+
+    pub fn batch_stats(&self) -> BatchStats {
+        self.batch.stats()
+    }
+
+    // see `Batch::set_static_layer`
+    pub fn set_static_layer(&mut self, device: &wgpu::Device, layer: u16, enabled: bool) {
+        self.batch.set_static_layer(device, layer, enabled);
+    }
+
+    // see `Batch::rebuild_static_layer`
+    pub fn rebuild_static_layer(&mut self, layer: u16) {
+        self.batch.rebuild_static_layer(layer);
+    }
+
+    pub(crate) fn rebuilt_static_layers(&self) -> Vec<u16> {
+        self.batch.rebuilt_static_layers()
+    }
+
+    pub fn snapshot(&self) -> BatchSnapshot<FontVertex> {
+        self.batch.snapshot()
+    }
+
+    // all restored glyphs are drawn from `atlas`
+    pub fn restore(&mut self, snapshot: BatchSnapshot<FontVertex>, atlas: &impl Atlas) {
+        self.clear();
+        let texture = self.atlas_texture_id(atlas, 0);
+        self.batch.restore(snapshot, |key| key.with_texture(texture));
+        culling::rewind_quads(self.batch.vertices_mut(), |v| v.pos);
+    }
+
+    // see `Batch::static_snapshots`
+    pub fn static_snapshots(&self) -> BTreeMap<u16, BatchSnapshot<FontVertex>> {
+        self.batch.static_snapshots()
+    }
+
+    // see `Batch::restore_static_layer`, the glyphs are drawn from `atlas`
+    // like those of `restore`
+    pub fn restore_static_layer(
+        &mut self,
+        device: &wgpu::Device,
+        layer: u16,
+        snapshot: BatchSnapshot<FontVertex>,
+        atlas: &impl Atlas,
+    ) {
+        let texture = self.atlas_texture_id(atlas, 0);
+        self.batch
+            .restore_static_layer(device, layer, snapshot, |key| key.with_texture(texture));
+    }
+
+    // Points the glyphs pushed from `fonts` before its `compact` at where they
+    // were copied to, for a batch that's kept across it instead of pushed
+    // again. Glyphs of other atlases are left alone. `prepare` uploads the
+    // patched batch.
+    pub fn apply_compaction(&mut self, compaction: &Compaction, fonts: &FontManager) {
+        // the page of the compacted atlas each texture id drew from, and the
+        // kind it was drawn as, which the moved glyphs keep
+        let old_pages: Vec<Option<(u32, AtlasKind)>> = self
+            .atlases
+            .iter()
+            .map(|atlas| {
+                let (bind_group, kind) = atlas.as_ref()?;
+                let page = compaction.old_pages.iter().position(|page| page == bind_group)?;
+                Some((page as u32, *kind))
+            })
+            .collect();
+        for quad in 0..self.batch.len() {
+            let key = self.batch.keys()[quad];
+            let Some(&Some((page, kind))) = (key.texture() as usize).checked_sub(1).and_then(|slot| old_pages.get(slot)) else {
+                continue;
+            };
+            let vertices = &mut self.batch.vertices_mut()[quad * 4..quad * 4 + 4];
+            let center = vertices.iter().fold([0.0, 0.0], |c, v| {
+                [c[0] + v.texture_coords[0] / 4.0, c[1] + v.texture_coords[1] / 4.0]
             });
-            self.ibo = ibo;
-        } else {
-            queue.write_buffer(&self.ibo, 0, bytemuck::cast_slice(&self.indices));
+            let Some(glyph) = compaction.moves.iter().find(|m| {
+                m.from_page == page
+                    && (m.from.0..=m.from.2).contains(&center[0])
+                    && (m.from.1..=m.from.3).contains(&center[1])
+            }) else {
+                continue;
+            };
+            for v in vertices {
+                v.texture_coords[0] += glyph.to.0 - glyph.from.0;
+                v.texture_coords[1] += glyph.to.1 - glyph.from.1;
+            }
+            let texture = self.texture_id(fonts.page_bind_group(glyph.to_page), kind);
+            self.batch.keys_mut()[quad] = key.with_texture(texture);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.issues = GeometryIssues::default();
+        let kept = self.batch.static_textures();
+        for (slot, atlas) in self.atlases.iter_mut().enumerate() {
+            if !kept.contains(&(slot as u16 + 1)) {
+                *atlas = None;
+            }
         }
+        self.batch.clear();
+    }
+
+    pub fn empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+
+    // the glyphs pushed since the last `clear`, to `turn_since` later
+    pub(crate) fn pushed(&self) -> usize {
+        self.batch.len()
+    }
+
+    // see `Batch::turn_since`
+    pub(crate) fn turn_since(&mut self, from: usize, origin: [f32; 2], angle: f32) {
+        self.batch.turn_since(from, origin, angle, |v| &mut v.pos);
     }
 }
 
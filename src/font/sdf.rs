@@ -0,0 +1,101 @@
+// Signed distance fields for `AtlasKind::Sdf` atlases.
+
+// how far, in atlas pixels, the field extends past the glyph outline
+pub const SDF_SPREAD: u32 = 8;
+
+const INF: f32 = 1e20;
+
+// Turns a coverage bitmap into a distance field padded by `SDF_SPREAD` on every
+// side. Alpha is 0.5 on the outline, rising towards 1 inside the glyph and
+// falling towards 0 outside, reaching either end `SDF_SPREAD` pixels away.
+pub(crate) fn distance_field(coverage: &image::RgbaImage) -> image::RgbaImage {
+    let (w, h) = (
+        coverage.width() + 2 * SDF_SPREAD,
+        coverage.height() + 2 * SDF_SPREAD,
+    );
+    let inside = |x: u32, y: u32| {
+        x >= SDF_SPREAD
+            && y >= SDF_SPREAD
+            && coverage
+                .get_pixel_checked(x - SDF_SPREAD, y - SDF_SPREAD)
+                .is_some_and(|p| p.0[3] >= 128)
+    };
+
+    // squared distance of every pixel to the nearest inside / outside pixel
+    let mut to_inside = vec![INF; (w * h) as usize];
+    let mut to_outside = vec![INF; (w * h) as usize];
+    for y in 0..h {
+        for x in 0..w {
+            let i = (y * w + x) as usize;
+            if inside(x, y) {
+                to_inside[i] = 0.0;
+            } else {
+                to_outside[i] = 0.0;
+            }
+        }
+    }
+    transform_2d(&mut to_inside, w as usize, h as usize);
+    transform_2d(&mut to_outside, w as usize, h as usize);
+
+    let mut field = image::RgbaImage::new(w, h);
+    for (i, pixel) in field.pixels_mut().enumerate() {
+        // both fields are zero on their own side, so this is positive inside
+        let dist = to_outside[i].sqrt() - to_inside[i].sqrt();
+        let alpha = (0.5 + dist / (2.0 * SDF_SPREAD as f32)).clamp(0.0, 1.0);
+        *pixel = image::Rgba([255, 255, 255, (alpha * 255.0).round() as u8]);
+    }
+    field
+}
+
+// exact euclidean distance transform, Felzenszwalb & Huttenlocher, one pass
+// over the columns and one over the rows
+fn transform_2d(grid: &mut [f32], w: usize, h: usize) {
+    let mut column = vec![0.0; h];
+    for x in 0..w {
+        for y in 0..h {
+            column[y] = grid[y * w + x];
+        }
+        let out = transform_1d(&column);
+        for y in 0..h {
+            grid[y * w + x] = out[y];
+        }
+    }
+    for row in grid.chunks_mut(w) {
+        let out = transform_1d(row);
+        row.copy_from_slice(&out);
+    }
+}
+
+fn transform_1d(f: &[f32]) -> Vec<f32> {
+    let n = f.len();
+    let mut d = vec![0.0; n];
+    // parabola vertices of the lower envelope and the boundaries between them
+    let mut v = vec![0usize; n];
+    let mut z = vec![0.0f32; n + 1];
+    let mut k = 0;
+    z[0] = -INF;
+    z[1] = INF;
+    let intersect = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2.0 * q as f32 - 2.0 * p as f32)
+    };
+    for q in 1..n {
+        let mut s = intersect(q, v[k]);
+        while s <= z[k] {
+            k -= 1;
+            s = intersect(q, v[k]);
+        }
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = INF;
+    }
+    k = 0;
+    for (q, d) in d.iter_mut().enumerate() {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+        let dq = q as f32 - v[k] as f32;
+        *d = dq * dq + f[v[k]];
+    }
+    d
+}
@@ -0,0 +1,45 @@
+use ab_glyph::{Font, ScaleFont};
+
+use super::layout::PositionedGlyph;
+
+// Shapes `text` with rustybuzz into glyphs positioned at `scale`, in visual
+// order, so ligatures, contextual forms and mark placement come out the way the
+// font wants them. Script and direction are guessed from the text, a run mixing
+// right-to-left and left-to-right text isn't reordered. Glyphs the font doesn't
+// have get no id, so they're drawn as the fallback and reported missing.
+pub(crate) fn shape(
+    font_data: &[u8],
+    // of the face in a font collection
+    index: u32,
+    font: &ab_glyph::FontArc,
+    scale: ab_glyph::PxScale,
+    text: &str,
+) -> Option<(Vec<PositionedGlyph>, f32)> {
+    let face = rustybuzz::Face::from_slice(font_data, index)?;
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    let output = rustybuzz::shape(&face, &[], buffer);
+
+    // the same font unit to pixel factors ab_glyph rasterizes with
+    let scaled = font.as_scaled(scale);
+    let (h_scale, v_scale) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+    let mut glyphs = vec![];
+    let mut pen = 0.0;
+    for (info, pos) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+        let c = text[info.cluster as usize..].chars().next().unwrap_or(' ');
+        glyphs.push(PositionedGlyph {
+            x: pen + pos.x_offset as f32 * h_scale,
+            // rustybuzz offsets point up
+            y: -pos.y_offset as f32 * v_scale,
+            c,
+            id: match info.glyph_id {
+                0 => None,
+                id => Some(ab_glyph::GlyphId(id as u16)),
+            },
+            index: info.cluster as usize,
+        });
+        pen += pos.x_advance as f32 * h_scale;
+    }
+    Some((glyphs, pen))
+}
@@ -0,0 +1,93 @@
+use crate::color::Color;
+
+// Decorations for `FontRenderer::push_text_styled` and `Renderer::push_text`.
+// Shadows and outlines are extra passes of the same glyphs drawn underneath
+// the text, so they work the same for bitmap and sdf atlases.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    pub color: Color,
+    // offset in pixels, and color
+    pub shadow: Option<([f32; 2], Color)>,
+    // thickness in pixels, and color
+    pub outline: Option<(f32, Color)>,
+    // color of a rect behind the whole text block, and how many pixels it
+    // reaches past the text on every side
+    pub highlight: Option<(Color, f32)>,
+    // Moves every glyph quad onto the closest physical pixel, so text doesn't
+    // shimmer while it or the camera scrolls by fractions of a pixel. Crisp
+    // only where a glyph's texels map 1:1 to pixels, i.e. text drawn at the
+    // atlas size without zoom; scaled text is better left unsnapped.
+    pub pixel_snap: bool,
+    // For labels in the world, like the names on a map: the smallest and
+    // largest height in view pixels the text is drawn at however far the
+    // camera zooms. Within them it's as large as its size in world units,
+    // past them it and its decorations scale around its top left corner.
+    pub screen_size: Option<(f32, f32)>,
+    // keeps the text upright on screen while the camera rotates, turning it
+    // around its top left corner
+    pub billboard: bool,
+}
+
+impl TextStyle {
+    pub fn new(color: impl Into<Color>) -> Self {
+        Self {
+            color: color.into(),
+            shadow: None,
+            outline: None,
+            highlight: None,
+            pixel_snap: false,
+            screen_size: None,
+            billboard: false,
+        }
+    }
+
+    pub fn with_shadow(mut self, offset: [f32; 2], color: impl Into<Color>) -> Self {
+        self.shadow = Some((offset, color.into()));
+        self
+    }
+
+    pub fn with_outline(mut self, thickness: f32, color: impl Into<Color>) -> Self {
+        self.outline = Some((thickness, color.into()));
+        self
+    }
+
+    pub fn with_highlight(mut self, color: impl Into<Color>, padding: f32) -> Self {
+        self.highlight = Some((color.into(), padding));
+        self
+    }
+
+    pub fn with_pixel_snap(mut self) -> Self {
+        self.pixel_snap = true;
+        self
+    }
+
+    pub fn with_screen_size(mut self, min: f32, max: f32) -> Self {
+        self.screen_size = Some((min, max));
+        self
+    }
+
+    pub fn with_billboard(mut self) -> Self {
+        self.billboard = true;
+        self
+    }
+
+    // the decorations of text drawn `factor` times as large
+    pub(crate) fn scaled(mut self, factor: f32) -> Self {
+        if let Some((offset, _)) = &mut self.shadow {
+            *offset = offset.map(|o| o * factor);
+        }
+        if let Some((thickness, _)) = &mut self.outline {
+            *thickness *= factor;
+        }
+        if let Some((_, padding)) = &mut self.highlight {
+            *padding *= factor;
+        }
+        self
+    }
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self::new(Color::WHITE)
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// Where the platform keeps installed fonts, the user's own first.
+pub fn system_font_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut dirs = vec![];
+    if cfg!(target_os = "windows") {
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+        }
+        let windir = std::env::var_os("WINDIR").unwrap_or("C:\\Windows".into());
+        dirs.push(PathBuf::from(windir).join("Fonts"));
+    } else if cfg!(target_os = "macos") {
+        dirs.extend(home.map(|home| home.join("Library/Fonts")));
+        dirs.push("/Library/Fonts".into());
+        dirs.push("/System/Library/Fonts".into());
+    } else {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".local/share")));
+        dirs.extend(data_home.map(|dir| dir.join("fonts")));
+        dirs.extend(home.map(|home| home.join(".fonts")));
+        let data_dirs = std::env::var("XDG_DATA_DIRS").unwrap_or("/usr/local/share:/usr/share".into());
+        dirs.extend(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(|dir| Path::new(dir).join("fonts")));
+    }
+    dirs
+}
+
+// The file and the index in it of the installed font whose family is
+// `family`, ignoring case, its regular style when it has one.
+pub(crate) fn find_system_font(family: &str) -> Option<(PathBuf, u32)> {
+    let (mut files, mut visited) = (vec![], HashSet::new());
+    for dir in system_font_dirs() {
+        collect_font_files(&dir, &mut files, &mut visited);
+    }
+    // files named like the family first, most fonts are, so the others are
+    // rarely parsed
+    let wanted = squash(family);
+    files.sort_by_key(|file| {
+        let stem = file.file_stem().map(|stem| squash(&stem.to_string_lossy())).unwrap_or_default();
+        !stem.starts_with(&wanted)
+    });
+
+    let mut found = None;
+    for file in files {
+        let Ok(data) = std::fs::read(&file) else {
+            continue;
+        };
+        for index in 0..ttf_parser::fonts_in_collection(&data).unwrap_or(1) {
+            let Ok(face) = ttf_parser::Face::parse(&data, index) else {
+                continue;
+            };
+            if !has_family(&face, family) {
+                continue;
+            }
+            if face.is_regular() {
+                return Some((file, index));
+            }
+            found.get_or_insert((file.clone(), index));
+        }
+    }
+    found
+}
+
+fn has_family(face: &ttf_parser::Face, family: &str) -> bool {
+    use ttf_parser::name_id::{FAMILY, TYPOGRAPHIC_FAMILY};
+    face.names()
+        .into_iter()
+        .filter(|name| name.name_id == FAMILY || name.name_id == TYPOGRAPHIC_FAMILY)
+        .filter_map(|name| name.to_string())
+        .any(|name| name.trim().eq_ignore_ascii_case(family.trim()))
+}
+
+// lowercase letters and digits only, "DejaVu Sans" and "DejaVuSans-Bold"
+// start the same
+fn squash(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+// Symlinked directories are followed, each directory is only read once by
+// where it really is, so links back up the tree don't recurse forever.
+fn collect_font_files(dir: &Path, files: &mut Vec<PathBuf>, visited: &mut HashSet<PathBuf>) {
+    let Ok(canonical) = dir.canonicalize() else {
+        return;
+    };
+    if !visited.insert(canonical) {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_font_files(&path, files, visited);
+            continue;
+        }
+        let is_font = path.extension().is_some_and(|ext| {
+            ["ttf", "otf", "ttc", "otc"].iter().any(|font| ext.eq_ignore_ascii_case(font))
+        });
+        if is_font {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symlink_loops_are_read_once() {
+        let root = std::env::temp_dir().join(format!("wrs-font-loop-{}", std::process::id()));
+        let fonts = root.join("fonts");
+        std::fs::create_dir_all(&fonts).unwrap();
+        std::fs::write(fonts.join("a.ttf"), []).unwrap();
+        std::os::unix::fs::symlink(&root, fonts.join("up")).unwrap();
+
+        let (mut files, mut visited) = (vec![], HashSet::new());
+        collect_font_files(&root, &mut files, &mut visited);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(files, [fonts.join("a.ttf")]);
+    }
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+// Where a '\t' moves the pen to, in monospace columns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TabStops {
+    // a stop every n columns
+    Every(usize),
+    // explicit, ascending stop columns; past the last stop a tab is one column wide
+    Columns(Vec<usize>),
+}
+
+impl Default for TabStops {
+    fn default() -> Self {
+        Self::Every(4)
+    }
+}
+
+impl TabStops {
+    // the column a tab typed at `column` advances to
+    pub fn next_stop(&self, column: usize) -> usize {
+        match self {
+            Self::Every(n) => {
+                let n = (*n).max(1);
+                (column / n + 1) * n
+            }
+            Self::Columns(stops) => stops
+                .iter()
+                .copied()
+                .find(|&stop| stop > column)
+                .unwrap_or(column + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tab_advances_to_the_next_stop() {
+        let every = TabStops::Every(4);
+        assert_eq!(every.next_stop(0), 4);
+        assert_eq!(every.next_stop(3), 4);
+        // on a stop moves on to the one after
+        assert_eq!(every.next_stop(4), 8);
+        assert_eq!(every.next_stop(9), 12);
+        // zero wide stops are one column
+        assert_eq!(TabStops::Every(0).next_stop(5), 6);
+
+        let columns = TabStops::Columns(vec![2, 10, 11]);
+        assert_eq!(columns.next_stop(0), 2);
+        assert_eq!(columns.next_stop(2), 10);
+        assert_eq!(columns.next_stop(10), 11);
+    }
+
+    #[test]
+    fn tab_past_the_last_stop_is_one_column() {
+        let columns = TabStops::Columns(vec![2, 10]);
+        assert_eq!(columns.next_stop(10), 11);
+        assert_eq!(columns.next_stop(25), 26);
+        assert_eq!(TabStops::Columns(vec![]).next_stop(3), 4);
+    }
+}
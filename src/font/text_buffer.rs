@@ -0,0 +1,95 @@
+use super::layout::PositionedGlyph;
+use super::{GlyphSource, TextLayout};
+
+// A string laid out once and drawn as often as wanted, wherever wanted, see
+// `FontRenderer::push_buffer` and `Frame::text_buffer`. Static labels skip the
+// wrapping, shaping and alignment `push_text` does every frame; changing the
+// text, size or layout lays it out again at the next draw.
+//
+// The layout is of the atlas it's drawn with first after a change. Drawing it
+// with another one takes `invalidate` first.
+#[derive(Debug, Clone)]
+pub struct TextBuffer {
+    text: String,
+    size: f32,
+    layout: TextLayout,
+    // glyphs relative to the top left corner and the size of the block, None
+    // until laid out since the last change
+    laid_out: Option<(Vec<PositionedGlyph>, (f32, f32))>,
+}
+
+impl TextBuffer {
+    // `size` is the pixel size to draw at, like `push_text`'s
+    pub fn new(text: impl Into<String>, size: f32) -> Self {
+        Self {
+            text: text.into(),
+            size,
+            layout: TextLayout::default(),
+            laid_out: None,
+        }
+    }
+
+    pub fn with_layout(mut self, layout: TextLayout) -> Self {
+        self.set_layout(layout);
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    pub fn layout(&self) -> &TextLayout {
+        &self.layout
+    }
+
+    // lays it out again only when `text` differs, so it can be set every frame
+    pub fn set_text(&mut self, text: &str) {
+        if self.text != text {
+            self.text.clear();
+            self.text.push_str(text);
+            self.invalidate();
+        }
+    }
+
+    pub fn set_size(&mut self, size: f32) {
+        if self.size != size {
+            self.size = size;
+            self.invalidate();
+        }
+    }
+
+    pub fn set_layout(&mut self, layout: TextLayout) {
+        self.layout = layout;
+        self.invalidate();
+    }
+
+    // lays it out again at the next draw
+    pub fn invalidate(&mut self) {
+        self.laid_out = None;
+    }
+
+    pub fn is_laid_out(&self) -> bool {
+        self.laid_out.is_some()
+    }
+
+    // the glyphs relative to the top left corner, laid out with `atlas` when
+    // they aren't yet
+    pub fn glyphs(&mut self, atlas: &impl GlyphSource) -> &[PositionedGlyph] {
+        &self.laid_out(atlas).0
+    }
+
+    // see `TextLayout::measure`
+    pub fn measure(&mut self, atlas: &impl GlyphSource) -> (f32, f32) {
+        self.laid_out(atlas).1
+    }
+
+    fn laid_out(&mut self, atlas: &impl GlyphSource) -> &(Vec<PositionedGlyph>, (f32, f32)) {
+        let (text, size, layout) = (&self.text, self.size, &self.layout);
+        self.laid_out
+            .get_or_insert_with(|| (layout.layout(text, size, atlas), layout.measure(text, size, atlas)))
+    }
+}
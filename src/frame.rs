@@ -0,0 +1,355 @@
+use std::time::{Duration, Instant};
+
+use crate::animation::{Animations, Tween};
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::color_space::ColorSpace;
+use crate::font::{FontRenderer, MissingGlyphs, TextBuffer, TextGrid, TextLayout, TextStyle};
+use crate::inset::{InsetView, InsetViewId};
+use crate::labels::{Label, LabelPlacer, Placement};
+use crate::layout::Layout;
+use crate::particles::{GpuParticles, GpuParticlesId, ParticleSystem};
+use crate::quad::{MaterialId, QuadRenderer};
+use crate::rect::{ClipShape, Rect};
+use crate::sort::SortKey;
+use crate::sprite::{Insets, SpriteRenderer, SpriteTexture};
+use crate::tilemap::{StreamingTilemap, StreamingTilemapId, TilemapId, TilemapRenderer};
+use crate::{MonoGlyphAtlas, Renderer, clock, debug_overlay};
+
+// The frame lifecycle:
+//
+//   let mut frame = renderer.begin_frame(); // ticks the clock, clears the batches
+//   frame.quad(..);                         // push the scene
+//   frame.submit();                         // toasts, upload, render, present
+//
+// The batches are only reachable through a `Frame`, and a frame borrows the
+// renderer mutably until it's submitted, so pushing outside of a frame or
+// starting a second one before the first is done doesn't compile. Dropping a
+// frame submits it as well.
+pub struct Frame<'a> {
+    pub(crate) renderer: &'a mut Renderer,
+}
+
+impl<'a> Frame<'a> {
+    pub(crate) fn new(renderer: &'a mut Renderer) -> Self {
+        Self { renderer }
+    }
+
+    // draws the toasts over the scene, uploads and renders it, the same as
+    // dropping the frame
+    pub fn submit(self) {}
+
+    // what the window spans in view pixels, a `Viewport`'s logical size when
+    // one is set
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.renderer.camera.view_size()
+    }
+
+    // physical pixels per logical one, world units are physical pixels unless
+    // the camera zooms
+    pub fn scale_factor(&self) -> f64 {
+        self.renderer.scale_factor
+    }
+
+    // the window's rect in the pixels of `size`, which screen space layers
+    // are drawn in, for placing HUD elements
+    pub fn layout(&self) -> Layout {
+        let size = self.size();
+        Layout::new(size.width as f32, size.height as f32)
+    }
+
+    // the window this frame is drawn into, for apps with more than one
+    pub fn window_id(&self) -> winit::window::WindowId {
+        self.renderer.get_window().id()
+    }
+
+    // draws another frame after this one, for animating in
+    // `RedrawMode::Reactive`, which otherwise waits for input
+    pub fn request_redraw(&self) {
+        self.renderer.get_window().request_redraw();
+    }
+
+    pub fn animations(&mut self) -> &mut Animations {
+        &mut self.renderer.animations
+    }
+
+    // advances `tween` by this frame's time, returns its value, and keeps
+    // frames coming until it's done
+    pub fn tween(&mut self, tween: &mut Tween) -> f32 {
+        let value = tween.update(self.renderer.clock.dt());
+        if !tween.is_done() {
+            self.renderer.animations.request_frame();
+        }
+        value
+    }
+
+    pub fn clock(&self) -> &clock::FrameClock {
+        &self.renderer.clock
+    }
+
+    // applies to everything pushed afterwards, `None` turns clipping off
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.renderer.quad_renderer.set_clip(clip);
+        self.renderer.sprite_renderer.set_clip(clip);
+        self.renderer.font_renderer.set_clip(clip);
+    }
+
+    // like `set_clip`, to a rounded rect or circle as well, see `ClipShape`
+    pub fn set_clip_shape(&mut self, clip: Option<ClipShape>) {
+        self.renderer.quad_renderer.set_clip_shape(clip);
+        self.renderer.sprite_renderer.set_clip_shape(clip);
+        self.renderer.font_renderer.set_clip_shape(clip);
+    }
+
+    // the clip everything is pushed with, `None` when nothing is clipped
+    pub fn clip_shape(&self) -> Option<ClipShape> {
+        self.renderer.quad_renderer.clip_shape()
+    }
+
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.renderer.set_color_space(color_space);
+    }
+
+    // Runs the egui ui of this frame, drawn over everything else, see
+    // `EguiLayer`.
+    #[cfg(feature = "egui")]
+    pub fn egui(&mut self, ui: impl FnMut(&egui::Context)) {
+        let window = self.renderer.window.clone();
+        self.renderer.egui().run(&window, ui);
+    }
+
+    // Runs `build` to push `layer` when it's dirty, what it pushes there is
+    // kept for the frames after, see `Renderer::set_static_layer`. `build`
+    // runs every frame for a layer that isn't static.
+    pub fn static_layer(&mut self, layer: u16, build: impl FnOnce(&mut Self)) {
+        match self.renderer.static_layers.get_mut(&layer) {
+            Some(false) => return,
+            Some(dirty) => {
+                *dirty = false;
+                self.renderer.rebuild_static_layer(layer);
+            }
+            None => {}
+        }
+        build(self);
+    }
+
+    pub fn quad(&mut self, x: f32, y: f32, w: f32, h: f32, color: impl Into<Color>) {
+        self.renderer.quad_renderer.push(x, y, w, h, color);
+    }
+
+    pub fn quad_with_key(&mut self, key: SortKey, x: f32, y: f32, w: f32, h: f32, color: impl Into<Color>) {
+        self.renderer
+            .quad_renderer
+            .push_with_key(key, x, y, w, h, color);
+    }
+
+    // see `QuadRenderer::push_with_z`
+    pub fn quad_with_z(&mut self, key: SortKey, z: f32, rect: Rect, color: impl Into<Color>) {
+        self.renderer
+            .quad_renderer
+            .push_with_z(key, z, rect, color);
+    }
+
+    // a filled polygon, see `QuadRenderer::push_polygon`
+    pub fn polygon(&mut self, points: &[[f32; 2]], color: impl Into<Color>) {
+        self.polygon_with_key(SortKey::default(), points, color);
+    }
+
+    pub fn polygon_with_key(&mut self, key: SortKey, points: &[[f32; 2]], color: impl Into<Color>) {
+        self.renderer
+            .quad_renderer
+            .push_polygon(key, points, color);
+    }
+
+    // see `Renderer::register_material`
+    pub fn quad_with_material(&mut self, key: SortKey, rect: Rect, color: impl Into<Color>, material: MaterialId) {
+        self.renderer
+            .quad_renderer
+            .push_with_material(key, rect, color, material);
+    }
+
+    // see `QuadRenderer::push_polygon_with_material`
+    pub fn polygon_with_material(&mut self, key: SortKey, points: &[[f32; 2]], color: impl Into<Color>, material: MaterialId) {
+        self.renderer
+            .quad_renderer
+            .push_polygon_with_material(key, points, color, material);
+    }
+
+    // see `Renderer::create_sprite_texture`
+    pub fn sprite(&mut self, rect: Rect, texture: &SpriteTexture) {
+        self.renderer.sprite_renderer.push(rect, texture);
+    }
+
+    // the whole of `texture` `z` into the screen, see `QuadRenderer::push_with_z`
+    pub fn sprite_with_z(&mut self, key: SortKey, z: f32, rect: Rect, texture: &SpriteTexture) {
+        self.renderer
+            .sprite_renderer
+            .push_with_z(key, z, rect, (0.0, 0.0, 1.0, 1.0), Color::WHITE, texture);
+    }
+
+    // see `SpriteRenderer::push_nine_slice`
+    pub fn nine_slice(&mut self, rect: Rect, texture: &SpriteTexture, insets: Insets) {
+        self.renderer
+            .sprite_renderer
+            .push_nine_slice(rect, texture, insets);
+    }
+
+    // the live particles of a cpu simulated system, as quads
+    pub fn particles(&mut self, system: &ParticleSystem) {
+        if !system.is_empty() || system.emitter.rate > 0.0 {
+            self.renderer.animations.request_frame();
+        }
+        system.push_to(&mut self.renderer.quad_renderer);
+    }
+
+    // a single line with the default font
+    pub fn text(&mut self, x: f32, y: f32, size: f32, color: impl Into<Color>, text: &str) -> Result<(), MissingGlyphs> {
+        let renderer = &mut *self.renderer;
+        renderer
+            .font_renderer
+            .push_str(x, y, size, color, text, &renderer.font_atlas)
+    }
+
+    pub fn text_with_key(&mut self, key: SortKey, x: f32, y: f32, size: f32, color: impl Into<Color>, text: &str) -> Result<(), MissingGlyphs> {
+        let renderer = &mut *self.renderer;
+        renderer
+            .font_renderer
+            .push_str_with_key(key, x, y, size, color, text, &renderer.font_atlas)
+    }
+
+    // see `Renderer::push_text`
+    pub fn styled_text(
+        &mut self,
+        x: f32,
+        y: f32,
+        size: f32,
+        text: &str,
+        layout: &TextLayout,
+        style: &TextStyle,
+    ) -> Result<(), MissingGlyphs> {
+        self.renderer.push_text(x, y, size, text, layout, style)
+    }
+
+    // see `Renderer::push_text_buffer`
+    pub fn text_buffer(&mut self, x: f32, y: f32, buffer: &mut TextBuffer, style: &TextStyle) -> Result<(), MissingGlyphs> {
+        self.renderer.push_text_buffer(x, y, buffer, style)
+    }
+
+    // see `Renderer::push_text_grid`
+    pub fn text_grid(&mut self, grid: &TextGrid, col: usize, row: usize, text: &str, style: &TextStyle) -> Result<(), MissingGlyphs> {
+        self.renderer.push_text_grid(grid, col, row, text, style)
+    }
+
+    // `text` placed within `rect` by the layout's `valign`, see
+    // `TextLayout::offset_in`
+    pub fn text_in(
+        &mut self,
+        rect: Rect,
+        size: f32,
+        text: &str,
+        layout: &TextLayout,
+        style: &TextStyle,
+    ) -> Result<(), MissingGlyphs> {
+        let (x, y) = layout.offset_in(text, size, &self.renderer.font_atlas, rect);
+        self.renderer.push_text(x, y, size, text, layout, style)
+    }
+
+    // Draws the labels `placer` finds room for where it put them, see
+    // `LabelPlacer`. Labels with missing glyphs are drawn with the fallback
+    // glyph and reported after all were pushed.
+    pub fn labels(&mut self, labels: &[Label], placer: &LabelPlacer) -> Result<Vec<Placement>, MissingGlyphs> {
+        let placements = self.renderer.place_labels(labels, placer);
+        let layout = TextLayout::default();
+        let mut missing = MissingGlyphs(vec![]);
+        for placement in &placements {
+            let label = &labels[placement.label];
+            let [x, y] = placement.position;
+            if let Err(err) = self.renderer.push_text(x, y, label.size, &label.text, &layout, &label.style) {
+                missing.merge(err);
+            }
+        }
+        missing.into_result().map(|_| placements)
+    }
+
+    pub fn toast(&mut self, text: impl Into<String>, duration: Duration) {
+        self.renderer.toast(text, duration);
+    }
+
+    // the default font, e.g. to measure text with
+    pub fn font_atlas(&self) -> &MonoGlyphAtlas {
+        &self.renderer.font_atlas
+    }
+
+    // the frame's batches, to push glyphs from other atlases (icon fonts, a
+    // `FontManager`, ...)
+    pub fn batches(&mut self) -> (&mut QuadRenderer, &mut FontRenderer) {
+        (&mut self.renderer.quad_renderer, &mut self.renderer.font_renderer)
+    }
+
+    // for sprites with sort keys, tints or texture regions
+    pub fn sprites(&mut self) -> &mut SpriteRenderer {
+        &mut self.renderer.sprite_renderer
+    }
+
+    // a tilemap of `Renderer::add_tilemap`, to change its tiles
+    pub fn tilemap(&mut self, id: TilemapId) -> &mut TilemapRenderer {
+        &mut self.renderer.tilemaps[id.0]
+    }
+
+    // a tilemap of `Renderer::add_streaming_tilemap`
+    pub fn streaming_tilemap(&mut self, id: StreamingTilemapId) -> &mut StreamingTilemap {
+        &mut self.renderer.streaming_tilemaps[id.0]
+    }
+
+    // particles of `Renderer::add_gpu_particles`, to move or change the emitter
+    pub fn gpu_particles(&mut self, id: GpuParticlesId) -> &mut GpuParticles {
+        &mut self.renderer.gpu_particles[id.0]
+    }
+
+    // a view of `Renderer::add_inset_view`, to move its camera or rect
+    pub fn inset_view(&mut self, id: InsetViewId) -> &mut InsetView {
+        &mut self.renderer.inset_views[id.0]
+    }
+
+    // see `Renderer::screen_to_world`
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (Option<InsetViewId>, (f32, f32)) {
+        self.renderer.screen_to_world(x, y)
+    }
+
+    pub fn camera(&self) -> &Camera {
+        self.renderer.camera()
+    }
+
+    pub fn set_camera_position(&mut self, position: [f32; 2]) {
+        self.renderer.set_camera_position(position);
+    }
+
+    pub fn set_camera_zoom(&mut self, zoom: f32) {
+        self.renderer.set_camera_zoom(zoom);
+    }
+
+    pub fn set_camera_rotation(&mut self, rotation: f32) {
+        self.renderer.set_camera_rotation(rotation);
+    }
+}
+
+impl Drop for Frame<'_> {
+    fn drop(&mut self) {
+        // don't present a half pushed scene while unwinding
+        if std::thread::panicking() {
+            return;
+        }
+        if !self.renderer.toasts.is_empty() {
+            let mut toasts = std::mem::take(&mut self.renderer.toasts);
+            // characters missing from the atlas are drawn as the fallback glyph
+            let _ = toasts.draw(self, Instant::now());
+            self.renderer.toasts = toasts;
+            self.renderer.animations.request_frame();
+        }
+        if self.renderer.debug_overlay {
+            let _ = debug_overlay::draw(self);
+        }
+        self.renderer.prepare_frame();
+        self.renderer.render();
+    }
+}
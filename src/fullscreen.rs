@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use winit::window::{Fullscreen, Window};
+
+// How a window covers its monitor, see `Renderer::set_fullscreen`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+    // a window without decorations the size of the monitor, switching to and
+    // from it is instant and other windows can still be shown over it
+    Borderless,
+    // takes the monitor over at its largest video mode, with the highest
+    // refresh rate of that size, where the platform supports it
+    Exclusive,
+}
+
+impl FullscreenMode {
+    // the mode `window` is in
+    pub fn of(window: &Window) -> Self {
+        match window.fullscreen() {
+            None => Self::Windowed,
+            Some(Fullscreen::Borderless(_)) => Self::Borderless,
+            Some(Fullscreen::Exclusive(_)) => Self::Exclusive,
+        }
+    }
+}
+
+// What `window` is set to for `mode` on its current monitor. Exclusive falls
+// back to borderless on monitors without video modes, like on wayland and the
+// web.
+pub(crate) fn fullscreen(window: &Window, mode: FullscreenMode) -> Option<Fullscreen> {
+    let monitor = window.current_monitor();
+    match mode {
+        FullscreenMode::Windowed => None,
+        FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+        FullscreenMode::Exclusive => {
+            let video_mode = monitor.as_ref().and_then(|monitor| {
+                monitor.video_modes().max_by_key(|video_mode| {
+                    let size = video_mode.size();
+                    (size.width * size.height, video_mode.refresh_rate_millihertz())
+                })
+            });
+            Some(match video_mode {
+                Some(video_mode) => Fullscreen::Exclusive(video_mode),
+                None => Fullscreen::Borderless(monitor),
+            })
+        }
+    }
+}
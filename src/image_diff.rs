@@ -0,0 +1,80 @@
+use image::{Rgba, RgbaImage};
+
+// the largest YIQ delta two pixels can have, black against white
+const MAX_DELTA: f32 = 35215.0;
+
+// Where two captures of the same scene differ and by how much, for triaging
+// visual regressions. `wrs-diff` shows it next to the captures.
+//
+// Pixels are compared by their perceived difference (the YIQ distance of
+// pixelmatch), which weighs brightness over hue the way eyes do, after
+// blending both over white. Captures of different sizes are compared over the
+// larger one, what only one of them covers differs fully.
+#[derive(Debug, Clone)]
+pub struct ImageDiff {
+    // the first capture faded to gray, differing pixels over it from yellow
+    // for barely to red for fully different
+    pub heatmap: RgbaImage,
+    // pixels differing by more than the threshold
+    pub differing: usize,
+    // perceived differences from 0 to 1
+    pub max: f32,
+    pub mean: f32,
+}
+
+impl ImageDiff {
+    // `threshold` is the perceived difference from 0 to 1 below which pixels
+    // count as equal, 0.1 hides dithering and filtering noise
+    pub fn new(a: &RgbaImage, b: &RgbaImage, threshold: f32) -> Self {
+        let (width, height) = (a.width().max(b.width()), a.height().max(b.height()));
+        let mut heatmap = RgbaImage::new(width, height);
+        let (mut differing, mut max, mut sum) = (0, 0.0f32, 0.0);
+        for (x, y, out) in heatmap.enumerate_pixels_mut() {
+            let (pa, pb) = (a.get_pixel_checked(x, y), b.get_pixel_checked(x, y));
+            let delta = match (pa, pb) {
+                (Some(pa), Some(pb)) => (yiq_delta(*pa, *pb) / MAX_DELTA).sqrt(),
+                _ => 1.0,
+            };
+            max = max.max(delta);
+            sum += delta;
+            *out = if delta > threshold {
+                differing += 1;
+                // yellow to red
+                Rgba([255, (255.0 * (1.0 - delta)) as u8, 0, 255])
+            } else {
+                let gray = pa.map_or(255.0, |p| 255.0 - (255.0 - luma(*p)) * 0.2);
+                Rgba([gray as u8, gray as u8, gray as u8, 255])
+            };
+        }
+        Self {
+            heatmap,
+            differing,
+            max,
+            mean: sum / (width as f32 * height as f32).max(1.0),
+        }
+    }
+
+    pub fn differs(&self) -> bool {
+        self.differing > 0
+    }
+}
+
+fn blend(pixel: Rgba<u8>) -> [f32; 3] {
+    let a = pixel[3] as f32 / 255.0;
+    [0, 1, 2].map(|i| 255.0 + (pixel[i] as f32 - 255.0) * a)
+}
+
+fn luma(pixel: Rgba<u8>) -> f32 {
+    let [r, g, b] = blend(pixel);
+    r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2
+}
+
+// the squared distance of two pixels in YIQ, weighted like pixelmatch
+fn yiq_delta(a: Rgba<u8>, b: Rgba<u8>) -> f32 {
+    let ([r1, g1, b1], [r2, g2, b2]) = (blend(a), blend(b));
+    let (dr, dg, db) = (r1 - r2, g1 - g2, b1 - b2);
+    let y = dr * 0.298_895_3 + dg * 0.586_622_5 + db * 0.114_482_2;
+    let i = dr * 0.595_978 - dg * 0.274_176_1 - db * 0.321_801_9;
+    let q = dr * 0.211_470_2 - dg * 0.522_617_1 + db * 0.311_146_9;
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
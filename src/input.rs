@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+
+// pixels one line of a line based scroll wheel counts as
+const SCROLL_LINE: f32 = 20.0;
+// pixels of scrolling with ctrl held that double the zoom, about seven notches
+// of a wheel
+const SCROLL_DOUBLING: f32 = 140.0;
+
+// the default of `App::fullscreen_key`
+pub const FULLSCREEN_KEY: KeyCode = KeyCode::F11;
+
+// Keyboard and mouse state collected from window events between two frames.
+// Held keys and buttons persist, presses, scrolling and gestures only last for
+// the frame they happened before.
+//
+// Touchpads pinch and rotate through gesture events on macOS and through
+// scrolling with ctrl held on windows and linux, which is what mice zoom with
+// as well, so `pinch` counts both and `scroll` leaves the latter out.
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    // pressed or repeated
+    keys_typed: HashSet<KeyCode>,
+    // text typed, without control characters
+    text: String,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    // physical window pixels
+    mouse: Option<(f32, f32)>,
+    mouse_moved: (f32, f32),
+    scroll: (f32, f32),
+    modifiers: ModifiersState,
+    // log2 of the zoom factor pinched
+    pinch: f32,
+    // radians, counterclockwise
+    rotation: f32,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                let PhysicalKey::Code(code) = event.physical_key else {
+                    return;
+                };
+                match event.state {
+                    ElementState::Pressed => {
+                        if !event.repeat {
+                            self.keys_pressed.insert(code);
+                        }
+                        self.keys_down.insert(code);
+                        self.keys_typed.insert(code);
+                        if let Some(text) = &event.text {
+                            self.text.extend(text.chars().filter(|c| !c.is_control()));
+                        }
+                    }
+                    ElementState::Released => {
+                        self.keys_down.remove(&code);
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    self.buttons_pressed.insert(*button);
+                    self.buttons_down.insert(*button);
+                }
+                ElementState::Released => {
+                    self.buttons_down.remove(button);
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let (x, y) = (position.x as f32, position.y as f32);
+                if let Some((last_x, last_y)) = self.mouse {
+                    self.mouse_moved.0 += x - last_x;
+                    self.mouse_moved.1 += y - last_y;
+                }
+                self.mouse = Some((x, y));
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.mouse = None;
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x * SCROLL_LINE, y * SCROLL_LINE),
+                    MouseScrollDelta::PixelDelta(p) => (p.x as f32, p.y as f32),
+                };
+                if self.modifiers.control_key() {
+                    self.pinch += y / SCROLL_DOUBLING;
+                } else {
+                    self.scroll.0 += x;
+                    self.scroll.1 += y;
+                }
+            }
+            WindowEvent::PinchGesture { delta, .. } => {
+                self.pinch += (1.0 + *delta as f32).max(0.01).log2();
+            }
+            WindowEvent::RotationGesture { delta, .. } => {
+                self.rotation += delta.to_radians();
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            // keys released while unfocused never report their release
+            WindowEvent::Focused(false) => {
+                self.keys_down.clear();
+                self.buttons_down.clear();
+                self.modifiers = ModifiersState::empty();
+            }
+            _ => {}
+        }
+    }
+
+    // forgets this frame's presses, movement, scrolling and gestures, call
+    // after drawing
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_typed.clear();
+        self.text.clear();
+        self.buttons_pressed.clear();
+        self.mouse_moved = (0.0, 0.0);
+        self.scroll = (0.0, 0.0);
+        self.pinch = 0.0;
+        self.rotation = 0.0;
+    }
+
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    // pressed since the last frame, key repeats don't count
+    pub fn was_key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    // pressed or repeated since the last frame, for keys that edit text like
+    // backspace
+    pub fn was_key_typed(&self, key: KeyCode) -> bool {
+        self.keys_typed.contains(&key)
+    }
+
+    // what was typed since the last frame, as the keyboard layout and input
+    // method produce it
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn was_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    // `None` while the cursor is outside the window
+    pub fn mouse_position(&self) -> Option<(f32, f32)> {
+        self.mouse
+    }
+
+    // physical pixels the cursor moved since the last frame
+    pub fn mouse_moved(&self) -> (f32, f32) {
+        self.mouse_moved
+    }
+
+    // pixels scrolled since the last frame, positive y is up / away from the user
+    pub fn scroll(&self) -> (f32, f32) {
+        self.scroll
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    // the zoom factor pinched since the last frame, above 1 for zooming in and
+    // 1 without a pinch
+    pub fn pinch(&self) -> f32 {
+        self.pinch.exp2()
+    }
+
+    // radians rotated with two fingers since the last frame, counterclockwise
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+}
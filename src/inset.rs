@@ -0,0 +1,122 @@
+use crate::camera::Camera;
+use crate::rect::Rect;
+use crate::state::InsetViewState;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InsetViewId(pub usize);
+
+// A second view of the world drawn into a rect of the window over the scene,
+// a minimap or picture in picture, see `Renderer::add_inset_view`. It draws the
+// world layers with a camera of its own, layers with a `Parallax` stay in the
+// main view. `Renderer::screen_to_world` routes points inside of it through
+// its camera, so clicks on an overview pane land where they point.
+pub struct InsetView {
+    // physical window pixels
+    rect: Rect,
+    camera: Camera,
+    queue: wgpu::Queue,
+    // hidden views are neither drawn nor hit
+    pub visible: bool,
+}
+
+impl InsetView {
+    pub(crate) fn new(device: &wgpu::Device, queue: &wgpu::Queue, rect: Rect, scale_factor: f64) -> Self {
+        let rect = rect.normalized();
+        let mut camera = Camera::new_from_size(device, Self::size(rect));
+        camera.set_scale_factor(scale_factor, queue);
+        Self {
+            rect,
+            camera,
+            queue: queue.clone(),
+            visible: true,
+        }
+    }
+
+    fn size(rect: Rect) -> winit::dpi::PhysicalSize<u32> {
+        winit::dpi::PhysicalSize::new(rect.w.round().max(1.0) as u32, rect.h.round().max(1.0) as u32)
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    // moves or resizes the view, the camera's position stays the world point
+    // at its top left corner
+    pub fn set_rect(&mut self, rect: Rect) {
+        self.rect = rect.normalized();
+        self.camera.resize(Self::size(self.rect), &self.queue);
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn state(&self) -> InsetViewState {
+        InsetViewState {
+            rect: self.rect,
+            camera: self.camera.state(),
+            visible: self.visible,
+        }
+    }
+
+    pub(crate) fn restore(&mut self, state: InsetViewState) {
+        self.rect = state.rect.normalized();
+        self.camera.restore(state.camera, &self.queue);
+        self.visible = state.visible;
+    }
+
+    pub fn set_position(&mut self, position: [f32; 2]) {
+        self.camera.set_position(position, &self.queue);
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.camera.set_zoom(zoom, &self.queue);
+    }
+
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.camera.set_rotation(rotation, &self.queue);
+    }
+
+    pub(crate) fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.camera.set_scale_factor(scale_factor, &self.queue);
+    }
+
+    // Shows all of `area` at the largest zoom it fits in with, centered.
+    pub fn fit(&mut self, area: Rect) {
+        let area = area.normalized();
+        let zoom = (self.rect.w / area.w.max(f32::EPSILON)).min(self.rect.h / area.h.max(f32::EPSILON));
+        let (w, h) = (self.rect.w / zoom, self.rect.h / zoom);
+        self.camera.set_zoom(zoom, &self.queue);
+        self.camera
+            .set_position([area.x - (w - area.w) / 2.0, area.y - (h - area.h) / 2.0], &self.queue);
+    }
+
+    // whether the view is shown at `x`, `y` in physical window pixels
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        self.visible && self.rect.contains(x, y)
+    }
+
+    // A point in physical window pixels, e.g. the mouse, in world units as
+    // seen through this view. None outside of it.
+    pub fn screen_to_world(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        self.contains(x, y)
+            .then(|| self.camera.screen_to_world(x - self.rect.x, y - self.rect.y))
+    }
+
+    // where a world point is shown in this view, in physical window pixels,
+    // which lies outside of its rect for points it doesn't show
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        let (x, y) = self.camera.world_to_screen(x, y);
+        (x + self.rect.x, y + self.rect.y)
+    }
+
+    // the part of its rect inside of a `target` sized window, in whole pixels,
+    // None when none of it is
+    pub(crate) fn scissor(&self, target: winit::dpi::PhysicalSize<u32>) -> Option<[u32; 4]> {
+        let x0 = self.rect.x.round().clamp(0.0, target.width as f32) as u32;
+        let y0 = self.rect.y.round().clamp(0.0, target.height as f32) as u32;
+        let x1 = self.rect.right().round().clamp(0.0, target.width as f32) as u32;
+        let y1 = self.rect.bottom().round().clamp(0.0, target.height as f32) as u32;
+        (x1 > x0 && y1 > y0).then_some([x0, y0, x1 - x0, y1 - y0])
+    }
+}
@@ -0,0 +1,156 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::camera::Camera;
+use crate::font::TextStyle;
+use crate::rect::Rect;
+
+// physical pixels of the grid cells placed labels are bucketed into, so a
+// label is only checked against the ones near it
+const CELL: f32 = 64.0;
+
+// Text naming a point of the world, like a city on a map, for `LabelPlacer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub text: String,
+    // in world units, the text's top left corner unless it's nudged
+    pub position: [f32; 2],
+    // in world units, `TextStyle::screen_size` keeps it readable
+    pub size: f32,
+    // higher ones are placed first and win where labels collide
+    pub priority: i32,
+    pub style: TextStyle,
+}
+
+impl Label {
+    pub fn new(text: impl Into<String>, position: [f32; 2], size: f32) -> Self {
+        Self {
+            text: text.into(),
+            position,
+            size,
+            priority: 0,
+            style: TextStyle::default(),
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_style(mut self, style: TextStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+// Where `LabelPlacer` put a label.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    // index into the placed labels
+    pub label: usize,
+    // the world point the text's top left corner goes at
+    pub position: [f32; 2],
+    // what the text covers in physical window pixels, e.g. for hovering
+    pub rect: Rect,
+}
+
+// Greedy decluttering of labels in screen space. Labels go from the highest
+// priority down, equal ones in order, each into the first spot that doesn't
+// overlap one placed before, and are hidden when there's none. The spots are
+// below right of the label's point, then with `nudge` below left, above right
+// and above left of it, the four corners text can hang from a point by.
+//
+// Labels are taken as upright on screen, which they are without camera
+// rotation or with `TextStyle::billboard`. Ones outside of the window are
+// left out and don't block any.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LabelPlacer {
+    // physical pixels kept free between labels
+    pub padding: f32,
+    pub nudge: bool,
+}
+
+impl Default for LabelPlacer {
+    fn default() -> Self {
+        Self {
+            padding: 2.0,
+            nudge: true,
+        }
+    }
+}
+
+impl LabelPlacer {
+    // `measure` gives the width and height of a label at its size in world
+    // units, like `TextLayout::measure`; `Renderer::place_labels` measures
+    // with the default font.
+    pub fn place(&self, labels: &[Label], camera: &Camera, measure: impl Fn(&Label) -> (f32, f32)) -> Vec<Placement> {
+        let size = camera.window_size();
+        let window = Rect::new(0.0, 0.0, size.width as f32, size.height as f32);
+        // physical pixels per world unit, through the viewport's scale too
+        let (x0, y0) = camera.world_to_screen(0.0, 0.0);
+        let (x1, y1) = camera.world_to_screen(1.0, 0.0);
+        let pixels = (x1 - x0).hypot(y1 - y0);
+
+        let mut order: Vec<usize> = (0..labels.len()).collect();
+        order.sort_by_key(|&i| Reverse(labels[i].priority));
+
+        let mut grid: HashMap<(i32, i32), Vec<Rect>> = HashMap::new();
+        let mut placed = vec![];
+        for i in order {
+            let label = &labels[i];
+            let (w, h) = measure(label);
+            let scale = match label.style.screen_size {
+                Some((min, max)) if label.size > 0.0 => {
+                    let zoom = camera.effective_zoom();
+                    (label.size * zoom).clamp(min, max) / zoom / label.size
+                }
+                _ => 1.0,
+            };
+            let (w, h) = (w * scale * pixels, h * scale * pixels);
+            let (ax, ay) = camera.world_to_screen(label.position[0], label.position[1]);
+            let spots: &[(f32, f32)] = if self.nudge {
+                &[(0.0, 0.0), (-w, 0.0), (0.0, -h), (-w, -h)]
+            } else {
+                &[(0.0, 0.0)]
+            };
+            let free = |rect: &Rect| {
+                let padded = Rect::new(
+                    rect.x - self.padding,
+                    rect.y - self.padding,
+                    rect.w + 2.0 * self.padding,
+                    rect.h + 2.0 * self.padding,
+                );
+                cells(&padded).all(|cell| {
+                    grid.get(&cell)
+                        .is_none_or(|rects| !rects.iter().any(|r| r.intersects(&padded)))
+                })
+            };
+            let Some(rect) = spots
+                .iter()
+                .map(|(dx, dy)| Rect::new(ax + dx, ay + dy, w, h))
+                .filter(|rect| rect.intersects(&window))
+                .find(free)
+            else {
+                continue;
+            };
+            for cell in cells(&rect) {
+                grid.entry(cell).or_default().push(rect);
+            }
+            let (x, y) = camera.screen_to_world(rect.x, rect.y);
+            placed.push(Placement {
+                label: i,
+                position: [x, y],
+                rect,
+            });
+        }
+        placed
+    }
+}
+
+// the grid cells `rect` touches
+fn cells(rect: &Rect) -> impl Iterator<Item = (i32, i32)> {
+    let (x0, y0) = ((rect.x / CELL).floor() as i32, (rect.y / CELL).floor() as i32);
+    let (x1, y1) = ((rect.right() / CELL).floor() as i32, (rect.bottom() / CELL).floor() as i32);
+    (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| (x, y)))
+}
@@ -0,0 +1,189 @@
+use crate::rect::Rect;
+
+// A distance in pixels or in percent of the parent's width, for horizontal
+// ones, or height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Px(f32),
+    Percent(f32),
+}
+
+impl Length {
+    // in pixels, within a parent `parent` pixels long
+    pub fn resolve(self, parent: f32) -> f32 {
+        match self {
+            Length::Px(px) => px,
+            Length::Percent(percent) => parent * percent / 100.0,
+        }
+    }
+}
+
+impl From<f32> for Length {
+    fn from(px: f32) -> Self {
+        Length::Px(px)
+    }
+}
+
+// The point of the parent a rect is pinned to with the same point of its own,
+// a `BottomRight` rect sits in the parent's bottom right corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // how far along the parent the anchor is, 0, 0.5 or 1 on each axis
+    pub fn fraction(self) -> (f32, f32) {
+        match self {
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+// A rect given relative to its parent, see `Layout::rect`. The margins keep it
+// off the edges it's anchored to, along a centered axis they move it right or
+// down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Anchored {
+    pub anchor: Anchor,
+    pub width: Length,
+    pub height: Length,
+    pub margin: [Length; 2],
+}
+
+impl Anchored {
+    pub fn new(anchor: Anchor, width: impl Into<Length>, height: impl Into<Length>) -> Self {
+        Self {
+            anchor,
+            width: width.into(),
+            height: height.into(),
+            margin: [Length::Px(0.0); 2],
+        }
+    }
+
+    pub fn with_margin(mut self, x: impl Into<Length>, y: impl Into<Length>) -> Self {
+        self.margin = [x.into(), y.into()];
+        self
+    }
+}
+
+// Resolves `Anchored`s into pixel rects within `bounds`, usually the window,
+// so HUD elements stay pinned where they belong when it's resized:
+//
+//   let minimap = Anchored::new(Anchor::TopRight, Length::Percent(20.0), Length::Percent(20.0))
+//       .with_margin(16.0, 16.0);
+//   // every frame
+//   let rect = frame.layout().rect(&minimap);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub bounds: Rect,
+}
+
+impl Layout {
+    // a `width` by `height` parent with its top left corner at 0, 0
+    pub fn new(width: f32, height: f32) -> Self {
+        Self::within(Rect::new(0.0, 0.0, width, height))
+    }
+
+    pub fn within(bounds: Rect) -> Self {
+        Self { bounds }
+    }
+
+    // keeps the top left corner where it is
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.bounds.w = width;
+        self.bounds.h = height;
+    }
+
+    pub fn rect(&self, anchored: &Anchored) -> Rect {
+        let bounds = self.bounds;
+        let (w, h) = (anchored.width.resolve(bounds.w), anchored.height.resolve(bounds.h));
+        let [margin_x, margin_y] = anchored.margin;
+        let (margin_x, margin_y) = (margin_x.resolve(bounds.w), margin_y.resolve(bounds.h));
+        let (fx, fy) = anchored.anchor.fraction();
+        let shift = |fraction: f32, margin: f32| if fraction == 1.0 { -margin } else { margin };
+        Rect::new(
+            bounds.x + fx * (bounds.w - w) + shift(fx, margin_x),
+            bounds.y + fy * (bounds.h - h) + shift(fy, margin_y),
+            w,
+            h,
+        )
+    }
+
+    // for placing rects within the rect of `anchored`
+    pub fn child(&self, anchored: &Anchored) -> Layout {
+        Layout::within(self.rect(anchored))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_sizes_at_each_anchor() {
+        let layout = Layout::within(Rect::new(10.0, 20.0, 200.0, 100.0));
+        let at = |anchor| layout.rect(&Anchored::new(anchor, 40.0, 20.0).with_margin(5.0, 5.0));
+        assert_eq!(at(Anchor::TopLeft), Rect::new(15.0, 25.0, 40.0, 20.0));
+        assert_eq!(at(Anchor::Center), Rect::new(95.0, 65.0, 40.0, 20.0));
+        assert_eq!(at(Anchor::BottomRight), Rect::new(165.0, 95.0, 40.0, 20.0));
+        assert_eq!(at(Anchor::Bottom), Rect::new(95.0, 95.0, 40.0, 20.0));
+    }
+
+    #[test]
+    fn percent_sizes_fill_and_follow_resizes() {
+        let mut layout = Layout::new(200.0, 100.0);
+        let fill = Anchored::new(Anchor::TopLeft, Length::Percent(100.0), Length::Percent(100.0));
+        assert_eq!(layout.rect(&fill), layout.bounds);
+        let side = Anchored::new(Anchor::Right, Length::Percent(25.0), Length::Percent(100.0))
+            .with_margin(Length::Percent(5.0), 0.0);
+        assert_eq!(layout.rect(&side), Rect::new(140.0, 0.0, 50.0, 100.0));
+
+        layout.resize(400.0, 50.0);
+        assert_eq!(layout.rect(&fill), Rect::new(0.0, 0.0, 400.0, 50.0));
+        assert_eq!(layout.rect(&side), Rect::new(280.0, 0.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn children_resolve_within_their_parent() {
+        let layout = Layout::new(200.0, 100.0);
+        let panel = layout.child(&Anchored::new(Anchor::TopRight, Length::Percent(50.0), Length::Percent(50.0)));
+        let half = Anchored::new(Anchor::BottomLeft, Length::Percent(50.0), Length::Percent(50.0));
+        assert_eq!(panel.rect(&half), Rect::new(100.0, 25.0, 50.0, 25.0));
+    }
+
+    #[test]
+    fn fixed_sizes_keep_to_their_minimum_and_overflow() {
+        // the parent shrinks, pixel sizes don't, percents shrink along to nothing
+        let mut layout = Layout::new(200.0, 100.0);
+        layout.resize(20.0, 0.0);
+        let fixed = Anchored::new(Anchor::TopLeft, 60.0, 30.0);
+        assert_eq!(layout.rect(&fixed), Rect::new(0.0, 0.0, 60.0, 30.0));
+        let percent = Anchored::new(Anchor::TopLeft, Length::Percent(50.0), Length::Percent(50.0));
+        assert_eq!(layout.rect(&percent), Rect::new(0.0, 0.0, 10.0, 0.0));
+
+        // what doesn't fit hangs off the side away from the anchor
+        let layout = Layout::new(100.0, 100.0);
+        let big = |anchor| layout.rect(&Anchored::new(anchor, 140.0, 120.0));
+        assert_eq!(big(Anchor::TopLeft), Rect::new(0.0, 0.0, 140.0, 120.0));
+        assert_eq!(big(Anchor::Center), Rect::new(-20.0, -10.0, 140.0, 120.0));
+        assert_eq!(big(Anchor::BottomRight), Rect::new(-40.0, -20.0, 140.0, 120.0));
+    }
+}
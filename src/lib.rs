@@ -0,0 +1,1531 @@
+pub mod animation;
+pub mod anomaly;
+pub mod app;
+pub mod assets;
+pub mod backend;
+pub mod batch;
+pub mod buffer;
+pub mod camera;
+pub mod camera_controls;
+mod canvas;
+pub mod clock;
+pub mod color;
+#[cfg(any(test, feature = "color-check"))]
+pub mod color_check;
+pub mod color_space;
+pub mod context;
+pub mod crash;
+pub mod culling;
+mod debug_overlay;
+pub mod depth;
+pub mod draw_list;
+#[cfg(feature = "egui")]
+pub mod egui_layer;
+pub mod export;
+pub mod font;
+pub mod frame;
+pub mod fullscreen;
+pub mod image_diff;
+pub mod input;
+pub mod inset;
+pub mod labels;
+pub mod layout;
+pub mod oit;
+pub mod particles;
+pub mod pipeline_2d;
+pub mod pipeline_cache;
+pub mod profiler;
+pub mod quad;
+pub mod quickstart;
+pub mod rect;
+pub mod remote;
+pub mod replay;
+pub mod retained;
+pub mod sanitize;
+pub mod scene;
+pub mod shader;
+pub mod software;
+pub mod sort;
+pub mod sprite;
+pub mod state;
+pub mod tessellate;
+pub mod tilemap;
+pub mod toast;
+mod trail;
+pub mod ui;
+pub mod viewport;
+pub mod visibility;
+use camera::Camera;
+use oit::Compositing;
+use state::{ClearMode, FontSource, RendererSettings, RendererState};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub use app::{App, Background, RedrawMode, run};
+pub use camera_controls::CameraControls;
+pub use context::GraphicsContext;
+pub use frame::Frame;
+pub use input::InputState;
+pub use quickstart::Canvas;
+pub use font::atlas::{MonoGlyphAtlas, create_monospace_atlas};
+
+pub struct Renderer {
+    window: Arc<winit::window::Window>,
+    context: GraphicsContext,
+    size: winit::dpi::PhysicalSize<u32>,
+    // None while the app is suspended, see `suspend`
+    surface: Option<wgpu::Surface<'static>>,
+    surface_fmt: wgpu::TextureFormat,
+    surface_usages: wgpu::TextureUsages,
+    // the present modes the surface supports, as of the last resize
+    present_modes: Vec<wgpu::PresentMode>,
+    // what frames are drawn into with `ClearMode::Preserve`
+    canvas: Option<canvas::Canvas>,
+    // what `toggle_fullscreen` switches to from a window
+    fullscreen: fullscreen::FullscreenMode,
+
+    camera: Camera,
+    settings: RendererSettings,
+
+    quad_renderer: quad::QuadRenderer,
+    sprite_renderer: sprite::SpriteRenderer,
+    // kept across frames, drawn under everything else
+    tilemaps: Vec<tilemap::TilemapRenderer>,
+    // kept across frames, drawn over the fixed tilemaps
+    streaming_tilemaps: Vec<tilemap::StreamingTilemap>,
+    // kept across frames, updated at the start of every `render`
+    gpu_particles: Vec<particles::GpuParticles>,
+    // drawn over the scene in the order they were added, see `add_inset_view`
+    inset_views: Vec<inset::InsetView>,
+
+    font_source: FontSource,
+    // pixel size of the font the atlas is rasterized from at a scale factor of 1
+    font_scale: f32,
+    font_atlas: MonoGlyphAtlas,
+    // the window's, the atlas is rasterized for it
+    scale_factor: f64,
+    font_renderer: font::FontRenderer,
+
+    oit: oit::OitCompositor,
+    // fades the canvas with `ClearMode::Trail`
+    trail: Option<trail::TrailFader>,
+    // with `RendererSettings::depth_buffer`
+    depth_buffer: Option<depth::DepthBuffer>,
+    draw_stats: sort::DrawStats,
+    // see `debug_overlay`
+    debug_overlay: bool,
+    // see `set_gpu_profiling`
+    profiler: Option<profiler::GpuProfiler>,
+    // see `set_anomaly_budget`
+    anomalies: Option<anomaly::AnomalyLog>,
+    // seconds the last `prepare_frame` took
+    upload_time: f32,
+    // see `set_static_layer`, whether each has to be built again
+    static_layers: BTreeMap<u16, bool>,
+    // see `load_assets`
+    asset_manifests: Vec<std::path::PathBuf>,
+    // see `egui`
+    #[cfg(feature = "egui")]
+    egui: Option<egui_layer::EguiLayer>,
+    geometry_issues: sanitize::GeometryIssues,
+
+    // ticked by `begin_frame`, the time source for animations
+    pub clock: clock::FrameClock,
+
+    // drawn over everything at the end of every frame, see `toast`
+    pub toasts: toast::Toasts,
+
+    // what keeps a `RedrawMode::Reactive` app drawing frames
+    pub animations: animation::Animations,
+}
+
+impl Renderer {
+    // a renderer on a `GraphicsContext` of its own
+    pub async fn new(window: Arc<winit::window::Window>) -> Self {
+        Self::with_context(&GraphicsContext::new().await, window)
+    }
+
+    // a renderer for one more window on the gpu of `context`
+    pub fn with_context(context: &GraphicsContext, window: Arc<winit::window::Window>) -> Self {
+        Self::with_settings(context, window, RendererSettings::default(), FontSource::Embedded, 128.0)
+    }
+
+    // The renderer `save_state` saved, on the gpu of `context`. Passing the
+    // host's context across a hot reload keeps the textures and atlases the
+    // app holds on to valid.
+    pub fn from_state(context: &GraphicsContext, window: Arc<winit::window::Window>, state: RendererState) -> Self {
+        let mut renderer = Self::with_settings(context, window, state.settings, state.font.source, state.font.scale);
+        renderer.camera.restore(state.camera, &renderer.context.queue);
+        renderer.set_camera_mode(state.camera.mode);
+        renderer.asset_manifests = state.assets;
+        for material in state.materials {
+            match renderer.register_material(&material.label, &material.source, material.uniform_size) {
+                Ok(id) if !material.uniform.is_empty() => renderer.set_material_uniform(id, &material.uniform),
+                Ok(_) => {}
+                // the ids of the ones after it would be off, their quads are drawn flat
+                Err(err) => {
+                    log::error!("restoring material {}: {err}", material.label);
+                    break;
+                }
+            }
+        }
+        for tilemap in state.tilemaps {
+            let texture = renderer.create_sprite_texture(&tilemap.tileset.image(), tilemap.tileset.filter);
+            let tileset = tilemap::Tileset::new(texture, tilemap.tile_width, tilemap.tile_height);
+            let id = renderer.add_tilemap(tileset, tilemap.width, tilemap.height, tilemap.layout.tile_size);
+            let restored = &mut renderer.tilemaps[id.0];
+            restored.set_tiles(&tilemap.tiles);
+            restored.set_origin(tilemap.layout.origin);
+            restored.set_projection(tilemap.layout.projection);
+            restored.set_layer(tilemap.layer);
+        }
+        for view in state.inset_views {
+            let id = renderer.add_inset_view(view.rect);
+            renderer.inset_views[id.0].restore(view);
+        }
+
+        let (device, queue) = (&renderer.context.device, &renderer.context.queue);
+        renderer.sprite_renderer.restore_textures(device, queue, &state.sprite_textures);
+        for (layer, kept) in state.static_layers {
+            renderer.quad_renderer.restore_static_layer(device, layer, kept.quads);
+            renderer.sprite_renderer.restore_static_layer(device, layer, kept.sprites);
+            renderer
+                .font_renderer
+                .restore_static_layer(device, layer, kept.glyphs, &renderer.font_atlas);
+            // built already
+            renderer.static_layers.insert(layer, false);
+        }
+        if let Some(frame) = state.frame {
+            renderer.quad_renderer.restore(frame.quads);
+            renderer.font_renderer.restore(frame.glyphs, &renderer.font_atlas);
+        }
+        renderer
+    }
+
+    fn with_settings(
+        context: &GraphicsContext,
+        window: Arc<winit::window::Window>,
+        settings: RendererSettings,
+        font_source: FontSource,
+        font_scale: f32,
+    ) -> Self {
+        let (device, queue) = (&context.device, &context.queue);
+
+        // wayland windows have no size until they're first shown, the render
+        // targets can't be empty and the `Resized` that follows fixes it
+        let size = window.inner_size();
+        let size = winit::dpi::PhysicalSize::new(size.width.max(1), size.height.max(1));
+
+        let surface = context.instance.create_surface(window.clone()).unwrap();
+
+        let capabilities = surface.get_capabilities(&context.adapter);
+
+        let surface_fmt = preferred_format(&settings, &capabilities);
+        let target_fmt = settings.surface_encoding.view_format(surface_fmt);
+
+        let scale_factor = window.scale_factor();
+        let mut cam = Camera::new_from_size(device, size);
+        cam.set_scale_factor(scale_factor, queue);
+
+        // font setup
+        let (font, index) = font_source.load_face().unwrap();
+        let atlas_scale = font::atlas_scale(font_scale, scale_factor);
+        let atlas = font::atlas::create_monospace_atlas_of_face(device, queue, &font, index, atlas_scale);
+
+        let mut renderer = Self {
+            window,
+            quad_renderer: quad::QuadRenderer::with_culling(device, &cam, target_fmt, settings.culling),
+            sprite_renderer: sprite::SpriteRenderer::with_culling(device, &cam, target_fmt, settings.culling),
+            tilemaps: vec![],
+            streaming_tilemaps: vec![],
+            gpu_particles: vec![],
+            font_renderer: font::FontRenderer::with_culling(device, &cam, &atlas, target_fmt, settings.culling),
+            oit: oit::OitCompositor::new(device, size, target_fmt),
+            trail: None,
+            depth_buffer: None,
+            context: context.clone(),
+            size,
+            surface: Some(surface),
+            surface_fmt,
+            surface_usages: capabilities.usages,
+            present_modes: capabilities.present_modes.clone(),
+            canvas: None,
+            fullscreen: fullscreen::FullscreenMode::Borderless,
+            inset_views: vec![],
+            camera: cam,
+            settings,
+            font_source,
+            font_scale,
+            font_atlas: atlas,
+            scale_factor,
+            toasts: toast::Toasts::new(),
+            animations: animation::Animations::new(),
+            draw_stats: sort::DrawStats::default(),
+            debug_overlay: false,
+            profiler: None,
+            anomalies: None,
+            upload_time: 0.0,
+            static_layers: BTreeMap::new(),
+            asset_manifests: vec![],
+            #[cfg(feature = "egui")]
+            egui: None,
+            geometry_issues: sanitize::GeometryIssues::default(),
+            clock: clock::FrameClock::new(),
+        };
+
+        // text and flat quads drawn one after the other keep their pipeline bound
+        let pipeline_2d = renderer.quad_renderer.pipeline_2d().clone();
+        renderer.font_renderer.set_pipeline_2d(device, pipeline_2d);
+        renderer.set_clear_mode(renderer.settings.clear);
+        renderer.set_world_bounds(renderer.settings.world_bounds);
+        renderer.set_color_space(renderer.settings.color_space);
+        renderer.set_depth_buffer(renderer.settings.depth_buffer);
+        for (layer, parallax) in renderer.settings.layer_parallax.clone() {
+            renderer.set_layer_parallax(layer, Some(parallax));
+        }
+        // widgets, toasts and the replay overlay are laid out in window pixels
+        for layer in [ui::UI_LAYER, toast::TOAST_LAYER, replay::OVERLAY_LAYER] {
+            renderer
+                .camera
+                .set_layer_parallax(device, queue, layer, Some(camera::Parallax::SCREEN));
+        }
+        renderer.settings.present_mode = renderer.supported_present_mode(renderer.settings.present_mode);
+        renderer.configure_surface();
+
+        renderer
+    }
+
+    // Everything `from_state` needs to set the renderer up again after a hot
+    // reload, see `RendererState`. Reads the textures of the static layers and
+    // tilemaps back from the gpu.
+    pub fn save_state(&self) -> RendererState {
+        let (device, queue) = (&self.context.device, &self.context.queue);
+        let mut quads = self.quad_renderer.static_snapshots();
+        let (mut sprites, sprite_textures) = self.sprite_renderer.static_snapshots(device, queue);
+        let mut glyphs = self.font_renderer.static_snapshots();
+        let static_layers = self
+            .static_layers
+            .keys()
+            .map(|layer| {
+                let kept = state::StaticLayerState {
+                    quads: quads.remove(layer).unwrap_or_default(),
+                    sprites: sprites.remove(layer).unwrap_or_default(),
+                    glyphs: glyphs.remove(layer).unwrap_or_default(),
+                };
+                (*layer, kept)
+            })
+            .collect();
+        RendererState {
+            settings: self.settings.clone(),
+            camera: self.camera.state(),
+            font: state::FontDesc {
+                source: self.font_source.clone(),
+                scale: self.font_scale,
+            },
+            assets: self.asset_manifests.clone(),
+            static_layers,
+            sprite_textures,
+            tilemaps: self.tilemaps.iter().map(|tilemap| tilemap.state(device, queue)).collect(),
+            materials: self.quad_renderer.materials(),
+            inset_views: self.inset_views.iter().map(inset::InsetView::state).collect(),
+            frame: None,
+        }
+    }
+
+    // `save_state` with the quads and text of the frame, for anomaly captures
+    fn capture_frame(&self) -> RendererState {
+        RendererState {
+            frame: Some(state::FrameState {
+                quads: self.quad_renderer.snapshot(),
+                glyphs: self.font_renderer.snapshot(),
+            }),
+            ..self.save_state()
+        }
+    }
+
+    // Starts a frame: ticks the clock and drops everything pushed for the last
+    // one. The scene can only be pushed through the returned `Frame`, see
+    // frame.rs for the whole lifecycle.
+    pub fn begin_frame(&mut self) -> Frame<'_> {
+        self.clock.tick();
+        // the batches still hold the frame the tick ended
+        if let Some(mut anomalies) = self.anomalies.take() {
+            if self.clock.frame() > 1 {
+                anomalies.check(self.clock.frame() - 1, self.frame_stats(), self.upload_time, || self.capture_frame());
+            }
+            self.anomalies = Some(anomalies);
+        }
+        self.quad_renderer.clear();
+        self.sprite_renderer.clear();
+        self.font_renderer.clear();
+        let cull = self.settings.cpu_culling.then(|| self.cull_rect());
+        self.quad_renderer.set_cull_rect(cull.clone());
+        self.sprite_renderer.set_cull_rect(cull.clone());
+        self.font_renderer.set_cull_rect(cull);
+        Frame::new(self)
+    }
+
+    // Called by `Frame::submit` before `render`, so no uploads happen while a
+    // pass is being encoded. Each batch uploads on its own, an empty one only
+    // resets its buffer lengths, so a frame of just quads or just text draws
+    // the same as a mixed one.
+    pub(crate) fn prepare_frame(&mut self) {
+        let start = Instant::now();
+        // pushing to a static layer replaces what every batch kept of it
+        let mut rebuilt = self.quad_renderer.rebuilt_static_layers();
+        rebuilt.extend(self.sprite_renderer.rebuilt_static_layers());
+        rebuilt.extend(self.font_renderer.rebuilt_static_layers());
+        for layer in rebuilt {
+            self.rebuild_static_layer(layer);
+        }
+        self.quad_renderer.prepare(&self.context.device, &self.context.queue);
+        self.sprite_renderer.prepare(&self.context.device, &self.context.queue);
+        for tilemap in &mut self.tilemaps {
+            tilemap.prepare(&self.context.device, &self.context.queue);
+        }
+        for tilemap in &mut self.streaming_tilemaps {
+            tilemap.update(&self.context.device, &self.context.queue, &self.camera);
+        }
+        self.font_renderer.prepare(&self.context.device, &self.context.queue);
+        self.upload_time = start.elapsed().as_secs_f32();
+    }
+
+    pub(crate) fn render(&mut self) {
+        let Some(surface) = &self.surface else {
+            return;
+        };
+        let surface_texture = match surface.get_current_texture() {
+            Ok(texture) => texture,
+            // the window changed under the surface, e.g. while suspended, the
+            // frame is dropped and the next one drawn into the new one
+            Err(err @ (wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)) => {
+                crash::note(format!("surface {err}, configured again"));
+                self.configure_surface();
+                self.window.request_redraw();
+                return;
+            }
+            Err(wgpu::SurfaceError::Timeout) => {
+                self.window.request_redraw();
+                return;
+            }
+            // out of memory or a backend error, the frame is skipped and the
+            // next redraw tries again rather than taking the app down
+            Err(err) => {
+                log::error!("getting the surface texture: {err}, frame skipped");
+                crash::note(format!("surface {err}, frame skipped"));
+                return;
+            }
+        };
+        let texture_view = surface_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor {
+                format: Some(self.view_fmt()),
+                ..Default::default()
+            });
+
+        let target_view = self.canvas.as_ref().map_or(&texture_view, |canvas| canvas.view());
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.begin_frame(&self.context.device);
+        }
+        let profiler = self.profiler.as_ref();
+        let pass_writes = |pass| profiler.and_then(|profiler| profiler.pass_writes(pass));
+        let mark = |renderpass: &mut wgpu::RenderPass, part| {
+            if let Some(profiler) = profiler {
+                profiler.mark(renderpass, part);
+            }
+        };
+
+        let mut encoder = self.context.device.create_command_encoder(&Default::default());
+        for particles in &mut self.gpu_particles {
+            particles.update(&mut encoder, &self.context.queue, self.clock.dt());
+        }
+
+        let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target_view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: self.settings.clear.load_op(),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: self.depth_buffer.as_ref().map(depth::DepthBuffer::attachment),
+            timestamp_writes: pass_writes(profiler::Pass::Main),
+            occlusion_query_set: None,
+        });
+
+        if let (ClearMode::Trail { color, half_life }, Some(fader)) = (self.settings.clear, &self.trail) {
+            let amount = trail::TrailFader::amount(half_life, self.clock.dt());
+            fader.fade(&self.context.queue, &mut renderpass, color, amount);
+        }
+
+        viewport::apply(&mut renderpass, &self.camera, self.size);
+
+        let settings = &self.settings;
+        let sorted = |layer| settings.compositing(layer) == Compositing::Sorted;
+        let blended = |layer| settings.compositing(layer) == Compositing::WeightedBlended;
+
+        let mut stats = sort::DrawStats::default();
+        // layers above the highest blended one are drawn after the oit
+        // composite, in a pass of their own, so it doesn't cover them
+        let top_blended = [
+            self.quad_renderer.layers(blended),
+            self.sprite_renderer.layers(blended),
+            self.font_renderer.layers(blended),
+        ]
+        .iter()
+        .filter_map(|layers| layers.last().copied())
+        .max();
+        let (batches, above): (Vec<_>, Vec<_>) = self
+            .draw_order(sorted)
+            .into_iter()
+            .partition(|batch| top_blended.is_none_or(|top| batch.layer <= top));
+        // the profiler's parts are only timed when each is drawn in one stretch
+        // of the main pass, a mark starts a part and ends the one before
+        let timed_parts = above.is_empty() && batches.is_sorted_by_key(|batch| profiler::part_index(batch.part));
+        let mut marked = 0;
+        let mut bound = sort::BoundPipeline::default();
+        for &batch in &batches {
+            let part = profiler::part_index(batch.part);
+            while timed_parts && marked <= part {
+                mark(&mut renderpass, marked);
+                marked += 1;
+            }
+            stats += self.draw_batch(&mut renderpass, &self.camera, batch, &mut bound);
+        }
+        while timed_parts && marked <= profiler::PARTS {
+            mark(&mut renderpass, marked);
+            marked += 1;
+        }
+
+        drop(renderpass);
+
+        let has_blended = top_blended.is_some();
+        if has_blended {
+            let mut oit_pass = self.oit.begin_pass_timed(
+                &mut encoder,
+                settings.target_ops(state::RenderTarget::OitAccum),
+                settings.target_ops(state::RenderTarget::OitReveal),
+                pass_writes(profiler::Pass::Oit),
+            );
+            viewport::apply(&mut oit_pass, &self.camera, self.size);
+            // in any order, text right after the quads shares their pipeline
+            let mut bound = sort::BoundPipeline::default();
+            stats += self.quad_renderer.render_oit_with(&mut oit_pass, &self.camera, blended, &mut bound);
+            stats += self.font_renderer.render_oit_with(&mut oit_pass, &self.camera, blended, &mut bound);
+            stats += self.sprite_renderer.render_oit(&mut oit_pass, &self.camera, blended);
+            drop(oit_pass);
+
+            let mut composite_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target_view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: pass_writes(profiler::Pass::Composite),
+                occlusion_query_set: None,
+            });
+            self.oit.composite(&mut composite_pass);
+        }
+        if !above.is_empty() {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Over Blended"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target_view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                // cleared again, these layers are drawn over everything below
+                depth_stencil_attachment: self.depth_buffer.as_ref().map(depth::DepthBuffer::attachment),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            viewport::apply(&mut pass, &self.camera, self.size);
+            let mut bound = sort::BoundPipeline::default();
+            for &batch in &above {
+                stats += self.draw_batch(&mut pass, &self.camera, batch, &mut bound);
+            }
+        }
+        if self.inset_views.iter().any(|view| view.visible) {
+            stats += self.render_inset_views(&mut encoder, target_view);
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_frame(&mut encoder, has_blended, timed_parts);
+        }
+
+        if let Some(canvas) = &self.canvas {
+            canvas.copy_to(&mut encoder, &surface_texture.texture);
+        }
+        #[allow(unused_mut)]
+        let mut commands = vec![];
+        #[cfg(feature = "egui")]
+        if let Some(egui) = &mut self.egui {
+            let size = [self.size.width, self.size.height];
+            commands = egui.render(&self.context.device, &self.context.queue, &mut encoder, &texture_view, size);
+        }
+
+        self.draw_stats = stats;
+        self.geometry_issues =
+            self.quad_renderer.issues() + self.sprite_renderer.issues() + self.font_renderer.issues();
+        commands.push(encoder.finish());
+        self.context.queue.submit(commands);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.after_submit();
+        }
+        self.window.pre_present_notify();
+        surface_texture.present();
+        if crash::is_installed() {
+            self.record_crash_report();
+        }
+    }
+
+    // Draws the world layers of the frame again into each visible inset view,
+    // in a pass of their own after the oit composite. Blended layers and gpu
+    // particles' simulation aren't repeated, the particles are drawn as they are.
+    fn render_inset_views(&self, encoder: &mut wgpu::CommandEncoder, target_view: &wgpu::TextureView) -> sort::DrawStats {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Inset Views"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target_view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            // the pipelines are built for it, cleared again since the main
+            // pass is done with it
+            depth_stencil_attachment: self.depth_buffer.as_ref().map(depth::DepthBuffer::attachment),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        let settings = &self.settings;
+        // layers with a `Parallax` belong to the main view
+        let world = |layer| settings.compositing(layer) == Compositing::Sorted && self.camera.layer_view(layer).is_none();
+        let mut stats = sort::DrawStats::default();
+        let batches = self.draw_order(world);
+        let mut bound = sort::BoundPipeline::default();
+        for view in self.inset_views.iter().filter(|view| view.visible) {
+            let Some([x, y, w, h]) = view.scissor(self.size) else {
+                continue;
+            };
+            let rect = view.rect();
+            let size = view.camera().window_size();
+            pass.set_viewport(rect.x.round(), rect.y.round(), size.width as f32, size.height as f32, 0.0, 1.0);
+            pass.set_scissor_rect(x, y, w, h);
+            for &batch in &batches {
+                stats += self.draw_batch(&mut pass, view.camera(), batch, &mut bound);
+            }
+        }
+        stats
+    }
+
+    // The batches of every renderer whose layer passes `draw_layer`, in the
+    // order they're drawn, see `sort::DrawBatch`. Tilemaps and gpu particles
+    // have no blended path and are always drawn.
+    fn draw_order(&self, draw_layer: impl Fn(u16) -> bool) -> Vec<sort::DrawBatch> {
+        use sort::{DrawBatch, Part};
+        let mut batches = vec![];
+        for (i, tilemap) in self.tilemaps.iter().enumerate() {
+            batches.push(DrawBatch { layer: tilemap.layer(), part: Part::Tilemap(i) });
+        }
+        for (i, tilemap) in self.streaming_tilemaps.iter().enumerate() {
+            batches.push(DrawBatch { layer: tilemap.layer(), part: Part::StreamingTilemap(i) });
+        }
+        for (i, particles) in self.gpu_particles.iter().enumerate() {
+            batches.push(DrawBatch { layer: particles.layer(), part: Part::GpuParticles(i) });
+        }
+        let parts = [
+            (Part::Quads, self.quad_renderer.layers(&draw_layer)),
+            (Part::Sprites, self.sprite_renderer.layers(&draw_layer)),
+            (Part::Text, self.font_renderer.layers(&draw_layer)),
+        ];
+        for (part, layers) in parts {
+            batches.extend(layers.into_iter().map(|layer| DrawBatch { layer, part }));
+        }
+        sort::merge_batches(batches)
+    }
+
+    // Draws the runs of one batch of `draw_order`. `bound` is the pipeline the
+    // batch before left set, quads and text share theirs.
+    fn draw_batch(
+        &self,
+        pass: &mut wgpu::RenderPass,
+        cam: &Camera,
+        batch: sort::DrawBatch,
+        bound: &mut sort::BoundPipeline,
+    ) -> sort::DrawStats {
+        use sort::Part;
+        let layer = |layer| layer == batch.layer;
+        let mut stats = match batch.part {
+            Part::Tilemap(i) => self.tilemaps[i].render(pass, cam, &self.sprite_renderer),
+            Part::StreamingTilemap(i) => self.streaming_tilemaps[i].render(pass, cam, &self.sprite_renderer),
+            Part::Quads => self.quad_renderer.render_with(pass, cam, layer, bound),
+            Part::Sprites => self.sprite_renderer.render(pass, cam, layer),
+            Part::GpuParticles(i) => self.gpu_particles[i].render(pass, cam),
+            Part::Text => self.font_renderer.render_with(pass, cam, layer, bound),
+        };
+        // the rest set pipelines of their own
+        if !matches!(batch.part, Part::Quads | Part::Text) {
+            bound.reset();
+        }
+        stats.batches = (stats.draw_calls > 0) as u32;
+        stats
+    }
+
+    fn record_crash_report(&self) {
+        let report = crash::WindowReport {
+            device: self.context.device.clone(),
+            queue: self.context.queue.clone(),
+            adapter: self.context.adapter.get_info(),
+            surface: self.surface_config(),
+            frame: self.clock.frame(),
+            stats: self.frame_stats(),
+            issues: self.geometry_issues,
+            canvas: self.canvas.as_ref().map(|canvas| canvas.texture().clone()),
+        };
+        crash::record(self.window.id(), report);
+    }
+
+    // A 0x0 size, which minimized windows get, is ignored and everything keeps
+    // the last size until the window is restored.
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width == 0 || new_size.height == 0 {
+            return;
+        }
+        self.size = new_size;
+        // for loops that don't forward `WindowEvent::ScaleFactorChanged`
+        self.set_scale_factor(self.window.scale_factor());
+        self.camera.resize(new_size, &self.context.queue);
+        self.oit.resize(&self.context.device, new_size);
+        if self.depth_buffer.is_some() {
+            self.depth_buffer = Some(depth::DepthBuffer::new(&self.context.device, new_size, 1));
+        }
+        if let Some(canvas) = &self.canvas {
+            self.canvas = Some(canvas.resized(&self.context.device, &self.context.queue, new_size));
+        }
+        // moving the window to another monitor can change what the surface supports
+        let Some(surface) = &self.surface else {
+            return;
+        };
+        let capabilities = surface.get_capabilities(&self.context.adapter);
+        self.present_modes = capabilities.present_modes.clone();
+        self.settings.present_mode = self.supported_present_mode(self.settings.present_mode);
+        let format = preferred_format(&self.settings, &capabilities);
+        if format != self.surface_fmt {
+            self.set_surface_format(format);
+        }
+        self.configure_surface();
+    }
+
+    // Drops the window's surface, call on `ApplicationHandler::suspended`.
+    // Android destroys the native window behind it on suspend, and wayland
+    // compositors can, so it has to go before that happens. The device,
+    // pipelines, atlases and everything kept across frames stay, frames
+    // submitted meanwhile are dropped. `run` takes care of it.
+    pub fn suspend(&mut self) {
+        if self.surface.take().is_some() {
+            crash::note("suspended");
+        }
+    }
+
+    // Creates the surface again after `suspend`, for the window as it is
+    // now, which can have a new size or format by then.
+    pub fn resume(&mut self) {
+        if self.surface.is_some() {
+            return;
+        }
+        self.surface = Some(self.context.instance.create_surface(self.window.clone()).unwrap());
+        crash::note("resumed");
+        match self.window.inner_size() {
+            // still minimized, configured at the last size until it's restored
+            size if size.width == 0 || size.height == 0 => self.configure_surface(),
+            size => self.resize(size),
+        }
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    // Switches the window between windowed, borderless and exclusive
+    // fullscreen. The surface, camera and render targets follow the new size
+    // right away on platforms that resize the window before this returns, on
+    // the others once the `WindowEvent::Resized` that follows reaches `resize`,
+    // which `run` takes care of. Returns the mode the window reports after,
+    // see `FullscreenMode::Exclusive` for where it falls back.
+    pub fn set_fullscreen(&mut self, mode: fullscreen::FullscreenMode) -> fullscreen::FullscreenMode {
+        if mode != fullscreen::FullscreenMode::Windowed {
+            self.fullscreen = mode;
+        }
+        self.window.set_fullscreen(fullscreen::fullscreen(&self.window, mode));
+        let size = self.window.inner_size();
+        if size != self.size && size.width > 0 && size.height > 0 {
+            self.resize(size);
+        }
+        crash::note(format!("fullscreen {mode:?}"));
+        self.fullscreen()
+    }
+
+    // back into a window, or into the fullscreen mode set last, borderless
+    // at first
+    pub fn toggle_fullscreen(&mut self) -> fullscreen::FullscreenMode {
+        match self.fullscreen() {
+            fullscreen::FullscreenMode::Windowed => self.set_fullscreen(self.fullscreen),
+            _ => self.set_fullscreen(fullscreen::FullscreenMode::Windowed),
+        }
+    }
+
+    pub fn fullscreen(&self) -> fullscreen::FullscreenMode {
+        fullscreen::FullscreenMode::of(&self.window)
+    }
+
+    // Switches vsync on or off from one frame to the next, e.g. from a
+    // settings menu. Only the surface is configured again, the pipelines and
+    // buffers stay as they are; a frame can't be in flight on the cpu side
+    // since a `Frame` borrows the renderer until it's presented, and frames
+    // queued on the gpu finish with the old mode. A mode the surface doesn't
+    // support falls back to `Fifo`, which every surface does. Returns the
+    // mode it switched to.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        let mode = self.supported_present_mode(mode);
+        if mode != self.settings.present_mode {
+            self.settings.present_mode = mode;
+            self.configure_surface();
+        }
+        mode
+    }
+
+    // `AutoVsync` or `AutoNoVsync`, which pick the best mode of either kind the
+    // surface supports
+    pub fn set_vsync(&mut self, vsync: bool) -> wgpu::PresentMode {
+        self.set_present_mode(if vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        })
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.settings.present_mode
+    }
+
+    // what the surface supports besides the `Auto` modes
+    pub fn supported_present_modes(&self) -> &[wgpu::PresentMode] {
+        &self.present_modes
+    }
+
+    fn supported_present_mode(&self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        match mode {
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => mode,
+            _ if self.present_modes.contains(&mode) => mode,
+            _ => wgpu::PresentMode::Fifo,
+        }
+    }
+
+    // Switches every pipeline over to drawing into a surface of `format`,
+    // through the `PipelineCache` of each renderer, so switching back to a
+    // format used before doesn't build anything. The canvas of
+    // `ClearMode::Preserve` starts over.
+    fn set_surface_format(&mut self, format: wgpu::TextureFormat) {
+        let device = &self.context.device;
+        self.surface_fmt = format;
+        let target_fmt = self.view_fmt();
+        self.quad_renderer.set_target_format(device, target_fmt);
+        self.sprite_renderer.set_target_format(device, target_fmt);
+        self.font_renderer.set_target_format(device, target_fmt);
+        for particles in &mut self.gpu_particles {
+            particles.set_target_format(device, target_fmt);
+        }
+        self.oit.set_target_format(device, target_fmt);
+        #[cfg(feature = "egui")]
+        if let Some(egui) = &mut self.egui {
+            egui.set_target_format(device, &self.context.queue, target_fmt);
+        }
+        if self.canvas.is_some() {
+            self.canvas = Some(canvas::Canvas::new(device, self.size, format, target_fmt));
+        }
+        if self.trail.is_some() {
+            self.trail = Some(trail::TrailFader::new(device, target_fmt, self.depth_format()));
+        }
+    }
+
+    // the format of the window's surface, see `is_wide_gamut`
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.surface_fmt
+    }
+
+    // Whether the colors drawn end up srgb encoded or linear on the surface,
+    // which `RendererSettings::surface_encoding` asks for but the surface's
+    // formats decide.
+    pub fn surface_encoding(&self) -> color_space::SurfaceEncoding {
+        color_space::SurfaceEncoding::of(self.view_fmt())
+    }
+
+    // the format the surface is drawn through
+    fn view_fmt(&self) -> wgpu::TextureFormat {
+        self.settings.surface_encoding.view_format(self.surface_fmt)
+    }
+
+    // Lays out and draws `text` with the default font, `x`, `y` being the top
+    // left corner of the block or the start of its first baseline, see
+    // `TextLayout::origin`. The highlight of `style` goes on the quad batch,
+    // so it's drawn under all text. The screen size and billboarding of
+    // `style` follow the camera as of this call.
+    pub fn push_text(
+        &mut self,
+        x: f32,
+        y: f32,
+        size: f32,
+        text: &str,
+        layout: &font::TextLayout,
+        style: &font::TextStyle,
+    ) -> Result<(), font::MissingGlyphs> {
+        let (size, style) = match style.screen_size {
+            Some((min, max)) => {
+                let zoom = self.camera.effective_zoom();
+                let clamped = (size * zoom).clamp(min, max) / zoom;
+                (clamped, style.scaled(clamped / size))
+            }
+            None => (size, *style),
+        };
+        let glyphs = layout.layout(text, size, &self.font_atlas);
+        let bounds = |atlas: &MonoGlyphAtlas| {
+            let (w, h) = layout.measure(text, size, atlas);
+            rect::Rect::new(0.0, -layout.origin_offset(size, atlas), w, h)
+        };
+        self.push_glyph_block(x, y, size, &glyphs, bounds, &style)
+    }
+
+    // `push_text` of a `TextBuffer`, which is only laid out again when it
+    // changed. The screen size of `style` is left out, it would lay the text
+    // out at another size every time the zoom changes.
+    pub fn push_text_buffer(
+        &mut self,
+        x: f32,
+        y: f32,
+        buffer: &mut font::TextBuffer,
+        style: &font::TextStyle,
+    ) -> Result<(), font::MissingGlyphs> {
+        let size = buffer.size();
+        let (w, h) = buffer.measure(&self.font_atlas);
+        let bounds = rect::Rect::new(0.0, -buffer.layout().origin_offset(size, &self.font_atlas), w, h);
+        self.push_glyph_block(x, y, size, buffer.glyphs(&self.font_atlas), |_| bounds, style)
+    }
+
+    // `text` with the default font on `grid`, starting in the cell at `col`,
+    // `row`. The highlight covers the cells from the first row and column to
+    // the last ones used, the screen size of `style` is left out, it would
+    // move the cells.
+    pub fn push_text_grid(
+        &mut self,
+        grid: &font::TextGrid,
+        col: usize,
+        row: usize,
+        text: &str,
+        style: &font::TextStyle,
+    ) -> Result<(), font::MissingGlyphs> {
+        let glyphs = grid.layout(col, row, text, &self.font_atlas);
+        let bounds = |atlas: &MonoGlyphAtlas| {
+            let cells = grid.cells(col, row, text);
+            let last_col = cells.iter().map(|cell| cell.0 + 1).max().unwrap_or(col);
+            let last_row = cells.iter().map(|cell| cell.1 + 1).max().unwrap_or(row);
+            let (w, h) = grid.cell_size(atlas);
+            rect::Rect::new(
+                col as f32 * w,
+                row as f32 * h,
+                last_col.saturating_sub(col) as f32 * w,
+                last_row.saturating_sub(row) as f32 * h,
+            )
+        };
+        self.push_glyph_block(grid.x, grid.y, grid.size, &glyphs, bounds, style)
+    }
+
+    // see `push_text`, `bounds` measures the block for the highlight, relative
+    // to `x`, `y`
+    fn push_glyph_block(
+        &mut self,
+        x: f32,
+        y: f32,
+        size: f32,
+        placed: &[font::layout::PositionedGlyph],
+        bounds: impl FnOnce(&MonoGlyphAtlas) -> rect::Rect,
+        style: &font::TextStyle,
+    ) -> Result<(), font::MissingGlyphs> {
+        let (quads, glyphs) = (self.quad_renderer.pushed(), self.font_renderer.pushed());
+        let turned = style.billboard && self.camera.rotation() != 0.0;
+        // culling what's turned into view afterwards would leave holes
+        let cull = turned.then(|| self.font_renderer.cull_rect().cloned()).flatten();
+        if turned {
+            self.quad_renderer.set_cull_rect(None);
+            self.font_renderer.set_cull_rect(None);
+        }
+        if let Some((color, padding)) = style.highlight {
+            let block = bounds(&self.font_atlas);
+            self.quad_renderer.push(
+                x + block.x - padding,
+                y + block.y - padding,
+                block.w + 2.0 * padding,
+                block.h + 2.0 * padding,
+                color,
+            );
+        }
+        self.font_renderer.set_pixel_grid(&self.camera);
+        let result = self
+            .font_renderer
+            .push_glyphs_styled(sort::SortKey::default(), x, y, size, placed, style, &self.font_atlas);
+        if turned {
+            // undoes the camera's rotation
+            let angle = -self.camera.rotation();
+            self.quad_renderer.turn_since(quads, [x, y], angle);
+            self.font_renderer.turn_since(glyphs, [x, y], angle);
+            self.quad_renderer.set_cull_rect(cull.clone());
+            self.font_renderer.set_cull_rect(cull);
+        }
+        result
+    }
+
+    // `LabelPlacer::place` measuring with the default font
+    pub fn place_labels(&self, labels: &[labels::Label], placer: &labels::LabelPlacer) -> Vec<labels::Placement> {
+        let layout = font::TextLayout::default();
+        placer.place(labels, &self.camera, |label| {
+            layout.measure(&label.text, label.size, &self.font_atlas)
+        })
+    }
+
+    // Shows a notification in a corner of the window for `duration`, e.g.
+    // `renderer.toast("Saved", Duration::from_secs(2))`.
+    pub fn toast(&mut self, text: impl Into<String>, duration: Duration) {
+        let text = text.into();
+        crash::note(format_args!("toast: {text}"));
+        self.toasts.push(text, duration);
+    }
+
+    // pre-rasterizes the glyphs of `text`, see `MonoGlyphAtlas::warm_cache`
+    pub fn warm_font_cache(&mut self, text: &str) -> Vec<char> {
+        self.font_atlas.warm_cache(&self.context.queue, text)
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    // Call on `WindowEvent::ScaleFactorChanged`. Rebuilds the font atlas when
+    // the display's dpi asks for a different rasterization size, see
+    // `font::atlas_scale`.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor == self.scale_factor {
+            return;
+        }
+        self.scale_factor = scale_factor;
+        self.camera.set_scale_factor(scale_factor, &self.context.queue);
+        for view in &mut self.inset_views {
+            view.set_scale_factor(scale_factor);
+        }
+
+        let atlas_scale = font::atlas_scale(self.font_scale, scale_factor);
+        if atlas_scale == font::GlyphSource::font_size(&self.font_atlas) {
+            return;
+        }
+        // the font loaded once already, keep the old atlas if it's gone since
+        let Ok((font, index)) = self.font_source.load_face() else {
+            return;
+        };
+        self.set_font_atlas(&font, index, atlas_scale);
+    }
+
+    // The font of `font_atlas` from now on, a system one by its family with
+    // `FontSource::System`. It's drawn monospaced like the embedded one, every
+    // glyph advancing as far as its 'M'. Keeps the old font when the new one
+    // can't be found or read.
+    pub fn set_font(&mut self, source: FontSource) -> std::io::Result<()> {
+        let (font, index) = source.load_face()?;
+        if ab_glyph::FontRef::try_from_slice_and_index(&font, index).is_err() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "not a font file"));
+        }
+        self.font_source = source;
+        let atlas_scale = font::atlas_scale(self.font_scale, self.scale_factor);
+        self.set_font_atlas(&font, index, atlas_scale);
+        Ok(())
+    }
+
+    fn set_font_atlas(&mut self, font: &[u8], index: u32, atlas_scale: f32) {
+        let (device, queue) = (&self.context.device, &self.context.queue);
+        self.font_atlas = font::atlas::create_monospace_atlas_of_face(device, queue, font, index, atlas_scale);
+        // their glyphs are in the old atlas
+        for dirty in self.static_layers.values_mut() {
+            *dirty = true;
+        }
+        self.set_camera_mode(self.camera.mode());
+    }
+
+    pub fn font_atlas(&self) -> &MonoGlyphAtlas {
+        &self.font_atlas
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    // draws at a fixed logical resolution letterboxed into the window, mouse
+    // positions map to it with `Camera::screen_to_logical`
+    pub fn set_viewport(&mut self, viewport: Option<viewport::Viewport>) {
+        self.camera.set_viewport(viewport, &self.context.queue);
+    }
+
+    pub fn set_camera_position(&mut self, position: [f32; 2]) {
+        self.camera.set_position(position, &self.context.queue);
+    }
+
+    pub fn set_camera_zoom(&mut self, zoom: f32) {
+        self.camera.set_zoom(zoom, &self.context.queue);
+    }
+
+    // see `Camera::set_rotation`
+    pub fn set_camera_rotation(&mut self, rotation: f32) {
+        self.camera.set_rotation(rotation, &self.context.queue);
+    }
+
+    // `PixelPerfect` also switches the font atlas to nearest sampling, `Free`
+    // back to linear
+    pub fn set_camera_mode(&mut self, mode: camera::CameraMode) {
+        self.camera.set_mode(mode, &self.context.queue);
+        let filter = match mode {
+            camera::CameraMode::Free => wgpu::FilterMode::Linear,
+            camera::CameraMode::PixelPerfect => wgpu::FilterMode::Nearest,
+        };
+        self.font_atlas.set_filter(&self.context.device, filter);
+    }
+
+    // `Assets::load` on this renderer's gpu, remembering the manifest for
+    // `save_state`
+    pub fn load_assets(&mut self, manifest: impl AsRef<std::path::Path>) -> image::ImageResult<assets::Assets> {
+        let manifest = manifest.as_ref();
+        let assets = assets::Assets::load(&self.context.device, &self.context.queue, manifest)?;
+        if !self.asset_manifests.iter().any(|loaded| loaded == manifest) {
+            self.asset_manifests.push(manifest.to_owned());
+        }
+        Ok(assets)
+    }
+
+    // `image` on the gpu for `Frame::sprite` and `Frame::nine_slice`, sampled
+    // with `filter`
+    pub fn create_sprite_texture(&self, image: &image::RgbaImage, filter: wgpu::FilterMode) -> sprite::SpriteTexture {
+        self.sprite_renderer
+            .create_texture(&self.context.device, &self.context.queue, image, filter)
+    }
+
+    // A `width` by `height` tilemap of `tile_size` world units per tile, empty
+    // until its tiles are set through `Frame::tilemap`. It's drawn every
+    // frame from then on, under everything pushed into the frame. Tiles are
+    // squares until `TilemapRenderer::set_projection` lays them out otherwise.
+    pub fn add_tilemap(
+        &mut self,
+        tileset: tilemap::Tileset,
+        width: u32,
+        height: u32,
+        tile_size: [f32; 2],
+    ) -> tilemap::TilemapId {
+        let mut tilemap = tilemap::TilemapRenderer::new(
+            &self.context.device,
+            tileset,
+            width,
+            height,
+            tile_size,
+        );
+        tilemap.set_color_space(self.settings.color_space);
+        self.tilemaps.push(tilemap);
+        tilemap::TilemapId(self.tilemaps.len() - 1)
+    }
+
+    // A tilemap without edges whose tile at each pair of tile coordinates is
+    // `generate`'s, see `StreamingTilemap`. Its settings can be changed through
+    // `Frame::streaming_tilemap`.
+    pub fn add_streaming_tilemap(
+        &mut self,
+        tileset: tilemap::Tileset,
+        tile_size: [f32; 2],
+        generate: impl Fn(i32, i32) -> Option<u32> + Send + Sync + 'static,
+    ) -> tilemap::StreamingTilemapId {
+        let mut tilemap = tilemap::StreamingTilemap::new(tileset, tile_size, generate);
+        tilemap.set_color_space(self.settings.color_space);
+        self.streaming_tilemaps.push(tilemap);
+        tilemap::StreamingTilemapId(self.streaming_tilemaps.len() - 1)
+    }
+
+    // see `QuadRenderer::register_material`
+    pub fn register_material(
+        &mut self,
+        label: &str,
+        source: &str,
+        uniform_size: wgpu::BufferAddress,
+    ) -> Result<quad::MaterialId, shader::ShaderError> {
+        self.quad_renderer
+            .register_material(&self.context.device, &self.camera, label, source, uniform_size)
+    }
+
+    pub fn set_material_uniform(&mut self, material: quad::MaterialId, data: &[u8]) {
+        self.quad_renderer
+            .set_material_uniform(&self.context.queue, material, data);
+    }
+
+    // `capacity` particles of `emitter` simulated on the gpu, see
+    // `GpuParticles`. The emitter can be changed through `Frame::gpu_particles`.
+    pub fn add_gpu_particles(&mut self, emitter: particles::Emitter, capacity: u32) -> particles::GpuParticlesId {
+        let mut particles = particles::GpuParticles::new(
+            &self.context.device,
+            &self.camera,
+            self.view_fmt(),
+            self.settings.culling,
+            emitter,
+            capacity,
+        );
+        particles.set_depth_format(&self.context.device, self.depth_format());
+        self.gpu_particles.push(particles);
+        particles::GpuParticlesId(self.gpu_particles.len() - 1)
+    }
+
+    // A view of the world in `rect` of the window, in physical pixels, drawn
+    // over the scene from the next frame on, see `InsetView`. Its camera starts
+    // at the world origin and is moved through `Frame::inset_view`.
+    pub fn add_inset_view(&mut self, rect: rect::Rect) -> inset::InsetViewId {
+        let view = inset::InsetView::new(&self.context.device, &self.context.queue, rect, self.scale_factor);
+        self.inset_views.push(view);
+        inset::InsetViewId(self.inset_views.len() - 1)
+    }
+
+    pub fn inset_view(&self, id: inset::InsetViewId) -> &inset::InsetView {
+        &self.inset_views[id.0]
+    }
+
+    // A point in physical window pixels, e.g. a click, in world units through
+    // the view it's shown in: the topmost visible inset view containing it,
+    // returned with it, or else the main camera.
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (Option<inset::InsetViewId>, (f32, f32)) {
+        for (i, view) in self.inset_views.iter().enumerate().rev() {
+            if let Some(point) = view.screen_to_world(x, y) {
+                return (Some(inset::InsetViewId(i)), point);
+            }
+        }
+        (None, self.camera.screen_to_world(x, y))
+    }
+
+    // state changes recorded by the last `render`, the oit composite not included
+    pub fn draw_stats(&self) -> sort::DrawStats {
+        self.draw_stats
+    }
+
+    // Measures how long the gpu takes for each part of a frame, see
+    // `GpuProfiler`, into `frame_stats`. Returns whether it does, which needs a
+    // device with timestamp queries.
+    pub fn set_gpu_profiling(&mut self, enabled: bool) -> bool {
+        self.profiler = if enabled {
+            profiler::GpuProfiler::new(&self.context.device, &self.context.queue)
+        } else {
+            None
+        };
+        self.profiler.is_some()
+    }
+
+    // the clock, the batches of the frame being pushed, the last frame's draw
+    // calls and, with `set_gpu_profiling`, the gpu time of a recent one
+    pub fn frame_stats(&self) -> profiler::FrameStats {
+        profiler::FrameStats {
+            fps: self.clock.fps(),
+            dt: self.clock.dt(),
+            draws: self.draw_stats,
+            batches: self.batch_stats(),
+            gpu: self.profiler.as_ref().and_then(profiler::GpuProfiler::latest),
+        }
+    }
+
+    // what the quad, sprite and text batches of the frame being pushed hold
+    pub fn batch_stats(&self) -> batch::BatchStats {
+        let mut stats = self.quad_renderer.batch_stats();
+        stats += self.sprite_renderer.batch_stats();
+        stats += self.font_renderer.batch_stats();
+        stats
+    }
+
+    // Draws the fps, the batch sizes, the draw calls, how often the batch
+    // buffers grew and with `set_gpu_profiling` the gpu times into the top
+    // left corner of every frame, over everything but toasts and replays.
+    pub fn debug_overlay(&mut self, enabled: bool) {
+        self.debug_overlay = enabled;
+        let parallax = enabled.then_some(camera::Parallax::SCREEN);
+        self.camera
+            .set_layer_parallax(&self.context.device, &self.context.queue, debug_overlay::DEBUG_LAYER, parallax);
+    }
+
+    // Keeps what's pushed to `layer` across frames for a background or a HUD
+    // that rarely changes: push it in `Frame::static_layer`, which only runs
+    // when the layer is dirty, and frames it doesn't change in neither build
+    // nor upload its quads. Pushing to it outside of that replaces it too,
+    // uploaded only when the quads differ from what was kept. Billboard text
+    // isn't turned in a static layer. Turning it off drops what was kept.
+    pub fn set_static_layer(&mut self, layer: u16, enabled: bool) {
+        let device = &self.context.device;
+        self.quad_renderer.set_static_layer(device, layer, enabled);
+        self.sprite_renderer.set_static_layer(device, layer, enabled);
+        self.font_renderer.set_static_layer(device, layer, enabled);
+        if enabled {
+            self.static_layers.insert(layer, true);
+        } else {
+            self.static_layers.remove(&layer);
+        }
+    }
+
+    // has the next `Frame::static_layer` of `layer` build it again
+    pub fn mark_static_layer_dirty(&mut self, layer: u16) {
+        if let Some(dirty) = self.static_layers.get_mut(&layer) {
+            *dirty = true;
+        }
+    }
+
+    // drops what every batch kept of a static layer for what's pushed to it next
+    pub(crate) fn rebuild_static_layer(&mut self, layer: u16) {
+        self.quad_renderer.rebuild_static_layer(layer);
+        self.sprite_renderer.rebuild_static_layer(layer);
+        self.font_renderer.rebuild_static_layer(layer);
+    }
+
+    // Checks every frame against `budget`, None stops. Frames that take longer
+    // or upload more are logged as warnings and kept, see `take_anomalies`.
+    pub fn set_anomaly_budget(&mut self, budget: Option<anomaly::AnomalyBudget>) {
+        match (&mut self.anomalies, budget) {
+            (Some(anomalies), Some(budget)) => anomalies.budget = budget,
+            (anomalies, budget) => *anomalies = budget.map(anomaly::AnomalyLog::new),
+        }
+    }
+
+    // the last frames over the anomaly budget, oldest first
+    pub fn take_anomalies(&mut self) -> Vec<anomaly::Anomaly> {
+        self.anomalies
+            .as_mut()
+            .map_or(vec![], |anomalies| anomalies.recent.drain(..).collect())
+    }
+
+    // what pushing fixed or dropped in the last presented frame
+    pub fn geometry_issues(&self) -> sanitize::GeometryIssues {
+        self.geometry_issues
+    }
+
+    pub fn set_world_bounds(&mut self, bounds: Option<rect::Rect>) {
+        self.settings.world_bounds = bounds;
+        self.quad_renderer.set_world_bounds(bounds);
+        self.sprite_renderer.set_world_bounds(bounds);
+        self.font_renderer.set_world_bounds(bounds);
+    }
+
+    // whether the surface got the format `RendererSettings::wide_gamut` asks for
+    pub fn is_wide_gamut(&self) -> bool {
+        self.surface_fmt == wgpu::TextureFormat::Rgba16Float
+    }
+
+    // see `QuadRenderer::set_color_space`
+    pub fn set_color_space(&mut self, color_space: color_space::ColorSpace) {
+        self.settings.color_space = color_space;
+        // their colors were converted when they were pushed
+        for dirty in self.static_layers.values_mut() {
+            *dirty = true;
+        }
+        self.quad_renderer.set_color_space(color_space);
+        self.sprite_renderer.set_color_space(color_space);
+        for tilemap in &mut self.tilemaps {
+            tilemap.set_color_space(color_space);
+        }
+        for tilemap in &mut self.streaming_tilemaps {
+            tilemap.set_color_space(color_space);
+        }
+        self.font_renderer.set_color_space(color_space);
+    }
+
+    // Has the pushes of every frame drop quads, sprites and glyphs entirely
+    // outside of what the camera and the visible inset views show, as of
+    // `begin_frame`. Moving the camera later in the frame, or drawing with
+    // other cameras, shows the gaps. Layers with a `Parallax` and static
+    // layers are never culled.
+    pub fn set_cpu_culling(&mut self, enabled: bool) {
+        self.settings.cpu_culling = enabled;
+    }
+
+    // see `set_cpu_culling`
+    fn cull_rect(&self) -> culling::CullRect {
+        let mut rect = self.camera.visible_rect();
+        for inset in self.inset_views.iter().filter(|inset| inset.visible) {
+            let seen = inset.camera().visible_rect();
+            let (x, y) = (rect.x.min(seen.x), rect.y.min(seen.y));
+            rect = rect::Rect::new(x, y, rect.right().max(seen.right()) - x, rect.bottom().max(seen.bottom()) - y);
+        }
+        culling::CullRect {
+            rect,
+            skip_layers: self.camera.parallax_layers(),
+        }
+    }
+
+    // linear rgba every frame is drawn over
+    pub fn set_clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.set_clear_mode(ClearMode::Color([r, g, b, a]));
+    }
+
+    // `Preserve` and `Trail` draw frames into an offscreen canvas copied to
+    // the window, where the surface can't be copied to they fall back to
+    // loading the surface texture, which then holds whichever frame used it
+    // last
+    pub fn set_clear_mode(&mut self, clear: ClearMode) {
+        self.settings.clear = clear;
+        let copyable = self.surface_usages.contains(wgpu::TextureUsages::COPY_DST);
+        let had_canvas = self.canvas.is_some();
+        self.canvas = match clear {
+            clear if clear.keeps_frame() && copyable => self
+                .canvas
+                .take()
+                .or_else(|| Some(canvas::Canvas::new(&self.context.device, self.size, self.surface_fmt, self.view_fmt()))),
+            _ => None,
+        };
+        self.trail = match clear {
+            ClearMode::Trail { .. } => self.trail.take().or_else(|| {
+                Some(trail::TrailFader::new(&self.context.device, self.view_fmt(), self.depth_format()))
+            }),
+            _ => None,
+        };
+        if self.canvas.is_some() != had_canvas {
+            self.configure_surface();
+        }
+    }
+
+    // see `RendererSettings::depth_buffer`
+    pub fn set_depth_buffer(&mut self, enabled: bool) {
+        let device = &self.context.device;
+        self.settings.depth_buffer = enabled;
+        self.depth_buffer = enabled.then(|| depth::DepthBuffer::new(device, self.size, 1));
+        let depth = self.depth_format();
+        self.quad_renderer.set_depth_format(device, depth);
+        self.sprite_renderer.set_depth_format(device, depth);
+        self.font_renderer.set_depth_format(device, depth);
+        for particles in &mut self.gpu_particles {
+            particles.set_depth_format(device, depth);
+        }
+        if self.trail.is_some() {
+            self.trail = Some(trail::TrailFader::new(device, self.view_fmt(), depth));
+        }
+    }
+
+    fn depth_format(&self) -> Option<wgpu::TextureFormat> {
+        self.depth_buffer.as_ref().map(|_| depth::DepthBuffer::FORMAT)
+    }
+
+    pub fn set_layer_compositing(&mut self, layer: u16, compositing: Compositing) {
+        self.settings.layer_compositing.insert(layer, compositing);
+    }
+
+    // How much of the camera's movement and zoom `layer` follows, `None`
+    // for all of it. The layer is drawn with a view of its own, the
+    // hit testing counterpart is `Camera::screen_to_layer`.
+    pub fn set_layer_parallax(&mut self, layer: u16, parallax: Option<camera::Parallax>) {
+        match parallax {
+            Some(parallax) => self.settings.layer_parallax.insert(layer, parallax),
+            None => self.settings.layer_parallax.remove(&layer),
+        };
+        self.camera
+            .set_layer_parallax(&self.context.device, &self.context.queue, layer, parallax);
+    }
+
+    pub fn set_target_ops(&mut self, target: state::RenderTarget, ops: state::TargetOps) {
+        self.settings.target_ops.insert(target, ops);
+    }
+
+    // shared with `with_context` to open more windows on the same gpu
+    pub fn context(&self) -> &GraphicsContext {
+        &self.context
+    }
+
+    // an `Arc` so apps can keep it, to resize it from `App::draw` say
+    pub fn get_window(&self) -> &Arc<winit::window::Window> {
+        &self.window
+    }
+
+    // the egui layer drawn over this window, added on first use, see
+    // `Frame::egui`
+    #[cfg(feature = "egui")]
+    pub fn egui(&mut self) -> &mut egui_layer::EguiLayer {
+        let target_fmt = self.view_fmt();
+        self.egui
+            .get_or_insert_with(|| egui_layer::EguiLayer::new(&self.context.device, &self.window, target_fmt))
+    }
+
+    // Passes a window event to the egui layer, if there's one, and returns
+    // whether it consumed it. `run` calls it for every event.
+    #[cfg(feature = "egui")]
+    pub fn egui_event(&mut self, event: &winit::event::WindowEvent) -> bool {
+        match &mut self.egui {
+            Some(egui) => egui.on_window_event(&self.window, event),
+            None => false,
+        }
+    }
+
+    fn configure_surface(&self) {
+        let surface_cfg = self.surface_config();
+        crash::note(format!(
+            "surface configured: {}x{} {:?} {:?}",
+            surface_cfg.width, surface_cfg.height, surface_cfg.format, surface_cfg.present_mode
+        ));
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.context.device, &surface_cfg);
+        }
+    }
+
+    fn surface_config(&self) -> wgpu::SurfaceConfiguration {
+        let usage = match self.canvas {
+            Some(_) => wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+            None => wgpu::TextureUsages::RENDER_ATTACHMENT,
+        };
+        wgpu::SurfaceConfiguration {
+            usage,
+            format: self.surface_fmt,
+            view_formats: vec![self.view_fmt()],
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            width: self.size.width,
+            height: self.size.height,
+            desired_maximum_frame_latency: 2,
+            present_mode: self.settings.present_mode,
+        }
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        crash::forget(self.window.id());
+    }
+}
+
+// `RendererSettings::wide_gamut`'s format where the surface supports it,
+// otherwise the surface's most preferred one of the asked for encoding, so
+// colors don't depend on which format a platform happens to list first
+fn preferred_format(settings: &RendererSettings, capabilities: &wgpu::SurfaceCapabilities) -> wgpu::TextureFormat {
+    let wide_gamut_fmt = wgpu::TextureFormat::Rgba16Float;
+    if settings.wide_gamut && capabilities.formats.contains(&wide_gamut_fmt) {
+        return wide_gamut_fmt;
+    }
+    capabilities
+        .formats
+        .iter()
+        .copied()
+        .find(|&format| color_space::SurfaceEncoding::of(format) == settings.surface_encoding)
+        .unwrap_or(capabilities.formats[0])
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline_cache::{PipelineCache, PipelineKey};
+use crate::state::{ClearMode, RenderTarget, TargetOps};
+
+// How the primitives of one layer are blended onto the frame.
+//
+// `Sorted` draws in sort key order with regular blending. `WeightedBlended`
+// renders the layer into an accumulation/revealage target pair and composites
+// it over the frame afterwards, so the result doesn't depend on draw order
+// (McGuire & Bavoil, "Weighted Blended Order-Independent Transparency").
+// The composite goes over the layers up to the highest blended one, sorted
+// layers between blended ones included; the layers above it, a HUD say, are
+// drawn after it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compositing {
+    #[default]
+    Sorted,
+    WeightedBlended,
+}
+
+pub(crate) const ACCUM_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+pub(crate) const REVEAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+// color targets for the `fs_oit` entry points of the primitive shaders
+pub(crate) fn targets() -> [Option<wgpu::ColorTargetState>; 2] {
+    [
+        Some(wgpu::ColorTargetState {
+            format: ACCUM_FORMAT,
+            blend: Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            }),
+            write_mask: wgpu::ColorWrites::ALL,
+        }),
+        Some(wgpu::ColorTargetState {
+            format: REVEAL_FORMAT,
+            blend: Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::REPLACE,
+            }),
+            write_mask: wgpu::ColorWrites::ALL,
+        }),
+    ]
+}
+
+pub struct OitCompositor {
+    accum: wgpu::TextureView,
+    reveal: wgpu::TextureView,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipelines: PipelineCache,
+    // what `composite` draws into
+    target: PipelineKey,
+}
+
+impl OitCompositor {
+    pub fn new(
+        device: &wgpu::Device,
+        size: winit::dpi::PhysicalSize<u32>,
+        surface_fmt: wgpu::TextureFormat,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("oit_composite.wgsl"));
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("OIT Composite"),
+            entries: &[texture_entry(0), texture_entry(1)],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let (accum, reveal, bind_group) = Self::create_targets(device, &bind_group_layout, size);
+
+        let mut compositor = Self {
+            accum,
+            reveal,
+            bind_group_layout,
+            bind_group,
+            shader,
+            pipeline_layout,
+            pipelines: PipelineCache::new(),
+            target: PipelineKey::new(surface_fmt, wgpu::BlendState::ALPHA_BLENDING),
+        };
+        compositor.set_target_format(device, surface_fmt);
+        compositor
+    }
+
+    // see `QuadRenderer::set_target_format`
+    pub fn set_target_format(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.target.format = format;
+        let (layout, shader) = (&self.pipeline_layout, &self.shader);
+        self.pipelines
+            .get_or_create(self.target, |key| create_composite_pipeline(device, layout, shader, key));
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) {
+        let (accum, reveal, bind_group) =
+            Self::create_targets(device, &self.bind_group_layout, size);
+        self.accum = accum;
+        self.reveal = reveal;
+        self.bind_group = bind_group;
+    }
+
+    // clears the accumulation target to 0 and the revealage target to 1
+    pub fn begin_pass<'e>(&self, encoder: &'e mut wgpu::CommandEncoder) -> wgpu::RenderPass<'e> {
+        let clear = ClearMode::default();
+        self.begin_pass_with(
+            encoder,
+            RenderTarget::OitAccum.default_ops(clear),
+            RenderTarget::OitReveal.default_ops(clear),
+        )
+    }
+
+    pub fn begin_pass_with<'e>(
+        &self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        accum: TargetOps,
+        reveal: TargetOps,
+    ) -> wgpu::RenderPass<'e> {
+        self.begin_pass_timed(encoder, accum, reveal, None)
+    }
+
+    // `begin_pass_with` writing the pass's begin and end timestamps
+    pub fn begin_pass_timed<'e>(
+        &self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        accum: TargetOps,
+        reveal: TargetOps,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
+    ) -> wgpu::RenderPass<'e> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("OIT Accumulate"),
+            color_attachments: &[
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &self.accum,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: accum.operations(),
+                }),
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &self.reveal,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: reveal.operations(),
+                }),
+            ],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            occlusion_query_set: None,
+        })
+    }
+
+    // blends the accumulated layers over whatever the pass' target already contains
+    pub fn composite(&self, render_pass: &mut wgpu::RenderPass) {
+        let pipeline = self.pipelines.get(self.target);
+        render_pass.set_pipeline(pipeline.expect("set_target_format builds the pipeline"));
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> (wgpu::TextureView, wgpu::TextureView, wgpu::BindGroup) {
+        let target = |label, format| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size.width.max(1),
+                        height: size.height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let accum = target("OIT Accum", ACCUM_FORMAT);
+        let reveal = target("OIT Reveal", REVEAL_FORMAT);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("OIT Composite"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&accum),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&reveal),
+                },
+            ],
+        });
+
+        (accum, reveal, bind_group)
+    }
+}
+
+fn create_composite_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    key: PipelineKey,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("OIT Composite"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(key.color_target())],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
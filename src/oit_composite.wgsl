@@ -0,0 +1,23 @@
+@group(0) @binding(0)
+var t_accum: texture_2d<f32>;
+@group(0) @binding(1)
+var t_reveal: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    // one triangle covering the whole screen
+    let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let p = vec2<i32>(pos.xy);
+    let reveal = textureLoad(t_reveal, p, 0).r;
+    if reveal >= 0.9999 {
+        discard;
+    }
+    let accum = textureLoad(t_accum, p, 0);
+    let average = accum.rgb / max(accum.a, 0.00001);
+    return vec4<f32>(average, 1.0 - reveal);
+}
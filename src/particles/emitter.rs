@@ -0,0 +1,304 @@
+use crate::quad::QuadRenderer;
+use crate::sort::SortKey;
+
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [f32; 3] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        [0, 1, 2].map(|i| self[i].lerp(other[i], t))
+    }
+}
+
+// A value over a particle's life, from `t` 0 when it spawns to 1 when it dies,
+// linear between its keys and constant before the first and after the last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: Lerp> Curve<T> {
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    pub fn linear(from: T, to: T) -> Self {
+        Self {
+            keys: vec![(0.0, from), (1.0, to)],
+        }
+    }
+
+    // adds a key at `t`, replacing one that's there already
+    pub fn with_key(mut self, t: f32, value: T) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        match self.keys.iter().position(|(k, _)| *k >= t) {
+            Some(i) if self.keys[i].0 == t => self.keys[i].1 = value,
+            Some(i) => self.keys.insert(i, (t, value)),
+            None => self.keys.push((t, value)),
+        }
+        self
+    }
+
+    pub fn sample(&self, t: f32) -> T {
+        let next = self.keys.iter().position(|(k, _)| *k >= t);
+        match next {
+            Some(0) => self.keys[0].1,
+            Some(i) => {
+                let (t0, a) = self.keys[i - 1];
+                let (t1, b) = self.keys[i];
+                a.lerp(b, (t - t0) / (t1 - t0))
+            }
+            None => self.keys[self.keys.len() - 1].1,
+        }
+    }
+}
+
+// What an emitter spawns and how its particles move and change over their life.
+// Particles are squares of `size` world units centered on their position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emitter {
+    pub position: [f32; 2],
+    // particles spawned per second
+    pub rate: f32,
+    // seconds, every particle picks one between the two
+    pub lifetime: [f32; 2],
+    // world units per second, picked per axis between min and max
+    pub velocity_min: [f32; 2],
+    pub velocity_max: [f32; 2],
+    // gravity, wind, ...
+    pub acceleration: [f32; 2],
+    pub size: Curve<f32>,
+    pub color: Curve<[f32; 3]>,
+    // spawning pauses while this many are alive
+    pub max_particles: usize,
+    pub key: SortKey,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0],
+            rate: 50.0,
+            lifetime: [1.0, 2.0],
+            velocity_min: [-50.0, -100.0],
+            velocity_max: [50.0, -50.0],
+            acceleration: [0.0, 0.0],
+            size: Curve::linear(8.0, 0.0),
+            color: Curve::constant([1.0; 3]),
+            max_particles: 10_000,
+            key: SortKey::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+    // seconds since it spawned
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    // how far along its life it is, 0 to 1
+    pub fn life(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+// The particles of one `Emitter`, simulated on the cpu and drawn as quads.
+// `update` once a frame, then push them with `Frame::particles`. For more
+// particles than that keeps up with, see `GpuParticles`.
+#[derive(Debug, Clone)]
+pub struct ParticleSystem {
+    pub emitter: Emitter,
+    particles: Vec<Particle>,
+    // fractional particles the rate owes from earlier updates
+    pending: f32,
+    rng: u32,
+}
+
+impl ParticleSystem {
+    pub fn new(emitter: Emitter) -> Self {
+        Self {
+            emitter,
+            particles: vec![],
+            pending: 0.0,
+            rng: 0x9e37_79b9,
+        }
+    }
+
+    // advances every particle by `dt` seconds, drops the dead and spawns new
+    // ones at the emitter's rate
+    pub fn update(&mut self, dt: f32) {
+        let [ax, ay] = self.emitter.acceleration;
+        for p in &mut self.particles {
+            p.age += dt;
+            p.velocity[0] += ax * dt;
+            p.velocity[1] += ay * dt;
+            p.position[0] += p.velocity[0] * dt;
+            p.position[1] += p.velocity[1] * dt;
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+
+        self.pending += self.emitter.rate * dt;
+        let spawn = self.pending.floor();
+        self.pending -= spawn;
+        self.burst(spawn as usize);
+    }
+
+    // spawns `count` particles right away, up to `max_particles`
+    pub fn burst(&mut self, count: usize) {
+        let count = count.min(self.emitter.max_particles.saturating_sub(self.particles.len()));
+        for _ in 0..count {
+            let [vx0, vy0] = self.emitter.velocity_min;
+            let [vx1, vy1] = self.emitter.velocity_max;
+            let [l0, l1] = self.emitter.lifetime;
+            let particle = Particle {
+                position: self.emitter.position,
+                velocity: [vx0.lerp(vx1, self.random()), vy0.lerp(vy1, self.random())],
+                age: 0.0,
+                lifetime: l0.lerp(l1, self.random()),
+            };
+            self.particles.push(particle);
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.pending = 0.0;
+    }
+
+    // pushes every live particle as a quad with the emitter's sort key
+    pub fn push_to(&self, quads: &mut QuadRenderer) {
+        for p in &self.particles {
+            let t = p.life();
+            let size = self.emitter.size.sample(t);
+            if size <= 0.0 {
+                continue;
+            }
+            let [x, y] = p.position;
+            let color = self.emitter.color.sample(t);
+            quads.push_with_key(self.emitter.key, x - size / 2.0, y - size / 2.0, size, size, color);
+        }
+    }
+
+    // xorshift, 0 to 1
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curve_sampling() {
+        let curve = Curve::linear(2.0, 4.0).with_key(0.5, 10.0);
+        // before the first key, on it, between keys, on the last and after it
+        assert_eq!(curve.sample(-1.0), 2.0);
+        assert_eq!(curve.sample(0.0), 2.0);
+        assert_eq!(curve.sample(0.25), 6.0);
+        assert_eq!(curve.sample(0.5), 10.0);
+        assert_eq!(curve.sample(0.75), 7.0);
+        assert_eq!(curve.sample(1.0), 4.0);
+        assert_eq!(curve.sample(2.0), 4.0);
+
+        // a last key before 1
+        let late = Curve::constant(1.0).with_key(0.0, 5.0).with_key(0.5, 5.0).with_key(0.75, 1.0);
+        assert_eq!(late.sample(0.9), 1.0);
+        assert_eq!(Curve::constant([0.5; 3]).sample(0.3), [0.5; 3]);
+    }
+
+    #[test]
+    fn with_key_replaces_a_key_at_the_same_t() {
+        let curve = Curve::linear(0.0, 1.0).with_key(1.0, 8.0).with_key(0.0, 4.0);
+        assert_eq!(curve, Curve::linear(4.0, 8.0));
+        // out of range keys land on the ends
+        assert_eq!(Curve::linear(0.0, 1.0).with_key(3.0, 2.0), Curve::linear(0.0, 2.0));
+        // and new ones go in order
+        let curve = Curve::constant(0.0).with_key(0.75, 3.0).with_key(0.25, 1.0);
+        assert_eq!(curve.keys, [(0.0, 0.0), (0.25, 1.0), (0.75, 3.0)]);
+    }
+
+    #[test]
+    fn burst_stops_at_max_particles() {
+        let mut system = ParticleSystem::new(Emitter {
+            max_particles: 5,
+            lifetime: [10.0, 10.0],
+            ..Emitter::default()
+        });
+        system.burst(3);
+        assert_eq!(system.len(), 3);
+        system.burst(3);
+        assert_eq!(system.len(), 5);
+        system.burst(1);
+        assert_eq!(system.len(), 5);
+        // and so does the rate
+        system.update(1.0);
+        assert_eq!(system.len(), 5);
+    }
+
+    #[test]
+    fn fractional_spawns_carry_over() {
+        let mut system = ParticleSystem::new(Emitter {
+            rate: 4.0,
+            lifetime: [10.0, 10.0],
+            ..Emitter::default()
+        });
+        let mut counts = vec![];
+        for _ in 0..4 {
+            system.update(0.125);
+            counts.push(system.len());
+        }
+        // half a particle per update
+        assert_eq!(counts, [0, 1, 1, 2]);
+
+        system.clear();
+        system.update(0.125);
+        assert!(system.is_empty(), "clear drops what's owed");
+    }
+
+    #[test]
+    fn particles_die_at_their_lifetime() {
+        let mut system = ParticleSystem::new(Emitter {
+            rate: 0.0,
+            lifetime: [1.0, 1.0],
+            velocity_min: [2.0, 0.0],
+            velocity_max: [2.0, 0.0],
+            ..Emitter::default()
+        });
+        system.burst(2);
+        system.update(0.5);
+        assert_eq!(system.particles()[0].position, [1.0, 0.0]);
+        assert_eq!(system.particles()[0].life(), 0.5);
+        system.update(0.5);
+        assert!(system.is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+use bytemuck::Zeroable;
+use wgpu::util::DeviceExt;
+
+use super::emitter::Emitter;
+use crate::camera::Camera;
+use crate::culling::Culling;
+use crate::pipeline_cache::{PipelineCache, PipelineKey};
+use crate::shader::ShaderPreprocessor;
+use crate::sort::DrawStats;
+
+const WORKGROUP_SIZE: u32 = 64;
+// samples of the size and color curves, `SAMPLES` in particle_params.wgsl
+const SAMPLES: usize = 16;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Params {
+    emitter: [f32; 2],
+    acceleration: [f32; 2],
+    velocity_min: [f32; 2],
+    velocity_max: [f32; 2],
+    lifetime: [f32; 2],
+    dt: f32,
+    spawn: u32,
+    seed: u32,
+    count: u32,
+    _padding: [u32; 2],
+    size: [[f32; 4]; SAMPLES / 4],
+    color: [[f32; 4]; SAMPLES],
+}
+
+// zeroed, a particle is dead, so a new buffer spawns from scratch
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuParticle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+    age: f32,
+    lifetime: f32,
+}
+
+// The particles of an `Emitter` simulated by a compute shader and drawn
+// instanced straight out of the buffer it updates, for counts the cpu
+// `ParticleSystem` can't keep up with. There's room for a fixed number of
+// particles, dead ones respawn at the emitter's rate.
+//
+// The curves are sampled `SAMPLES` times over a life and `max_particles`
+// doesn't apply. Only the layer of `emitter.key` does: gpu particles are drawn
+// over the quads and sprites of that layer and under its text. Added with
+// `Renderer::add_gpu_particles`.
+pub struct GpuParticles {
+    pub emitter: Emitter,
+    capacity: u32,
+    update_pipeline: wgpu::ComputePipeline,
+    draw_shader: wgpu::ShaderModule,
+    draw_layout: wgpu::PipelineLayout,
+    culling: Culling,
+    draw_pipelines: PipelineCache,
+    // what `render` draws into
+    target: PipelineKey,
+    params: wgpu::Buffer,
+    particles: wgpu::Buffer,
+    spawned: wgpu::Buffer,
+    update_bind_group: wgpu::BindGroup,
+    draw_bind_group: wgpu::BindGroup,
+    pending: f32,
+    seed: u32,
+}
+
+impl GpuParticles {
+    pub fn new(
+        device: &wgpu::Device,
+        cam: &Camera,
+        surface_fmt: wgpu::TextureFormat,
+        culling: Culling,
+        emitter: Emitter,
+        capacity: u32,
+    ) -> Self {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.add_include("wrs/particle_params.wgsl", include_str!("particle_params.wgsl"));
+        let update_shader = preprocessor
+            .create_module(device, "Particle Update Shader", include_str!("particle_update.wgsl"))
+            .expect("bundled shader includes exist");
+        let draw_shader = preprocessor
+            .create_module(device, "Particle Draw Shader", include_str!("particle_draw.wgsl"))
+            .expect("bundled shader includes exist");
+
+        let capacity = capacity.max(1);
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Params"),
+            size: std::mem::size_of::<Params>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let particles = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particles"),
+            contents: bytemuck::cast_slice(&vec![GpuParticle::zeroed(); capacity as usize]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+        });
+        let spawned = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particles Spawned"),
+            size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let buffer_entry = |binding, visibility, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage = wgpu::BufferBindingType::Storage { read_only: false };
+        let update_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Particle Update"),
+            entries: &[
+                buffer_entry(0, wgpu::ShaderStages::COMPUTE, wgpu::BufferBindingType::Uniform),
+                buffer_entry(1, wgpu::ShaderStages::COMPUTE, storage),
+                buffer_entry(2, wgpu::ShaderStages::COMPUTE, storage),
+            ],
+        });
+        let update_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Particle Update"),
+            layout: &update_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: particles.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: spawned.as_entire_binding(),
+                },
+            ],
+        });
+        let update_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Particle Update Pipeline"),
+            layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&update_layout],
+                push_constant_ranges: &[],
+            })),
+            module: &update_shader,
+            entry_point: Some("update"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        let draw_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Particle Draw"),
+            entries: &[buffer_entry(0, wgpu::ShaderStages::VERTEX, wgpu::BufferBindingType::Uniform)],
+        });
+        let draw_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Particle Draw"),
+            layout: &draw_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            }],
+        });
+        let draw_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[cam.get_bind_group_layout(), &draw_layout],
+            push_constant_ranges: &[],
+        });
+
+        let mut particles = Self {
+            emitter,
+            capacity,
+            update_pipeline,
+            draw_shader,
+            draw_layout: draw_pipeline_layout,
+            culling,
+            draw_pipelines: PipelineCache::new(),
+            target: PipelineKey::new(surface_fmt, wgpu::BlendState::REPLACE),
+            params,
+            particles,
+            spawned,
+            update_bind_group,
+            draw_bind_group,
+            pending: 0.0,
+            seed: 0,
+        };
+        particles.set_target_format(device, surface_fmt);
+        particles
+    }
+
+    // see `QuadRenderer::set_target_format`
+    pub fn set_target_format(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.target.format = format;
+        self.build_pipeline(device);
+    }
+
+    // see `QuadRenderer::set_depth_format`
+    pub fn set_depth_format(&mut self, device: &wgpu::Device, depth: Option<wgpu::TextureFormat>) {
+        self.target.depth = depth;
+        self.build_pipeline(device);
+    }
+
+    fn build_pipeline(&mut self, device: &wgpu::Device) {
+        let (layout, shader, culling) = (&self.draw_layout, &self.draw_shader, self.culling);
+        self.draw_pipelines
+            .get_or_create(self.target, |key| create_draw_pipeline(device, layout, shader, key, culling));
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    // Advances the particles by `dt` seconds in a compute pass of `encoder`,
+    // which has to run before the pass `render` draws into.
+    pub fn update(&mut self, encoder: &mut wgpu::CommandEncoder, queue: &wgpu::Queue, dt: f32) {
+        self.pending += self.emitter.rate * dt;
+        let spawn = self.pending.floor().min(self.capacity as f32);
+        self.pending -= spawn;
+        self.seed = self.seed.wrapping_add(1);
+
+        let samples = |i: usize| i as f32 / (SAMPLES - 1) as f32;
+        let mut size = [[0.0; 4]; SAMPLES / 4];
+        for i in 0..SAMPLES {
+            size[i / 4][i % 4] = self.emitter.size.sample(samples(i));
+        }
+        let color = std::array::from_fn(|i| {
+            let [r, g, b] = self.emitter.color.sample(samples(i));
+            [r, g, b, 1.0]
+        });
+        let params = Params {
+            emitter: self.emitter.position,
+            acceleration: self.emitter.acceleration,
+            velocity_min: self.emitter.velocity_min,
+            velocity_max: self.emitter.velocity_max,
+            lifetime: self.emitter.lifetime,
+            dt,
+            spawn: spawn as u32,
+            seed: self.seed,
+            count: self.capacity,
+            _padding: [0; 2],
+            size,
+            color,
+        };
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+        queue.write_buffer(&self.spawned, 0, bytemuck::bytes_of(&0u32));
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Particle Update"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.update_pipeline);
+        pass.set_bind_group(0, &self.update_bind_group, &[]);
+        pass.dispatch_workgroups(self.capacity.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    pub fn layer(&self) -> u16 {
+        self.emitter.key.layer()
+    }
+
+    // draws every particle slot, dead ones collapse to nothing
+    pub fn render(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera) -> DrawStats {
+        let pipeline = self.draw_pipelines.get(self.target);
+        render_pass.set_pipeline(pipeline.expect("set_target_format builds the pipeline"));
+        render_pass.set_bind_group(0, cam.get_bind_group(), &[]);
+        render_pass.set_bind_group(1, &self.draw_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.particles.slice(..));
+        render_pass.draw(0..6, 0..self.capacity);
+        DrawStats {
+            draw_calls: 1,
+            pipeline_binds: 1,
+            bind_group_binds: 2,
+            quads: self.capacity,
+            ..DrawStats::default()
+        }
+    }
+}
+
+fn create_draw_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    key: PipelineKey,
+    culling: Culling,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Particle Draw Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<GpuParticle>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &[
+                    wgpu::VertexAttribute {
+                        offset: 0,
+                        shader_location: 0,
+                        format: wgpu::VertexFormat::Float32x2,
+                    },
+                    wgpu::VertexAttribute {
+                        offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                        shader_location: 1,
+                        format: wgpu::VertexFormat::Float32,
+                    },
+                    wgpu::VertexAttribute {
+                        offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                        shader_location: 2,
+                        format: wgpu::VertexFormat::Float32,
+                    },
+                ],
+            }],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        primitive: culling.primitive_state(),
+        depth_stencil: key.depth_stencil(),
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(key.color_target())],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
@@ -0,0 +1,7 @@
+mod emitter;
+mod gpu;
+pub use emitter::{Curve, Emitter, Lerp, Particle, ParticleSystem};
+pub use gpu::GpuParticles;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GpuParticlesId(pub usize);
@@ -0,0 +1,60 @@
+#include "wrs/globals.wgsl"
+#include "wrs/particle_params.wgsl"
+
+@group(1) @binding(0)
+var<uniform> params: Params;
+
+struct VertexOut {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+struct Instance {
+    @location(0) position: vec2<f32>,
+    @location(1) age: f32,
+    @location(2) lifetime: f32,
+}
+
+// clockwise from the top left corner, as the triangles 0 1 2 and 0 2 3 of a batch quad
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(-0.5, -0.5),
+    vec2<f32>(0.5, -0.5),
+    vec2<f32>(0.5, 0.5),
+    vec2<f32>(-0.5, -0.5),
+    vec2<f32>(0.5, 0.5),
+    vec2<f32>(-0.5, 0.5),
+);
+
+fn sample_size(t: f32) -> f32 {
+    let f = t * f32(SAMPLES - 1u);
+    let i = min(u32(f), SAMPLES - 2u);
+    let a = params.size[i / 4u][i % 4u];
+    let b = params.size[(i + 1u) / 4u][(i + 1u) % 4u];
+    return mix(a, b, f - f32(i));
+}
+
+fn sample_color(t: f32) -> vec3<f32> {
+    let f = t * f32(SAMPLES - 1u);
+    let i = min(u32(f), SAMPLES - 2u);
+    return mix(params.color[i].rgb, params.color[i + 1u].rgb, f - f32(i));
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32, particle: Instance) -> VertexOut {
+    var out: VertexOut;
+    if particle.age >= particle.lifetime {
+        // dead, every corner on the same point outside of the view
+        out.clip_position = vec4<f32>(-2.0, -2.0, 0.0, 1.0);
+        return out;
+    }
+    let t = particle.age / particle.lifetime;
+    let corner = particle.position + CORNERS[vertex] * sample_size(t);
+    out.clip_position = camera.view_proj * vec4<f32>(corner, 0.0, 1.0);
+    out.color = sample_color(t);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
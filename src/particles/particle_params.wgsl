@@ -0,0 +1,26 @@
+// the uniform `GpuParticles` writes every frame, mirrors `Params` in gpu.rs
+struct Params {
+    emitter: vec2<f32>,
+    acceleration: vec2<f32>,
+    velocity_min: vec2<f32>,
+    velocity_max: vec2<f32>,
+    lifetime: vec2<f32>,
+    dt: f32,
+    // particles that may spawn this frame
+    spawn: u32,
+    seed: u32,
+    count: u32,
+    // `SAMPLES` samples of the size and color curves, evenly spread over a life
+    size: array<vec4<f32>, 4>,
+    color: array<vec4<f32>, 16>,
+}
+
+const SAMPLES: u32 = 16u;
+
+// a particle as it's stored, mirrors `GpuParticle` in gpu.rs
+struct Particle {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+    age: f32,
+    lifetime: f32,
+}
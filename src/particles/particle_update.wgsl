@@ -0,0 +1,45 @@
+#include "wrs/particle_params.wgsl"
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+@group(0) @binding(1)
+var<storage, read_write> particles: array<Particle>;
+// how many dead particles respawned this frame, reset before every update
+@group(0) @binding(2)
+var<storage, read_write> spawned: atomic<u32>;
+
+// pcg hash
+fn hash(x: u32) -> u32 {
+    let state = x * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random(state: ptr<function, u32>) -> f32 {
+    *state = hash(*state);
+    return f32(*state >> 8u) / 16777216.0;
+}
+
+@compute @workgroup_size(64)
+fn update(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.count {
+        return;
+    }
+    var p = particles[i];
+    p.age += params.dt;
+    if p.age < p.lifetime {
+        p.velocity += params.acceleration * params.dt;
+        p.position += p.velocity * params.dt;
+    } else if atomicAdd(&spawned, 1u) < params.spawn {
+        var rng = hash(i ^ hash(params.seed));
+        p.position = params.emitter;
+        p.velocity = mix(params.velocity_min, params.velocity_max, vec2<f32>(random(&rng), random(&rng)));
+        p.lifetime = mix(params.lifetime.x, params.lifetime.y, random(&rng));
+        p.age = 0.0;
+    } else {
+        // stays dead without its age growing forever
+        p.age = p.lifetime;
+    }
+    particles[i] = p;
+}
@@ -0,0 +1,116 @@
+use crate::camera::Camera;
+use crate::culling::Culling;
+use crate::font::FontVertex;
+use crate::font::atlas::{atlas_bind_group, atlas_bind_group_layout, create_atlas_texture};
+use crate::oit;
+use crate::pipeline_cache::{PipelineCache, PipelineKey};
+use crate::shader::ShaderPreprocessor;
+use std::sync::{Arc, Mutex};
+
+// The texture layer of vertices drawn without a texture, their texels are white.
+pub const FLAT: u32 = u32::MAX;
+
+// The pipeline flat quads and bitmap glyphs are both drawn with, font_shader.wgsl's
+// `fs_main` over an atlas page. Flat quads bind `no_texture`, glyphs their
+// page, so a frame of rects and text changes bind groups between the two but
+// not the pipeline, see `BoundPipeline`. Clones share what `build` builds,
+// `QuadRenderer` and `FontRenderer` each hold one and `Renderer` gives them
+// the same, see `FontRenderer::set_pipeline_2d`.
+//
+// Sprites keep their own pipeline: their textures are plain 2d ones, which GL
+// can't view as an array.
+#[derive(Debug, Clone)]
+pub struct Pipeline2d {
+    shader: wgpu::ShaderModule,
+    layout: wgpu::PipelineLayout,
+    culling: Culling,
+    pipelines: Arc<Mutex<PipelineCache>>,
+    oit_pipeline: wgpu::RenderPipeline,
+    // an empty page, flat vertices don't read it
+    no_texture: wgpu::BindGroup,
+}
+
+impl Pipeline2d {
+    pub fn new(device: &wgpu::Device, cam: &Camera, culling: Culling) -> Self {
+        let shader = ShaderPreprocessor::new()
+            .create_module(device, "2D Shader", include_str!("font/font_shader.wgsl"))
+            .expect("bundled shader includes exist");
+        let page_layout = atlas_bind_group_layout(device);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[cam.get_bind_group_layout(), &page_layout],
+            push_constant_ranges: &[],
+        });
+        let oit_pipeline = create_pipeline(device, &layout, &shader, "fs_oit", &oit::targets(), 1, None, culling);
+        let (_, view) = create_atlas_texture(device, (1, 1), 1);
+        let (_, no_texture) = atlas_bind_group(device, &page_layout, &view, wgpu::FilterMode::Nearest);
+        Self {
+            shader,
+            layout,
+            culling,
+            pipelines: Arc::new(Mutex::new(PipelineCache::new())),
+            oit_pipeline,
+            no_texture,
+        }
+    }
+
+    // the pipeline for `target`, if no clone built it yet
+    pub fn build(&self, device: &wgpu::Device, target: PipelineKey) {
+        let (layout, shader, culling) = (&self.layout, &self.shader, self.culling);
+        self.pipelines.lock().unwrap().get_or_create(target, |key| {
+            create_pipeline(device, layout, shader, "fs_main", &[Some(key.color_target())], key.samples, key.depth_stencil(), culling)
+        });
+    }
+
+    pub fn get(&self, target: PipelineKey) -> wgpu::RenderPipeline {
+        let pipelines = self.pipelines.lock().unwrap();
+        pipelines.get(target).expect("pipelines are built for every target").clone()
+    }
+
+    // into an `OitCompositor` pass
+    pub fn oit(&self) -> &wgpu::RenderPipeline {
+        &self.oit_pipeline
+    }
+
+    pub fn no_texture(&self) -> &wgpu::BindGroup {
+        &self.no_texture
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+    targets: &[Option<wgpu::ColorTargetState>],
+    samples: u32,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    culling: Culling,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[FontVertex::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        primitive: culling.primitive_state(),
+        depth_stencil,
+        multisample: wgpu::MultisampleState {
+            count: samples,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(entry_point),
+            targets,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
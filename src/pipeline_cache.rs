@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+// What a render pipeline draws into, the part of it that changes with the
+// target rather than the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub format: wgpu::TextureFormat,
+    pub blend: wgpu::BlendState,
+    pub samples: u32,
+    // the format of the pass's depth attachment, if it has one
+    pub depth: Option<wgpu::TextureFormat>,
+}
+
+impl PipelineKey {
+    pub fn new(format: wgpu::TextureFormat, blend: wgpu::BlendState) -> Self {
+        Self {
+            format,
+            blend,
+            samples: 1,
+            depth: None,
+        }
+    }
+
+    pub fn with_samples(self, samples: u32) -> Self {
+        Self { samples, ..self }
+    }
+
+    pub fn with_blend(self, blend: wgpu::BlendState) -> Self {
+        Self { blend, ..self }
+    }
+
+    pub fn with_depth(self, depth: Option<wgpu::TextureFormat>) -> Self {
+        Self { depth, ..self }
+    }
+
+    pub fn color_target(&self) -> wgpu::ColorTargetState {
+        wgpu::ColorTargetState {
+            format: self.format,
+            blend: Some(self.blend),
+            write_mask: wgpu::ColorWrites::ALL,
+        }
+    }
+
+    // Nearer fragments win, equally near ones are drawn in submission order,
+    // so without a depth set everything still draws like without the buffer.
+    pub fn depth_stencil(&self) -> Option<wgpu::DepthStencilState> {
+        self.depth.map(|format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        })
+    }
+}
+
+// The pipelines of one shader entry point, built the first time a target
+// needs them and kept for when it comes back, so a surface that changes its
+// format (another backend, a window moved to an hdr monitor) or an export
+// into a different one doesn't rebuild what was built before.
+//
+// Renderers build what they're about to draw with in a `&mut self` call,
+// `set_target_format` or `prepare_multisampled`, and look it up with `get`
+// while the pass borrows them.
+#[derive(Debug, Default)]
+pub struct PipelineCache {
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+}
+
+impl PipelineCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_or_create(
+        &mut self,
+        key: PipelineKey,
+        create: impl FnOnce(PipelineKey) -> wgpu::RenderPipeline,
+    ) -> &wgpu::RenderPipeline {
+        self.pipelines.entry(key).or_insert_with(|| create(key))
+    }
+
+    pub fn get(&self, key: PipelineKey) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(&key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+}
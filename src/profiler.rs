@@ -0,0 +1,238 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::batch::BatchStats;
+use crate::sort::{DrawStats, Part};
+
+// The timestamps of a frame by their index in the query set: the main pass's
+// begin and end, then marks written inside of it before the tilemaps and after
+// each of its parts, then the begin and end of the oit and composite passes.
+const MAIN_BEGIN: u32 = 0;
+const MAIN_END: u32 = 1;
+const FIRST_MARK: u32 = 2;
+// tilemaps, quads, sprites, particles and text
+pub(crate) const PARTS: u32 = 5;
+const OIT_BEGIN: u32 = 8;
+const COMPOSITE_END: u32 = 11;
+const QUERIES: u32 = 12;
+// resolved into their own 256 byte aligned block of the buffers, they aren't
+// written in frames without blended layers
+const BLENDED_OFFSET: wgpu::BufferAddress = wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT;
+const BUFFER_SIZE: wgpu::BufferAddress = 2 * wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT;
+// frames whose timestamps can be on their way back at once
+const SLOTS: usize = 3;
+
+// Gpu milliseconds of the parts of a frame. The parts of the main pass are
+// only measured on devices with `TIMESTAMP_QUERY_INSIDE_PASSES`, in frames
+// whose layers draw each part in one stretch, they're None elsewhere.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuTimings {
+    // from the start of the main pass to the end of the last one
+    pub total: f32,
+    pub main_pass: f32,
+    pub tilemaps: Option<f32>,
+    pub quads: Option<f32>,
+    pub sprites: Option<f32>,
+    pub particles: Option<f32>,
+    pub text: Option<f32>,
+    // the oit pass and compositing it over the frame, 0 without blended layers
+    pub blended: f32,
+}
+
+// What the last frame cost, see `Renderer::frame_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    // the clock's smoothed frames per second
+    pub fps: f32,
+    // seconds the last frame took
+    pub dt: f32,
+    pub draws: DrawStats,
+    pub batches: BatchStats,
+    // the newest frame read back, a few frames old, with `set_gpu_profiling`
+    pub gpu: Option<GpuTimings>,
+}
+
+struct Slot {
+    readback: wgpu::Buffer,
+    // set by the map callback once the timestamps can be read
+    mapped: Arc<AtomicBool>,
+    in_flight: bool,
+    blended: bool,
+    parts: bool,
+}
+
+// Timestamp queries around the render passes of `Renderer::render`, read back
+// a few frames later so waiting on them never stalls one. A frame goes
+// unmeasured when every slot is still on its way back.
+pub struct GpuProfiler {
+    query_set: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    slots: Vec<Slot>,
+    // the slot of the frame being recorded
+    current: Option<usize>,
+    inside_passes: bool,
+    // nanoseconds per timestamp tick
+    period: f32,
+    latest: Option<GpuTimings>,
+}
+
+impl GpuProfiler {
+    // None when the device wasn't created with `Features::TIMESTAMP_QUERY`,
+    // which `GraphicsContext::new` asks for where the adapter has it
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Frame Timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: QUERIES,
+        });
+        let resolve = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Timestamps"),
+            size: BUFFER_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let slots = (0..SLOTS)
+            .map(|_| Slot {
+                readback: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Frame Timestamps Readback"),
+                    size: BUFFER_SIZE,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                mapped: Arc::new(AtomicBool::new(false)),
+                in_flight: false,
+                blended: false,
+                parts: false,
+            })
+            .collect();
+        Some(Self {
+            query_set,
+            resolve,
+            slots,
+            current: None,
+            inside_passes: device.features().contains(wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES),
+            period: queue.get_timestamp_period(),
+            latest: None,
+        })
+    }
+
+    pub fn latest(&self) -> Option<GpuTimings> {
+        self.latest
+    }
+
+    // Reads back the frames whose timestamps arrived and picks a slot for the
+    // next one. Call before recording it.
+    pub fn begin_frame(&mut self, device: &wgpu::Device) {
+        let _ = device.poll(wgpu::PollType::Poll);
+        for i in 0..self.slots.len() {
+            let slot = &self.slots[i];
+            if !slot.in_flight || !slot.mapped.swap(false, Ordering::Acquire) {
+                continue;
+            }
+            let timings = {
+                let data = slot.readback.slice(..).get_mapped_range();
+                self.timings(bytemuck::cast_slice(&data), slot.blended, slot.parts)
+            };
+            let slot = &mut self.slots[i];
+            slot.readback.unmap();
+            slot.in_flight = false;
+            self.latest = Some(timings);
+        }
+        self.current = self.slots.iter().position(|slot| !slot.in_flight);
+    }
+
+    // the begin and end timestamps of `pass`, None when the frame goes unmeasured
+    pub fn pass_writes(&self, pass: Pass) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.current?;
+        let (begin, end) = match pass {
+            Pass::Main => (MAIN_BEGIN, MAIN_END),
+            Pass::Oit => (OIT_BEGIN, OIT_BEGIN + 1),
+            Pass::Composite => (COMPOSITE_END - 1, COMPOSITE_END),
+        };
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(begin),
+            end_of_pass_write_index: Some(end),
+        })
+    }
+
+    // Marks the end of part `part` of the main pass, or its start with 0.
+    // Only written on devices that can write timestamps inside of passes.
+    pub fn mark(&self, render_pass: &mut wgpu::RenderPass, part: u32) {
+        if self.current.is_some() && self.inside_passes {
+            render_pass.write_timestamp(&self.query_set, FIRST_MARK + part);
+        }
+    }
+
+    // Resolves what the frame wrote into its slot, after its last pass. `parts`
+    // tells whether every part of the main pass was marked.
+    pub fn end_frame(&mut self, encoder: &mut wgpu::CommandEncoder, blended: bool, parts: bool) {
+        let Some(current) = self.current else {
+            return;
+        };
+        let main_end = if self.inside_passes { FIRST_MARK + PARTS + 1 } else { FIRST_MARK };
+        encoder.resolve_query_set(&self.query_set, MAIN_BEGIN..main_end, &self.resolve, 0);
+        if blended {
+            encoder.resolve_query_set(&self.query_set, OIT_BEGIN..QUERIES, &self.resolve, BLENDED_OFFSET);
+        }
+        let slot = &mut self.slots[current];
+        encoder.copy_buffer_to_buffer(&self.resolve, 0, &slot.readback, 0, BUFFER_SIZE);
+        slot.blended = blended;
+        slot.parts = parts;
+    }
+
+    // starts reading the frame's timestamps back, after it was submitted
+    pub fn after_submit(&mut self) {
+        let Some(current) = self.current.take() else {
+            return;
+        };
+        let slot = &mut self.slots[current];
+        slot.in_flight = true;
+        let mapped = slot.mapped.clone();
+        slot.readback.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            mapped.store(result.is_ok(), Ordering::Release);
+        });
+    }
+
+    fn timings(&self, ticks: &[u64], blended: bool, parts: bool) -> GpuTimings {
+        let ms = |from: u64, to: u64| to.saturating_sub(from) as f32 * self.period / 1e6;
+        let at = |query: u32| ticks[query as usize];
+        let blended_at = |query: u32| ticks[(BLENDED_OFFSET / 8) as usize + (query - OIT_BEGIN) as usize];
+        let part = |i: u32| {
+            (self.inside_passes && parts).then(|| ms(at(FIRST_MARK + i), at(FIRST_MARK + i + 1)))
+        };
+        let end = if blended { blended_at(COMPOSITE_END) } else { at(MAIN_END) };
+        GpuTimings {
+            total: ms(at(MAIN_BEGIN), end),
+            main_pass: ms(at(MAIN_BEGIN), at(MAIN_END)),
+            tilemaps: part(0),
+            quads: part(1),
+            sprites: part(2),
+            particles: part(3),
+            text: part(4),
+            blended: if blended { ms(blended_at(OIT_BEGIN), blended_at(COMPOSITE_END)) } else { 0.0 },
+        }
+    }
+}
+
+// the part of the main pass `part` is timed in, see `GpuProfiler::mark`
+pub(crate) fn part_index(part: Part) -> u32 {
+    match part {
+        Part::Tilemap(_) | Part::StreamingTilemap(_) => 0,
+        Part::Quads => 1,
+        Part::Sprites => 2,
+        Part::GpuParticles(_) => 3,
+        Part::Text => 4,
+    }
+}
+
+// The passes of a frame whose begin and end are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    Main,
+    Oit,
+    Composite,
+}
@@ -0,0 +1,37 @@
+use crate::pipeline_cache::PipelineCache;
+use crate::state::MaterialState;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub u16);
+
+// A fragment shader quads are drawn with instead of their flat color, see
+// `QuadRenderer::register_material`. Its pipelines are built when it's
+// registered and for every target the quads are drawn into afterwards.
+pub(crate) struct Material {
+    pub(crate) shader: wgpu::ShaderModule,
+    pub(crate) layout: wgpu::PipelineLayout,
+    pub(crate) pipelines: PipelineCache,
+    pub(crate) oit_pipeline: wgpu::RenderPipeline,
+    // bound as group 1, for materials with a uniform block
+    pub(crate) uniform: Option<(wgpu::Buffer, wgpu::BindGroup)>,
+    // what it was registered with, see `RendererState`
+    pub(crate) state: MaterialState,
+}
+
+impl Material {
+    pub(crate) fn uniform_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Material Uniform"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+}
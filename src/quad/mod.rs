@@ -1,2 +1,4 @@
+mod material;
 mod renderer;
-pub use renderer::QuadRenderer;
+pub use material::MaterialId;
+pub use renderer::{QuadRenderer, Vertex};
@@ -1,30 +1,67 @@
+#include "wrs/globals.wgsl"
+#include "wrs/color.wgsl"
+#include "wrs/clip.wgsl"
+#include "wrs/oit.wgsl"
+
 struct VertexOut {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
+    @location(1) world_pos: vec2<f32>,
+    @location(2) clip: vec4<f32>,
+    // 0, 0 at the top left corner of the quad, 1, 1 at the bottom right
+    @location(3) local: vec2<f32>,
+    @location(4) alpha: f32,
+    @location(5) clip_radius: f32,
 };
 
+// laid out like font_shader.wgsl's, the texture layer at 5 isn't read
 struct VertexIn {
     @location(0) pos: vec3<f32>,
     @location(1) color: vec3<f32>,
+    @location(2) local: vec2<f32>,
+    @location(3) clip: vec4<f32>,
+    @location(4) color_space: u32,
+    @location(6) clip_radius: f32,
+    @location(7) alpha: f32,
 }
 
-struct CameraUniform {
-    view_proj: mat4x4<f32>,
-};
-@group(0) @binding(0)
-var<uniform> camera: CameraUniform;
-
 @vertex
 fn vs_main(
     model: VertexIn
 ) -> VertexOut {
     var out: VertexOut;
-    out.color = model.color;
+    out.color = to_srgb(model.color, model.color_space);
     out.clip_position = camera.view_proj * vec4<f32>(model.pos, 1.0);
+    out.world_pos = model.pos.xy;
+    out.clip = model.clip;
+    out.local = model.local;
+    out.alpha = model.alpha;
+    out.clip_radius = model.clip_radius;
     return out;
 }
 
+// flat quads are drawn by `Pipeline2d`, this shader only by materials
+#ifdef MATERIAL
+// defines `fn material(color: vec3<f32>, local: vec2<f32>, world_pos: vec2<f32>) -> vec4<f32>`
+#include "wrs/material.wgsl"
+
+@fragment
+fn fs_material(in: VertexOut) -> @location(0) vec4<f32> {
+    let clip = clip_coverage(in.world_pos, in.clip, in.clip_radius);
+    if clip <= 0.0 {
+        discard;
+    }
+    let color = material(in.color, in.local, in.world_pos);
+    return vec4<f32>(color.rgb, color.a * clip);
+}
+
 @fragment
-fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 1.0);
+fn fs_material_oit(in: VertexOut) -> OitOut {
+    let clip = clip_coverage(in.world_pos, in.clip, in.clip_radius);
+    if clip <= 0.0 {
+        discard;
+    }
+    let color = material(in.color, in.local, in.world_pos);
+    return oit_out(vec4<f32>(color.rgb, color.a * clip), in.clip_position.z);
 }
+#endif
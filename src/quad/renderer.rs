@@ -1,185 +1,650 @@
-use crate::camera::Camera;
-use wgpu::util::DeviceExt;
+use super::material::{Material, MaterialId};
+use crate::batch::{Batch, BatchStats};
+use crate::camera::{self, Camera};
+use crate::color::Color;
+use crate::color_space::ColorSpace;
+use crate::culling::{self, CullRect, Culling};
+use crate::font::FontVertex;
+use crate::oit;
+use crate::pipeline_2d::{FLAT, Pipeline2d};
+use crate::pipeline_cache::{PipelineCache, PipelineKey};
+use crate::rect::{ClipShape, Rect};
+use crate::sanitize::{GeometryIssues, sanitize};
+use crate::shader::{ShaderError, ShaderPreprocessor};
+use crate::sort::{BoundPipeline, DrawStats, SortKey};
+use crate::tessellate;
+use std::collections::{BTreeMap, BTreeSet};
+use crate::state::{BatchSnapshot, MaterialState};
+use serde::{Deserialize, Serialize};
+
+// untextured quads sort before every textured primitive, material `n` takes
+// the texture id `n + 1` so a change of material splits the batch
+const TEXTURE_ID: u16 = 0;
+
+const INITIAL_QUADS: wgpu::BufferAddress = 256;
+
+// flat quads and materials are both blended by their alpha
+const BLEND: wgpu::BlendState = wgpu::BlendState::ALPHA_BLENDING;
+const MATERIAL_BLEND: wgpu::BlendState = wgpu::BlendState::ALPHA_BLENDING;
 
 impl QuadRenderer {
     pub fn new(device: &wgpu::Device, cam: &Camera, surface_fmt: wgpu::TextureFormat) -> Self {
-        let shader = device.create_shader_module(wgpu::include_wgsl!("quad_shader.wgsl"));
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        Self::with_culling(device, cam, surface_fmt, Culling::default())
+    }
+
+    pub fn with_culling(device: &wgpu::Device, cam: &Camera, surface_fmt: wgpu::TextureFormat, culling: Culling) -> Self {
+        let mut renderer = Self {
+            pipeline: Pipeline2d::new(device, cam, culling),
+            culling,
+            target: PipelineKey::new(surface_fmt, BLEND),
+            msaa_target: None,
+            materials: vec![],
+            batch: Batch::new(device, "Quad Batch", INITIAL_QUADS),
+            clip: ClipShape::EVERYTHING,
+            color_space: ColorSpace::Srgb,
+            world_bounds: None,
+            cull: None,
+            issues: GeometryIssues::default(),
+        };
+        renderer.build_pipelines(device, renderer.target);
+        renderer
+    }
+
+    // what flat quads are drawn with, for `FontRenderer::set_pipeline_2d`
+    pub fn pipeline_2d(&self) -> &Pipeline2d {
+        &self.pipeline
+    }
+
+    pub fn push(&mut self, x: f32, y: f32, w: f32, h: f32, color: impl Into<Color>) {
+        self.push_with_key(SortKey::default(), x, y, w, h, color);
+    }
+    // A negative `w` or `h` extends the quad left of `x` or above `y`, quads
+    // with NaN or infinite coordinates are dropped. Both are counted in `issues`.
+    pub fn push_with_key(&mut self, key: SortKey, x: f32, y: f32, w: f32, h: f32, color: impl Into<Color>) {
+        self.push_quad(key.with_texture(TEXTURE_ID), Rect::new(x, y, w, h), 0.0, color.into());
+    }
+
+    // A quad `z` into the screen, from 0 to `camera::DEPTH`. With a depth
+    // buffer (`RendererSettings::depth_buffer`) nearer quads cover further ones
+    // whatever order they're drawn in, without one it's only the OIT weight.
+    // Everything pushed without a `z` is nearest.
+    pub fn push_with_z(&mut self, key: SortKey, z: f32, rect: Rect, color: impl Into<Color>) {
+        self.push_quad(key.with_texture(TEXTURE_ID), rect, -z.clamp(0.0, camera::DEPTH), color.into());
+    }
+
+    // A filled polygon, `points` going around its outline in either direction,
+    // see `tessellate::triangulate`. It's drawn as triangles in the quad batch,
+    // one quad each with its last corner doubled.
+    pub fn push_polygon(&mut self, key: SortKey, points: &[[f32; 2]], color: impl Into<Color>) {
+        self.push_triangulated(key.with_texture(TEXTURE_ID), points, color.into());
+    }
+
+    // `push_polygon` drawn by `material`, its `local` running over the
+    // polygon's bounding box
+    pub fn push_polygon_with_material(&mut self, key: SortKey, points: &[[f32; 2]], color: impl Into<Color>, material: MaterialId) {
+        debug_assert!((material.0 as usize) < self.materials.len(), "unknown material");
+        self.push_triangulated(key.with_texture(material.0 + 1), points, color.into());
+    }
+
+    // `key` has its texture id already
+    fn push_triangulated(&mut self, key: SortKey, points: &[[f32; 2]], color: Color) {
+        if !points.iter().flatten().all(|v| v.is_finite()) {
+            self.issues.non_finite += 1;
+            return;
+        }
+        let Some(bbox) = Rect::bounding(points) else {
+            return;
+        };
+        if self.culls(key, &bbox) {
+            return;
+        }
+        let clamped = self.world_bounds.filter(|bounds| !bounds.contains_rect(&bbox));
+        if clamped.is_some() {
+            self.issues.clamped += 1;
+        }
+        let ((clip, clip_radius), color_space) = (self.clip.to_bounds(), self.color_space.id());
+        let vertex = |pos: [f32; 2]| Vertex {
+            pos: [pos[0], pos[1], 0.0],
+            color: color.to_rgb(),
+            local: [
+                (pos[0] - bbox.x) / bbox.w.max(f32::EPSILON),
+                (pos[1] - bbox.y) / bbox.h.max(f32::EPSILON),
+            ],
+            clip,
+            color_space,
+            layer: FLAT,
+            clip_radius,
+            alpha: color.a,
+        };
+        for [a, b, c] in tessellate::triangulate(points) {
+            let triangle = [points[a], points[b], points[c]];
+            let Some(bounds) = clamped else {
+                let [a, b, c] = triangle.map(vertex);
+                self.batch.push(key, [a, b, c, c]);
+                continue;
+            };
+            // what's left of a triangle is convex, a fan around its first point
+            let clipped = tessellate::clip_convex(&triangle, bounds);
+            for i in 2..clipped.len() {
+                let [a, b, c] = [clipped[0], clipped[i - 1], clipped[i]].map(vertex);
+                self.batch.push(key, [a, b, c, c]);
+            }
+        }
+    }
+
+    // a quad drawn by `material` instead of with its flat color, which the
+    // material gets as its `color`, without the alpha
+    pub fn push_with_material(&mut self, key: SortKey, rect: Rect, color: impl Into<Color>, material: MaterialId) {
+        debug_assert!((material.0 as usize) < self.materials.len(), "unknown material");
+        self.push_quad(key.with_texture(material.0 + 1), rect, 0.0, color.into());
+    }
+
+    // `z` is the world's, negative into the screen
+    fn push_quad(&mut self, key: SortKey, rect: Rect, z: f32, color: Color) {
+        if self.culls(key, &rect) {
+            return;
+        }
+        // flipped sides would wind the quad the other way around, which culling
+        // drops, the local coordinates stay mirrored
+        let Some((Rect { x, y, w, h }, (u0, v0, u1, v1))) =
+            sanitize(rect, (0.0, 0.0, 1.0, 1.0), self.world_bounds, &mut self.issues)
+        else {
+            return;
+        };
+        let (clip, clip_radius) = self.clip.to_bounds();
+        let color_space = self.color_space.id();
+        let vertex = |pos: [f32; 2], local| Vertex {
+            pos: [pos[0], pos[1], z],
+            color: color.to_rgb(),
+            local,
+            clip,
+            color_space,
+            layer: FLAT,
+            clip_radius,
+            alpha: color.a,
+        };
+        self.batch.push(key, [
+            vertex([x, y], [u0, v0]),
+            vertex([x + w, y], [u1, v0]),
+            vertex([x + w, y + h], [u1, v1]),
+            vertex([x, y + h], [u0, v1]),
+        ]);
+    }
+
+    // Builds a material out of the WGSL `source`, which defines
+    //
+    //   fn material(color: vec3<f32>, local: vec2<f32>, world_pos: vec2<f32>) -> vec4<f32>
+    //
+    // returning the straight alpha color of a fragment, `local` running from
+    // 0, 0 at the top left corner of the quad to 1, 1 at the bottom right.
+    // Materials are blended by their alpha. With a `uniform_size` the source
+    // also declares its uniform block as
+    //
+    //   @group(1) @binding(0) var<uniform> params: Params;
+    //
+    // which `set_material_uniform` fills. The source can `#include` what the
+    // crate's shaders do, see `ShaderPreprocessor`.
+    pub fn register_material(
+        &mut self,
+        device: &wgpu::Device,
+        cam: &Camera,
+        label: &str,
+        source: &str,
+        uniform_size: wgpu::BufferAddress,
+    ) -> Result<MaterialId, ShaderError> {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.add_include("wrs/material.wgsl", source);
+        preprocessor.define("MATERIAL", "");
+        let shader = preprocessor.create_module(device, label, include_str!("quad_shader.wgsl"))?;
+
+        let uniform_layout = Material::uniform_layout(device);
+        let uniform = (uniform_size > 0).then(|| {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: uniform_size.next_multiple_of(16),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout: &uniform_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            (buffer, bind_group)
+        });
+        let bind_group_layouts: &[&wgpu::BindGroupLayout] = match uniform {
+            Some(_) => &[cam.get_bind_group_layout(), &uniform_layout],
+            None => &[cam.get_bind_group_layout()],
+        };
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[cam.get_bind_group_layout()],
+            bind_group_layouts,
             push_constant_ranges: &[],
         });
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
+        let oit_pipeline =
+            create_pipeline(device, &layout, &shader, "fs_material_oit", &oit::targets(), 1, None, self.culling);
+        self.materials.push(Material {
+            shader,
+            layout,
+            pipelines: PipelineCache::new(),
+            oit_pipeline,
+            uniform,
+            state: MaterialState {
+                label: label.to_string(),
+                source: source.to_string(),
+                uniform_size,
+                uniform: vec![],
             },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_fmt,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            multiview: None,
-            cache: None,
         });
-        Self {
-            render_pipeline: pipeline,
-            vertices: vec![],
-            indices: vec![],
-            vbo: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: &[],
-                usage: wgpu::BufferUsages::VERTEX,
-            }),
-            ibo: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: &[],
-                usage: wgpu::BufferUsages::INDEX,
-            }),
-            has_data: false,
+        self.build_pipelines(device, self.target);
+        if let Some(msaa_target) = self.msaa_target {
+            self.build_pipelines(device, msaa_target);
         }
+        Ok(MaterialId(self.materials.len() as u16 - 1))
     }
-    pub fn push(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 3]) {
-        self.has_data = true;
-        let start = self.vertices.len() as u16;
 
-        self.vertices.extend_from_slice(&[
-            Vertex {
-                pos: [x, y, 0.0],
-                color,
-            },
-            Vertex {
-                pos: [x + w, y, 0.0],
-                color,
-            },
-            Vertex {
-                pos: [x + w, y + h, 0.0],
-                color,
-            },
-            Vertex {
-                pos: [x, y + h, 0.0],
-                color,
-            },
-        ]);
+    // writes `data` to the start of the uniform block of `material`
+    pub fn set_material_uniform(&mut self, queue: &wgpu::Queue, material: MaterialId, data: &[u8]) {
+        let material = &mut self.materials[material.0 as usize];
+        if let Some((buffer, _)) = &material.uniform {
+            queue.write_buffer(buffer, 0, data);
+            let kept = &mut material.state.uniform;
+            kept.resize(kept.len().max(data.len()), 0);
+            kept[..data.len()].copy_from_slice(data);
+        }
+    }
+
+    // by `MaterialId`, for registering them again
+    pub fn materials(&self) -> Vec<MaterialState> {
+        self.materials.iter().map(|material| material.state.clone()).collect()
+    }
 
-        self.indices
-            .extend_from_slice(&[start, start + 1, start + 2, start, start + 2, start + 3]);
+    // Everything a frame needs before its render pass starts: sorts the batch
+    // and uploads it. Call once per frame, after the last push.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        debug_assert_eq!(culling::count_mis_wound(self.batch.vertices(), |v| v.pos), 0, "mis-wound quads in the batch");
+        self.batch.prepare(device, queue);
     }
-    pub fn flush(
+
+    // The format of the target `render` draws into from now on, when the
+    // surface's changes. Pipelines for a format drawn into before are reused.
+    pub fn set_target_format(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.target.format = format;
+        self.build_pipelines(device, self.target);
+    }
+
+    // The format of the depth attachment of the pass `render` draws into,
+    // `None` for a pass without one.
+    pub fn set_depth_format(&mut self, device: &wgpu::Device, depth: Option<wgpu::TextureFormat>) {
+        self.target.depth = depth;
+        self.build_pipelines(device, self.target);
+    }
+
+    // Builds the pipeline `render_multisampled` draws with into a `format`
+    // target with `samples` samples and a `depth` attachment, for offscreen
+    // exports. It's kept for later calls with the same target.
+    pub fn prepare_multisampled(
         &mut self,
-        render_pass: &mut wgpu::RenderPass,
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        cam: &Camera,
+        format: wgpu::TextureFormat,
+        samples: u32,
+        depth: Option<wgpu::TextureFormat>,
     ) {
-        if self.has_data {
-            self.upload_data(device, queue);
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, cam.get_bind_group(), &[]);
-            render_pass.set_vertex_buffer(0, self.vbo.slice(..));
-            render_pass.set_index_buffer(self.ibo.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..self.indices.len() as u32, 0, 0..1);
+        let target = PipelineKey::new(format, BLEND).with_samples(samples).with_depth(depth);
+        self.msaa_target = Some(target);
+        self.build_pipelines(device, target);
+    }
+
+    // the pipelines of flat quads and of every material for `target`
+    fn build_pipelines(&mut self, device: &wgpu::Device, target: PipelineKey) {
+        let culling = self.culling;
+        self.pipeline.build(device, target);
+        for material in &mut self.materials {
+            let (layout, shader) = (&material.layout, &material.shader);
+            material.pipelines.get_or_create(target.with_blend(MATERIAL_BLEND), |key| {
+                create_pipeline(device, layout, shader, "fs_material", &[Some(key.color_target())], key.samples, key.depth_stencil(), culling)
+            });
         }
     }
 
-    pub fn clear(&mut self) {
-        self.indices.clear();
-        self.vertices.clear();
-        self.has_data = false;
+    // draws the layers `draw_layer` accepts of what the last `prepare` uploaded
+    pub fn render(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, draw_layer: impl Fn(u16) -> bool) -> DrawStats {
+        self.render_with(render_pass, cam, draw_layer, &mut BoundPipeline::default())
     }
 
-    pub fn empty(&self) -> bool {
-        self.vertices.is_empty()
+    // `render` after whatever set `bound` in the same pass, binding the
+    // pipeline only where it changes
+    pub fn render_with(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        cam: &Camera,
+        draw_layer: impl Fn(u16) -> bool,
+        bound: &mut BoundPipeline,
+    ) -> DrawStats {
+        if !self.batch.is_drawable() {
+            return DrawStats::default();
+        }
+        self.draw_runs(render_pass, cam, Pass::Main, draw_layer, bound)
     }
 
-    pub fn upload_data(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
-        if self.vertices.is_empty() {
-            return;
+    // `render` into the target set up by `prepare_multisampled`
+    pub fn render_multisampled(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, draw_layer: impl Fn(u16) -> bool) -> DrawStats {
+        if !self.batch.is_drawable() {
+            return DrawStats::default();
         }
-        if (self.vbo.size() as usize) < self.vertices.len() * std::mem::size_of::<Vertex>() {
-            self.vbo.destroy();
-            let vbo = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(&self.vertices),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
-            self.vbo = vbo;
-        } else {
-            queue.write_buffer(&self.vbo, 0, bytemuck::cast_slice(&self.vertices));
+        assert!(self.msaa_target.is_some(), "render_multisampled without prepare_multisampled");
+        self.draw_runs(render_pass, cam, Pass::Multisampled, draw_layer, &mut BoundPipeline::default())
+    }
+
+    // `render` into an `OitCompositor` pass
+    pub fn render_oit(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, draw_layer: impl Fn(u16) -> bool) -> DrawStats {
+        self.render_oit_with(render_pass, cam, draw_layer, &mut BoundPipeline::default())
+    }
+
+    // `render_oit` like `render_with`
+    pub fn render_oit_with(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        cam: &Camera,
+        draw_layer: impl Fn(u16) -> bool,
+        bound: &mut BoundPipeline,
+    ) -> DrawStats {
+        if !self.batch.is_drawable() {
+            return DrawStats::default();
         }
+        self.draw_runs(render_pass, cam, Pass::Oit, draw_layer, bound)
+    }
 
-        if (self.ibo.size() as usize) < self.indices.len() * std::mem::size_of::<u16>() {
-            self.ibo.destroy();
-            let ibo = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(&self.indices),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            });
-            self.ibo = ibo;
-        } else {
-            queue.write_buffer(&self.ibo, 0, bytemuck::cast_slice(&self.indices));
+    fn draw_runs(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        cam: &Camera,
+        pass: Pass,
+        draw_layer: impl Fn(u16) -> bool,
+        bound: &mut BoundPipeline,
+    ) -> DrawStats {
+        render_pass.set_bind_group(0, cam.get_bind_group(), &[]);
+        let mut stats = DrawStats {
+            bind_group_binds: 1,
+            ..DrawStats::default()
+        };
+        let (mut bound_buffers, mut bound_view, mut bound_texture) = (None, None, None);
+        for (kept, view, texture, range) in self.batch.static_runs(draw_layer, |layer| cam.layer_view(layer)) {
+            if bound_buffers != Some(kept) {
+                self.batch.set_run_buffers(render_pass, kept);
+                bound_buffers = Some(kept);
+            }
+            if view != bound_view {
+                render_pass.set_bind_group(0, cam.view_bind_group(view), &[]);
+                bound_view = view;
+                stats.bind_group_binds += 1;
+            }
+            if bound_texture != Some(texture) {
+                let material = self.material(texture);
+                bound.set(render_pass, &self.pipeline(pass, texture), &mut stats);
+                let bind_group = match material {
+                    Some(material) => material.uniform.as_ref().map(|(_, bind_group)| bind_group),
+                    None => Some(self.pipeline.no_texture()),
+                };
+                if let Some(bind_group) = bind_group {
+                    render_pass.set_bind_group(1, bind_group, &[]);
+                    stats.bind_group_binds += 1;
+                }
+                bound_texture = Some(texture);
+            }
+            stats.quads += range.len() as u32 / 6;
+            render_pass.draw_indexed(range, 0, 0..1);
+            stats.draw_calls += 1;
         }
+        stats
+    }
+
+    // the material a texture id of the batch stands for, None for flat quads
+    fn material(&self, texture: u16) -> Option<&Material> {
+        texture.checked_sub(1).map(|m| &self.materials[m as usize])
+    }
+
+    fn pipeline(&self, pass: Pass, texture: u16) -> wgpu::RenderPipeline {
+        let target = match pass {
+            Pass::Main => self.target,
+            Pass::Multisampled => self.msaa_target.unwrap(),
+            Pass::Oit => {
+                return self.material(texture).map_or(self.pipeline.oit(), |m| &m.oit_pipeline).clone();
+            }
+        };
+        match self.material(texture) {
+            Some(m) => m.pipelines.get(target.with_blend(MATERIAL_BLEND)).expect("pipelines are built for every target").clone(),
+            None => self.pipeline.get(target),
+        }
+    }
+
+    pub fn has_layer(&self, pred: impl Fn(u16) -> bool) -> bool {
+        self.batch.has_layer(pred)
+    }
+
+    pub fn layers(&self, pred: impl Fn(u16) -> bool) -> BTreeSet<u16> {
+        self.batch.layers(pred)
+    }
+
+    // Clips everything pushed afterwards to `clip` in the fragment shader. The
+    // rect travels with the vertices, so changing it doesn't split the batch.
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = ClipShape::Rect(clip.unwrap_or(Rect::EVERYTHING));
+    }
+
+    // like `set_clip`, to a rounded rect or circle as well
+    pub fn set_clip_shape(&mut self, clip: Option<ClipShape>) {
+        self.clip = clip.unwrap_or(ClipShape::EVERYTHING);
+    }
+
+    // what `set_clip` or `set_clip_shape` last set, `None` when nothing is clipped
+    pub fn clip_shape(&self) -> Option<ClipShape> {
+        Some(self.clip).filter(|clip| *clip != ClipShape::EVERYTHING)
+    }
+
+    // the space the colors of everything pushed afterwards are in, converted
+    // in the shader like the clip rect
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.color_space = color_space;
+    }
+
+    // Cuts everything pushed afterwards to `bounds`, dropping what's entirely
+    // outside of them, to keep runaway coordinates out of the batch.
+    pub fn set_world_bounds(&mut self, bounds: Option<Rect>) {
+        self.world_bounds = bounds;
+    }
+
+    // Drops what's pushed entirely outside of `cull` from then on, see
+    // `Renderer::set_cpu_culling`. Static layers aren't culled, they're kept
+    // for frames the camera may have moved in.
+    pub fn set_cull_rect(&mut self, cull: Option<CullRect>) {
+        self.cull = cull;
+    }
+
+    fn culls(&self, key: SortKey, bounds: &Rect) -> bool {
+        let culled = self.cull.as_ref().is_some_and(|cull| cull.culls(key.layer(), bounds));
+        culled && !self.batch.is_static_layer(key.layer())
+    }
+
+    // what pushing fixed or dropped since the last `clear`
+    pub fn issues(&self) -> GeometryIssues {
+        self.issues
+    }
+
+    pub fn batch_stats(&self) -> BatchStats {
+        self.batch.stats()
+    }
+
+    // see `Batch::set_static_layer`
+    pub fn set_static_layer(&mut self, device: &wgpu::Device, layer: u16, enabled: bool) {
+        self.batch.set_static_layer(device, layer, enabled);
+    }
+
+    // see `Batch::rebuild_static_layer`
+    pub fn rebuild_static_layer(&mut self, layer: u16) {
+        self.batch.rebuild_static_layer(layer);
+    }
+
+    pub(crate) fn rebuilt_static_layers(&self) -> Vec<u16> {
+        self.batch.rebuilt_static_layers()
+    }
+
+    pub fn snapshot(&self) -> BatchSnapshot<Vertex> {
+        self.batch.snapshot()
+    }
+
+    // see `Batch::static_snapshots`
+    pub fn static_snapshots(&self) -> BTreeMap<u16, BatchSnapshot<Vertex>> {
+        self.batch.static_snapshots()
+    }
+
+    // quads of materials that aren't registered come back flat
+    pub fn restore(&mut self, snapshot: BatchSnapshot<Vertex>) {
+        let materials = self.materials.len();
+        self.batch.restore(snapshot, |key| registered(key, materials));
+        // snapshots from before negative sizes were normalized can hold flipped quads
+        culling::rewind_quads(self.batch.vertices_mut(), |v| v.pos);
+    }
+
+    // see `Batch::restore_static_layer`, like `restore` for materials
+    pub fn restore_static_layer(&mut self, device: &wgpu::Device, layer: u16, snapshot: BatchSnapshot<Vertex>) {
+        let materials = self.materials.len();
+        self.batch
+            .restore_static_layer(device, layer, snapshot, |key| registered(key, materials));
+    }
+
+    pub fn clear(&mut self) {
+        self.issues = GeometryIssues::default();
+        self.batch.clear();
+    }
+
+    pub fn empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+
+    // the quads pushed since the last `clear`, to `turn_since` later
+    pub(crate) fn pushed(&self) -> usize {
+        self.batch.len()
+    }
+
+    // see `Batch::turn_since`
+    pub(crate) fn turn_since(&mut self, from: usize, origin: [f32; 2], angle: f32) {
+        self.batch.turn_since(from, origin, angle, |v| &mut v.pos);
     }
 }
 
 
 pub struct QuadRenderer {
-    render_pipeline: wgpu::RenderPipeline,
-    vertices: Vec<Vertex>,
-    indices: Vec<u16>,
-    vbo: wgpu::Buffer,
-    ibo: wgpu::Buffer,
-    has_data: bool,
+    pipeline: Pipeline2d,
+    culling: Culling,
+    // what `render` draws into, and `render_multisampled` since the last
+    // `prepare_multisampled`
+    target: PipelineKey,
+    msaa_target: Option<PipelineKey>,
+    materials: Vec<Material>,
+    batch: Batch<Vertex>,
+    clip: ClipShape,
+    color_space: ColorSpace,
+    world_bounds: Option<Rect>,
+    cull: Option<CullRect>,
+    issues: GeometryIssues,
+}
+
+// the pipelines of a quad or material, per kind of pass
+#[derive(Clone, Copy)]
+enum Pass {
+    Main,
+    Oit,
+    Multisampled,
+}
+
+// `key` with the material it was pushed with when there are `materials`
+// registered, else flat
+fn registered(key: SortKey, materials: usize) -> SortKey {
+    if key.texture() as usize <= materials {
+        key
+    } else {
+        key.with_texture(TEXTURE_ID)
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+    targets: &[Option<wgpu::ColorTargetState>],
+    samples: u32,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    culling: Culling,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[Vertex::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        primitive: culling.primitive_state(),
+        depth_stencil,
+        multisample: wgpu::MultisampleState {
+            count: samples,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(entry_point),
+            targets,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+// laid out like `FontVertex`, so flat quads and glyphs draw with one `Pipeline2d`
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
+pub struct Vertex {
     pos: [f32; 3],
     color: [f32; 3],
+    // where in the quad the vertex is, for materials
+    #[serde(default)]
+    local: [f32; 2],
+    clip: [f32; 4],
+    // `ColorSpace::id`, recordings from before it existed are srgb
+    #[serde(default)]
+    color_space: u32,
+    // always `FLAT`, where `FontVertex` has its atlas layer
+    #[serde(skip, default = "flat")]
+    layer: u32,
+    // `ClipShape` corners, 0 for a plain clip rect
+    #[serde(default)]
+    clip_radius: f32,
+    // straight, recordings from before it existed are opaque
+    #[serde(default = "opaque")]
+    alpha: f32,
+}
+
+const _: () = assert!(std::mem::size_of::<Vertex>() == std::mem::size_of::<FontVertex>());
+
+fn flat() -> u32 {
+    FLAT
+}
+
+fn opaque() -> f32 {
+    1.0
 }
 
 impl Vertex {
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
-        }
+        FontVertex::desc()
     }
 }
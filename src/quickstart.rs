@@ -0,0 +1,123 @@
+use winit::window::Window;
+
+use crate::app::{self, App};
+use crate::color::Color;
+use crate::frame::Frame;
+use crate::input::InputState;
+
+// The shortest way to get something on screen, a window and a closure called
+// every frame to draw into it:
+//
+//   wrs::Canvas::run("hello", 800, 600, |canvas, input| {
+//       canvas.clear([0.1, 0.1, 0.1]);
+//       canvas.rect(20.0, 20.0, 200.0, 100.0, [0.2, 0.4, 0.8]);
+//       if let Some((x, y)) = input.mouse_position() {
+//           canvas.circle(x, y, 10.0, [1.0, 0.2, 0.2]);
+//       }
+//       canvas.text(20.0, 140.0, 32.0, [1.0, 1.0, 1.0], "hello");
+//   })
+//
+// Coordinates are physical window pixels from the top left, like the mouse.
+// `frame` reaches everything else, for outgrowing it a piece at a time before
+// moving to `App`.
+pub struct Canvas<'a, 'f> {
+    frame: &'a mut Frame<'f>,
+}
+
+impl<'a, 'f> Canvas<'a, 'f> {
+    // Opens a `width` by `height` window, in logical pixels, and calls `draw`
+    // every frame until it's closed.
+    pub fn run(
+        title: &str,
+        width: u32,
+        height: u32,
+        draw: impl FnMut(&mut Canvas, &InputState),
+    ) -> Result<(), winit::error::EventLoopError> {
+        let attributes = Window::default_attributes()
+            .with_title(title)
+            .with_inner_size(winit::dpi::LogicalSize::new(width, height));
+        app::run_with(
+            attributes,
+            Quickstart {
+                draw,
+                input: InputState::new(),
+            },
+        )
+    }
+
+    pub fn frame(&mut self) -> &mut Frame<'f> {
+        self.frame
+    }
+
+    // seconds since the last frame
+    pub fn dt(&self) -> f32 {
+        self.frame.clock().dt()
+    }
+
+    // the window's size in physical pixels
+    pub fn size(&self) -> (f32, f32) {
+        let size = self.frame.size();
+        (size.width as f32, size.height as f32)
+    }
+
+    // what the window is filled with before anything is drawn, from this frame
+    // on
+    pub fn clear(&mut self, color: impl Into<Color>) {
+        let Color { r, g, b, a } = color.into();
+        self.frame.renderer.set_clear_color(r, g, b, a);
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: impl Into<Color>) {
+        self.frame.quad(x, y, w, h, color);
+    }
+
+    pub fn circle(&mut self, x: f32, y: f32, radius: f32, color: impl Into<Color>) {
+        // segments a pixel or two long, so the outline looks round
+        let segments = (radius.abs() * 0.5).clamp(12.0, 128.0) as usize;
+        let points: Vec<[f32; 2]> = (0..segments)
+            .map(|i| {
+                let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+                [x + radius * angle.cos(), y + radius * angle.sin()]
+            })
+            .collect();
+        self.frame.polygon(&points, color);
+    }
+
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, color: impl Into<Color>) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len <= 0.0 {
+            return;
+        }
+        let (nx, ny) = (-dy / len * width / 2.0, dx / len * width / 2.0);
+        let points = [
+            [from.0 + nx, from.1 + ny],
+            [to.0 + nx, to.1 + ny],
+            [to.0 - nx, to.1 - ny],
+            [from.0 - nx, from.1 - ny],
+        ];
+        self.frame.polygon(&points, color);
+    }
+
+    // one line with the default font, its top left at `x`, `y`, characters
+    // the font doesn't have drawn as its fallback glyph
+    pub fn text(&mut self, x: f32, y: f32, size: f32, color: impl Into<Color>, text: &str) {
+        let _ = self.frame.text(x, y, size, color, text);
+    }
+}
+
+struct Quickstart<F> {
+    draw: F,
+    // of the frame being drawn, `draw` gets no input of its own
+    input: InputState,
+}
+
+impl<F: FnMut(&mut Canvas, &InputState)> App for Quickstart<F> {
+    fn update(&mut self, _dt: f32, input: &InputState) {
+        self.input.clone_from(input);
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        (self.draw)(&mut Canvas { frame }, &self.input);
+    }
+}
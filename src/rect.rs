@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+// Axis aligned rectangle in pixels, `x`, `y` being the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+// What `Frame::set_clip_shape` clips to, evaluated per pixel in the fragment
+// shader like a clip rect, so widgets can clip their own contents, like an
+// image inside of a rounded card, without breaking the batch. Curved edges
+// are antialiased, straight ones are hard.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ClipShape {
+    Rect(Rect),
+    // the radius is capped at half the shorter side
+    RoundedRect { rect: Rect, radius: f32 },
+    Circle { center: [f32; 2], radius: f32 },
+}
+
+impl ClipShape {
+    pub const EVERYTHING: ClipShape = ClipShape::Rect(Rect::EVERYTHING);
+
+    // the bounds and corner radius vertices carry it as
+    pub(crate) fn to_bounds(self) -> ([f32; 4], f32) {
+        match self {
+            ClipShape::Rect(rect) => (rect.to_bounds(), 0.0),
+            ClipShape::RoundedRect { rect, radius } => (rect.normalized().to_bounds(), radius.max(0.0)),
+            ClipShape::Circle { center: [x, y], radius } => {
+                let radius = radius.max(0.0);
+                (Rect::new(x - radius, y - radius, 2.0 * radius, 2.0 * radius).to_bounds(), radius)
+            }
+        }
+    }
+}
+
+impl Rect {
+    // covers every representable position, used as "no clipping"
+    pub const EVERYTHING: Rect = Rect {
+        x: f32::MIN / 2.0,
+        y: f32::MIN / 2.0,
+        w: f32::MAX,
+        h: f32::MAX,
+    };
+
+    pub const fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    // the same area with a non-negative width and height, a negative side
+    // meaning the rect extends left of or above `x`, `y`
+    pub fn normalized(self) -> Self {
+        let (x, w) = if self.w < 0.0 { (self.x + self.w, -self.w) } else { (self.x, self.w) };
+        let (y, h) = if self.h < 0.0 { (self.y + self.h, -self.h) } else { (self.y, self.h) };
+        Self { x, y, w, h }
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.w
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.h
+    }
+
+    pub fn contains(&self, px: f32, py: f32) -> bool {
+        px >= self.x && py >= self.y && px < self.right() && py < self.bottom()
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    // whether `other` lies entirely inside of this one, edges included
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.x >= self.x && other.y >= self.y && other.right() <= self.right() && other.bottom() <= self.bottom()
+    }
+
+    // the smallest rect around `points`, None without any
+    pub fn bounding(points: &[[f32; 2]]) -> Option<Self> {
+        let first = points.first()?;
+        let (mut min, mut max) = (*first, *first);
+        for p in points {
+            min = [min[0].min(p[0]), min[1].min(p[1])];
+            max = [max[0].max(p[0]), max[1].max(p[1])];
+        }
+        Some(Self::new(min[0], min[1], max[0] - min[0], max[1] - min[1]))
+    }
+
+    // min x, min y, max x, max y as the shaders expect clip rects
+    pub fn to_bounds(self) -> [f32; 4] {
+        [self.x, self.y, self.right(), self.bottom()]
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::draw_list::DrawList;
+
+// Debug streaming of draw lists over plain TCP. Frames are sent the way
+// `DrawList::write_framed` writes them, so anything that can open a socket can
+// act as the viewer, see `examples/remote_viewer.rs`.
+
+// a viewer that can't take a frame within this long is dropped instead of
+// stalling the app
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+pub struct DebugServer {
+    listener: TcpListener,
+    clients: Vec<TcpStream>,
+}
+
+impl DebugServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            clients: vec![],
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    // Accepts pending viewers and sends `list` to all of them. Viewers whose
+    // connection fails are dropped, the app keeps running either way.
+    pub fn broadcast(&mut self, list: &DrawList) {
+        self.accept_clients();
+        if self.clients.is_empty() {
+            return;
+        }
+
+        let mut frame = vec![];
+        list.write_framed(&mut frame).unwrap();
+        self.clients
+            .retain_mut(|client| client.write_all(&frame).is_ok());
+    }
+
+    fn accept_clients(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            let ready = stream.set_nonblocking(false).is_ok()
+                && stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_ok()
+                && stream.set_nodelay(true).is_ok();
+            if ready {
+                self.clients.push(stream);
+            }
+        }
+    }
+}
+
+pub struct DebugClient {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl DebugClient {
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream,
+            buffer: vec![],
+        })
+    }
+
+    // Reads whatever arrived without blocking and returns the newest complete
+    // frame, older frames that arrived in the meantime are skipped.
+    pub fn latest(&mut self) -> io::Result<Option<DrawList>> {
+        let mut chunk = [0; 16 * 1024];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let (frames, start) = DrawList::read_framed(&self.buffer);
+        let list = frames
+            .last()
+            .map(|frame| DrawList::from_bytes(frame))
+            .transpose()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.buffer.drain(..start);
+        Ok(list)
+    }
+}
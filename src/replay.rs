@@ -0,0 +1,245 @@
+use std::path::Path;
+
+use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+use crate::backend::RenderBackend;
+use crate::draw_list::{DecodeError, DrawCommand, DrawList};
+use crate::font::MissingGlyphs;
+use crate::rect::Rect;
+use crate::sort::SortKey;
+
+const TIMELINE_HEIGHT: f32 = 24.0;
+// drawn above everything a recorded frame can contain, pinned to the screen
+pub(crate) const OVERLAY_LAYER: u16 = u16::MAX;
+
+// Draw lists of consecutive frames. Saved files are the frames one after the
+// other as written by `DrawList::write_framed`, the same stream the debug
+// server sends, so a captured socket stream loads as a recording too.
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub frames: Vec<DrawList>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn capture(&mut self, frame: &DrawList) {
+        self.frames.push(frame.clone());
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut out = vec![];
+        for frame in &self.frames {
+            frame.write_framed(&mut out)?;
+        }
+        std::fs::write(path, out)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let (frames, _) = DrawList::read_framed(&bytes);
+        let frames = frames
+            .into_iter()
+            .map(DrawList::from_bytes)
+            .collect::<Result<Vec<_>, DecodeError>>()
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        Ok(Self { frames })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameStats {
+    pub quads: usize,
+    pub texts: usize,
+    pub glyphs: usize,
+    pub clips: usize,
+    // size of the binary encoding
+    pub bytes: usize,
+}
+
+impl FrameStats {
+    pub fn of(frame: &DrawList) -> Self {
+        let mut stats = Self {
+            bytes: frame.to_bytes().len(),
+            ..Self::default()
+        };
+        for command in &frame.commands {
+            match command {
+                DrawCommand::Quad { .. } => stats.quads += 1,
+                DrawCommand::Text { text, .. } => {
+                    stats.texts += 1;
+                    stats.glyphs += text.chars().filter(|c| !c.is_whitespace()).count();
+                }
+                DrawCommand::Clip { .. } => stats.clips += 1,
+                DrawCommand::Unknown => {}
+            }
+        }
+        stats
+    }
+}
+
+// Plays a `Recording` back frame by frame with a timeline at the bottom of the
+// window. Left/Right step, Space plays/pauses, Home/End jump to either end and
+// clicking or dragging on the timeline scrubs.
+pub struct Replay {
+    recording: Recording,
+    frame: usize,
+    playing: bool,
+    scrubbing: bool,
+    cursor: (f32, f32),
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            frame: 0,
+            playing: false,
+            scrubbing: false,
+            cursor: (0.0, 0.0),
+        }
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.recording.frames.len()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn set_frame(&mut self, frame: usize) {
+        self.frame = frame.min(self.frame_count().saturating_sub(1));
+    }
+
+    pub fn step_forward(&mut self) {
+        self.playing = false;
+        self.set_frame(self.frame + 1);
+    }
+
+    pub fn step_backward(&mut self) {
+        self.playing = false;
+        self.set_frame(self.frame.saturating_sub(1));
+    }
+
+    pub fn toggle_playing(&mut self) {
+        if !self.playing && self.frame + 1 >= self.frame_count() {
+            self.frame = 0;
+        }
+        self.playing = !self.playing;
+    }
+
+    // call once per displayed frame, moves on while playing
+    pub fn advance(&mut self) {
+        if self.playing {
+            if self.frame + 1 >= self.frame_count() {
+                self.playing = false;
+            } else {
+                self.frame += 1;
+            }
+        }
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.recording
+            .frames
+            .get(self.frame)
+            .map(FrameStats::of)
+            .unwrap_or_default()
+    }
+
+    // Returns true when the replay used the event.
+    pub fn handle_event(&mut self, event: &WindowEvent, window_size: (f32, f32)) -> bool {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+                let PhysicalKey::Code(code) = event.physical_key else {
+                    return false;
+                };
+                match code {
+                    KeyCode::ArrowRight => self.step_forward(),
+                    KeyCode::ArrowLeft => self.step_backward(),
+                    KeyCode::Space => self.toggle_playing(),
+                    KeyCode::Home => self.set_frame(0),
+                    KeyCode::End => self.set_frame(usize::MAX),
+                    _ => return false,
+                }
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = (position.x as f32, position.y as f32);
+                if self.scrubbing {
+                    self.scrub(window_size.0);
+                }
+                self.scrubbing
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let on_timeline = self.cursor.1 >= window_size.1 - TIMELINE_HEIGHT;
+                self.scrubbing = *state == ElementState::Pressed && on_timeline;
+                if self.scrubbing {
+                    self.scrub(window_size.0);
+                }
+                on_timeline
+            }
+            _ => false,
+        }
+    }
+
+    fn scrub(&mut self, width: f32) {
+        self.playing = false;
+        let t = (self.cursor.0 / width).clamp(0.0, 1.0);
+        self.set_frame((t * self.frame_count() as f32) as usize);
+    }
+
+    // pushes the current frame followed by the timeline and stats overlay
+    pub fn draw(&self, backend: &mut impl RenderBackend) -> Result<(), MissingGlyphs> {
+        let mut missing = MissingGlyphs(vec![]);
+        if let Some(frame) = self.recording.frames.get(self.frame)
+            && let Err(err) = frame.submit(backend)
+        {
+            missing.merge(err);
+        }
+
+        let size = backend.size();
+        let (w, h) = (size.width as f32, size.height as f32);
+        let bar = Rect::new(0.0, h - TIMELINE_HEIGHT, w, TIMELINE_HEIGHT);
+        let progress = match self.frame_count() {
+            0 | 1 => 1.0,
+            n => self.frame as f32 / (n - 1) as f32,
+        };
+        let key = SortKey::new(OVERLAY_LAYER, 0);
+
+        backend.set_clip(None);
+        backend.push_quad(key, bar, [0.1, 0.1, 0.1]);
+        backend.push_quad(key, Rect { w: bar.w * progress, ..bar }, [0.2, 0.4, 0.8]);
+        let marker = (bar.w * progress - 2.0).clamp(0.0, (bar.w - 4.0).max(0.0));
+        backend.push_quad(key, Rect { x: marker, w: 4.0, ..bar }, [1.0, 1.0, 1.0]);
+
+        let stats = self.stats();
+        let line = format!(
+            "{} {}/{}  quads {}  text {} ({} glyphs)  clips {}  {} bytes",
+            if self.playing { "play" } else { "pause" },
+            self.frame + 1,
+            self.frame_count(),
+            stats.quads,
+            stats.texts,
+            stats.glyphs,
+            stats.clips,
+            stats.bytes,
+        );
+        if let Err(err) = backend.push_str(key, 8.0, 8.0, Some(16.0), [1.0, 1.0, 0.0], &line) {
+            missing.merge(err);
+        }
+        missing.into_result()
+    }
+}
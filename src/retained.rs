@@ -0,0 +1,217 @@
+use crate::Renderer;
+use crate::color::Color;
+use crate::font::MissingGlyphs;
+use crate::frame::Frame;
+use crate::rect::Rect;
+use crate::sort::SortKey;
+use crate::sprite::SpriteTexture;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(pub usize);
+
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    Quad { color: Color },
+    Sprite { texture: SpriteTexture, tint: Color },
+    // a single line with the default font, `rect.h` is its size
+    Text { color: Color, text: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub rect: Rect,
+    pub kind: NodeKind,
+    pub visible: bool,
+}
+
+// Retained mode drawing for guis where most of the screen stays the same from
+// frame to frame: nodes are added once, changed through their ids, and the
+// scene pushes them into a static layer of its own only in frames after
+// something changed, see `Renderer::set_static_layer`. Frames without changes
+// neither push nor upload anything. Nodes are drawn in the order they were
+// added, later ones on top.
+//
+//   let mut scene = RetainedScene::new(&mut renderer, 5);
+//   let button = scene.add_quad(Rect::new(10.0, 10.0, 80.0, 24.0), Color::BLUE);
+//   // every frame
+//   scene.set_position(button, [x, 10.0]);
+//   scene.draw(&mut frame)?;
+pub struct RetainedScene {
+    layer: u16,
+    nodes: Vec<Option<Node>>,
+    dirty: bool,
+}
+
+impl RetainedScene {
+    // a scene drawn into `layer`, which it makes static and which nothing else
+    // should push to
+    pub fn new(renderer: &mut Renderer, layer: u16) -> Self {
+        renderer.set_static_layer(layer, true);
+        Self {
+            layer,
+            nodes: vec![],
+            dirty: true,
+        }
+    }
+
+    pub fn layer(&self) -> u16 {
+        self.layer
+    }
+
+    pub fn add(&mut self, node: Node) -> NodeId {
+        self.dirty = true;
+        self.nodes.push(Some(node));
+        NodeId(self.nodes.len() - 1)
+    }
+
+    pub fn add_quad(&mut self, rect: Rect, color: impl Into<Color>) -> NodeId {
+        self.add(Node {
+            rect,
+            kind: NodeKind::Quad { color: color.into() },
+            visible: true,
+        })
+    }
+
+    pub fn add_sprite(&mut self, rect: Rect, texture: &SpriteTexture) -> NodeId {
+        self.add(Node {
+            rect,
+            kind: NodeKind::Sprite {
+                texture: texture.clone(),
+                tint: Color::WHITE,
+            },
+            visible: true,
+        })
+    }
+
+    pub fn add_text(&mut self, x: f32, y: f32, size: f32, color: impl Into<Color>, text: impl Into<String>) -> NodeId {
+        self.add(Node {
+            rect: Rect::new(x, y, 0.0, size),
+            kind: NodeKind::Text {
+                color: color.into(),
+                text: text.into(),
+            },
+            visible: true,
+        })
+    }
+
+    // None once removed
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0)?.as_ref()
+    }
+
+    // marks the scene changed whether the node is or not, the setters only
+    // when they change something
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        let node = self.nodes.get_mut(id.0)?.as_mut()?;
+        self.dirty = true;
+        Some(node)
+    }
+
+    // Ids aren't reused, so an old id of a removed node changes nothing.
+    pub fn remove(&mut self, id: NodeId) -> Option<Node> {
+        let node = self.nodes.get_mut(id.0)?.take()?;
+        self.dirty = true;
+        Some(node)
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.dirty = true;
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // moves the node's top left corner to `position`
+    pub fn set_position(&mut self, id: NodeId, [x, y]: [f32; 2]) {
+        self.update(id, |node| {
+            node.rect.x = x;
+            node.rect.y = y;
+        });
+    }
+
+    pub fn set_rect(&mut self, id: NodeId, rect: Rect) {
+        self.update(id, |node| node.rect = rect);
+    }
+
+    // the color of a quad or text, the tint of a sprite
+    pub fn set_color(&mut self, id: NodeId, color: impl Into<Color>) {
+        let color = color.into();
+        self.update(id, |node| match &mut node.kind {
+            NodeKind::Quad { color: c } | NodeKind::Text { color: c, .. } | NodeKind::Sprite { tint: c, .. } => *c = color,
+        });
+    }
+
+    // changes nothing for nodes that aren't text
+    pub fn set_text(&mut self, id: NodeId, text: &str) {
+        self.update(id, |node| {
+            if let NodeKind::Text { text: t, .. } = &mut node.kind {
+                t.clear();
+                t.push_str(text);
+            }
+        });
+    }
+
+    pub fn set_visible(&mut self, id: NodeId, visible: bool) {
+        self.update(id, |node| node.visible = visible);
+    }
+
+    // applies `change` and marks the scene dirty when it did change the node
+    fn update(&mut self, id: NodeId, change: impl FnOnce(&mut Node)) {
+        let Some(Some(node)) = self.nodes.get_mut(id.0) else {
+            return;
+        };
+        let (rect, visible) = (node.rect, node.visible);
+        let kind = node_state(&node.kind);
+        change(node);
+        if node.rect != rect || node.visible != visible || node_state(&node.kind) != kind {
+            self.dirty = true;
+        }
+    }
+
+    // Pushes the nodes when something changed since the last call, the layer
+    // keeps showing what was pushed before otherwise. Call it every frame.
+    pub fn draw(&mut self, frame: &mut Frame) -> Result<(), MissingGlyphs> {
+        if self.dirty {
+            frame.renderer.mark_static_layer_dirty(self.layer);
+            self.dirty = false;
+        }
+        let mut missing = MissingGlyphs(vec![]);
+        let layer = self.layer;
+        frame.static_layer(layer, |frame| {
+            for (i, node) in self.nodes.iter().enumerate() {
+                let Some(node) = node.as_ref().filter(|node| node.visible) else {
+                    continue;
+                };
+                let key = SortKey::new(layer, i as u32);
+                let rect = node.rect;
+                match &node.kind {
+                    NodeKind::Quad { color } => frame.quad_with_key(key, rect.x, rect.y, rect.w, rect.h, *color),
+                    NodeKind::Sprite { texture, tint } => {
+                        frame.sprites().push_with_key(key, rect, (0.0, 0.0, 1.0, 1.0), *tint, texture);
+                    }
+                    NodeKind::Text { color, text } => {
+                        if let Err(err) = frame.text_with_key(key, rect.x, rect.y, rect.h, *color, text) {
+                            missing.merge(err);
+                        }
+                    }
+                }
+            }
+        });
+        missing.into_result()
+    }
+}
+
+// what of a node's kind a change is compared by, textures by their identity
+fn node_state(kind: &NodeKind) -> (Color, Option<String>, Option<wgpu::Texture>) {
+    match kind {
+        NodeKind::Quad { color } => (*color, None, None),
+        NodeKind::Sprite { texture, tint } => (*tint, None, Some(texture.texture.clone())),
+        NodeKind::Text { color, text } => (*color, Some(text.clone()), None),
+    }
+}
@@ -0,0 +1,145 @@
+use crate::rect::Rect;
+
+// What pushing had to fix or drop, counted per batch until it's cleared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GeometryIssues {
+    // dropped for a NaN or infinite coordinate, which would poison the batch
+    pub non_finite: u32,
+    // had a negative width or height
+    pub normalized: u32,
+    // cut to the world bounds, or dropped for lying outside of them
+    pub clamped: u32,
+}
+
+impl GeometryIssues {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::ops::Add for GeometryIssues {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            non_finite: self.non_finite + other.non_finite,
+            normalized: self.normalized + other.normalized,
+            clamped: self.clamped + other.clamped,
+        }
+    }
+}
+
+// u0, v0, u1, v1 of a textured rect, untextured ones pass zeros
+pub(crate) type Uv = (f32, f32, f32, f32);
+
+// The rect and texture coordinates a pushed primitive is drawn with, None when
+// there's nothing left to draw. Flipped sides are normalized with the texture
+// staying mirrored, and `bounds` cuts the rect and its texture alike.
+pub(crate) fn sanitize(rect: Rect, uv: Uv, bounds: Option<Rect>, issues: &mut GeometryIssues) -> Option<(Rect, Uv)> {
+    let Rect { x, y, w, h } = rect;
+    if ![x, y, w, h, rect.right(), rect.bottom()].iter().all(|v| v.is_finite()) {
+        issues.non_finite += 1;
+        return None;
+    }
+
+    let (mut u0, mut v0, mut u1, mut v1) = uv;
+    if w < 0.0 || h < 0.0 {
+        issues.normalized += 1;
+        if w < 0.0 {
+            std::mem::swap(&mut u0, &mut u1);
+        }
+        if h < 0.0 {
+            std::mem::swap(&mut v0, &mut v1);
+        }
+    }
+    let rect = rect.normalized();
+    let Some(bounds) = bounds else {
+        return Some((rect, (u0, v0, u1, v1)));
+    };
+
+    let (x0, x1) = (rect.x.max(bounds.x), rect.right().min(bounds.right()));
+    let (y0, y1) = (rect.y.max(bounds.y), rect.bottom().min(bounds.bottom()));
+    if (x0, x1, y0, y1) == (rect.x, rect.right(), rect.y, rect.bottom()) {
+        return Some((rect, (u0, v0, u1, v1)));
+    }
+    issues.clamped += 1;
+    if x1 <= x0 || y1 <= y0 {
+        return None;
+    }
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let (tx0, tx1) = ((x0 - rect.x) / rect.w, (x1 - rect.x) / rect.w);
+    let (ty0, ty1) = ((y0 - rect.y) / rect.h, (y1 - rect.y) / rect.h);
+    Some((
+        Rect::new(x0, y0, x1 - x0, y1 - y0),
+        (lerp(u0, u1, tx0), lerp(v0, v1, ty0), lerp(u0, u1, tx1), lerp(v0, v1, ty1)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UV: Uv = (0.0, 0.0, 1.0, 1.0);
+
+    fn run(rect: Rect, bounds: Option<Rect>) -> (Option<(Rect, Uv)>, GeometryIssues) {
+        let mut issues = GeometryIssues::default();
+        (sanitize(rect, UV, bounds, &mut issues), issues)
+    }
+
+    #[test]
+    fn non_finite_rects_are_dropped() {
+        for rect in [
+            Rect::new(f32::NAN, 0.0, 10.0, 10.0),
+            Rect::new(0.0, 0.0, f32::NAN, 10.0),
+            Rect::new(0.0, f32::INFINITY, 10.0, 10.0),
+            Rect::new(0.0, 0.0, 10.0, f32::NEG_INFINITY),
+            // finite, but its right side isn't
+            Rect::new(f32::MAX, 0.0, f32::MAX, 10.0),
+        ] {
+            let (out, issues) = run(rect, None);
+            assert_eq!(out, None, "{rect:?}");
+            assert_eq!(issues, GeometryIssues { non_finite: 1, ..Default::default() });
+        }
+    }
+
+    #[test]
+    fn negative_sizes_flip_and_mirror_the_texture() {
+        let (out, issues) = run(Rect::new(10.0, 10.0, -4.0, 6.0), None);
+        assert_eq!(out, Some((Rect::new(6.0, 10.0, 4.0, 6.0), (1.0, 0.0, 0.0, 1.0))));
+        assert_eq!(issues, GeometryIssues { normalized: 1, ..Default::default() });
+
+        let (out, issues) = run(Rect::new(10.0, 10.0, -4.0, -6.0), None);
+        assert_eq!(out, Some((Rect::new(6.0, 4.0, 4.0, 6.0), (1.0, 1.0, 0.0, 0.0))));
+        assert_eq!(issues.normalized, 1);
+    }
+
+    #[test]
+    fn rects_inside_the_bounds_are_untouched() {
+        let rect = Rect::new(1.0, 2.0, 3.0, 4.0);
+        let (out, issues) = run(rect, Some(Rect::new(0.0, 0.0, 10.0, 10.0)));
+        assert_eq!(out, Some((rect, UV)));
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn uvs_are_cut_with_the_rect() {
+        // the right half and the bottom quarter are outside
+        let (out, issues) = run(Rect::new(0.0, 0.0, 20.0, 40.0), Some(Rect::new(-5.0, 0.0, 15.0, 30.0)));
+        assert_eq!(out, Some((Rect::new(0.0, 0.0, 10.0, 30.0), (0.0, 0.0, 0.5, 0.75))));
+        assert_eq!(issues, GeometryIssues { clamped: 1, ..Default::default() });
+
+        // flipped first, so the cut keeps the mirrored texture's left half
+        let (out, _) = run(Rect::new(20.0, 0.0, -20.0, 10.0), Some(Rect::new(0.0, 0.0, 10.0, 10.0)));
+        assert_eq!(out, Some((Rect::new(0.0, 0.0, 10.0, 10.0), (1.0, 0.0, 0.5, 1.0))));
+    }
+
+    #[test]
+    fn rects_outside_the_bounds_are_dropped() {
+        let (out, issues) = run(Rect::new(20.0, 0.0, 5.0, 5.0), Some(Rect::new(0.0, 0.0, 10.0, 10.0)));
+        assert_eq!(out, None);
+        assert_eq!(issues, GeometryIssues { clamped: 1, ..Default::default() });
+        // touching the edge leaves nothing to draw either
+        let (out, _) = run(Rect::new(10.0, 0.0, 5.0, 5.0), Some(Rect::new(0.0, 0.0, 10.0, 10.0)));
+        assert_eq!(out, None);
+    }
+}
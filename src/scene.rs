@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::font::MissingGlyphs;
+use crate::frame::Frame;
+use crate::rect::Rect;
+use crate::sort::SortKey;
+use crate::sprite::{Insets, SpriteTexture};
+
+// A scene written by hand or by a design tool, loaded at runtime so a layout
+// can change without rebuilding the app:
+//
+//   {
+//     "version": 1,
+//     "camera": {"position": [0, 0], "zoom": 1},
+//     "entities": [
+//       {"type": "quad", "rect": {"x": 0, "y": 0, "w": 100, "h": 40}, "color": [0.2, 0.2, 0.3]},
+//       {"type": "text", "x": 8, "y": 8, "size": 24, "color": [1, 1, 1], "text": "Play"},
+//       {"type": "sprite", "rect": {...}, "image": "button.png", "nine_slice": {"left": 4, ...}}
+//     ]
+//   }
+//
+// `camera`, `key`, `tint`, `nine_slice` and `nearest` may be left out. Image
+// paths are relative to the scene file. wrs has no lights, entity types it
+// doesn't know, lights included, are skipped like draw list commands are.
+pub const SCENE_VERSION: u16 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u16,
+    #[serde(default)]
+    pub camera: Option<SceneCamera>,
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SceneCamera {
+    pub position: [f32; 2],
+    #[serde(default = "one")]
+    pub zoom: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Entity {
+    Quad {
+        rect: Rect,
+        color: [f32; 3],
+        #[serde(default)]
+        key: SortKey,
+    },
+    // a single line with the default font
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        color: [f32; 3],
+        text: String,
+        #[serde(default)]
+        key: SortKey,
+    },
+    Sprite {
+        rect: Rect,
+        image: PathBuf,
+        #[serde(default = "white")]
+        tint: [f32; 3],
+        #[serde(default)]
+        nine_slice: Option<Insets>,
+        // nearest sampling for pixel art
+        #[serde(default)]
+        nearest: bool,
+        #[serde(default)]
+        key: SortKey,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+fn white() -> [f32; 3] {
+    [1.0; 3]
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u16),
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Json(err) => write!(f, "{err}"),
+            Self::UnsupportedVersion(v) => write!(
+                f,
+                "scene version {v} is newer than the supported version {SCENE_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl Scene {
+    pub fn from_json(s: &str) -> Result<Self, SceneError> {
+        let scene: Self = serde_json::from_str(s).map_err(SceneError::Json)?;
+        if scene.version > SCENE_VERSION {
+            return Err(SceneError::UnsupportedVersion(scene.version));
+        }
+        Ok(scene)
+    }
+
+    // the scene in the file at `path`, its image paths made relative to the
+    // working directory
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let mut scene = Self::from_json(&std::fs::read_to_string(path).map_err(SceneError::Io)?)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for entity in &mut scene.entities {
+            if let Entity::Sprite { image, .. } = entity {
+                *image = dir.join(&*image);
+            }
+        }
+        Ok(scene)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+// A scene file that's loaded again whenever it changes on disk, along with the
+// textures of its sprites. Call `reload_if_changed` once a frame, then `draw`.
+pub struct LiveScene {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    scene: Scene,
+    // None for images that failed to load, so they aren't retried every frame
+    textures: HashMap<PathBuf, Option<SpriteTexture>>,
+}
+
+impl LiveScene {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, SceneError> {
+        let path = path.into();
+        let modified = modified(&path);
+        let scene = Scene::from_path(&path)?;
+        Ok(Self {
+            path,
+            modified,
+            scene,
+            textures: HashMap::new(),
+        })
+    }
+
+    // None while the file is unchanged. A file that fails to load keeps the
+    // last scene that did, so a half saved edit doesn't blank the window.
+    pub fn reload_if_changed(&mut self) -> Option<Result<(), SceneError>> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Scene::from_path(&self.path).map(|scene| {
+            self.scene = scene;
+            // edited images load again on the next draw
+            self.textures.clear();
+        }))
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // images sprites refer to that couldn't be loaded, their sprites are skipped
+    pub fn missing_images(&self) -> impl Iterator<Item = &Path> {
+        self.textures
+            .iter()
+            .filter(|(_, texture)| texture.is_none())
+            .map(|(path, _)| path.as_path())
+    }
+
+    // Pushes the scene into `frame` and moves the camera to the scene's, when
+    // it has one.
+    pub fn draw(&mut self, frame: &mut Frame) -> Result<(), MissingGlyphs> {
+        if let Some(camera) = self.scene.camera {
+            frame.set_camera_position(camera.position);
+            frame.set_camera_zoom(camera.zoom);
+        }
+        let mut missing = MissingGlyphs(vec![]);
+        for entity in &self.scene.entities {
+            match entity {
+                Entity::Quad { rect, color, key } => {
+                    frame.quad_with_key(*key, rect.x, rect.y, rect.w, rect.h, *color);
+                }
+                Entity::Text {
+                    x,
+                    y,
+                    size,
+                    color,
+                    text,
+                    key,
+                } => {
+                    if let Err(err) = frame.text_with_key(*key, *x, *y, *size, *color, text) {
+                        missing.merge(err);
+                    }
+                }
+                Entity::Sprite {
+                    rect,
+                    image,
+                    tint,
+                    nine_slice,
+                    nearest,
+                    key,
+                } => {
+                    let texture = self
+                        .textures
+                        .entry(image.clone())
+                        .or_insert_with(|| load_texture(frame, image, *nearest));
+                    let Some(texture) = texture else {
+                        continue;
+                    };
+                    match nine_slice {
+                        Some(insets) => frame
+                            .sprites()
+                            .push_nine_slice_with_key(*key, *rect, *tint, texture, *insets),
+                        None => frame
+                            .sprites()
+                            .push_with_key(*key, *rect, (0.0, 0.0, 1.0, 1.0), *tint, texture),
+                    }
+                }
+                Entity::Unknown => {}
+            }
+        }
+        missing.into_result()
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn load_texture(frame: &Frame, path: &Path, nearest: bool) -> Option<SpriteTexture> {
+    let image = image::open(path).ok()?.to_rgba8();
+    let filter = if nearest {
+        wgpu::FilterMode::Nearest
+    } else {
+        wgpu::FilterMode::Linear
+    };
+    Some(frame.renderer.create_sprite_texture(&image, filter))
+}
@@ -0,0 +1,360 @@
+use std::collections::{HashMap, HashSet};
+
+// Bumped whenever a bundled include changes in a way that breaks shaders using
+// it. Every include is reachable both as `wrs/name.wgsl`, which follows the
+// crate, and as `wrs/v1/name.wgsl`, which keeps meaning this version.
+pub const SHADER_VERSION: u32 = 1;
+
+// name, source of the includes bundled with the crate
+const LIBRARY: &[(&str, &str)] = &[
+    // the camera uniform at group 0 binding 0, which `Camera` binds
+    ("globals.wgsl", include_str!("shaders/globals.wgsl")),
+    // `to_srgb` for `ColorSpace`, srgb transfer functions
+    ("color.wgsl", include_str!("shaders/color.wgsl")),
+    // `outside_clip` and `clip_coverage` for the clips of `set_clip` and `set_clip_shape`
+    ("clip.wgsl", include_str!("shaders/clip.wgsl")),
+    // `OitOut` and `oit_out` for a `fs_oit` entry point
+    ("oit.wgsl", include_str!("shaders/oit.wgsl")),
+    // signed distances of simple shapes and their antialiased coverage
+    ("sdf.wgsl", include_str!("shaders/sdf.wgsl")),
+];
+
+#[derive(Debug)]
+pub enum ShaderError {
+    MissingInclude { file: String, line: usize, path: String },
+    IncludeCycle { file: String, line: usize, path: String },
+    // an #else or #endif without an #ifdef, or an #ifdef without an #endif
+    UnbalancedConditional { file: String, line: usize },
+    UnknownDirective { file: String, line: usize, directive: String },
+    // the expanded source isn't valid WGSL, `message` is wgpu's
+    Invalid { label: String, message: String },
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingInclude { file, line, path } => {
+                write!(f, "{file}:{line}: no shader include \"{path}\"")
+            }
+            Self::IncludeCycle { file, line, path } => {
+                write!(f, "{file}:{line}: \"{path}\" includes itself")
+            }
+            Self::UnbalancedConditional { file, line } => {
+                write!(f, "{file}:{line}: unbalanced #ifdef / #else / #endif")
+            }
+            Self::UnknownDirective { file, line, directive } => {
+                write!(f, "{file}:{line}: unknown directive #{directive}")
+            }
+            Self::Invalid { label, message } => write!(f, "{label}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+// Expands the directives of a WGSL source before it's handed to wgpu, so shared
+// code is written once and custom materials can pull in what the crate's own
+// shaders use:
+//
+//   #include "wrs/globals.wgsl"   pastes an include, each one at most once
+//   #define NAME value            replaces the identifier NAME from here on
+//   #undef NAME
+//   #ifdef NAME / #ifndef NAME / #else / #endif
+//
+// Directives take a line of their own. `WRS_SHADER_VERSION` is predefined to
+// `SHADER_VERSION`.
+#[derive(Debug, Clone)]
+pub struct ShaderPreprocessor {
+    includes: HashMap<String, String>,
+    defines: HashMap<String, String>,
+}
+
+impl Default for ShaderPreprocessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// an #ifdef being expanded
+struct Conditional {
+    parent_active: bool,
+    taken: bool,
+    in_else: bool,
+}
+
+// the state of one `process` call, shared by the files it includes
+struct Expansion {
+    out: String,
+    defines: HashMap<String, String>,
+    included: HashSet<String>,
+    including: Vec<String>,
+}
+
+impl ShaderPreprocessor {
+    pub fn new() -> Self {
+        let mut includes = HashMap::new();
+        for (name, source) in LIBRARY {
+            includes.insert(format!("wrs/{name}"), source.to_string());
+        }
+        let mut defines = HashMap::new();
+        defines.insert("WRS_SHADER_VERSION".to_string(), SHADER_VERSION.to_string());
+        Self { includes, defines }
+    }
+
+    // makes `source` includable as `path`, replacing what was there
+    pub fn add_include(&mut self, path: &str, source: impl Into<String>) {
+        self.includes.insert(unversioned(path), source.into());
+    }
+
+    // a define every processed source starts with, `#undef` only lasts for
+    // the one source
+    pub fn define(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.defines.insert(name.into(), value.into());
+    }
+
+    pub fn process(&self, source: &str) -> Result<String, ShaderError> {
+        let mut expansion = Expansion {
+            out: String::new(),
+            defines: self.defines.clone(),
+            included: HashSet::new(),
+            including: vec![],
+        };
+        self.expand("<source>", source, &mut expansion)?;
+        Ok(expansion.out)
+    }
+
+    pub fn create_module(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+    ) -> Result<wgpu::ShaderModule, ShaderError> {
+        let source = self.process(source)?;
+        // user shaders can be invalid, which would otherwise end up in the
+        // device's uncaptured error handler
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(err) => Err(ShaderError::Invalid {
+                label: label.to_string(),
+                message: err.to_string(),
+            }),
+            None => Ok(module),
+        }
+    }
+
+    fn expand(&self, file: &str, source: &str, ex: &mut Expansion) -> Result<(), ShaderError> {
+        let mut conditionals: Vec<Conditional> = vec![];
+        let unbalanced = |line| ShaderError::UnbalancedConditional {
+            file: file.to_string(),
+            line,
+        };
+        for (i, text) in source.lines().enumerate() {
+            let line = i + 1;
+            let active = conditionals
+                .last()
+                .is_none_or(|c| c.parent_active && c.taken != c.in_else);
+            let Some(directive) = text.trim_start().strip_prefix('#') else {
+                if active {
+                    substitute(text, &ex.defines, &mut ex.out);
+                    ex.out.push('\n');
+                }
+                continue;
+            };
+            let (name, arg) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+            let arg = arg.trim();
+            match name {
+                "ifdef" | "ifndef" => conditionals.push(Conditional {
+                    parent_active: active,
+                    taken: ex.defines.contains_key(arg) == (name == "ifdef"),
+                    in_else: false,
+                }),
+                "else" => match conditionals.last_mut() {
+                    Some(c) if !c.in_else => c.in_else = true,
+                    _ => return Err(unbalanced(line)),
+                },
+                "endif" => {
+                    conditionals.pop().ok_or_else(|| unbalanced(line))?;
+                }
+                _ if !active => {}
+                "define" => {
+                    let (key, value) = arg.split_once(char::is_whitespace).unwrap_or((arg, ""));
+                    ex.defines.insert(key.to_string(), value.trim().to_string());
+                }
+                "undef" => {
+                    ex.defines.remove(arg);
+                }
+                "include" => {
+                    let path = unversioned(arg.trim_matches('"'));
+                    if ex.including.contains(&path) {
+                        return Err(ShaderError::IncludeCycle {
+                            file: file.to_string(),
+                            line,
+                            path,
+                        });
+                    }
+                    if !ex.included.insert(path.clone()) {
+                        continue;
+                    }
+                    let Some(included) = self.includes.get(&path) else {
+                        return Err(ShaderError::MissingInclude {
+                            file: file.to_string(),
+                            line,
+                            path,
+                        });
+                    };
+                    ex.including.push(path.clone());
+                    self.expand(&path, included, ex)?;
+                    ex.including.pop();
+                }
+                _ => {
+                    return Err(ShaderError::UnknownDirective {
+                        file: file.to_string(),
+                        line,
+                        directive: name.to_string(),
+                    });
+                }
+            }
+        }
+        if !conditionals.is_empty() {
+            return Err(unbalanced(source.lines().count()));
+        }
+        Ok(())
+    }
+}
+
+// `wrs/v1/name.wgsl` is `wrs/name.wgsl`, so mixing both includes it once
+fn unversioned(path: &str) -> String {
+    match path.strip_prefix(&format!("wrs/v{SHADER_VERSION}/")) {
+        Some(name) => format!("wrs/{name}"),
+        None => path.to_string(),
+    }
+}
+
+// `text` with every identifier that's defined replaced by its value
+fn substitute(text: &str, defines: &HashMap<String, String>, out: &mut String) {
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        let prefix = &rest[..start];
+        let end = rest[start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .map_or(rest.len(), |n| start + n);
+        let word = &rest[start..end];
+        out.push_str(prefix);
+        // struct members and suffixes of numbers like the f of 1f stay
+        let member = prefix.ends_with(|c: char| c.is_ascii_digit() || c == '.');
+        match defines.get(word) {
+            Some(value) if !member => out.push_str(value),
+            _ => out.push_str(word),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(source: &str) -> Vec<&str> {
+        source.lines().filter(|line| !line.is_empty()).collect()
+    }
+
+    #[test]
+    fn nested_includes_expand_once() {
+        let mut pre = ShaderPreprocessor::new();
+        pre.add_include("a.wgsl", "#include \"b.wgsl\"\n#include \"c.wgsl\"\nfn a() {}");
+        pre.add_include("b.wgsl", "#include \"c.wgsl\"\nfn b() {}");
+        pre.add_include("c.wgsl", "fn c() {}");
+        let out = pre.process("#include \"a.wgsl\"\n#include \"c.wgsl\"\nfn main() {}").unwrap();
+        assert_eq!(lines(&out), ["fn c() {}", "fn b() {}", "fn a() {}", "fn main() {}"]);
+    }
+
+    #[test]
+    fn versioned_and_plain_paths_are_one_include() {
+        let versioned = format!("#include \"wrs/v{SHADER_VERSION}/sdf.wgsl\"");
+        let source = format!("#include \"wrs/sdf.wgsl\"\n{versioned}");
+        let out = ShaderPreprocessor::new().process(&source).unwrap();
+        assert_eq!(out, ShaderPreprocessor::new().process("#include \"wrs/sdf.wgsl\"").unwrap());
+    }
+
+    #[test]
+    fn missing_include() {
+        let mut pre = ShaderPreprocessor::new();
+        pre.add_include("a.wgsl", "fn a() {}\n#include \"gone.wgsl\"");
+        match pre.process("\n#include \"a.wgsl\"") {
+            Err(ShaderError::MissingInclude { file, line, path }) => {
+                assert_eq!((file.as_str(), line, path.as_str()), ("a.wgsl", 2, "gone.wgsl"));
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn include_cycle() {
+        let mut pre = ShaderPreprocessor::new();
+        pre.add_include("a.wgsl", "#include \"b.wgsl\"");
+        pre.add_include("b.wgsl", "#include \"a.wgsl\"");
+        match pre.process("#include \"a.wgsl\"") {
+            Err(ShaderError::IncludeCycle { file, path, .. }) => {
+                assert_eq!((file.as_str(), path.as_str()), ("b.wgsl", "a.wgsl"));
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn defines_replace_whole_identifiers() {
+        let mut pre = ShaderPreprocessor::new();
+        pre.define("SIZE", "4.0");
+        let source = "\
+let a = SIZE * 2.0;
+let b = SIZES + MY_SIZE;
+let c = v.SIZE;
+#define SIZE 8.0
+#define HALF SIZE / 2.0
+let d = SIZE;
+#undef SIZE
+let e = SIZE + HALF;
+let v = WRS_SHADER_VERSION;";
+        let out = pre.process(source).unwrap();
+        assert_eq!(lines(&out), [
+            "let a = 4.0 * 2.0;",
+            "let b = SIZES + MY_SIZE;",
+            "let c = v.SIZE;",
+            "let d = 8.0;",
+            // values aren't expanded again
+            "let e = SIZE + SIZE / 2.0;",
+            &format!("let v = {SHADER_VERSION};"),
+        ]);
+        // the #undef only lasted for that source
+        assert_eq!(lines(&pre.process("SIZE").unwrap()), ["4.0"]);
+    }
+
+    #[test]
+    fn conditionals() {
+        let mut pre = ShaderPreprocessor::new();
+        pre.define("ON", "");
+        let source = "\
+#ifdef ON
+on
+#ifndef ON
+nested
+#else
+nested else
+#endif
+#else
+off
+#endif
+#ifdef OFF
+#unknown directives in skipped code are fine
+#endif";
+        assert_eq!(lines(&pre.process(source).unwrap()), ["on", "nested else"]);
+        assert!(matches!(pre.process("#ifdef ON\n"), Err(ShaderError::UnbalancedConditional { .. })));
+        assert!(matches!(pre.process("#endif"), Err(ShaderError::UnbalancedConditional { line: 1, .. })));
+        assert!(matches!(pre.process("#pragma x"), Err(ShaderError::UnknownDirective { .. })));
+    }
+}
@@ -0,0 +1,18 @@
+#include "wrs/sdf.wgsl"
+
+// clip rects are min x, min y, max x, max y in world units, see `set_clip`
+fn outside_clip(world_pos: vec2<f32>, clip: vec4<f32>) -> bool {
+    return any(world_pos < clip.xy) || any(world_pos >= clip.zw);
+}
+
+// How much of the pixel at `world_pos` a clip rect with corners rounded by
+// `radius` covers, see `ClipShape`. Without a radius it's `outside_clip`'s
+// hard edge.
+fn clip_coverage(world_pos: vec2<f32>, clip: vec4<f32>, radius: f32) -> f32 {
+    let half_size = (clip.zw - clip.xy) * 0.5;
+    let r = min(radius, min(half_size.x, half_size.y));
+    // derivatives need every pixel of the quad to get here
+    let rounded = sdf_coverage(sd_rounded_box(world_pos - clip.xy - half_size, half_size, r));
+    let hard = select(1.0, 0.0, outside_clip(world_pos, clip));
+    return select(rounded, hard, radius <= 0.0);
+}
@@ -0,0 +1,23 @@
+// linear display p3 to linear srgb, see `ColorSpace`
+const P3_TO_SRGB = mat3x3<f32>(
+    vec3<f32>(1.2249401, -0.0420569, -0.0196376),
+    vec3<f32>(-0.2249404, 1.0420571, -0.0786361),
+    vec3<f32>(0.0, 0.0, 1.0982735),
+);
+
+// `color_space` is `ColorSpace::id`
+fn to_srgb(color: vec3<f32>, color_space: u32) -> vec3<f32> {
+    if color_space == 1u {
+        return P3_TO_SRGB * color;
+    }
+    return color;
+}
+
+// the srgb transfer functions, for targets without an srgb view
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
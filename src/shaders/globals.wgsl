@@ -0,0 +1,7 @@
+// the camera's view projection, bound by every wrs pipeline as group 0
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
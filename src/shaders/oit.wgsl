@@ -0,0 +1,15 @@
+// targets of the `fs_oit` entry points, see `OitCompositor`
+struct OitOut {
+    @location(0) accum: vec4<f32>,
+    @location(1) reveal: f32,
+};
+
+// weighted blended order independent transparency of a straight alpha color
+// at depth `z`
+fn oit_out(color: vec4<f32>, z: f32) -> OitOut {
+    let w = clamp(pow(min(1.0, color.a * 10.0) + 0.01, 3.0) * 1e8 * pow(1.0 - z * 0.9, 3.0), 0.01, 3000.0);
+    var out: OitOut;
+    out.accum = vec4<f32>(color.rgb * color.a, color.a) * w;
+    out.reveal = color.a;
+    return out;
+}
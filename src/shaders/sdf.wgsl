@@ -0,0 +1,30 @@
+// Signed distances of shapes centered on the origin, negative inside, for
+// materials that draw their shape into a quad.
+fn sd_circle(p: vec2<f32>, radius: f32) -> f32 {
+    return length(p) - radius;
+}
+
+fn sd_box(p: vec2<f32>, half_size: vec2<f32>) -> f32 {
+    let d = abs(p) - half_size;
+    return length(max(d, vec2<f32>(0.0))) + min(max(d.x, d.y), 0.0);
+}
+
+fn sd_rounded_box(p: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    return sd_box(p, half_size - vec2<f32>(radius)) - radius;
+}
+
+// pointy top, `radius` being the distance from the center to a corner
+fn sd_hexagon(p: vec2<f32>, radius: f32) -> f32 {
+    let k = vec3<f32>(-0.866025404, 0.5, 0.577350269);
+    var q = abs(p.yx);
+    q -= 2.0 * min(dot(k.xy, q), 0.0) * k.xy;
+    let r = radius * 0.866025404;
+    q -= vec2<f32>(clamp(q.x, -k.z * r, k.z * r), r);
+    return length(q) * sign(q.y);
+}
+
+// antialiased coverage of a distance, about one pixel wide
+fn sdf_coverage(d: f32) -> f32 {
+    let aa = max(fwidth(d), 0.0001);
+    return clamp(0.5 - d / aa, 0.0, 1.0);
+}
@@ -0,0 +1,269 @@
+use image::RgbaImage;
+
+use crate::backend::RenderBackend;
+use crate::color::{Color, linear_to_srgb, srgb_to_linear};
+use crate::font::{CpuGlyphAtlas, GlyphSource, MissingGlyphs, TabStops, layout};
+use crate::rect::{ClipShape, Rect};
+use crate::sanitize::{GeometryIssues, sanitize};
+use crate::sort::SortKey;
+
+// Draws on the cpu into an image, for tests, thumbnails and machines without a
+// usable adapter. It follows the rules of the wgpu renderer so both produce the
+// same pixels: a pixel is covered when its center is inside a primitive (and
+// inside its clip rect), primitives are drawn layer by layer in sort key order
+// with a layer's quads before its text, blended by their alpha in linear space, and colors are
+// written srgb encoded like the renderer's srgb surface view. Every layer is
+// drawn `Compositing::Sorted`.
+pub struct SoftwareRenderer {
+    size: winit::dpi::PhysicalSize<u32>,
+    target: RgbaImage,
+    atlas: CpuGlyphAtlas,
+    tab_stops: TabStops,
+    clip: Rect,
+    world_bounds: Option<Rect>,
+    issues: GeometryIssues,
+    quads: Vec<Primitive>,
+    glyphs: Vec<Primitive>,
+}
+
+struct Primitive {
+    key: SortKey,
+    rect: Rect,
+    // only used by glyphs
+    uv: (f32, f32, f32, f32),
+    color: Color,
+    clip: Rect,
+}
+
+impl SoftwareRenderer {
+    pub fn new(size: winit::dpi::PhysicalSize<u32>, atlas: CpuGlyphAtlas) -> Self {
+        Self {
+            size,
+            target: RgbaImage::new(size.width, size.height),
+            atlas,
+            tab_stops: TabStops::default(),
+            clip: Rect::EVERYTHING,
+            world_bounds: None,
+            issues: GeometryIssues::default(),
+            quads: vec![],
+            glyphs: vec![],
+        }
+    }
+
+    // the last rendered frame
+    pub fn image(&self) -> &RgbaImage {
+        &self.target
+    }
+
+    pub fn atlas(&self) -> &CpuGlyphAtlas {
+        &self.atlas
+    }
+
+    pub fn set_tab_stops(&mut self, tab_stops: TabStops) {
+        self.tab_stops = tab_stops;
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.target = RgbaImage::new(new_size.width, new_size.height);
+    }
+
+    // same as `QuadRenderer::set_world_bounds`
+    pub fn set_world_bounds(&mut self, bounds: Option<Rect>) {
+        self.world_bounds = bounds;
+    }
+
+    // what pushing fixed or dropped since `begin_frame`
+    pub fn issues(&self) -> GeometryIssues {
+        self.issues
+    }
+
+    // drops everything pushed for the previous frame
+    pub fn begin_frame(&mut self) {
+        self.issues = GeometryIssues::default();
+        self.quads.clear();
+        self.glyphs.clear();
+    }
+
+    pub fn render(&mut self) {
+        self.target.fill(0);
+        let quads = std::mem::take(&mut self.quads);
+        let glyphs = std::mem::take(&mut self.glyphs);
+        let mut order: Vec<(&Primitive, bool)> =
+            quads.iter().map(|p| (p, false)).chain(glyphs.iter().map(|p| (p, true))).collect();
+        order.sort_by_key(|&(p, textured)| (p.key.layer(), textured, p.key));
+        for (primitive, textured) in order {
+            self.fill(primitive, textured);
+        }
+        self.quads = quads;
+        self.glyphs = glyphs;
+    }
+
+    fn fill(&mut self, primitive: &Primitive, textured: bool) {
+        let Rect { x, y, w, h } = primitive.rect;
+        let (x0, x1) = (pixel_start(x, self.size.width), pixel_start(x + w, self.size.width));
+        let (y0, y1) = (pixel_start(y, self.size.height), pixel_start(y + h, self.size.height));
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let center = (px as f32 + 0.5, py as f32 + 0.5);
+                if !primitive.clip.contains(center.0, center.1) {
+                    continue;
+                }
+                let (rgb, alpha) = if textured {
+                    let (u0, v0, u1, v1) = primitive.uv;
+                    let u = u0 + (center.0 - x) / w * (u1 - u0);
+                    let v = v0 + (center.1 - y) / h * (v1 - v0);
+                    let texel = sample(&self.atlas.image, u, v);
+                    if texel[3] < 0.001 {
+                        continue;
+                    }
+                    let c = primitive.color;
+                    ([c.r * texel[0], c.g * texel[1], c.b * texel[2]], texel[3] * c.a)
+                } else {
+                    (primitive.color.to_rgb(), primitive.color.a)
+                };
+                // `BlendState::ALPHA_BLENDING` over what's there
+                let under = self.target.get_pixel(px, py).0;
+                let blend = |src: f32, dst: u8| {
+                    let dst = srgb_to_linear(dst as f32 / 255.0);
+                    let c = src.clamp(0.0, 1.0) * alpha + dst * (1.0 - alpha);
+                    (linear_to_srgb(c) * 255.0).round() as u8
+                };
+                let a = alpha + under[3] as f32 / 255.0 * (1.0 - alpha);
+                self.target.put_pixel(
+                    px,
+                    py,
+                    image::Rgba([
+                        blend(rgb[0], under[0]),
+                        blend(rgb[1], under[1]),
+                        blend(rgb[2], under[2]),
+                        (a * 255.0).round() as u8,
+                    ]),
+                );
+            }
+        }
+    }
+}
+
+// First pixel whose center is at or right of `edge`. The edge is snapped to
+// the 1/256 pixel grid gpus rasterize with first.
+fn pixel_start(edge: f32, limit: u32) -> u32 {
+    let edge = (edge * 256.0).round() / 256.0;
+    (edge - 0.5).ceil().clamp(0.0, limit as f32) as u32
+}
+
+// bilinear, clamped to the edge, with the srgb texels decoded like an
+// Rgba8UnormSrgb texture would be
+fn sample(image: &RgbaImage, u: f32, v: f32) -> [f32; 4] {
+    let (w, h) = image.dimensions();
+    let x = u * w as f32 - 0.5;
+    let y = v * h as f32 - 0.5;
+    let (fx, fy) = (x - x.floor(), y - y.floor());
+    let texel = |tx: f32, ty: f32| {
+        let tx = (tx as i64).clamp(0, w as i64 - 1) as u32;
+        let ty = (ty as i64).clamp(0, h as i64 - 1) as u32;
+        let p = image.get_pixel(tx, ty).0;
+        [
+            srgb_to_linear(p[0] as f32 / 255.0),
+            srgb_to_linear(p[1] as f32 / 255.0),
+            srgb_to_linear(p[2] as f32 / 255.0),
+            p[3] as f32 / 255.0,
+        ]
+    };
+    let (x, y) = (x.floor(), y.floor());
+    let (a, b) = (texel(x, y), texel(x + 1.0, y));
+    let (c, d) = (texel(x, y + 1.0), texel(x + 1.0, y + 1.0));
+    let mut out = [0.0; 4];
+    for i in 0..4 {
+        let top = a[i] + (b[i] - a[i]) * fx;
+        let bottom = c[i] + (d[i] - c[i]) * fx;
+        out[i] = top + (bottom - top) * fy;
+    }
+    out
+}
+
+impl RenderBackend for SoftwareRenderer {
+    fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip.unwrap_or(Rect::EVERYTHING);
+    }
+
+    fn clip(&self) -> Option<ClipShape> {
+        Some(self.clip).filter(|clip| *clip != Rect::EVERYTHING).map(ClipShape::Rect)
+    }
+
+    fn set_clip_shape(&mut self, clip: Option<ClipShape>) {
+        self.clip = match clip {
+            None => Rect::EVERYTHING,
+            Some(ClipShape::Rect(rect)) => rect,
+            Some(shape) => {
+                let ([left, top, right, bottom], _) = shape.to_bounds();
+                Rect::new(left, top, right - left, bottom - top)
+            }
+        };
+    }
+
+    fn push_quad(&mut self, key: SortKey, rect: Rect, color: impl Into<Color>) {
+        let Some((rect, uv)) = sanitize(rect, (0.0, 0.0, 0.0, 0.0), self.world_bounds, &mut self.issues) else {
+            return;
+        };
+        self.quads.push(Primitive {
+            key,
+            rect,
+            uv,
+            color: color.into(),
+            clip: self.clip,
+        });
+    }
+
+    fn push_str(
+        &mut self,
+        key: SortKey,
+        x: f32,
+        y: f32,
+        size: Option<f32>,
+        color: impl Into<Color>,
+        text: &str,
+    ) -> Result<(), MissingGlyphs> {
+        let color = color.into();
+        let size = size.unwrap_or(self.atlas.font_size());
+        let scale = size / self.atlas.font_size();
+        let mut missing = MissingGlyphs(vec![]);
+        for placed in layout::layout_line(text, &self.tab_stops, &self.atlas) {
+            if self.atlas.glyph(placed.c).is_none() {
+                missing.merge(MissingGlyphs(vec![placed.c]));
+            }
+            let Some(glyph) = self.atlas.glyph_or_fallback(placed.c) else {
+                continue;
+            };
+            // same arithmetic as `FontRenderer`, so edges round the same way
+            let (pen_x, pen_y) = (x + placed.x * scale, y + placed.y * scale);
+            let rect = Rect::new(
+                pen_x + glyph.offset.0 * scale,
+                pen_y + glyph.offset.1 * scale,
+                glyph.size.0 * scale,
+                glyph.size.1 * scale,
+            );
+            let Some((rect, uv)) = sanitize(rect, glyph.uv, self.world_bounds, &mut self.issues) else {
+                continue;
+            };
+            self.glyphs.push(Primitive {
+                key,
+                rect,
+                uv,
+                color: if glyph.color { Color::WHITE.with_alpha(color.a) } else { color },
+                clip: self.clip,
+            });
+        }
+        missing.into_result()
+    }
+
+    fn measure_str(&self, size: Option<f32>, text: &str) -> (f32, f32) {
+        let scale = size.map_or(1.0, |size| size / self.atlas.font_size());
+        let width = layout::line_width(text, &self.tab_stops, &self.atlas);
+        (width * scale, self.atlas.line_height() * scale)
+    }
+}
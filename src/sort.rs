@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+// A 64-bit draw order key attached to every pushed primitive.
+//
+// Bit layout, most significant first:
+//   63..48  layer      (user supplied)
+//   47..32  texture id (filled in by the renderer the primitive is pushed to)
+//   31..0   user bits  (user supplied, free for e.g. depth or material ordering)
+//
+// Sorting contract: within one renderer's batch, primitives are drawn in
+// ascending key order, and primitives with equal keys keep their push order.
+// Across renderers the frame is drawn layer by layer, see `DrawBatch`: a quad
+// on layer 2 is drawn over text on layer 1. Within a layer tilemaps come first,
+// then quads, sprites, gpu particles and text. Tilemaps take their layer from
+// `TilemapRenderer::set_layer`, gpu particles from their emitter's key.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct SortKey(pub u64);
+
+impl SortKey {
+    pub fn new(layer: u16, user: u32) -> Self {
+        Self(((layer as u64) << 48) | user as u64)
+    }
+
+    pub fn layer(self) -> u16 {
+        (self.0 >> 48) as u16
+    }
+
+    pub fn texture(self) -> u16 {
+        (self.0 >> 32) as u16
+    }
+
+    pub fn user(self) -> u32 {
+        self.0 as u32
+    }
+
+    // renderers put the id of what a primitive is drawn with here, so batches
+    // split into runs where it changes, see `Batch::runs`
+    pub fn with_texture(self, texture: u16) -> Self {
+        Self((self.0 & !(0xffff << 32)) | ((texture as u64) << 32))
+    }
+}
+
+// reorders whole quads (4 vertices, 6 indices each) so that `keys` is ascending
+pub(crate) fn sort_quads<V: Copy>(
+    keys: &mut [SortKey],
+    vertices: &mut Vec<V>,
+    indices: &mut Vec<u32>,
+) {
+    if keys.is_sorted() {
+        return;
+    }
+
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by_key(|&i| keys[i]);
+
+    let sorted_keys: Vec<SortKey> = order.iter().map(|&i| keys[i]).collect();
+    keys.copy_from_slice(&sorted_keys);
+
+    let old = std::mem::take(vertices);
+    indices.clear();
+    for (n, &i) in order.iter().enumerate() {
+        vertices.extend_from_slice(&old[i * 4..i * 4 + 4]);
+        let start = (n * 4) as u32;
+        indices.extend_from_slice(&[start, start + 1, start + 2, start, start + 2, start + 3]);
+    }
+}
+
+// What draws a `DrawBatch`, in the order the parts of one layer are drawn.
+// Tilemaps and gpu particles are told apart by their index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Part {
+    Tilemap(usize),
+    StreamingTilemap(usize),
+    Quads,
+    Sprites,
+    GpuParticles(usize),
+    Text,
+}
+
+// One layer of one part of the frame. `Renderer::render` collects them from
+// every renderer and draws them ordered by layer, then part, each part's
+// runs in its own texture order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DrawBatch {
+    pub layer: u16,
+    pub part: Part,
+}
+
+// the draw order of `batches`, without duplicates
+pub(crate) fn merge_batches(batches: impl IntoIterator<Item = DrawBatch>) -> Vec<DrawBatch> {
+    let mut merged: Vec<DrawBatch> = batches.into_iter().collect();
+    merged.sort();
+    merged.dedup();
+    merged
+}
+
+// Render pass state changes recorded by a render call, to see what batching costs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrawStats {
+    // the `DrawBatch`es that drew something
+    pub batches: u32,
+    pub draw_calls: u32,
+    pub pipeline_binds: u32,
+    pub bind_group_binds: u32,
+    // drawn by those calls, the fewer calls per quad the better they batched
+    pub quads: u32,
+}
+
+impl std::ops::AddAssign for DrawStats {
+    fn add_assign(&mut self, other: Self) {
+        self.batches += other.batches;
+        self.draw_calls += other.draw_calls;
+        self.pipeline_binds += other.pipeline_binds;
+        self.bind_group_binds += other.bind_group_binds;
+        self.quads += other.quads;
+    }
+}
+
+// The pipeline last set on a render pass, so renderers drawing one after the
+// other with the same pipeline (quads and bitmap text, see `Pipeline2d`) only
+// bind it once. Anything that sets a pipeline without it has to `reset` it.
+#[derive(Debug, Default)]
+pub struct BoundPipeline(Option<wgpu::RenderPipeline>);
+
+impl BoundPipeline {
+    pub fn set(&mut self, pass: &mut wgpu::RenderPass, pipeline: &wgpu::RenderPipeline, stats: &mut DrawStats) {
+        if self.0.as_ref() != Some(pipeline) {
+            pass.set_pipeline(pipeline);
+            self.0 = Some(pipeline.clone());
+            stats.pipeline_binds += 1;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.0 = None;
+    }
+}
+
+// index ranges covering the runs of consecutive quads with the same texture
+// whose layer passes `pred`, clamped to the `index_count` indices actually uploaded
+pub(crate) fn layer_runs(
+    keys: &[SortKey],
+    index_count: u32,
+    pred: impl Fn(u16) -> bool,
+) -> Vec<(u16, Range<u32>)> {
+    view_runs(keys, index_count, pred, |_| None)
+        .into_iter()
+        .map(|(_, texture, run)| (texture, run))
+        .collect()
+}
+
+// `layer_runs` that also split where `view` of the layer changes, see
+// `Camera::layer_view`
+pub(crate) fn view_runs(
+    keys: &[SortKey],
+    index_count: u32,
+    pred: impl Fn(u16) -> bool,
+    view: impl Fn(u16) -> Option<u16>,
+) -> Vec<(Option<u16>, u16, Range<u32>)> {
+    let mut runs: Vec<(Option<u16>, u16, Range<u32>)> = vec![];
+    for (i, key) in keys.iter().enumerate() {
+        let quad = i as u32 * 6..i as u32 * 6 + 6;
+        if quad.end > index_count {
+            break;
+        }
+        if !pred(key.layer()) {
+            continue;
+        }
+        let key_view = view(key.layer());
+        match runs.last_mut() {
+            Some((v, texture, run))
+                if *v == key_view && *texture == key.texture() && run.end == quad.start =>
+            {
+                run.end = quad.end
+            }
+            _ => runs.push((key_view, key.texture(), quad)),
+        }
+    }
+    runs
+}
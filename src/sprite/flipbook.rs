@@ -0,0 +1,178 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::font::packer::ShelfPacker;
+
+// widest sheet `bake` packs into, taller ones are fine
+const MAX_SHEET_WIDTH: u32 = 4096;
+
+// where one frame is in the sheet, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlipBookFrame {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+// What's saved next to a baked sheet, `image` relative to the metadata file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlipBookMeta {
+    pub image: PathBuf,
+    pub fps: f32,
+    #[serde(default = "looping")]
+    pub looping: bool,
+    pub frames: Vec<FlipBookFrame>,
+}
+
+fn looping() -> bool {
+    true
+}
+
+// An animation baked from a sequence of images into one sprite sheet, drawn by
+// pushing the sheet's texture with the uv of the current frame:
+//
+//   let book = FlipBook::bake_dir("art/walk", 12.0)?;
+//   let texture = renderer.create_sprite_texture(&book.sheet, wgpu::FilterMode::Nearest);
+//   frame.sprites().push_with_key(key, rect, book.uv(book.frame_at(time)), [1.0; 3], &texture);
+//
+// `wrs-pack` bakes a directory ahead of time into a png and the json of
+// `FlipBookMeta`, which `load` reads back.
+#[derive(Debug, Clone)]
+pub struct FlipBook {
+    pub sheet: image::RgbaImage,
+    pub meta: FlipBookMeta,
+}
+
+impl FlipBook {
+    // Packs `frames` in order into a sheet. `fps` is how many are shown per
+    // second.
+    pub fn bake(frames: &[image::RgbaImage], fps: f32) -> Self {
+        let widest = frames.iter().map(|f| f.width()).max().unwrap_or(1);
+        let area: u64 = frames.iter().map(|f| (f.width() + 1) as u64 * (f.height() + 1) as u64).sum();
+        let width = ((area as f64).sqrt().ceil() as u32).clamp(widest, MAX_SHEET_WIDTH.max(widest));
+
+        let mut packer = ShelfPacker::new(width, u32::MAX);
+        let placed: Vec<FlipBookFrame> = frames
+            .iter()
+            .map(|f| {
+                let (x, y) = packer
+                    .allocate(f.width(), f.height())
+                    .expect("the sheet is as wide as the widest frame and unbounded in height");
+                FlipBookFrame {
+                    x,
+                    y,
+                    w: f.width(),
+                    h: f.height(),
+                }
+            })
+            .collect();
+
+        let mut sheet = image::RgbaImage::new(width, packer.used_height().max(1));
+        for (frame, at) in frames.iter().zip(&placed) {
+            image::imageops::replace(&mut sheet, frame, at.x.into(), at.y.into());
+        }
+        Self {
+            sheet,
+            meta: FlipBookMeta {
+                image: PathBuf::new(),
+                fps,
+                looping: true,
+                frames: placed,
+            },
+        }
+    }
+
+    // Bakes the pngs in `dir` in the order of the last number in their names,
+    // so `walk_2.png` comes before `walk_10.png`.
+    pub fn bake_dir(dir: impl AsRef<Path>, fps: f32) -> image::ImageResult<Self> {
+        let frames = numbered_pngs(dir.as_ref())?
+            .iter()
+            .map(|path| Ok(image::open(path)?.to_rgba8()))
+            .collect::<image::ImageResult<Vec<_>>>()?;
+        Ok(Self::bake(&frames, fps))
+    }
+
+    // writes the sheet to `png` and the metadata next to it with a json extension
+    pub fn save(&mut self, png: impl AsRef<Path>) -> image::ImageResult<()> {
+        let png = png.as_ref();
+        self.sheet.save(png)?;
+        self.meta.image = png.file_name().map(PathBuf::from).unwrap_or_default();
+        let json = serde_json::to_string_pretty(&self.meta).map_err(std::io::Error::other)?;
+        std::fs::write(png.with_extension("json"), json)?;
+        Ok(())
+    }
+
+    // a sheet saved by `save` or `wrs-pack`, from its json
+    pub fn load(json: impl AsRef<Path>) -> image::ImageResult<Self> {
+        let json = json.as_ref();
+        let meta: FlipBookMeta = serde_json::from_str(&std::fs::read_to_string(json)?)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        let image = json.parent().unwrap_or(Path::new("")).join(&meta.image);
+        let sheet = image::open(image)?.to_rgba8();
+        Ok(Self { sheet, meta })
+    }
+
+    pub fn len(&self) -> usize {
+        self.meta.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meta.frames.is_empty()
+    }
+
+    // seconds one pass through the frames takes
+    pub fn duration(&self) -> f32 {
+        self.len() as f32 / self.meta.fps
+    }
+
+    // the frame shown `time` seconds into the animation, which holds its last
+    // frame when it doesn't loop
+    pub fn frame_at(&self, time: f32) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        let frame = (time.max(0.0) * self.meta.fps) as usize;
+        if self.meta.looping {
+            frame % self.len()
+        } else {
+            frame.min(self.len() - 1)
+        }
+    }
+
+    // u0, v0, u1, v1 of `frame` in the sheet
+    pub fn uv(&self, frame: usize) -> (f32, f32, f32, f32) {
+        let Some(f) = self.meta.frames.get(frame) else {
+            return (0.0, 0.0, 0.0, 0.0);
+        };
+        let (w, h) = (self.sheet.width() as f32, self.sheet.height() as f32);
+        (
+            f.x as f32 / w,
+            f.y as f32 / h,
+            (f.x + f.w) as f32 / w,
+            (f.y + f.h) as f32 / h,
+        )
+    }
+}
+
+// the pngs directly in `dir`, ordered by the last number in their names
+fn numbered_pngs(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")))
+        .collect();
+    paths.sort_by_cached_key(|path| {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        (last_number(&stem), stem)
+    });
+    Ok(paths)
+}
+
+fn last_number(name: &str) -> Option<u64> {
+    let end = name.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = name[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    name[start..end].parse().ok()
+}
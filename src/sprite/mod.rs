@@ -0,0 +1,4 @@
+mod flipbook;
+mod renderer;
+pub use flipbook::{FlipBook, FlipBookFrame, FlipBookMeta};
+pub use renderer::{Insets, SpriteRenderer, SpriteTexture, SpriteVertex};
@@ -0,0 +1,634 @@
+use crate::batch::{Batch, BatchStats};
+use crate::camera::{self, Camera};
+use crate::color::Color;
+use crate::color_space::ColorSpace;
+use crate::culling::{self, CullRect, Culling};
+use crate::{export, oit};
+use crate::pipeline_cache::{PipelineCache, PipelineKey};
+use crate::rect::{ClipShape, Rect};
+use crate::sanitize::{GeometryIssues, sanitize};
+use crate::shader::ShaderPreprocessor;
+use crate::sort::{DrawStats, SortKey};
+use crate::state::{BatchSnapshot, TextureState};
+use image::EncodableLayout;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+const INITIAL_QUADS: wgpu::BufferAddress = 256;
+
+// An image uploaded for `SpriteRenderer`, shared by every sprite drawn from it.
+#[derive(Debug, Clone)]
+pub struct SpriteTexture {
+    pub texture: wgpu::Texture,
+    pub bind_group: wgpu::BindGroup,
+    pub width: u32,
+    pub height: u32,
+    pub filter: wgpu::FilterMode,
+}
+
+impl SpriteTexture {
+    // its pixels read back from the gpu, see `Renderer::save_state`
+    pub fn state(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> TextureState {
+        TextureState {
+            width: self.width,
+            height: self.height,
+            filter: self.filter,
+            pixels: export::read_texture(device, queue, &self.texture).into_raw(),
+        }
+    }
+}
+
+// Widths of the borders of a nine-slice texture in texels, see
+// `SpriteRenderer::push_nine_slice`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Insets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Insets {
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub fn uniform(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+}
+
+// Textured quads out of any number of `SpriteTexture`s, drawn with straight
+// alpha blending and tinted by a color.
+pub struct SpriteRenderer {
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    culling: Culling,
+    pipelines: PipelineCache,
+    oit_pipeline: wgpu::RenderPipeline,
+    // what `render` draws into
+    target: PipelineKey,
+    bind_group_layout: wgpu::BindGroupLayout,
+    batch: Batch<SpriteVertex>,
+    clip: ClipShape,
+    color_space: ColorSpace,
+    world_bounds: Option<Rect>,
+    cull: Option<CullRect>,
+    issues: GeometryIssues,
+    // the textures sprites were pushed from this frame or are kept in a
+    // static layer, by texture id from 1 on, the free ids are None
+    textures: Vec<Option<SpriteTexture>>,
+}
+
+impl SpriteRenderer {
+    pub fn new(device: &wgpu::Device, cam: &Camera, surface_fmt: wgpu::TextureFormat) -> Self {
+        Self::with_culling(device, cam, surface_fmt, Culling::default())
+    }
+
+    pub fn with_culling(device: &wgpu::Device, cam: &Camera, surface_fmt: wgpu::TextureFormat, culling: Culling) -> Self {
+        let shader = ShaderPreprocessor::new()
+            .create_module(device, "Sprite Shader", include_str!("sprite_shader.wgsl"))
+            .expect("bundled shader includes exist");
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Sprite Texture"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[cam.get_bind_group_layout(), &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let oit_pipeline = create_pipeline(device, &pipeline_layout, &shader, "fs_oit", &oit::targets(), None, culling);
+        let mut renderer = Self {
+            shader,
+            pipeline_layout,
+            culling,
+            pipelines: PipelineCache::new(),
+            oit_pipeline,
+            target: PipelineKey::new(surface_fmt, wgpu::BlendState::ALPHA_BLENDING),
+            bind_group_layout,
+            batch: Batch::new(device, "Sprite Batch", INITIAL_QUADS),
+            clip: ClipShape::EVERYTHING,
+            color_space: ColorSpace::Srgb,
+            world_bounds: None,
+            cull: None,
+            issues: GeometryIssues::default(),
+            textures: vec![],
+        };
+        renderer.set_target_format(device, surface_fmt);
+        renderer
+    }
+
+    // see `QuadRenderer::set_target_format`
+    pub fn set_target_format(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.target.format = format;
+        self.build_pipeline(device);
+    }
+
+    // see `QuadRenderer::set_depth_format`
+    pub fn set_depth_format(&mut self, device: &wgpu::Device, depth: Option<wgpu::TextureFormat>) {
+        self.target.depth = depth;
+        self.build_pipeline(device);
+    }
+
+    fn build_pipeline(&mut self, device: &wgpu::Device) {
+        let (layout, shader, culling) = (&self.pipeline_layout, &self.shader, self.culling);
+        self.pipelines.get_or_create(self.target, |key| {
+            create_pipeline(device, layout, shader, "fs_main", &[Some(key.color_target())], key.depth_stencil(), culling)
+        });
+    }
+
+    // `filter` is `Nearest` for pixel art, `Linear` for everything drawn
+    // scaled smoothly
+    pub fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &image::RgbaImage,
+        filter: wgpu::FilterMode,
+    ) -> SpriteTexture {
+        let (width, height) = image.dimensions();
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Sprite Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            // copied from for `SpriteTexture::state`
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        if width > 0 && height > 0 {
+            queue.write_texture(
+                texture.as_image_copy(),
+                image.as_bytes(),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(height),
+                },
+                size,
+            );
+        }
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Sprite Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Sprite Texture"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        SpriteTexture {
+            texture,
+            bind_group,
+            width,
+            height,
+            filter,
+        }
+    }
+
+    // the whole of `texture` stretched over `rect`
+    pub fn push(&mut self, rect: Rect, texture: &SpriteTexture) {
+        self.push_with_key(SortKey::default(), rect, (0.0, 0.0, 1.0, 1.0), Color::WHITE, texture);
+    }
+
+    // The part `uv` (u0, v0, u1, v1) of `texture` over `rect`, its colors
+    // and alpha multiplied by `tint`. A negative `w` or `h` mirrors the sprite.
+    pub fn push_with_key(
+        &mut self,
+        key: SortKey,
+        rect: Rect,
+        uv: (f32, f32, f32, f32),
+        tint: impl Into<Color>,
+        texture: &SpriteTexture,
+    ) {
+        self.push_sprite(key, rect, uv, 0.0, tint.into(), texture);
+    }
+
+    // `push_with_key` `z` into the screen, see `QuadRenderer::push_with_z`
+    pub fn push_with_z(
+        &mut self,
+        key: SortKey,
+        z: f32,
+        rect: Rect,
+        uv: (f32, f32, f32, f32),
+        tint: impl Into<Color>,
+        texture: &SpriteTexture,
+    ) {
+        self.push_sprite(key, rect, uv, -z.clamp(0.0, camera::DEPTH), tint.into(), texture);
+    }
+
+    // `z` is the world's, negative into the screen
+    fn push_sprite(&mut self, key: SortKey, rect: Rect, uv: (f32, f32, f32, f32), z: f32, tint: Color, texture: &SpriteTexture) {
+        if self.culls(key, &rect) {
+            return;
+        }
+        let tint = tint.to_array();
+        let Some((Rect { x, y, w, h }, (u0, v0, u1, v1))) =
+            sanitize(rect, uv, self.world_bounds, &mut self.issues)
+        else {
+            return;
+        };
+        let texture = self.texture_id(texture);
+        let clip = self.clip.to_bounds();
+        let color_space = self.color_space.id();
+        let vertex = |[x, y]: [f32; 2], texture_coords| SpriteVertex::new([x, y, z], tint, texture_coords, clip, color_space);
+        self.batch.push(key.with_texture(texture), [
+            vertex([x, y], [u0, v0]),
+            vertex([x + w, y], [u1, v0]),
+            vertex([x + w, y + h], [u1, v1]),
+            vertex([x, y + h], [u0, v1]),
+        ]);
+    }
+
+    // Stretches `texture` over `rect` as 3x3 parts: the corners keep their size
+    // of `insets` texels, the edges stretch along their side only and the
+    // center both ways, so one texture makes panels and buttons of any size.
+    // Corners shrink evenly where `rect` is smaller than the borders.
+    pub fn push_nine_slice(&mut self, rect: Rect, texture: &SpriteTexture, insets: Insets) {
+        self.push_nine_slice_with_key(SortKey::default(), rect, Color::WHITE, texture, insets);
+    }
+
+    pub fn push_nine_slice_with_key(
+        &mut self,
+        key: SortKey,
+        rect: Rect,
+        tint: impl Into<Color>,
+        texture: &SpriteTexture,
+        insets: Insets,
+    ) {
+        let tint = tint.into();
+        let rect = rect.normalized();
+        let Insets { left, top, right, bottom } = insets;
+        let sx = (rect.w / (left + right)).min(1.0);
+        let sy = (rect.h / (top + bottom)).min(1.0);
+        let xs = [rect.x, rect.x + left * sx, rect.right() - right * sx, rect.right()];
+        let ys = [rect.y, rect.y + top * sy, rect.bottom() - bottom * sy, rect.bottom()];
+        let (tw, th) = (texture.width.max(1) as f32, texture.height.max(1) as f32);
+        let us = [0.0, left / tw, 1.0 - right / tw, 1.0];
+        let vs = [0.0, top / th, 1.0 - bottom / th, 1.0];
+
+        for row in 0..3 {
+            for col in 0..3 {
+                let part = Rect::new(xs[col], ys[row], xs[col + 1] - xs[col], ys[row + 1] - ys[row]);
+                // empty borders and the center of a rect all borders
+                if part.w <= 0.0 || part.h <= 0.0 {
+                    continue;
+                }
+                let uv = (us[col], vs[row], us[col + 1], vs[row + 1]);
+                self.push_with_key(key, part, uv, tint, texture);
+            }
+        }
+    }
+
+    fn texture_id(&mut self, texture: &SpriteTexture) -> u16 {
+        let slot = match self
+            .textures
+            .iter()
+            .position(|kept| kept.as_ref().is_some_and(|kept| kept.bind_group == texture.bind_group))
+        {
+            Some(slot) => slot,
+            None => match self.textures.iter().position(Option::is_none) {
+                Some(free) => {
+                    self.textures[free] = Some(texture.clone());
+                    free
+                }
+                None => {
+                    self.textures.push(Some(texture.clone()));
+                    self.textures.len() - 1
+                }
+            },
+        };
+        slot as u16 + 1
+    }
+
+    // Sorts the batch and uploads it. Call once per frame, after the last push.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        debug_assert_eq!(culling::count_mis_wound(self.batch.vertices(), |v| v.pos), 0, "mis-wound sprites in the batch");
+        self.batch.prepare(device, queue);
+    }
+
+    // draws the layers `draw_layer` accepts of what the last `prepare` uploaded
+    pub fn render(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, draw_layer: impl Fn(u16) -> bool) -> DrawStats {
+        if !self.batch.is_drawable() {
+            return DrawStats::default();
+        }
+        self.draw_runs(render_pass, cam, self.pipeline(), draw_layer)
+    }
+
+    // `render` into an `OitCompositor` pass
+    pub fn render_oit(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, draw_layer: impl Fn(u16) -> bool) -> DrawStats {
+        if !self.batch.is_drawable() {
+            return DrawStats::default();
+        }
+        self.draw_runs(render_pass, cam, &self.oit_pipeline, draw_layer)
+    }
+
+    fn draw_runs(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        cam: &Camera,
+        pipeline: &wgpu::RenderPipeline,
+        draw_layer: impl Fn(u16) -> bool,
+    ) -> DrawStats {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, cam.get_bind_group(), &[]);
+        let mut stats = DrawStats {
+            pipeline_binds: 1,
+            bind_group_binds: 1,
+            ..DrawStats::default()
+        };
+        let (mut bound_buffers, mut bound_view, mut bound) = (None, None, None);
+        for (kept, view, texture, range) in self.batch.static_runs(draw_layer, |layer| cam.layer_view(layer)) {
+            if bound_buffers != Some(kept) {
+                self.batch.set_run_buffers(render_pass, kept);
+                bound_buffers = Some(kept);
+            }
+            if view != bound_view {
+                render_pass.set_bind_group(0, cam.view_bind_group(view), &[]);
+                bound_view = view;
+                stats.bind_group_binds += 1;
+            }
+            if bound != Some(texture) {
+                let bind_group = self.textures[texture as usize - 1].as_ref().map(|texture| &texture.bind_group);
+                render_pass.set_bind_group(1, bind_group, &[]);
+                bound = Some(texture);
+                stats.bind_group_binds += 1;
+            }
+            stats.quads += range.len() as u32 / 6;
+            render_pass.draw_indexed(range, 0, 0..1);
+            stats.draw_calls += 1;
+        }
+        stats
+    }
+
+    // the pipeline `render` draws with, which `TilemapRenderer` shares
+    pub(crate) fn pipeline(&self) -> &wgpu::RenderPipeline {
+        self.pipelines
+            .get(self.target)
+            .expect("set_target_format builds the pipeline")
+    }
+
+    pub fn has_layer(&self, pred: impl Fn(u16) -> bool) -> bool {
+        self.batch.has_layer(pred)
+    }
+
+    pub fn layers(&self, pred: impl Fn(u16) -> bool) -> BTreeSet<u16> {
+        self.batch.layers(pred)
+    }
+
+    // see `QuadRenderer::set_clip`
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = ClipShape::Rect(clip.unwrap_or(Rect::EVERYTHING));
+    }
+
+    // see `QuadRenderer::set_clip_shape`
+    pub fn set_clip_shape(&mut self, clip: Option<ClipShape>) {
+        self.clip = clip.unwrap_or(ClipShape::EVERYTHING);
+    }
+
+    // the space the tints and textures of everything pushed afterwards are in
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.color_space = color_space;
+    }
+
+    // see `QuadRenderer::set_world_bounds`, cut sprites keep the part of the
+    // texture that's inside
+    pub fn set_world_bounds(&mut self, bounds: Option<Rect>) {
+        self.world_bounds = bounds;
+    }
+
+    // see `QuadRenderer::set_cull_rect`
+    pub fn set_cull_rect(&mut self, cull: Option<CullRect>) {
+        self.cull = cull;
+    }
+
+    fn culls(&self, key: SortKey, bounds: &Rect) -> bool {
+        let culled = self.cull.as_ref().is_some_and(|cull| cull.culls(key.layer(), bounds));
+        culled && !self.batch.is_static_layer(key.layer())
+    }
+
+    // what pushing fixed or dropped since the last `clear`
+    pub fn issues(&self) -> GeometryIssues {
+        self.issues
+    }
+
+    pub fn batch_stats(&self) -> BatchStats {
+        self.batch.stats()
+    }
+
+    // see `Batch::set_static_layer`
+    pub fn set_static_layer(&mut self, device: &wgpu::Device, layer: u16, enabled: bool) {
+        self.batch.set_static_layer(device, layer, enabled);
+    }
+
+    // see `Batch::rebuild_static_layer`
+    pub fn rebuild_static_layer(&mut self, layer: u16) {
+        self.batch.rebuild_static_layer(layer);
+    }
+
+    pub(crate) fn rebuilt_static_layers(&self) -> Vec<u16> {
+        self.batch.rebuilt_static_layers()
+    }
+
+    // What the static layers kept, and the textures they draw from by texture
+    // id from 1 on, read back from the gpu.
+    pub fn static_snapshots(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> (BTreeMap<u16, BatchSnapshot<SpriteVertex>>, Vec<Option<TextureState>>) {
+        let kept = self.batch.static_textures();
+        let textures = self
+            .textures
+            .iter()
+            .enumerate()
+            .map(|(slot, texture)| {
+                let texture = texture.as_ref().filter(|_| kept.contains(&(slot as u16 + 1)))?;
+                Some(texture.state(device, queue))
+            })
+            .collect();
+        (self.batch.static_snapshots(), textures)
+    }
+
+    // Uploads `textures` again under the ids `static_snapshots` gave them,
+    // for the static layers restored with `restore_static_layer`. Before
+    // anything is pushed.
+    pub fn restore_textures(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, textures: &[Option<TextureState>]) {
+        self.textures = textures
+            .iter()
+            .map(|state| {
+                let state = state.as_ref()?;
+                Some(self.create_texture(device, queue, &state.image(), state.filter))
+            })
+            .collect();
+    }
+
+    // see `Batch::restore_static_layer`, the ids of the sprites' textures are
+    // those of `restore_textures`
+    pub fn restore_static_layer(&mut self, device: &wgpu::Device, layer: u16, snapshot: BatchSnapshot<SpriteVertex>) {
+        self.batch.restore_static_layer(device, layer, snapshot, |key| key);
+    }
+
+    pub fn clear(&mut self) {
+        self.issues = GeometryIssues::default();
+        let kept = self.batch.static_textures();
+        for (slot, texture) in self.textures.iter_mut().enumerate() {
+            if !kept.contains(&(slot as u16 + 1)) {
+                *texture = None;
+            }
+        }
+        self.batch.clear();
+    }
+
+    pub fn empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+    targets: &[Option<wgpu::ColorTargetState>],
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    culling: Culling,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[SpriteVertex::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        primitive: culling.primitive_state(),
+        depth_stencil,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(entry_point),
+            targets,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
+pub struct SpriteVertex {
+    pos: [f32; 3],
+    // straight alpha
+    tint: [f32; 4],
+    texture_coords: [f32; 2],
+    clip: [f32; 4],
+    // `ColorSpace::id`
+    color_space: u32,
+    // see `QuadRenderer`'s vertices
+    clip_radius: f32,
+}
+
+impl SpriteVertex {
+    pub(crate) fn new(
+        pos: [f32; 3],
+        tint: [f32; 4],
+        texture_coords: [f32; 2],
+        (clip, clip_radius): ([f32; 4], f32),
+        color_space: u32,
+    ) -> Self {
+        Self {
+            pos,
+            tint,
+            texture_coords,
+            clip,
+            color_space,
+            clip_radius,
+        }
+    }
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SpriteVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 13]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 14]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32,
+                },
+            ],
+        }
+    }
+}
@@ -0,0 +1,68 @@
+#include "wrs/globals.wgsl"
+#include "wrs/color.wgsl"
+#include "wrs/clip.wgsl"
+#include "wrs/oit.wgsl"
+
+struct VertexOut {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tint: vec4<f32>,
+    @location(1) texture_coords: vec2<f32>,
+    @location(2) world_pos: vec2<f32>,
+    @location(3) clip: vec4<f32>,
+    @location(4) @interpolate(flat) color_space: u32,
+    @location(5) clip_radius: f32,
+};
+
+struct VertexIn {
+    @location(0) pos: vec3<f32>,
+    @location(1) tint: vec4<f32>,
+    @location(2) texture_coords: vec2<f32>,
+    @location(3) clip: vec4<f32>,
+    @location(4) color_space: u32,
+    @location(5) clip_radius: f32,
+}
+
+@vertex
+fn vs_main(
+    model: VertexIn
+) -> VertexOut {
+    var out: VertexOut;
+    out.tint = model.tint;
+    out.clip_position = camera.view_proj * vec4<f32>(model.pos, 1.0);
+    out.texture_coords = model.texture_coords;
+    out.world_pos = model.pos.xy;
+    out.clip = model.clip;
+    out.color_space = model.color_space;
+    out.clip_radius = model.clip_radius;
+    return out;
+}
+
+@group(1) @binding(0)
+var t_sprite: texture_2d<f32>;
+@group(1) @binding(1)
+var s_sprite: sampler;
+
+fn sprite_color(in: VertexOut) -> vec4<f32> {
+    let tex = textureSample(t_sprite, s_sprite, in.texture_coords);
+    return vec4<f32>(to_srgb(in.tint.rgb * tex.rgb, in.color_space), in.tint.a * tex.a);
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let clip = clip_coverage(in.world_pos, in.clip, in.clip_radius);
+    let color = sprite_color(in) * vec4<f32>(1.0, 1.0, 1.0, clip);
+    if clip <= 0.0 || color.a < 0.001 {
+        discard;
+    }
+    return color;
+}
+
+@fragment
+fn fs_oit(in: VertexOut) -> OitOut {
+    let clip = clip_coverage(in.world_pos, in.clip, in.clip_radius);
+    let color = sprite_color(in) * vec4<f32>(1.0, 1.0, 1.0, clip);
+    if clip <= 0.0 || color.a < 0.001 {
+        discard;
+    }
+    return oit_out(color, in.clip_position.z);
+}
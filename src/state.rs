@@ -0,0 +1,372 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::camera::{CameraState, Parallax};
+use crate::color_space::{ColorSpace, SurfaceEncoding};
+use crate::culling::Culling;
+use crate::font::FontVertex;
+use crate::oit::Compositing;
+use crate::quad::Vertex;
+use crate::rect::Rect;
+use crate::sort::SortKey;
+use crate::sprite::SpriteVertex;
+use crate::tilemap::TileLayout;
+
+// What a renderer was set up with, for `Renderer::from_state` to set up the
+// same one after a hot reload: the settings with the layers' parallax, the
+// camera, the font, the asset manifests, the static layers and the textures
+// they draw, the tilemaps, the materials and the inset views. The ids handed
+// out for them stay valid. What's pushed every frame isn't part of it, the app
+// pushes it again, and neither are streaming tilemaps and gpu particles, whose
+// generators and emitters the app adds again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RendererState {
+    pub settings: RendererSettings,
+    pub camera: CameraState,
+    pub font: FontDesc,
+    // the manifests `Renderer::load_assets` loaded, for the app to load again
+    #[serde(default)]
+    pub assets: Vec<PathBuf>,
+    #[serde(default)]
+    pub static_layers: BTreeMap<u16, StaticLayerState>,
+    // what the static layers' sprites are drawn from, by texture id from 1 on
+    #[serde(default)]
+    pub sprite_textures: Vec<Option<TextureState>>,
+    // by `TilemapId`
+    #[serde(default)]
+    pub tilemaps: Vec<TilemapState>,
+    // by `MaterialId`
+    #[serde(default)]
+    pub materials: Vec<MaterialState>,
+    // by `InsetViewId`
+    #[serde(default)]
+    pub inset_views: Vec<InsetViewState>,
+    // the quads and text of the frame, only in an `Anomaly`'s capture
+    #[serde(default)]
+    pub frame: Option<FrameState>,
+}
+
+impl RendererState {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(s: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RendererSettings {
+    pub present_mode: wgpu::PresentMode,
+    // layers missing from the map use `Compositing::Sorted`
+    #[serde(default)]
+    pub layer_compositing: BTreeMap<u16, Compositing>,
+    // Asks for an Rgba16Float surface, which the platforms present as extended
+    // linear srgb, so colors outside of srgb (see `ColorSpace`) aren't clipped.
+    // Falls back to the default format where the surface doesn't support it.
+    #[serde(default)]
+    pub wide_gamut: bool,
+    #[serde(default)]
+    pub clear: ClearMode,
+    // fixed when the pipelines are built
+    #[serde(default)]
+    pub culling: Culling,
+    // primitives are cut to these, see `QuadRenderer::set_world_bounds`
+    #[serde(default)]
+    pub world_bounds: Option<Rect>,
+    // targets missing from the map use `RenderTarget::default_ops`
+    #[serde(default)]
+    pub target_ops: BTreeMap<RenderTarget, TargetOps>,
+    // layers missing from the map follow the camera fully
+    #[serde(default)]
+    pub layer_parallax: BTreeMap<u16, Parallax>,
+    // Whether the surface applies the srgb curve to what's drawn. The
+    // surface format is picked to match, falling back to a view of the
+    // other encoding where a surface only has one. `wide_gamut` surfaces are
+    // always linear, see `Renderer::surface_encoding`.
+    #[serde(default)]
+    pub surface_encoding: SurfaceEncoding,
+    // what colors are in until `Renderer::set_color_space` changes it
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    // Gives the window's pass a depth buffer, so geometry pushed with a `z`
+    // covers what's further away whatever order it's drawn in. Translucent
+    // geometry still hides what's drawn behind it later, push it back to front
+    // or into a `WeightedBlended` layer.
+    #[serde(default)]
+    pub depth_buffer: bool,
+    // see `Renderer::set_cpu_culling`
+    #[serde(default)]
+    pub cpu_culling: bool,
+}
+
+impl RendererSettings {
+    pub fn compositing(&self, layer: u16) -> Compositing {
+        self.layer_compositing
+            .get(&layer)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn target_ops(&self, target: RenderTarget) -> TargetOps {
+        self.target_ops
+            .get(&target)
+            .copied()
+            .unwrap_or_else(|| target.default_ops(self.clear))
+    }
+}
+
+impl Default for RendererSettings {
+    fn default() -> Self {
+        Self {
+            present_mode: wgpu::PresentMode::Immediate,
+            layer_compositing: BTreeMap::new(),
+            wide_gamut: false,
+            clear: ClearMode::default(),
+            culling: Culling::default(),
+            world_bounds: None,
+            target_ops: BTreeMap::new(),
+            layer_parallax: BTreeMap::new(),
+            surface_encoding: SurfaceEncoding::default(),
+            color_space: ColorSpace::default(),
+            depth_buffer: false,
+            cpu_culling: false,
+        }
+    }
+}
+
+// What a frame is drawn over.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ClearMode {
+    // linear rgba
+    Color([f32; 4]),
+    // the previous frame, for painting style apps that only push what changed
+    Preserve,
+    // The previous frame faded towards the linear rgba `color`, halfway every
+    // `half_life` seconds, so whatever moves leaves a trail behind. Targets
+    // other than the window's treat it like `Preserve`.
+    Trail { color: [f32; 4], half_life: f32 },
+}
+
+impl ClearMode {
+    pub(crate) fn load_op(self) -> wgpu::LoadOp<wgpu::Color> {
+        match self {
+            Self::Color([r, g, b, a]) => wgpu::LoadOp::Clear(wgpu::Color {
+                r: r as f64,
+                g: g as f64,
+                b: b as f64,
+                a: a as f64,
+            }),
+            Self::Preserve | Self::Trail { .. } => wgpu::LoadOp::Load,
+        }
+    }
+
+    // whether frames are drawn over the previous one, which takes a canvas
+    pub(crate) fn keeps_frame(self) -> bool {
+        !matches!(self, Self::Color(_))
+    }
+}
+
+impl Default for ClearMode {
+    fn default() -> Self {
+        Self::Color([0.0; 4])
+    }
+}
+
+// What's kept of a target once its pass ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum StoreMode {
+    #[default]
+    Store,
+    // for transient targets nothing reads afterwards, tiled gpus then never
+    // write them back to memory
+    Discard,
+}
+
+impl StoreMode {
+    pub(crate) fn store_op(self) -> wgpu::StoreOp {
+        match self {
+            Self::Store => wgpu::StoreOp::Store,
+            Self::Discard => wgpu::StoreOp::Discard,
+        }
+    }
+}
+
+// How a pass loads and stores one of its targets, also for passes of your own
+// through `operations`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct TargetOps {
+    #[serde(default)]
+    pub load: ClearMode,
+    #[serde(default)]
+    pub store: StoreMode,
+}
+
+impl TargetOps {
+    pub fn new(load: ClearMode, store: StoreMode) -> Self {
+        Self { load, store }
+    }
+
+    pub fn operations(self) -> wgpu::Operations<wgpu::Color> {
+        wgpu::Operations {
+            load: self.load.load_op(),
+            store: self.store.store_op(),
+        }
+    }
+}
+
+// The targets frames pass through besides the window's own, whose load is
+// `RendererSettings::clear` and which is always stored to be presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RenderTarget {
+    // the summed colors of `Compositing::WeightedBlended` layers, cleared to
+    // 0. `Preserve` keeps adding every frame's layers on top, for trails
+    // that stay until it's cleared again.
+    OitAccum,
+    // their revealage, cleared to 1, `Preserve` it along with `OitAccum`
+    OitReveal,
+    // the msaa target of `export::render_scene`, which is resolved in the
+    // same pass and never read again
+    ExportMsaa,
+}
+
+impl RenderTarget {
+    // The oit targets are read by the composite pass after theirs, so unlike
+    // the msaa target they have to be stored.
+    pub fn default_ops(self, clear: ClearMode) -> TargetOps {
+        match self {
+            Self::OitAccum => TargetOps::new(ClearMode::Color([0.0; 4]), StoreMode::Store),
+            Self::OitReveal => TargetOps::new(ClearMode::Color([1.0; 4]), StoreMode::Store),
+            Self::ExportMsaa => match clear {
+                // there's no previous frame offscreen
+                ClearMode::Preserve => TargetOps::new(ClearMode::Color([0.0, 0.0, 0.0, 1.0]), StoreMode::Discard),
+                ClearMode::Trail { color, .. } => TargetOps::new(ClearMode::Color(color), StoreMode::Discard),
+                clear => TargetOps::new(clear, StoreMode::Discard),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FontDesc {
+    pub source: FontSource,
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FontSource {
+    // the iosevka font bundled into the binary
+    Embedded,
+    Path(std::path::PathBuf),
+    // an installed font by its family name, see `FontManager::load_system_font`
+    System(String),
+}
+
+impl FontSource {
+    // the whole file, of a system font in a collection too, see `load_face`
+    pub fn load(&self) -> std::io::Result<std::borrow::Cow<'static, [u8]>> {
+        match self {
+            Self::Embedded => Ok(std::borrow::Cow::Borrowed(include_bytes!(
+                "iosevka-regular.ttf"
+            ))),
+            Self::Path(path) => std::fs::read(path).map(std::borrow::Cow::Owned),
+            Self::System(_) => self.load_face().map(|(data, _)| data),
+        }
+    }
+
+    // `load` and the index of the font in it, only a system font can be
+    // other than the first of a collection
+    pub fn load_face(&self) -> std::io::Result<(std::borrow::Cow<'static, [u8]>, u32)> {
+        let Self::System(family) = self else {
+            return Ok((self.load()?, 0));
+        };
+        let (path, index) = crate::font::find_system_font(family).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, format!("no installed font family {family:?}"))
+        })?;
+        Ok((std::borrow::Cow::Owned(std::fs::read(path)?), index))
+    }
+}
+
+// What the batches kept of a static layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaticLayerState {
+    pub quads: BatchSnapshot<Vertex>,
+    pub sprites: BatchSnapshot<SpriteVertex>,
+    pub glyphs: BatchSnapshot<FontVertex>,
+}
+
+// The quads and text pushed for a frame. Sprites, tilemaps and particles
+// aren't part of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameState {
+    pub quads: BatchSnapshot<Vertex>,
+    pub glyphs: BatchSnapshot<FontVertex>,
+}
+
+// The pixels of a `SpriteTexture`, read back from the gpu.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextureState {
+    pub width: u32,
+    pub height: u32,
+    pub filter: wgpu::FilterMode,
+    // rgba, row after row
+    pub pixels: Vec<u8>,
+}
+
+impl TextureState {
+    pub fn image(&self) -> image::RgbaImage {
+        image::RgbaImage::from_raw(self.width, self.height, self.pixels.clone())
+            .unwrap_or_else(|| image::RgbaImage::new(self.width, self.height))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TilemapState {
+    pub tileset: TextureState,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub width: u32,
+    pub height: u32,
+    pub layout: TileLayout,
+    pub layer: u16,
+    // row after row
+    pub tiles: Vec<Option<u32>>,
+}
+
+// The arguments of `Renderer::register_material` and what was last written
+// to its uniform block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterialState {
+    pub label: String,
+    pub source: String,
+    pub uniform_size: wgpu::BufferAddress,
+    #[serde(default)]
+    pub uniform: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct InsetViewState {
+    pub rect: Rect,
+    pub camera: CameraState,
+    pub visible: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSnapshot<V> {
+    pub vertices: Vec<V>,
+    pub indices: Vec<u32>,
+    #[serde(default)]
+    pub keys: Vec<SortKey>,
+}
+
+impl<V> Default for BatchSnapshot<V> {
+    fn default() -> Self {
+        Self {
+            vertices: vec![],
+            indices: vec![],
+            keys: vec![],
+        }
+    }
+}
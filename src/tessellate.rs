@@ -0,0 +1,180 @@
+use crate::rect::Rect;
+
+// Splits a simple polygon into triangles by ear clipping, for drawing filled
+// shapes out of the quad batch. `points` go around the outline in either
+// direction; the triangles index into them and wind clockwise on screen like
+// pushed quads do.
+//
+// Repeated and collinear points are fine. A polygon that crosses itself has
+// no ears left at some point, the rest of it is then cut off a corner at a
+// time, which fills most of it but not necessarily right.
+pub fn triangulate(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    let area = signed_area(points);
+    if points.len() < 3 || area == 0.0 || !area.is_finite() {
+        return vec![];
+    }
+    let mut ring: Vec<usize> = if area > 0.0 {
+        (0..points.len()).collect()
+    } else {
+        (0..points.len()).rev().collect()
+    };
+
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    let (mut i, mut misses) = (0, 0);
+    while ring.len() > 3 {
+        let len = ring.len();
+        i %= len;
+        let corner = [ring[(i + len - 1) % len], ring[i], ring[(i + 1) % len]];
+        let [a, b, c] = corner.map(|index| points[index]);
+        let turn = cross(a, b, c);
+        // a flat corner goes without a triangle, a stuck one without checking
+        let stuck = misses >= len;
+        if turn <= 0.0 && !stuck {
+            if turn == 0.0 {
+                ring.remove(i);
+                misses = 0;
+            } else {
+                i += 1;
+                misses += 1;
+            }
+            continue;
+        }
+        if !stuck && ring.iter().any(|&p| !corner.contains(&p) && inside([a, b, c], points[p])) {
+            i += 1;
+            misses += 1;
+            continue;
+        }
+        if turn > 0.0 {
+            triangles.push(corner);
+        }
+        ring.remove(i);
+        misses = 0;
+    }
+    let corner = [ring[0], ring[1], ring[2]];
+    if cross(points[corner[0]], points[corner[1]], points[corner[2]]) > 0.0 {
+        triangles.push(corner);
+    }
+    triangles
+}
+
+// Twice the area of the polygon, positive when it goes clockwise on screen.
+pub fn signed_area(points: &[[f32; 2]]) -> f32 {
+    let mut sum = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        sum += a[0] * b[1] - b[0] * a[1];
+    }
+    sum
+}
+
+// The part of a convex polygon inside of `rect`, Sutherland-Hodgman style.
+pub(crate) fn clip_convex(points: &[[f32; 2]], rect: Rect) -> Vec<[f32; 2]> {
+    // the side each edge keeps things on, as (axis, bound, keep the larger side)
+    let edges = [
+        (0, rect.x, true),
+        (0, rect.right(), false),
+        (1, rect.y, true),
+        (1, rect.bottom(), false),
+    ];
+    let mut polygon = points.to_vec();
+    for (axis, bound, above) in edges {
+        let keeps = |p: [f32; 2]| if above { p[axis] >= bound } else { p[axis] <= bound };
+        let input = std::mem::take(&mut polygon);
+        for (i, &p) in input.iter().enumerate() {
+            let q = input[(i + 1) % input.len()];
+            if keeps(p) {
+                polygon.push(p);
+            }
+            if keeps(p) != keeps(q) {
+                let t = (bound - p[axis]) / (q[axis] - p[axis]);
+                polygon.push([p[0] + (q[0] - p[0]) * t, p[1] + (q[1] - p[1]) * t]);
+            }
+        }
+    }
+    polygon
+}
+
+// positive when `a`, `b`, `c` turn clockwise on screen
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+// whether `p` lies in the clockwise triangle or on its edges, a point at one
+// of its corners blocks it too since it would pinch the outline
+fn inside([a, b, c]: [[f32; 2]; 3], p: [f32; 2]) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every triangle winds clockwise and together they cover the polygon
+    fn assert_fills(points: &[[f32; 2]], triangles: &[[usize; 3]]) {
+        let mut area = 0.0;
+        for &[a, b, c] in triangles {
+            let turn = cross(points[a], points[b], points[c]);
+            assert!(turn > 0.0, "{:?} winds the wrong way", [a, b, c]);
+            area += turn;
+        }
+        assert!((area - signed_area(points).abs()).abs() < 1e-3, "covers {area}");
+    }
+
+    const SQUARE: [[f32; 2]; 4] = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+
+    #[test]
+    fn clockwise_square() {
+        assert!(signed_area(&SQUARE) > 0.0);
+        let triangles = triangulate(&SQUARE);
+        assert_eq!(triangles.len(), 2);
+        assert_fills(&SQUARE, &triangles);
+    }
+
+    #[test]
+    fn counter_clockwise_square() {
+        let mut points = SQUARE;
+        points.reverse();
+        assert!(signed_area(&points) < 0.0);
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 2);
+        assert_fills(&points, &triangles);
+    }
+
+    #[test]
+    fn collinear_points_get_no_flat_triangle() {
+        let points = [[0.0, 0.0], [5.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+        assert_fills(&points, &triangulate(&points));
+        // all on one line, nothing to fill
+        assert!(triangulate(&[[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]).is_empty());
+    }
+
+    #[test]
+    fn concave_polygon() {
+        // an L, its inner corner at 5, 5
+        let points = [[0.0, 0.0], [10.0, 0.0], [10.0, 5.0], [5.0, 5.0], [5.0, 10.0], [0.0, 10.0]];
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 4);
+        assert_fills(&points, &triangles);
+        // none reaches across the notch
+        for &[a, b, c] in &triangles {
+            assert!(!inside([points[a], points[b], points[c]], [7.5, 7.5]));
+        }
+    }
+
+    #[test]
+    fn fewer_than_three_points() {
+        assert!(triangulate(&[]).is_empty());
+        assert!(triangulate(&[[0.0, 0.0]]).is_empty());
+        assert!(triangulate(&[[0.0, 0.0], [1.0, 1.0]]).is_empty());
+    }
+
+    #[test]
+    fn clipping() {
+        let triangle = [[0.0, 0.0], [10.0, 0.0], [0.0, 10.0]];
+        assert!(clip_convex(&triangle, Rect::new(20.0, 20.0, 5.0, 5.0)).is_empty());
+        let inside = clip_convex(&triangle, Rect::new(-5.0, -5.0, 20.0, 20.0));
+        assert_eq!(inside, triangle);
+        let cut = clip_convex(&triangle, Rect::new(0.0, 0.0, 5.0, 20.0));
+        assert!((signed_area(&cut) - 75.0).abs() < 1e-3, "{cut:?}");
+    }
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rect::Rect;
+
+// How the tiles of a map are laid out in the world. `tile_size` is the size of
+// a tile's footprint: the square, the diamond or the hexagon's bounding box.
+//
+// Isometric tiles are drawn as wide as the diamond and as tall as the
+// tileset's tiles are for that width, standing on the diamond's bottom, so a
+// tall tile (a wall, a tree, a stack of blocks) reaches over the tiles behind
+// it. Rows are drawn top to bottom and left to right, back to front for an
+// isometric map. Hexagonal maps keep their rows and columns in offset
+// coordinates, with every odd row shifted half a tile right for pointy ones
+// and every odd column half a tile down for flat ones.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TileProjection {
+    #[default]
+    Square,
+    Isometric,
+    HexPointy,
+    HexFlat,
+}
+
+// Where the tiles of a map are in the world, the conversions between tile
+// coordinates and world points every tilemap shares. Tile coordinates can lie
+// outside of a map.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TileLayout {
+    pub projection: TileProjection,
+    // the world point the top left corner of the footprint of tile 0, 0 is at
+    pub origin: [f32; 2],
+    // in world units
+    pub tile_size: [f32; 2],
+}
+
+impl TileLayout {
+    // square tiles from the world's origin on
+    pub fn new(tile_size: [f32; 2]) -> Self {
+        Self {
+            projection: TileProjection::Square,
+            origin: [0.0, 0.0],
+            tile_size,
+        }
+    }
+
+    // the bounding box of a tile's footprint
+    pub fn footprint(&self, x: i32, y: i32) -> Rect {
+        let [ox, oy] = self.origin;
+        let [tw, th] = self.tile_size;
+        let (fx, fy) = (x as f32, y as f32);
+        let (x0, y0) = match self.projection {
+            TileProjection::Square => (fx * tw, fy * th),
+            TileProjection::Isometric => ((fx - fy) * tw / 2.0, (fx + fy) * th / 2.0),
+            TileProjection::HexPointy => ((fx + (y & 1) as f32 / 2.0) * tw, fy * th * 0.75),
+            TileProjection::HexFlat => (fx * tw * 0.75, (fy + (x & 1) as f32 / 2.0) * th),
+        };
+        Rect::new(ox + x0, oy + y0, tw, th)
+    }
+
+    // the world point in the middle of the footprint of a tile
+    pub fn tile_center(&self, x: i32, y: i32) -> [f32; 2] {
+        let footprint = self.footprint(x, y);
+        [footprint.x + footprint.w / 2.0, footprint.y + footprint.h / 2.0]
+    }
+
+    // the tile whose footprint `point` lies in
+    pub fn world_to_tile(&self, point: [f32; 2]) -> (i32, i32) {
+        let [tw, th] = self.tile_size;
+        // relative to the middle of tile 0, 0
+        let x = point[0] - self.origin[0] - tw / 2.0;
+        let y = point[1] - self.origin[1] - th / 2.0;
+        match self.projection {
+            TileProjection::Square => ((x / tw + 0.5).floor() as i32, (y / th + 0.5).floor() as i32),
+            TileProjection::Isometric => {
+                // a diamond is where both axes round to its tile
+                let (u, v) = (x / (tw / 2.0), y / (th / 2.0));
+                (((u + v) / 2.0).round() as i32, ((v - u) / 2.0).round() as i32)
+            }
+            TileProjection::HexPointy => {
+                let r = y / (th * 0.75);
+                let (q, r) = hex_round(x / tw - r / 2.0, r);
+                (q + (r - (r & 1)) / 2, r)
+            }
+            TileProjection::HexFlat => {
+                let q = x / (tw * 0.75);
+                let (q, r) = hex_round(q, y / th - q / 2.0);
+                (q, r + (q - (q & 1)) / 2)
+            }
+        }
+    }
+
+    // The smallest and largest tile coordinates of the tiles `rect` touches,
+    // and maybe a few more around it for hexagonal maps.
+    pub(crate) fn tile_range(&self, rect: Rect) -> ((i32, i32), (i32, i32)) {
+        let corners = [
+            [rect.x, rect.y],
+            [rect.right(), rect.y],
+            [rect.x, rect.bottom()],
+            [rect.right(), rect.bottom()],
+        ]
+        .map(|point| self.world_to_tile(point));
+        let (mut min, mut max) = (corners[0], corners[0]);
+        for (x, y) in corners {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        // a hexagon's neighbors reach into its bounding box
+        if matches!(self.projection, TileProjection::HexPointy | TileProjection::HexFlat) {
+            (min, max) = ((min.0 - 1, min.1 - 1), (max.0 + 1, max.1 + 1));
+        }
+        (min, max)
+    }
+}
+
+// The nearest hexagon to fractional axial coordinates, by rounding the cube
+// coordinates and fixing the one that rounded furthest.
+fn hex_round(q: f32, r: f32) -> (i32, i32) {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as i32, rr as i32)
+}
@@ -0,0 +1,6 @@
+mod layout;
+mod renderer;
+mod streaming;
+pub use layout::{TileLayout, TileProjection};
+pub use renderer::{TilemapId, TilemapRenderer, Tileset};
+pub use streaming::{StreamingTilemap, StreamingTilemapId};
@@ -0,0 +1,372 @@
+use super::layout::{TileLayout, TileProjection};
+use crate::buffer::GrowableBuffer;
+use crate::camera::Camera;
+use crate::color_space::ColorSpace;
+use crate::rect::{ClipShape, Rect};
+use crate::sort::DrawStats;
+use crate::state::TilemapState;
+use crate::sprite::{SpriteRenderer, SpriteTexture, SpriteVertex};
+
+// tiles along each side of a chunk, so a full chunk's 4 vertices per tile
+// still fit the u16 indices
+pub(super) const CHUNK_SIZE: u32 = 32;
+
+// An image cut into a grid of equally sized tiles, numbered left to right and
+// top to bottom from 0.
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    pub texture: SpriteTexture,
+    pub tile_width: u32,
+    pub tile_height: u32,
+}
+
+impl Tileset {
+    pub fn new(texture: SpriteTexture, tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            texture,
+            tile_width: tile_width.max(1),
+            tile_height: tile_height.max(1),
+        }
+    }
+
+    pub fn columns(&self) -> u32 {
+        (self.texture.width / self.tile_width).max(1)
+    }
+
+    pub fn len(&self) -> u32 {
+        self.columns() * (self.texture.height / self.tile_height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // u0, v0, u1, v1 of tile `index`, None past the last tile
+    pub fn uv(&self, index: u32) -> Option<[f32; 4]> {
+        if index >= self.len() {
+            return None;
+        }
+        let (tw, th) = (self.texture.width as f32, self.texture.height as f32);
+        let x = (index % self.columns() * self.tile_width) as f32;
+        let y = (index / self.columns() * self.tile_height) as f32;
+        Some([
+            x / tw,
+            y / th,
+            (x + self.tile_width as f32) / tw,
+            (y + self.tile_height as f32) / th,
+        ])
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TilemapId(pub usize);
+
+struct Chunk {
+    vertices: GrowableBuffer,
+    quads: u32,
+    dirty: bool,
+}
+
+// A grid of tiles out of one `Tileset` that stays on the gpu between frames.
+// The grid is split into chunks of `CHUNK_SIZE` tiles squared, each with a
+// vertex buffer of its own that `prepare` only rebuilds when one of its tiles
+// changed, and `render` skips the chunks outside of the camera's view.
+//
+// Tiles are drawn with the sprite pipeline on layer 0 until `set_layer`,
+// under the frame's batches of the same layer, with `Renderer::add_tilemap`
+// and `Frame::tilemap`.
+pub struct TilemapRenderer {
+    tileset: Tileset,
+    width: u32,
+    height: u32,
+    layout: TileLayout,
+    color_space: ColorSpace,
+    layer: u16,
+    tiles: Vec<Option<u32>>,
+    chunks: Vec<Chunk>,
+    // the indices of a full chunk, shared by all of them
+    indices: wgpu::Buffer,
+}
+
+impl TilemapRenderer {
+    // `width` by `height` empty tiles of `tile_size` world units each
+    pub fn new(
+        device: &wgpu::Device,
+        tileset: Tileset,
+        width: u32,
+        height: u32,
+        tile_size: [f32; 2],
+    ) -> Self {
+        let chunks = (0..width.div_ceil(CHUNK_SIZE) * height.div_ceil(CHUNK_SIZE))
+            .map(|_| Chunk {
+                vertices: GrowableBuffer::new(
+                    device,
+                    "Tilemap Chunk",
+                    wgpu::BufferUsages::VERTEX,
+                    wgpu::COPY_BUFFER_ALIGNMENT,
+                ),
+                quads: 0,
+                dirty: false,
+            })
+            .collect();
+        let indices: Vec<u16> = (0..(CHUNK_SIZE * CHUNK_SIZE) as u16)
+            .flat_map(|quad| {
+                let start = quad * 4;
+                [start, start + 1, start + 2, start, start + 2, start + 3]
+            })
+            .collect();
+        let indices = wgpu::util::DeviceExt::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("Tilemap Indices"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self {
+            tileset,
+            width,
+            height,
+            layout: TileLayout::new(tile_size),
+            color_space: ColorSpace::Srgb,
+            layer: 0,
+            tiles: vec![None; (width * height) as usize],
+            chunks,
+            indices,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn tileset(&self) -> &Tileset {
+        &self.tileset
+    }
+
+    // its tiles and the tileset's pixels read back from the gpu, see
+    // `Renderer::save_state`
+    pub fn state(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> TilemapState {
+        TilemapState {
+            tileset: self.tileset.texture.state(device, queue),
+            tile_width: self.tileset.tile_width,
+            tile_height: self.tileset.tile_height,
+            width: self.width,
+            height: self.height,
+            layout: self.layout,
+            layer: self.layer,
+            tiles: self.tiles.clone(),
+        }
+    }
+
+    pub fn tile(&self, x: u32, y: u32) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+
+    // `None` clears the tile, positions outside of the map are ignored
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: Option<u32>) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let slot = &mut self.tiles[(y * self.width + x) as usize];
+        if *slot != tile {
+            *slot = tile;
+            let chunk = self.chunk_index(x / CHUNK_SIZE, y / CHUNK_SIZE);
+            self.chunks[chunk].dirty = true;
+        }
+    }
+
+    // sets the whole map from `tiles`, row after row
+    pub fn set_tiles(&mut self, tiles: &[Option<u32>]) {
+        for (i, tile) in tiles.iter().take(self.tiles.len()).enumerate() {
+            let i = i as u32;
+            self.set_tile(i % self.width, i / self.width, *tile);
+        }
+    }
+
+    // the world point the top left corner of the footprint of tile 0, 0 is at
+    pub fn set_origin(&mut self, origin: [f32; 2]) {
+        if self.layout.origin != origin {
+            self.layout.origin = origin;
+            self.mark_all_dirty();
+        }
+    }
+
+    pub fn set_projection(&mut self, projection: TileProjection) {
+        if self.layout.projection != projection {
+            self.layout.projection = projection;
+            self.mark_all_dirty();
+        }
+    }
+
+    pub fn projection(&self) -> TileProjection {
+        self.layout.projection
+    }
+
+    pub fn layout(&self) -> TileLayout {
+        self.layout
+    }
+
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        if self.color_space != color_space {
+            self.color_space = color_space;
+            self.mark_all_dirty();
+        }
+    }
+
+    pub fn layer(&self) -> u16 {
+        self.layer
+    }
+
+    // the layer of the frame the map is drawn on, see `SortKey`
+    pub fn set_layer(&mut self, layer: u16) {
+        self.layer = layer;
+    }
+
+    // the world area the map's tiles are drawn over
+    pub fn bounds(&self) -> Rect {
+        if self.width == 0 || self.height == 0 {
+            return Rect::new(self.layout.origin[0], self.layout.origin[1], 0.0, 0.0);
+        }
+        self.area(0, 0, self.width - 1, self.height - 1)
+    }
+
+    // the world point in the middle of a tile's footprint, see `TileLayout`
+    pub fn tile_center(&self, x: i32, y: i32) -> [f32; 2] {
+        self.layout.tile_center(x, y)
+    }
+
+    // the tile whose footprint `point` lies in, which can lie outside of the map
+    pub fn world_to_tile(&self, point: [f32; 2]) -> (i32, i32) {
+        self.layout.world_to_tile(point)
+    }
+
+    // where the middle of a tile is in physical window pixels
+    pub fn tile_to_screen(&self, cam: &Camera, x: i32, y: i32) -> (f32, f32) {
+        let [wx, wy] = self.tile_center(x, y);
+        cam.world_to_screen(wx, wy)
+    }
+
+    // the tile under a point in physical window pixels, like the mouse position
+    pub fn screen_to_tile(&self, cam: &Camera, x: f32, y: f32) -> (i32, i32) {
+        let (wx, wy) = cam.screen_to_world(x, y);
+        self.world_to_tile([wx, wy])
+    }
+
+    // what a tile's image covers, its footprint unless it's a tall isometric one
+    fn tile_rect(&self, x: i32, y: i32) -> Rect {
+        let footprint = self.layout.footprint(x, y);
+        if self.layout.projection != TileProjection::Isometric {
+            return footprint;
+        }
+        let h = footprint.w * self.tileset.tile_height as f32 / self.tileset.tile_width as f32;
+        Rect::new(footprint.x, footprint.bottom() - h, footprint.w, h)
+    }
+
+    // The world area of the tiles from `x0`, `y0` to `x1`, `y1` inclusive. The
+    // outermost tiles are on the block's edges, and on the second row and
+    // column for the shifted ones of a hexagonal map.
+    fn area(&self, x0: u32, y0: u32, x1: u32, y1: u32) -> Rect {
+        let xs = [x0, (x0 + 1).min(x1), x1.saturating_sub(1).max(x0), x1];
+        let ys = [y0, (y0 + 1).min(y1), y1.saturating_sub(1).max(y0), y1];
+        let corners: Vec<[f32; 2]> = ys
+            .iter()
+            .flat_map(|&y| xs.iter().map(move |&x| (x, y)))
+            .flat_map(|(x, y)| {
+                let rect = self.tile_rect(x as i32, y as i32);
+                [[rect.x, rect.y], [rect.right(), rect.bottom()]]
+            })
+            .collect();
+        Rect::bounding(&corners).expect("a block has tiles")
+    }
+
+    fn chunk_index(&self, cx: u32, cy: u32) -> usize {
+        (cy * self.width.div_ceil(CHUNK_SIZE) + cx) as usize
+    }
+
+    fn chunk_rect(&self, cx: u32, cy: u32) -> Rect {
+        let (x0, y0) = (cx * CHUNK_SIZE, cy * CHUNK_SIZE);
+        let x1 = ((cx + 1) * CHUNK_SIZE).min(self.width) - 1;
+        let y1 = ((cy + 1) * CHUNK_SIZE).min(self.height) - 1;
+        self.area(x0, y0, x1, y1)
+    }
+
+    fn mark_all_dirty(&mut self) {
+        for chunk in &mut self.chunks {
+            chunk.dirty = true;
+        }
+    }
+
+    // Rebuilds the chunks whose tiles changed since the last call and returns
+    // how many it uploaded.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> usize {
+        let mut uploaded = 0;
+        let chunks_x = self.width.div_ceil(CHUNK_SIZE);
+        for i in 0..self.chunks.len() {
+            if !self.chunks[i].dirty {
+                continue;
+            }
+            let (cx, cy) = (i as u32 % chunks_x, i as u32 / chunks_x);
+            let vertices = self.chunk_vertices(cx, cy);
+            let chunk = &mut self.chunks[i];
+            chunk.vertices.write(device, queue, bytemuck::cast_slice(&vertices));
+            chunk.quads = (vertices.len() / 4) as u32;
+            chunk.dirty = false;
+            uploaded += 1;
+        }
+        uploaded
+    }
+
+    fn chunk_vertices(&self, cx: u32, cy: u32) -> Vec<SpriteVertex> {
+        let clip = ClipShape::EVERYTHING.to_bounds();
+        let color_space = self.color_space.id();
+        let mut vertices = vec![];
+        for y in cy * CHUNK_SIZE..((cy + 1) * CHUNK_SIZE).min(self.height) {
+            for x in cx * CHUNK_SIZE..((cx + 1) * CHUNK_SIZE).min(self.width) {
+                let Some([u0, v0, u1, v1]) = self.tile(x, y).and_then(|tile| self.tileset.uv(tile)) else {
+                    continue;
+                };
+                let Rect { x: x0, y: y0, w: tw, h: th } = self.tile_rect(x as i32, y as i32);
+                let vertex = |[x, y]: [f32; 2], uv| SpriteVertex::new([x, y, 0.0], [1.0; 4], uv, clip, color_space);
+                vertices.extend_from_slice(&[
+                    vertex([x0, y0], [u0, v0]),
+                    vertex([x0 + tw, y0], [u1, v0]),
+                    vertex([x0 + tw, y0 + th], [u1, v1]),
+                    vertex([x0, y0 + th], [u0, v1]),
+                ]);
+            }
+        }
+        vertices
+    }
+
+    // draws the chunks the camera sees, as of the last `prepare`
+    // `sprites` has the pipeline for the target
+    pub fn render(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, sprites: &SpriteRenderer) -> DrawStats {
+        let view = cam.visible_rect();
+        let chunks_x = self.width.div_ceil(CHUNK_SIZE);
+        let mut stats = DrawStats::default();
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            let (cx, cy) = (i as u32 % chunks_x, i as u32 / chunks_x);
+            if chunk.quads == 0 || !self.chunk_rect(cx, cy).intersects(&view) {
+                continue;
+            }
+            if stats.draw_calls == 0 {
+                render_pass.set_pipeline(sprites.pipeline());
+                render_pass.set_bind_group(0, cam.get_bind_group(), &[]);
+                render_pass.set_bind_group(1, &self.tileset.texture.bind_group, &[]);
+                render_pass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint16);
+                stats.pipeline_binds = 1;
+                stats.bind_group_binds = 2;
+            }
+            render_pass.set_vertex_buffer(0, chunk.vertices.slice());
+            render_pass.draw_indexed(0..chunk.quads * 6, 0, 0..1);
+            stats.draw_calls += 1;
+            stats.quads += chunk.quads;
+        }
+        stats
+    }
+}
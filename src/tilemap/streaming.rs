@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use super::layout::{TileLayout, TileProjection};
+use super::renderer::{CHUNK_SIZE, TilemapRenderer, Tileset};
+use crate::camera::Camera;
+use crate::color_space::ColorSpace;
+use crate::sort::DrawStats;
+use crate::sprite::SpriteRenderer;
+
+// the tile at a pair of tile coordinates, `None` for no tile
+type Generator = dyn Fn(i32, i32) -> Option<u32> + Send + Sync;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StreamingTilemapId(pub usize);
+
+struct Job {
+    chunk: (i32, i32),
+    generation: u64,
+}
+
+struct Generated {
+    chunk: (i32, i32),
+    generation: u64,
+    tiles: Vec<Option<u32>>,
+}
+
+// A tilemap without edges whose tiles come from a callback, for procedural
+// worlds. It's kept in chunks of `CHUNK_SIZE` tiles squared: the ones within
+// `load_margin` chunks of the camera's view are generated on worker threads,
+// uploaded once as they come in, at most `max_uploads` a frame, and dropped
+// again once they're further than `keep_margin` chunks from it.
+//
+// Chunks the camera moves to before they're generated stay empty for a few
+// frames rather than stalling one; a `load_margin` wide enough for how fast
+// the camera moves hides that. Added with `Renderer::add_streaming_tilemap`,
+// drawn over the fixed tilemaps of its layer.
+pub struct StreamingTilemap {
+    tileset: Tileset,
+    layout: TileLayout,
+    color_space: ColorSpace,
+    layer: u16,
+    pub load_margin: u32,
+    pub keep_margin: u32,
+    pub max_uploads: usize,
+    chunks: HashMap<(i32, i32), TilemapRenderer>,
+    // requested from the workers and not back yet
+    pending: HashSet<(i32, i32)>,
+    // generated and waiting for an upload
+    ready: Vec<Generated>,
+    // bumped by `regenerate`, what was requested before is thrown away
+    generation: u64,
+    jobs: Sender<Job>,
+    results: Receiver<Generated>,
+    workers: usize,
+}
+
+impl StreamingTilemap {
+    // `generate` gives the tile at a pair of tile coordinates, called from a
+    // worker thread per chunk. Tiles are `tile_size` world units squares
+    // until `set_projection`.
+    pub fn new(
+        tileset: Tileset,
+        tile_size: [f32; 2],
+        generate: impl Fn(i32, i32) -> Option<u32> + Send + Sync + 'static,
+    ) -> Self {
+        let generate: Arc<Generator> = Arc::new(generate);
+        let (jobs, job_queue) = mpsc::channel::<Job>();
+        let job_queue = Arc::new(Mutex::new(job_queue));
+        let (done, results) = mpsc::channel();
+        // leave a core to the render thread
+        let workers = std::thread::available_parallelism().map_or(1, |n| n.get().saturating_sub(1).clamp(1, 4));
+        for i in 0..workers {
+            let (job_queue, done, generate) = (job_queue.clone(), done.clone(), generate.clone());
+            std::thread::Builder::new()
+                .name(format!("wrs tile generator {i}"))
+                .spawn(move || {
+                    loop {
+                        // the queue is only locked while waiting for a job
+                        let job = job_queue.lock().unwrap().recv();
+                        // ends once the tilemap and its job sender are dropped
+                        let Ok(Job { chunk, generation }) = job else {
+                            return;
+                        };
+                        let size = CHUNK_SIZE as i32;
+                        let (x0, y0) = (chunk.0 * size, chunk.1 * size);
+                        let tiles = (0..size * size)
+                            .map(|i| generate(x0 + i % size, y0 + i / size))
+                            .collect();
+                        if done.send(Generated { chunk, generation, tiles }).is_err() {
+                            return;
+                        }
+                    }
+                })
+                .expect("tile generator threads can be spawned");
+        }
+        Self {
+            tileset,
+            layout: TileLayout::new(tile_size),
+            color_space: ColorSpace::Srgb,
+            layer: 0,
+            load_margin: 1,
+            keep_margin: 3,
+            max_uploads: 4,
+            chunks: HashMap::new(),
+            pending: HashSet::new(),
+            ready: vec![],
+            generation: 0,
+            jobs,
+            results,
+            workers,
+        }
+    }
+
+    pub fn layout(&self) -> TileLayout {
+        self.layout
+    }
+
+    // the world point the top left corner of the footprint of tile 0, 0 is at
+    pub fn set_origin(&mut self, origin: [f32; 2]) {
+        if self.layout.origin != origin {
+            self.layout.origin = origin;
+            self.regenerate();
+        }
+    }
+
+    pub fn set_projection(&mut self, projection: TileProjection) {
+        if self.layout.projection != projection {
+            self.layout.projection = projection;
+            self.regenerate();
+        }
+    }
+
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.color_space = color_space;
+        for chunk in self.chunks.values_mut() {
+            chunk.set_color_space(color_space);
+        }
+    }
+
+    pub fn layer(&self) -> u16 {
+        self.layer
+    }
+
+    // see `TilemapRenderer::set_layer`
+    pub fn set_layer(&mut self, layer: u16) {
+        self.layer = layer;
+    }
+
+    // Drops every chunk to generate them again, for when what the callback
+    // returns changed. The old ones are gone until the new ones come in.
+    pub fn regenerate(&mut self) {
+        self.generation += 1;
+        self.chunks.clear();
+        self.pending.clear();
+        self.ready.clear();
+    }
+
+    // chunks uploaded and drawn when in view
+    pub fn loaded(&self) -> usize {
+        self.chunks.len()
+    }
+
+    // The chunk with tile `x`, `y`.
+    pub fn chunk_of(x: i32, y: i32) -> (i32, i32) {
+        let size = CHUNK_SIZE as i32;
+        (x.div_euclid(size), y.div_euclid(size))
+    }
+
+    // the chunks from the smallest to the largest of the view around `cam`,
+    // `margin` chunks further out on each side
+    fn chunk_range(&self, cam: &Camera, margin: u32) -> ((i32, i32), (i32, i32)) {
+        let (min, max) = self.layout.tile_range(cam.visible_rect());
+        let margin = margin as i32;
+        let (min, max) = (Self::chunk_of(min.0, min.1), Self::chunk_of(max.0, max.1));
+        ((min.0 - margin, min.1 - margin), (max.0 + margin, max.1 + margin))
+    }
+
+    // Uploads what the workers finished, drops the chunks out of reach and
+    // asks for the ones coming into it, nearest first. Returns how many
+    // chunks it uploaded.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, cam: &Camera) -> usize {
+        let (keep_min, keep_max) = self.chunk_range(cam, self.keep_margin);
+        let kept =
+            |(x, y): (i32, i32)| (keep_min.0..=keep_max.0).contains(&x) && (keep_min.1..=keep_max.1).contains(&y);
+        self.chunks.retain(|chunk, _| kept(*chunk));
+
+        while let Ok(generated) = self.results.try_recv() {
+            if generated.generation == self.generation {
+                self.pending.remove(&generated.chunk);
+                self.ready.push(generated);
+            }
+        }
+        self.ready.retain(|generated| kept(generated.chunk));
+
+        let mut uploaded = 0;
+        while uploaded < self.max_uploads {
+            let Some(generated) = self.ready.pop() else {
+                break;
+            };
+            let mut chunk =
+                TilemapRenderer::new(device, self.tileset.clone(), CHUNK_SIZE, CHUNK_SIZE, self.layout.tile_size);
+            let size = CHUNK_SIZE as i32;
+            let corner = self.layout.footprint(generated.chunk.0 * size, generated.chunk.1 * size);
+            // the chunks start on even rows and columns, so the offset rows of
+            // a hexagonal map line up with the whole map's
+            chunk.set_projection(self.layout.projection);
+            chunk.set_origin([corner.x, corner.y]);
+            chunk.set_color_space(self.color_space);
+            chunk.set_tiles(&generated.tiles);
+            chunk.prepare(device, queue);
+            self.chunks.insert(generated.chunk, chunk);
+            uploaded += 1;
+        }
+
+        // a few jobs per worker in flight, so the queue doesn't fill up with
+        // chunks the camera has left again by the time they're generated
+        let (min, max) = self.chunk_range(cam, self.load_margin);
+        // twice the distance from the middle of the range, in whole chunks
+        let distance = |(x, y): (i32, i32)| (2 * x - min.0 - max.0).pow(2) + (2 * y - min.1 - max.1).pow(2);
+        let mut wanted: Vec<(i32, i32)> = (min.1..=max.1)
+            .flat_map(|y| (min.0..=max.0).map(move |x| (x, y)))
+            .filter(|chunk| {
+                !self.chunks.contains_key(chunk)
+                    && !self.pending.contains(chunk)
+                    && !self.ready.iter().any(|generated| generated.chunk == *chunk)
+            })
+            .collect();
+        wanted.sort_by_key(|chunk| distance(*chunk));
+        for chunk in wanted.into_iter().take((self.workers * 2).saturating_sub(self.pending.len())) {
+            let job = Job {
+                chunk,
+                generation: self.generation,
+            };
+            if self.jobs.send(job).is_ok() {
+                self.pending.insert(chunk);
+            }
+        }
+        uploaded
+    }
+
+    // draws the loaded chunks the camera sees, `sprites` has the pipeline for
+    // the target
+    pub fn render(&self, render_pass: &mut wgpu::RenderPass, cam: &Camera, sprites: &SpriteRenderer) -> DrawStats {
+        let mut stats = DrawStats::default();
+        // in chunk order, back to front for an isometric map
+        let mut chunks: Vec<_> = self.chunks.iter().collect();
+        chunks.sort_by_key(|((x, y), _)| (*y, *x));
+        for (_, chunk) in chunks {
+            stats += chunk.render(render_pass, cam, sprites);
+        }
+        stats
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use crate::animation::Easing;
+use crate::backend::RenderBackend;
+use crate::color::Color;
+use crate::font::MissingGlyphs;
+use crate::rect::Rect;
+use crate::sort::SortKey;
+
+const TEXT_SIZE: f32 = 16.0;
+const PADDING: f32 = 8.0;
+// from the window edges
+const MARGIN: f32 = 16.0;
+// between two toasts
+const SPACING: f32 = 8.0;
+const SLIDE_IN: Duration = Duration::from_millis(150);
+const FADE_OUT: Duration = Duration::from_millis(300);
+const BACKGROUND: Color = Color::rgb(0.02, 0.02, 0.02);
+const TEXT_COLOR: Color = Color::WHITE;
+// above the application, below the replay overlay, pinned to the screen
+pub(crate) const TOAST_LAYER: u16 = u16::MAX - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+// Short notifications stacked in a corner of the window, the newest one closest
+// to the corner. They slide in from the side and fade out once their time is
+// up.
+#[derive(Debug, Clone, Default)]
+pub struct Toasts {
+    pub corner: Corner,
+    toasts: Vec<Toast>,
+}
+
+#[derive(Debug, Clone)]
+struct Toast {
+    text: String,
+    shown: Instant,
+    duration: Duration,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // shows `text` for `duration`, starting now
+    pub fn push(&mut self, text: impl Into<String>, duration: Duration) {
+        self.toasts.push(Toast {
+            text: text.into(),
+            shown: Instant::now(),
+            duration,
+        });
+    }
+
+    // false once every toast has run out, until then the window has to keep
+    // redrawing for them to animate
+    pub fn is_empty(&self) -> bool {
+        self.toasts.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.toasts.clear();
+    }
+
+    // Draws the toasts as they are at `now` and forgets the ones that ran out.
+    pub fn draw(&mut self, backend: &mut impl RenderBackend, now: Instant) -> Result<(), MissingGlyphs> {
+        self.toasts
+            .retain(|toast| now.saturating_duration_since(toast.shown) < toast.duration);
+
+        let size = backend.size();
+        let (w, h) = (size.width as f32, size.height as f32);
+        let key = SortKey::new(TOAST_LAYER, 0);
+        let mut missing = MissingGlyphs(vec![]);
+        let mut offset = MARGIN;
+        backend.set_clip(None);
+        for toast in self.toasts.iter().rev() {
+            let age = now.saturating_duration_since(toast.shown);
+            let (text_w, text_h) = backend.measure_str(Some(TEXT_SIZE), &toast.text);
+            let (box_w, box_h) = (text_w + 2.0 * PADDING, text_h + 2.0 * PADDING);
+
+            let slide = Easing::Out.apply(age.as_secs_f32() / SLIDE_IN.as_secs_f32());
+            let hidden = (1.0 - slide) * (box_w + MARGIN);
+            let x = match self.corner {
+                Corner::TopLeft | Corner::BottomLeft => MARGIN - hidden,
+                Corner::TopRight | Corner::BottomRight => w - MARGIN - box_w + hidden,
+            };
+            let y = match self.corner {
+                Corner::TopLeft | Corner::TopRight => offset,
+                Corner::BottomLeft | Corner::BottomRight => h - offset - box_h,
+            };
+            let left = toast.duration.saturating_sub(age);
+            let opacity = (left.as_secs_f32() / FADE_OUT.as_secs_f32()).min(1.0);
+            let fade = |color: Color| color.with_alpha(opacity);
+
+            backend.push_quad(key, Rect::new(x, y, box_w, box_h), fade(BACKGROUND));
+            let text = (x + PADDING, y + PADDING);
+            if let Err(err) = backend.push_str(key, text.0, text.1, Some(TEXT_SIZE), fade(TEXT_COLOR), &toast.text) {
+                missing.merge(err);
+            }
+            offset += box_h + SPACING;
+        }
+        missing.into_result()
+    }
+}
+
+// `t` in 0..1 eased so movement starts fast and settles gently
@@ -0,0 +1,120 @@
+use wgpu::util::DeviceExt;
+
+use crate::shader::ShaderPreprocessor;
+
+const SHADER: &str = "
+@group(0) @binding(0)
+var<uniform> fade: vec4<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    // one triangle covering the whole target
+    let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return fade;
+}
+";
+
+// Fades what's in the target towards a color for `ClearMode::Trail`, drawn
+// first thing in the pass that loaded the previous frame. It passes the depth
+// test of a pass with a depth buffer without writing to it.
+pub(crate) struct TrailFader {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl TrailFader {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, depth: Option<wgpu::TextureFormat>) -> Self {
+        let shader = ShaderPreprocessor::new()
+            .create_module(device, "Trail Shader", SHADER)
+            .expect("bundled shader includes exist");
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Trail Fade"),
+            contents: bytemuck::cast_slice(&[0.0f32; 4]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Trail Fade"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Trail Fade"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Trail Fade Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: depth.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            multiview: None,
+            cache: None,
+        });
+        Self {
+            pipeline,
+            buffer,
+            bind_group,
+        }
+    }
+
+    // how far the previous frame fades after `dt` seconds
+    pub fn amount(half_life: f32, dt: f32) -> f32 {
+        if half_life <= 0.0 {
+            return 1.0;
+        }
+        1.0 - 0.5f32.powf(dt / half_life)
+    }
+
+    pub fn fade(&self, queue: &wgpu::Queue, render_pass: &mut wgpu::RenderPass, color: [f32; 4], amount: f32) {
+        let [r, g, b, a] = color;
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[r, g, b, a * amount.clamp(0.0, 1.0)]));
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,465 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::RangeInclusive;
+
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+use crate::backend::RenderBackend;
+use crate::input::InputState;
+use crate::rect::{ClipShape, Rect};
+use crate::sort::SortKey;
+
+// above the application, below the debug overlay and toasts, pinned to the
+// screen like them
+pub(crate) const UI_LAYER: u16 = u16::MAX - 3;
+// pixels the focus outline sticks out of a widget
+const FOCUS_OUTLINE: f32 = 2.0;
+const CARET_WIDTH: f32 = 2.0;
+// the box of a checkbox, as a fraction of the widget's height
+const CHECKBOX_BOX: f32 = 0.7;
+
+// Colors and sizes every widget is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiStyle {
+    pub text_size: f32,
+    // between a widget's edge and its text
+    pub padding: f32,
+    pub text: [f32; 3],
+    // placeholder text of empty text inputs
+    pub weak_text: [f32; 3],
+    pub background: [f32; 3],
+    pub hovered: [f32; 3],
+    pub pressed: [f32; 3],
+    // slider fill, check mark, caret and the outline of the focused widget
+    pub accent: [f32; 3],
+}
+
+impl Default for UiStyle {
+    fn default() -> Self {
+        Self {
+            text_size: 16.0,
+            padding: 6.0,
+            text: [0.92, 0.92, 0.92],
+            weak_text: [0.5, 0.5, 0.5],
+            background: [0.18, 0.18, 0.2],
+            hovered: [0.26, 0.26, 0.3],
+            pressed: [0.12, 0.12, 0.14],
+            accent: [0.3, 0.55, 0.95],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct WidgetId(u64);
+
+// What the ui remembers from one frame to the next, the widget being pressed
+// and the one with keyboard focus. Keep one around for as long as the widgets
+// are shown.
+#[derive(Debug, Clone, Default)]
+pub struct UiState {
+    pub style: UiStyle,
+    // the widget the mouse went down on, until it goes up
+    active: Option<WidgetId>,
+    focused: Option<WidgetId>,
+    // byte index into the focused text input
+    caret: usize,
+}
+
+impl UiState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // whether a widget has keyboard focus, so the app leaves the keys alone
+    pub fn has_focus(&self) -> bool {
+        self.focused.is_some()
+    }
+}
+
+// How a widget's rect was interacted with this frame.
+struct Response {
+    hovered: bool,
+    // held down on, the mouse may have left it since
+    held: bool,
+    clicked: bool,
+    focused: bool,
+}
+
+// Immediate mode widgets drawn over the application: every frame the app calls
+// the widgets it wants shown and reacts to what they return.
+//
+//   let mut ui = Ui::new(frame, &self.input, &mut self.ui_state);
+//   if ui.button("Save", Rect::new(10.0, 10.0, 100.0, 28.0)) {
+//       self.save();
+//   }
+//   ui.slider("Volume", Rect::new(10.0, 50.0, 200.0, 28.0), &mut self.volume, 0.0..=1.0);
+//   ui.checkbox("Grid", Rect::new(10.0, 90.0, 200.0, 28.0), &mut self.grid);
+//   ui.text_input("Name", Rect::new(10.0, 130.0, 200.0, 28.0), &mut self.name);
+//
+// `input` is the one `App::update` got this frame. Rects are window pixels from
+// the top left like the mouse, which is where they're drawn while the camera
+// isn't moved. Widgets are told apart by their labels, so two on screen need
+// different ones; everything from a "##" on is left out of the shown label.
+//
+// Clicking a widget gives it keyboard focus, tab and shift tab move it on in the
+// order the widgets are called and escape drops it. A focused button is pressed
+// with enter or space, a checkbox toggled with space and a slider moved with
+// the arrow keys.
+pub struct Ui<'a, B: RenderBackend> {
+    backend: &'a mut B,
+    input: &'a InputState,
+    state: &'a mut UiState,
+    depth: u32,
+    // of this frame, for moving focus on with tab
+    first: Option<WidgetId>,
+    previous: Option<WidgetId>,
+    focus_next: bool,
+    // shift tab on the first widget, which wraps around to the last one
+    focus_last: bool,
+    // the backend's clip before the ui, put back when it's dropped
+    clip: Option<ClipShape>,
+}
+
+impl<'a, B: RenderBackend> Ui<'a, B> {
+    pub fn new(backend: &'a mut B, input: &'a InputState, state: &'a mut UiState) -> Self {
+        if input.was_key_pressed(KeyCode::Escape) {
+            state.focused = None;
+        }
+        let clip = backend.clip();
+        backend.set_clip(None);
+        Self {
+            backend,
+            input,
+            state,
+            depth: 0,
+            first: None,
+            previous: None,
+            focus_next: false,
+            focus_last: false,
+            clip,
+        }
+    }
+
+    pub fn style(&self) -> &UiStyle {
+        &self.state.style
+    }
+
+    // text that doesn't react to anything, vertically centered in `rect`
+    pub fn label(&mut self, text: &str, rect: Rect) {
+        let color = self.state.style.text;
+        self.text(rect, rect.x, text, color);
+    }
+
+    // true in the frame it's clicked
+    pub fn button(&mut self, label: &str, rect: Rect) -> bool {
+        let id = widget_id(label);
+        let response = self.interact(id, rect);
+        let keyed = response.focused
+            && (self.input.was_key_pressed(KeyCode::Enter) || self.input.was_key_pressed(KeyCode::Space));
+        self.frame(rect, &response);
+        let label = shown(label);
+        let width = self.backend.measure_str(Some(self.state.style.text_size), label).0;
+        let color = self.state.style.text;
+        self.text(rect, rect.x + (rect.w - width) / 2.0, label, color);
+        response.clicked || keyed
+    }
+
+    // a box that's ticked while `checked`, true in the frame it's toggled
+    pub fn checkbox(&mut self, label: &str, rect: Rect, checked: &mut bool) -> bool {
+        let id = widget_id(label);
+        let response = self.interact(id, rect);
+        let toggled = response.clicked || (response.focused && self.input.was_key_pressed(KeyCode::Space));
+        if toggled {
+            *checked = !*checked;
+        }
+        let side = rect.h * CHECKBOX_BOX;
+        let check_box = Rect::new(rect.x, rect.y + (rect.h - side) / 2.0, side, side);
+        self.frame(check_box, &response);
+        if *checked {
+            let inset = side / 4.0;
+            let mark = Rect::new(check_box.x + inset, check_box.y + inset, side - 2.0 * inset, side - 2.0 * inset);
+            let (key, accent) = (self.next_key(), self.state.style.accent);
+            self.backend.push_quad(key, mark, accent);
+        }
+        let color = self.state.style.text;
+        let x = check_box.right() + self.state.style.padding;
+        self.text(rect, x, shown(label), color);
+        toggled
+    }
+
+    // Drags `value` within `range` along the rect's width, a focused slider
+    // moves by a hundredth of the range per arrow key. True in frames the
+    // value changes.
+    pub fn slider(&mut self, label: &str, rect: Rect, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let id = widget_id(label);
+        let response = self.interact(id, rect);
+        let (min, max) = (*range.start(), *range.end());
+        let old = *value;
+        if response.held
+            && let Some((x, _)) = self.input.mouse_position()
+        {
+            let t = ((x - rect.x) / rect.w.max(1.0)).clamp(0.0, 1.0);
+            *value = min + (max - min) * t;
+        }
+        if response.focused {
+            let step = (max - min) / 100.0;
+            if self.input.was_key_typed(KeyCode::ArrowLeft) {
+                *value -= step;
+            }
+            if self.input.was_key_typed(KeyCode::ArrowRight) {
+                *value += step;
+            }
+        }
+        *value = value.clamp(min.min(max), max.max(min));
+
+        self.frame(rect, &response);
+        let t = if max != min { (*value - min) / (max - min) } else { 0.0 };
+        let fill = Rect::new(rect.x, rect.y, rect.w * t.clamp(0.0, 1.0), rect.h);
+        let (key, accent) = (self.next_key(), self.state.style.accent);
+        self.backend.push_quad(key, fill, accent);
+        let text = format!("{}: {:.2}", shown(label), *value);
+        let width = self.backend.measure_str(Some(self.state.style.text_size), &text).0;
+        let color = self.state.style.text;
+        self.text(rect, rect.x + (rect.w - width) / 2.0, &text, color);
+        *value != old
+    }
+
+    // A single line of editable text, the label shown while it's empty. Typing
+    // goes in at the caret, which is put where the input is clicked and moved
+    // with the arrow keys, home and end. True in frames the text changes.
+    pub fn text_input(&mut self, label: &str, rect: Rect, text: &mut String) -> bool {
+        let id = widget_id(label);
+        let response = self.interact(id, rect);
+        let style = self.state.style;
+        let text_x = rect.x + style.padding;
+        let mut changed = false;
+        if response.focused {
+            let mut caret = self.state.caret.min(text.len());
+            while !text.is_char_boundary(caret) {
+                caret -= 1;
+            }
+            if response.hovered
+                && self.input.was_mouse_pressed(MouseButton::Left)
+                && let Some((x, _)) = self.input.mouse_position()
+            {
+                caret = self.index_at(text, x - text_x);
+            }
+            let typed = self.input.text();
+            if !typed.is_empty() {
+                text.insert_str(caret, typed);
+                caret += typed.len();
+                changed = true;
+            }
+            if self.input.was_key_typed(KeyCode::Backspace) && caret > 0 {
+                let len = char_before(text, caret);
+                caret -= len;
+                text.replace_range(caret..caret + len, "");
+                changed = true;
+            }
+            if self.input.was_key_typed(KeyCode::Delete) && caret < text.len() {
+                let len = char_after(text, caret);
+                text.replace_range(caret..caret + len, "");
+                changed = true;
+            }
+            if self.input.was_key_typed(KeyCode::ArrowLeft) {
+                caret -= char_before(text, caret);
+            }
+            if self.input.was_key_typed(KeyCode::ArrowRight) {
+                caret += char_after(text, caret);
+            }
+            if self.input.was_key_pressed(KeyCode::Home) {
+                caret = 0;
+            }
+            if self.input.was_key_pressed(KeyCode::End) {
+                caret = text.len();
+            }
+            self.state.caret = caret;
+        }
+
+        self.frame(rect, &response);
+        self.backend.set_clip(Some(rect));
+        if text.is_empty() {
+            self.text(rect, text_x, shown(label), style.weak_text);
+        } else {
+            self.text(rect, text_x, text, style.text);
+        }
+        if response.focused {
+            let (width, height) = self.backend.measure_str(Some(style.text_size), &text[..self.state.caret]);
+            let caret = Rect::new(text_x + width, rect.y + (rect.h - height) / 2.0, CARET_WIDTH, height);
+            let key = self.next_key();
+            self.backend.push_quad(key, caret, style.accent);
+        }
+        self.backend.set_clip(None);
+        changed
+    }
+
+    // Hover, press and focus of the widget `id` at `rect`, and moving the focus
+    // on with tab.
+    fn interact(&mut self, id: WidgetId, rect: Rect) -> Response {
+        let input = self.input;
+        let hovered = input.mouse_position().is_some_and(|(x, y)| rect.contains(x, y));
+        if input.was_mouse_pressed(MouseButton::Left) {
+            if hovered {
+                self.state.active = Some(id);
+                if self.state.focused != Some(id) {
+                    self.state.caret = usize::MAX;
+                }
+                self.state.focused = Some(id);
+            } else if self.state.focused == Some(id) {
+                self.state.focused = None;
+            }
+        }
+        let mut clicked = false;
+        if self.state.active == Some(id) && !input.is_mouse_down(MouseButton::Left) {
+            self.state.active = None;
+            clicked = hovered;
+        }
+
+        if self.focus_next {
+            self.focus(id);
+            self.focus_next = false;
+        } else if self.state.focused == Some(id) && input.was_key_pressed(KeyCode::Tab) {
+            if input.modifiers().shift_key() {
+                match self.previous {
+                    Some(previous) => self.focus(previous),
+                    None => self.focus_last = true,
+                }
+            } else {
+                self.focus_next = true;
+            }
+        }
+        self.first.get_or_insert(id);
+        self.previous = Some(id);
+
+        Response {
+            hovered,
+            held: self.state.active == Some(id),
+            clicked,
+            // not yet in the frame tab moved the focus to it, the key
+            // belonged to the widget before
+            focused: self.state.focused == Some(id) && !input.was_key_pressed(KeyCode::Tab),
+        }
+    }
+
+    fn focus(&mut self, id: WidgetId) {
+        self.state.focused = Some(id);
+        self.state.caret = usize::MAX;
+    }
+
+    // the background of a widget, with an outline while it's focused
+    fn frame(&mut self, rect: Rect, response: &Response) {
+        let style = self.state.style;
+        if response.focused {
+            let outline = Rect::new(
+                rect.x - FOCUS_OUTLINE,
+                rect.y - FOCUS_OUTLINE,
+                rect.w + 2.0 * FOCUS_OUTLINE,
+                rect.h + 2.0 * FOCUS_OUTLINE,
+            );
+            let key = self.next_key();
+            self.backend.push_quad(key, outline, style.accent);
+        }
+        let color = match (response.held, response.hovered) {
+            (true, _) => style.pressed,
+            (false, true) => style.hovered,
+            (false, false) => style.background,
+        };
+        let key = self.next_key();
+        self.backend.push_quad(key, rect, color);
+    }
+
+    // one line starting at `x`, vertically centered in `rect`; characters the
+    // font doesn't have are drawn as its fallback glyph
+    fn text(&mut self, rect: Rect, x: f32, text: &str, color: [f32; 3]) {
+        let size = Some(self.state.style.text_size);
+        let height = self.backend.measure_str(size, text).1;
+        let key = self.next_key();
+        let _ = self.backend.push_str(key, x, rect.y + (rect.h - height) / 2.0, size, color, text);
+    }
+
+    // the char boundary of `text` closest to `x` pixels into it
+    fn index_at(&self, text: &str, x: f32) -> usize {
+        let size = Some(self.state.style.text_size);
+        text.char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .min_by(|&a, &b| {
+                let distance = |i: usize| (self.backend.measure_str(size, &text[..i]).0 - x).abs();
+                distance(a).total_cmp(&distance(b))
+            })
+            .unwrap_or(0)
+    }
+
+    fn next_key(&mut self) -> SortKey {
+        self.depth += 1;
+        SortKey::new(UI_LAYER, self.depth)
+    }
+}
+
+impl<B: RenderBackend> Drop for Ui<'_, B> {
+    // tab on the last widget wraps around to the first, shift tab on the first
+    // to the last
+    fn drop(&mut self) {
+        if self.focus_next
+            && let Some(first) = self.first
+        {
+            self.focus(first);
+        }
+        if self.focus_last
+            && let Some(last) = self.previous
+        {
+            self.focus(last);
+        }
+        self.backend.set_clip_shape(self.clip);
+    }
+}
+
+fn widget_id(label: &str) -> WidgetId {
+    let mut hasher = DefaultHasher::new();
+    label.hash(&mut hasher);
+    WidgetId(hasher.finish())
+}
+
+// bytes of the char in front of byte `i`
+fn char_before(text: &str, i: usize) -> usize {
+    text[..i].chars().next_back().map_or(0, char::len_utf8)
+}
+
+// bytes of the char at byte `i`
+fn char_after(text: &str, i: usize) -> usize {
+    text[i..].chars().next().map_or(0, char::len_utf8)
+}
+
+// the label without its "##" suffix
+fn shown(label: &str) -> &str {
+    label.split("##").next().unwrap_or(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::create_cpu_glyph_atlas;
+    use crate::software::SoftwareRenderer;
+    use crate::state::FontSource;
+
+    #[test]
+    fn clip_is_put_back_after_the_ui() {
+        let font = FontSource::Embedded.load().unwrap();
+        let mut backend =
+            SoftwareRenderer::new(winit::dpi::PhysicalSize::new(64, 64), create_cpu_glyph_atlas(&font, 16.0));
+        let (input, mut state) = (InputState::new(), UiState::new());
+        let clip = Rect::new(4.0, 4.0, 32.0, 32.0);
+        backend.set_clip(Some(clip));
+        {
+            let mut ui = Ui::new(&mut backend, &input, &mut state);
+            assert_eq!(ui.backend.clip(), None);
+            ui.text_input("name", Rect::new(0.0, 0.0, 60.0, 20.0), &mut String::from("text"));
+            assert_eq!(ui.backend.clip(), None);
+        }
+        assert_eq!(backend.clip(), Some(ClipShape::Rect(clip)));
+
+        backend.set_clip(None);
+        drop(Ui::new(&mut backend, &input, &mut state));
+        assert_eq!(backend.clip(), None);
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rect::Rect;
+
+// A fixed logical resolution the scene is drawn at, scaled up as far as it
+// fits into the window and centered, with the rest of the window left to the
+// clear color as bars. Set it on the `Camera`, which then builds its projection
+// over the logical size.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+    // only scale by whole numbers, so every logical pixel covers the same
+    // number of physical ones, unless the window is smaller than the viewport
+    #[serde(default)]
+    pub integer_scale: bool,
+}
+
+impl Viewport {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            integer_scale: false,
+        }
+    }
+
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        winit::dpi::PhysicalSize::new(self.width, self.height)
+    }
+
+    // physical pixels per logical pixel in a `target` sized window
+    pub fn scale(&self, target: winit::dpi::PhysicalSize<u32>) -> f32 {
+        let scale = (target.width as f32 / self.width.max(1) as f32)
+            .min(target.height as f32 / self.height.max(1) as f32);
+        if self.integer_scale && scale >= 1.0 { scale.floor() } else { scale }
+    }
+
+    // the part of a `target` sized window the logical resolution is shown in
+    pub fn area(&self, target: winit::dpi::PhysicalSize<u32>) -> Rect {
+        let scale = self.scale(target);
+        let (w, h) = (
+            (self.width as f32 * scale).min(target.width as f32),
+            (self.height as f32 * scale).min(target.height as f32),
+        );
+        Rect::new(
+            ((target.width as f32 - w) / 2.0).floor(),
+            ((target.height as f32 - h) / 2.0).floor(),
+            w,
+            h,
+        )
+    }
+
+    // a point in physical window pixels in logical pixels, which lies outside
+    // of the logical size over the bars
+    pub fn to_logical(&self, target: winit::dpi::PhysicalSize<u32>, x: f32, y: f32) -> (f32, f32) {
+        let area = self.area(target);
+        let scale = self.scale(target);
+        ((x - area.x) / scale, (y - area.y) / scale)
+    }
+
+    pub fn to_physical(&self, target: winit::dpi::PhysicalSize<u32>, x: f32, y: f32) -> (f32, f32) {
+        let area = self.area(target);
+        let scale = self.scale(target);
+        (x * scale + area.x, y * scale + area.y)
+    }
+}
+
+// Restricts a pass drawing into a `target` sized texture to the camera's
+// viewport area, which also clips what's drawn outside of the logical size.
+pub(crate) fn apply(render_pass: &mut wgpu::RenderPass, cam: &crate::camera::Camera, target: winit::dpi::PhysicalSize<u32>) {
+    if let Some(viewport) = cam.viewport() {
+        let area = viewport.area(target);
+        if area.w > 0.0 && area.h > 0.0 {
+            render_pass.set_viewport(area.x, area.y, area.w, area.h, 0.0, 1.0);
+        }
+    }
+}
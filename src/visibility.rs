@@ -0,0 +1,144 @@
+use crate::rect::Rect;
+
+// rays are cast this far, in radians, to either side of every endpoint to see
+// past the corners of occluders
+const CORNER_OFFSET: f32 = 1e-4;
+
+// A wall that blocks sight, from `a` to `b` in world units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub a: [f32; 2],
+    pub b: [f32; 2],
+}
+
+impl Segment {
+    pub const fn new(a: [f32; 2], b: [f32; 2]) -> Self {
+        Self { a, b }
+    }
+
+    // the four sides of `rect`, for boxes that block sight
+    pub fn rect(rect: Rect) -> [Segment; 4] {
+        let Rect { x, y, w, h } = rect.normalized();
+        let corners = [[x, y], [x + w, y], [x + w, y + h], [x, y + h]];
+        std::array::from_fn(|i| Segment::new(corners[i], corners[(i + 1) % 4]))
+    }
+
+    // the distance from `point` to the nearest point of the segment
+    fn distance(&self, point: [f32; 2]) -> f32 {
+        let e = sub(self.b, self.a);
+        let w = sub(point, self.a);
+        let len = e[0] * e[0] + e[1] * e[1];
+        let s = if len > 0.0 { ((w[0] * e[0] + w[1] * e[1]) / len).clamp(0.0, 1.0) } else { 0.0 };
+        let d = sub(w, [e[0] * s, e[1] * s]);
+        d[0].hypot(d[1])
+    }
+
+    // how far along the ray from `origin` in `dir` it hits this segment, in
+    // lengths of `dir`
+    fn ray_hit(&self, origin: [f32; 2], dir: [f32; 2]) -> Option<f32> {
+        let e = sub(self.b, self.a);
+        let denom = cross(dir, e);
+        if denom.abs() < f32::EPSILON {
+            return None;
+        }
+        let w = sub(self.a, origin);
+        let t = cross(w, e) / denom;
+        let s = cross(w, dir) / denom;
+        (t > 0.0 && (0.0..=1.0).contains(&s)).then_some(t)
+    }
+}
+
+// The area visible from `origin`, a polygon of points ordered by angle
+// around it. It's star shaped from `origin`, so `triangles` fans it into the
+// mesh of a light or the shape a shadow leaves lit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibilityPolygon {
+    pub origin: [f32; 2],
+    pub points: Vec<[f32; 2]>,
+}
+
+impl VisibilityPolygon {
+    // Sweeps rays around `origin` through the endpoints of every occluder and
+    // keeps the nearest hit of each. Sight ends at `bounds`, from outside of
+    // them nothing is visible.
+    pub fn new(origin: [f32; 2], occluders: &[Segment], bounds: Rect) -> Self {
+        let bounds = bounds.normalized();
+        if !bounds.contains(origin[0], origin[1]) {
+            return Self { origin, points: vec![] };
+        }
+        // nearest first, so a ray stops looking once the rest are further than
+        // what it already hit
+        let mut segments: Vec<(f32, Segment)> = occluders
+            .iter()
+            .copied()
+            .chain(Segment::rect(bounds))
+            .map(|s| (s.distance(origin), s))
+            .collect();
+        segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut angles: Vec<f32> = segments
+            .iter()
+            .flat_map(|(_, s)| [s.a, s.b])
+            .flat_map(|p| {
+                let angle = f32::atan2(p[1] - origin[1], p[0] - origin[0]);
+                [angle - CORNER_OFFSET, angle, angle + CORNER_OFFSET]
+            })
+            .collect();
+        angles.sort_by(f32::total_cmp);
+        angles.dedup_by(|a, b| *a - *b < CORNER_OFFSET / 4.0);
+
+        let points = angles
+            .into_iter()
+            .filter_map(|angle| {
+                let dir = [angle.cos(), angle.sin()];
+                let mut t = f32::INFINITY;
+                for (distance, s) in &segments {
+                    if *distance >= t {
+                        break;
+                    }
+                    if let Some(hit) = s.ray_hit(origin, dir) {
+                        t = t.min(hit);
+                    }
+                }
+                t.is_finite().then_some([origin[0] + dir[0] * t, origin[1] + dir[1] * t])
+            })
+            .collect();
+        Self { origin, points }
+    }
+
+    // whether `point` can be seen from the origin
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        let [px, py] = point;
+        let mut inside = false;
+        for (i, a) in self.points.iter().enumerate() {
+            let b = self.points[(i + 1) % self.points.len()];
+            if (a[1] > py) != (b[1] > py) && px < a[0] + (py - a[1]) / (b[1] - a[1]) * (b[0] - a[0]) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    // the polygon as a fan of triangles around the origin
+    pub fn triangles(&self) -> impl Iterator<Item = [[f32; 2]; 3]> + '_ {
+        let n = self.points.len();
+        (0..n).map(move |i| [self.origin, self.points[i], self.points[(i + 1) % n]])
+    }
+}
+
+// whether nothing in `occluders` is between `from` and `to`, for a single
+// query where a whole `VisibilityPolygon` isn't worth it
+pub fn line_of_sight(from: [f32; 2], to: [f32; 2], occluders: &[Segment]) -> bool {
+    let dir = sub(to, from);
+    !occluders
+        .iter()
+        .any(|s| s.ray_hit(from, dir).is_some_and(|t| t < 1.0))
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn cross(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
@@ -0,0 +1,76 @@
+// Renders quad-only, text-only and mixed frames headlessly, one after another
+// with the same renderers, and checks that every frame shows exactly what was
+// pushed for it. Finishing a frame used to skip uploading when either batch
+// was empty. Back faces are culled, so a quad pushed with a negative size only
+// shows up when it's wound like every other quad.
+use winit::dpi::PhysicalSize;
+use wrs::camera::Camera;
+use wrs::culling::Culling;
+use wrs::font::FontRenderer;
+use wrs::quad::QuadRenderer;
+use wrs::state::{FontSource, RendererSettings};
+
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(128, 64);
+// inside the quad
+const QUAD_PROBE: (u32, u32) = (16, 16);
+// the text is drawn in the right half
+const TEXT_AREA: u32 = 64;
+
+#[test]
+fn frames_show_what_was_pushed() {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+        .expect("no graphics adapter available");
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).unwrap();
+
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let cam = Camera::new_from_size(&device, SIZE);
+    let font = FontSource::Embedded.load().unwrap();
+    let atlas = wrs::create_monospace_atlas(&device, &queue, &font, 32.0);
+    let culling = Culling {
+        cull_mode: Some(wgpu::Face::Back),
+        ..Culling::default()
+    };
+    let mut quads = QuadRenderer::with_culling(&device, &cam, format, culling);
+    let mut text = FontRenderer::with_culling(&device, &cam, &atlas, format, culling);
+    let settings = RendererSettings::default();
+
+    // the text-only and quad-only frames right after a mixed one are the
+    // ones that used to show the mixed frame's leftovers
+    for (name, with_quad, with_text, flipped) in [
+        ("mixed", true, true, false),
+        ("quads only", true, false, false),
+        ("text only", false, true, false),
+        ("empty", false, false, false),
+        ("mixed again", true, true, false),
+        ("text only again", false, true, false),
+        ("flipped quad", true, false, true),
+    ] {
+        quads.clear();
+        text.clear();
+        if with_quad && flipped {
+            quads.push(40.0, 40.0, -32.0, -32.0, [1.0, 0.0, 0.0]);
+        } else if with_quad {
+            quads.push(8.0, 8.0, 32.0, 32.0, [1.0, 0.0, 0.0]);
+        }
+        if with_text {
+            text.push_str(TEXT_AREA as f32, 8.0, 32.0, [1.0, 1.0, 1.0], "##", &atlas)
+                .unwrap();
+        }
+        let image =
+            wrs::export::render_scene(&device, &queue, &cam, &mut quads, &mut text, &settings, SIZE);
+
+        let has_quad = image.get_pixel(QUAD_PROBE.0, QUAD_PROBE.1).0[0] > 128;
+        let has_text = image
+            .enumerate_pixels()
+            .any(|(x, _, pixel)| x >= TEXT_AREA && pixel.0[1] > 128);
+        assert_eq!(has_quad, with_quad, "{name}: quad");
+        assert_eq!(has_text, with_text, "{name}: text");
+        // nothing pushed ends up outside of where it was pushed
+        let stray = image
+            .enumerate_pixels()
+            .any(|(x, y, pixel)| x < TEXT_AREA && y >= 48 && pixel.0[..3] != [0, 0, 0]);
+        assert!(!stray, "{name}: drew below the quad");
+    }
+}
@@ -0,0 +1,261 @@
+// Runs the frame loop headlessly over a simulated 5 seconds of animation, text
+// and window resizes, once at 60 and once at 144 frames per second. The
+// animation advances in fixed steps, so both runs have to end on the same
+// image, and resident memory mustn't keep growing once the buffers have
+// settled. Prefers a software adapter (lavapipe, llvmpipe, warp) so it runs
+// the same in CI, and fails without one.
+//
+// With `WRS_WINDOW_TESTS` set it then drives an `App` through `app::run_with`
+// in a real window, resizing it between frames, which needs a display (CI runs
+// it under xvfb). It has the main thread to itself, winit wants it, so this
+// test brings its own `main`.
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+use wrs::Renderer;
+use wrs::app::App;
+use wrs::camera::Camera;
+use wrs::clock::FrameClock;
+use wrs::font::FontRenderer;
+use wrs::frame::Frame;
+use wrs::quad::QuadRenderer;
+use wrs::sort::SortKey;
+use wrs::state::{FontSource, RendererSettings};
+
+const DURATION: Duration = Duration::from_secs(5);
+const STEP: Duration = Duration::from_millis(10);
+const BALLS: usize = 24;
+// simulated seconds at which the "window" is resized, and to what
+const RESIZES: [(f32, PhysicalSize<u32>); 3] = [
+    (0.0, PhysicalSize::new(320, 180)),
+    (1.5, PhysicalSize::new(200, 300)),
+    (3.5, PhysicalSize::new(256, 144)),
+];
+// resident memory the second half of a run may add
+const MAX_GROWTH: u64 = 32 * 1024 * 1024;
+// frames a window gets to take on a size it was asked for
+const RESIZE_FRAMES: u32 = 120;
+
+struct Ball {
+    pos: [f32; 2],
+    vel: [f32; 2],
+    color: [f32; 3],
+}
+
+fn main() {
+    env_logger::init();
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        force_fallback_adapter: true,
+        ..Default::default()
+    }))
+    .or_else(|_| pollster::block_on(instance.request_adapter(&Default::default())));
+    let adapter = adapter.expect("no graphics adapter available");
+    println!("adapter: {}", adapter.get_info().name);
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).unwrap();
+
+    let mut failed = false;
+    let mut checksums = vec![];
+    for fps in [60, 144] {
+        let (checksum, growth) = run(&device, &queue, fps);
+        println!(
+            "{fps} fps: checksum {checksum:016x}, memory growth {} KiB",
+            growth.map_or("?".into(), |g| (g / 1024).to_string())
+        );
+        if growth.is_some_and(|g| g > MAX_GROWTH) {
+            println!("  memory kept growing");
+            failed = true;
+        }
+        checksums.push(checksum);
+    }
+    if checksums.windows(2).any(|w| w[0] != w[1]) {
+        println!("final frames differ between frame rates");
+        failed = true;
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+
+    if std::env::var_os("WRS_WINDOW_TESTS").is_none() {
+        println!("window loop skipped, set WRS_WINDOW_TESTS where there's a display");
+        return;
+    }
+    let app = WindowLoop {
+        window: None,
+        balls: balls(),
+        frames: 0,
+        resizes: 0,
+        pending: None,
+    };
+    let attributes = Window::default_attributes().with_inner_size(RESIZES[0].1);
+    if let Err(err) = wrs::app::run_with(attributes, app) {
+        panic!("no window: {err}");
+    }
+    panic!("the window closed before the loop was done");
+}
+
+// Draws the scene of the headless runs into a window through the real
+// `App` callbacks, asking the window for every size of `RESIZES` in turn.
+// Ends the process once it took on the last one.
+struct WindowLoop {
+    window: Option<Arc<Window>>,
+    balls: Vec<Ball>,
+    frames: u32,
+    resizes: usize,
+    // the size asked for, and the frame it was asked in
+    pending: Option<(PhysicalSize<u32>, u32)>,
+}
+
+impl App for WindowLoop {
+    fn setup(&mut self, renderer: &mut Renderer) {
+        self.window = Some(renderer.get_window().clone());
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        self.frames += 1;
+        let size = frame.size();
+        assert!(size.width > 0 && size.height > 0, "frame {} has no pixels", self.frames);
+        let dt = frame.clock().dt();
+        let bounds = [size.width as f32 - 8.0, size.height as f32 - 8.0];
+        for ball in &mut self.balls {
+            ball.step(dt, bounds);
+        }
+
+        frame.quad(0.0, 0.0, size.width as f32, size.height as f32, [0.02, 0.02, 0.05]);
+        for ball in &self.balls {
+            frame.quad_with_key(SortKey::new(1, 0), ball.pos[0], ball.pos[1], 8.0, 8.0, ball.color);
+        }
+        let text = format!("frame {}", self.frames);
+        frame.text_with_key(SortKey::new(2, 0), 4.0, 4.0, 16.0, [1.0, 1.0, 1.0], &text).unwrap();
+
+        match self.pending {
+            Some((wanted, _)) if size == wanted => {
+                self.pending = None;
+                self.resizes += 1;
+            }
+            Some((wanted, asked)) => assert!(
+                self.frames - asked < RESIZE_FRAMES,
+                "the window is still {size:?} {RESIZE_FRAMES} frames after asking for {wanted:?}"
+            ),
+            None if self.resizes == RESIZES.len() => {
+                println!("window loop: {} frames over {} sizes", self.frames, RESIZES.len());
+                std::process::exit(0);
+            }
+            None => {
+                let wanted = RESIZES[self.resizes].1;
+                let _ = self.window.as_ref().unwrap().request_inner_size(wanted);
+                self.pending = Some((wanted, self.frames));
+            }
+        }
+    }
+}
+
+fn balls() -> Vec<Ball> {
+    (0..BALLS)
+        .map(|i| {
+            let f = i as f32 / BALLS as f32;
+            Ball {
+                pos: [f * 150.0, f * 90.0],
+                vel: [40.0 + f * 80.0, 120.0 - f * 70.0],
+                color: [f, 1.0 - f, 0.5],
+            }
+        })
+        .collect()
+}
+
+impl Ball {
+    // bounces off the edges of `bounds`
+    fn step(&mut self, dt: f32, bounds: [f32; 2]) {
+        for ((pos, vel), bound) in self.pos.iter_mut().zip(&mut self.vel).zip(bounds) {
+            *pos += *vel * dt;
+            if *pos < 0.0 || *pos > bound {
+                *vel = -*vel;
+                *pos = pos.clamp(0.0, bound);
+            }
+        }
+    }
+}
+
+// returns the checksum of the last frame and how much resident memory the
+// second half of the run added
+fn run(device: &wgpu::Device, queue: &wgpu::Queue, fps: u32) -> (u64, Option<u64>) {
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let mut size = RESIZES[0].1;
+    let mut cam = Camera::new_from_size(device, size);
+    let font = FontSource::Embedded.load().unwrap();
+    let atlas = wrs::create_monospace_atlas(device, queue, &font, 32.0);
+    let mut quads = QuadRenderer::new(device, &cam, format);
+    let mut text = FontRenderer::new(device, &cam, &atlas, format);
+    let settings = RendererSettings::default();
+
+    let mut balls = balls();
+    let mut steps = 0u32;
+
+    let start = Instant::now();
+    let mut clock = FrameClock::starting_at(start);
+    let frames = DURATION.as_secs() as u32 * fps;
+    let mut halfway_memory = None;
+    let mut image = None;
+    for frame in 1..=frames {
+        clock.tick_at(start + DURATION * frame / frames);
+        clock.fixed_update(STEP, |step| {
+            let bounds = [size.width as f32 - 8.0, size.height as f32 - 8.0];
+            for ball in &mut balls {
+                ball.step(step.as_secs_f32(), bounds);
+            }
+            steps += 1;
+        });
+
+        let resize = RESIZES
+            .iter()
+            .rfind(|(at, _)| clock.elapsed().as_secs_f32() >= *at)
+            .unwrap()
+            .1;
+        if resize != size {
+            size = resize;
+            cam.resize(size, queue);
+        }
+
+        quads.clear();
+        text.clear();
+        quads.push(0.0, 0.0, size.width as f32, size.height as f32, [0.02, 0.02, 0.05]);
+        for ball in &balls {
+            let key = SortKey::new(1, 0);
+            quads.push_with_key(key, ball.pos[0].round(), ball.pos[1].round(), 8.0, 8.0, ball.color);
+        }
+        text.push_str_with_key(SortKey::new(2, 0), 4.0, 4.0, 16.0, [1.0, 1.0, 1.0], &format!("step {steps}"), &atlas)
+            .unwrap();
+
+        // every frame is rendered and read back, only the last one is kept
+        let target = wrs::export::render_scene(device, queue, &cam, &mut quads, &mut text, &settings, size);
+        if frame == frames / 2 {
+            halfway_memory = resident_memory();
+        }
+        if frame == frames {
+            image = Some(target);
+        }
+    }
+
+    let growth = halfway_memory
+        .zip(resident_memory())
+        .map(|(half, end)| end.saturating_sub(half));
+    (checksum(image.unwrap().as_raw()), growth)
+}
+
+// fnv-1a
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// bytes, only known on linux
+fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}